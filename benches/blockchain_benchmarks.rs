@@ -3,70 +3,42 @@ use mini_block_chain::modules::{
     blockchain::{BlockChain, Block},
     merkle_tree::{MerkleTree, Account},
     zk_proof,
-    validation::ValidatorNode,
-    utxo::{UTXOSet, UTXOTransaction, TxInput, TxOutput, OutPoint, UTXO},
+    utxo::{UTXOSet, UTXOTransaction, TxInput, TxOutput, OutPoint, UTXO, SIGHASH_ALL},
+    verifier,
 };
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
 use std::time::Duration;
 
-fn benchmark_signature_generation(c: &mut Criterion) {
-    c.bench_function("generate_signature", |b| {
-        let (secret_key, public_key) = zk_proof::generate_keypair().unwrap();
-        let private_key_hex = secret_key.to_string();
-        let public_key_hex = hex::encode(public_key.serialize());
-        let recipient_public_key = "04abcd1234567890abcdef";
-        let amount = "100";
-        let nonce = 1u64;
-        
+fn benchmark_schnorr_proof(c: &mut Criterion) {
+    let mut group = c.benchmark_group("schnorr_proof");
+
+    // Setup
+    let (secret_key, public_key) = zk_proof::generate_keypair().unwrap();
+    let private_key_hex = secret_key.to_string();
+    let public_key_hex = hex::encode(public_key.serialize());
+    let commitment = zk_proof::derive_schnorr_commitment(&private_key_hex).unwrap();
+    let message = b"transaction message";
+    let proof = zk_proof::create_schnorr_proof(&private_key_hex, message).unwrap();
+
+    group.bench_function("create", |b| {
         b.iter(|| {
-            zk_proof::sign_transaction(
+            zk_proof::create_transaction_proof(
                 black_box(&private_key_hex),
                 black_box(&public_key_hex),
-                black_box(&recipient_public_key.to_string()),
-                black_box(&amount.to_string()),
-                black_box(nonce)
+                black_box("04abcd1234567890abcdef"),
+                black_box("100"),
+                black_box("1"),
+                black_box(1u64)
             ).unwrap()
         });
     });
-}
 
-fn benchmark_signature_verification(c: &mut Criterion) {
-    let mut group = c.benchmark_group("signature_verification");
-    group.measurement_time(Duration::from_secs(10));
-    
-    // Setup
-    let (secret_key, public_key) = zk_proof::generate_keypair().unwrap();
-    let private_key_hex = secret_key.to_string();
-    let public_key_hex = hex::encode(public_key.serialize());
-    let recipient_public_key = "04abcd1234567890abcdef";
-    let amount = "100";
-    let nonce = 1u64;
-    
-    let signature = zk_proof::sign_transaction(
-        &private_key_hex,
-        &public_key_hex,
-        &recipient_public_key.to_string(),
-        &amount.to_string(),
-        nonce
-    ).unwrap();
-    
-    let validator_node = ValidatorNode::new();
-    
     group.bench_function("verify", |b| {
-        let runtime = tokio::runtime::Runtime::new().unwrap();
         b.iter(|| {
-            runtime.block_on(async {
-                zk_proof::verify_transaction_signature(
-                    black_box(&signature),
-                    black_box(&public_key_hex),
-                    black_box(recipient_public_key),
-                    black_box(amount),
-                    black_box(nonce),
-                    black_box(validator_node.clone())
-                ).await
-            })
+            zk_proof::verify_schnorr_proof(black_box(&proof), black_box(&commitment), black_box(message))
         });
     });
-    
+
     group.finish();
 }
 
@@ -80,6 +52,7 @@ fn benchmark_merkle_tree_operations(c: &mut Criterion) {
             public_key_hash: vec![5, 6, 7, 8],
             balance: 100,
             nonce: 0,
+            schnorr_commitment: vec![],
         };
         
         b.iter(|| {
@@ -95,6 +68,7 @@ fn benchmark_merkle_tree_operations(c: &mut Criterion) {
                 public_key_hash: vec![5, 6, 7, 8],
                 balance: 100 + i,
                 nonce: 0,
+                schnorr_commitment: vec![],
             };
             tree.insert_account(account);
         }
@@ -121,7 +95,9 @@ fn benchmark_blockchain_operations(c: &mut Criterion) {
             recipient: vec![4, 5, 6],
             recipient_balance: 110,
             amount: 10,
+            fee: 1,
             time: 12345,
+            validator_rewards: vec![],
             hash: vec![],
         };
         
@@ -141,7 +117,9 @@ fn benchmark_blockchain_operations(c: &mut Criterion) {
                 recipient: vec![4, 5, 6],
                 recipient_balance: 110 + i,
                 amount: 10,
+                fee: 1,
                 time: 12345 + i as u64,
+                validator_rewards: vec![],
                 hash: vec![],
             };
             blockchain.push_block_to_chain(block);
@@ -165,7 +143,9 @@ fn benchmark_json_serialization(c: &mut Criterion) {
         recipient: vec![4, 5, 6],
         recipient_balance: 110,
         amount: 10,
+        fee: 1,
         time: 12345,
+        validator_rewards: vec![],
         hash: vec![7, 8, 9],
     };
     
@@ -233,10 +213,12 @@ fn benchmark_utxo_transaction_creation(c: &mut Criterion) {
             outpoint: OutPoint::new(vec![1, 2, 3], 0),
             signature: "signature".to_string(),
             public_key: vec![4, 5, 6],
+            relative_lock: None,
         };
         let output = TxOutput {
             amount: 100,
             recipient: vec![7, 8, 9],
+            confidential: None,
         };
         
         b.iter(|| {
@@ -253,10 +235,12 @@ fn benchmark_utxo_transaction_creation(c: &mut Criterion) {
             outpoint: OutPoint::new(vec![1, 2, 3], 0),
             signature: "signature".to_string(),
             public_key: vec![4, 5, 6],
+            relative_lock: None,
         };
         let output = TxOutput {
             amount: 100,
             recipient: vec![7, 8, 9],
+            confidential: None,
         };
         let tx = UTXOTransaction::new(vec![input], vec![output], 12345);
         
@@ -283,10 +267,12 @@ fn benchmark_utxo_transaction_validation(c: &mut Criterion) {
         outpoint: prev_outpoint,
         signature: "signature".to_string(),
         public_key: vec![4, 5, 6],
+        relative_lock: None,
     };
     let output = TxOutput {
         amount: 900,
         recipient: vec![7, 8, 9],
+        confidential: None,
     };
     let tx = UTXOTransaction::new(vec![input], vec![output], 12345);
     
@@ -294,7 +280,7 @@ fn benchmark_utxo_transaction_validation(c: &mut Criterion) {
         b.iter(|| {
             let input_amount = black_box(&tx).total_input_amount(black_box(&utxo_set));
             let output_amount = black_box(&tx).total_output_amount();
-            input_amount.unwrap_or(0) >= output_amount
+            input_amount.unwrap_or(0) >= output_amount.unwrap_or(u64::MAX)
         });
     });
     
@@ -316,6 +302,64 @@ fn benchmark_utxo_transaction_validation(c: &mut Criterion) {
     group.finish();
 }
 
+// Builds a transaction with the given number of inputs, each signed by its own key
+fn signed_utxo_transaction(input_count: usize, timestamp: u64) -> UTXOTransaction {
+    let keys: Vec<SecretKey> = (0..input_count).map(|_| zk_proof::generate_keypair().unwrap().0).collect();
+    let inputs = keys.iter().enumerate().map(|(vout, key)| {
+        let public_key = PublicKey::from_secret_key(verifier::secp_context(), key).serialize().to_vec();
+        TxInput::new(OutPoint::new(vec![1; 32], vout as u32), String::new(), public_key)
+    }).collect();
+
+    let mut tx = UTXOTransaction::new(inputs, vec![TxOutput::new(100, vec![7; 20])], timestamp);
+    for (input_index, key) in keys.iter().enumerate() {
+        tx.sign_input(input_index, key, SIGHASH_ALL).unwrap();
+    }
+    tx
+}
+
+fn benchmark_utxo_signature_verification(c: &mut Criterion) {
+    let mut group = c.benchmark_group("utxo_signature_verification");
+    group.measurement_time(Duration::from_secs(10));
+
+    // Setup: a transaction with 64 inputs and a block of 16 transactions with 8 inputs each
+    let tx = signed_utxo_transaction(64, 12345);
+    let block: Vec<UTXOTransaction> = (0..16).map(|i| signed_utxo_transaction(8, 12345 + i)).collect();
+
+    // One input at a time on the calling thread, for comparison
+    group.bench_function("transaction_64_inputs_sequential", |b| {
+        b.iter(|| {
+            (0..tx.inputs.len()).all(|input_index| black_box(&tx).verify_input(input_index).is_ok())
+        });
+    });
+
+    group.bench_function("transaction_64_inputs_parallel", |b| {
+        b.iter(|| {
+            verifier::verify_transaction_signatures(black_box(&tx)).unwrap()
+        });
+    });
+
+    group.bench_function("block_128_inputs_sequential", |b| {
+        b.iter(|| {
+            black_box(&block).iter().all(|tx| (0..tx.inputs.len()).all(|input_index| tx.verify_input(input_index).is_ok()))
+        });
+    });
+
+    group.bench_function("block_128_inputs_parallel", |b| {
+        b.iter(|| {
+            verifier::verify_block_signatures(black_box(&block)).unwrap()
+        });
+    });
+
+    // The cost each verification paid when it built its own context, saved by the shared one
+    group.bench_function("create_verification_context", |b| {
+        b.iter(|| {
+            black_box(Secp256k1::verification_only())
+        });
+    });
+
+    group.finish();
+}
+
 fn benchmark_utxo_serialization(c: &mut Criterion) {
     let mut group = c.benchmark_group("utxo_serialization");
     
@@ -323,10 +367,12 @@ fn benchmark_utxo_serialization(c: &mut Criterion) {
         outpoint: OutPoint::new(vec![1, 2, 3], 0),
         signature: "signature".to_string(),
         public_key: vec![4, 5, 6],
+        relative_lock: None,
     };
     let output = TxOutput {
         amount: 100,
         recipient: vec![7, 8, 9],
+        confidential: None,
     };
     let tx = UTXOTransaction::new(vec![input], vec![output], 12345);
     
@@ -349,14 +395,14 @@ fn benchmark_utxo_serialization(c: &mut Criterion) {
 
 criterion_group!(
     benches,
-    benchmark_signature_generation,
-    benchmark_signature_verification,
+    benchmark_schnorr_proof,
     benchmark_merkle_tree_operations,
     benchmark_blockchain_operations,
     benchmark_json_serialization,
     benchmark_utxo_operations,
     benchmark_utxo_transaction_creation,
     benchmark_utxo_transaction_validation,
+    benchmark_utxo_signature_verification,
     benchmark_utxo_serialization
 );
 criterion_main!(benches);
\ No newline at end of file
@@ -2,14 +2,30 @@
 
 // Make each submodule public to be accessible from main.rs or other parts
 pub mod modules {
+    pub mod address;
     pub mod adopt_network_state;
     pub mod blockchain;
+    pub mod bridge;
+    pub mod coin_selection;
+    pub mod confidential;
     pub mod consensus;
+    pub mod encoding;
     pub mod constants;
+    pub mod events;
+    pub mod hd_keys;
+    pub mod htlc;
     pub mod merkle_tree;
+    pub mod multisig;
     pub mod network;
+    pub mod query;
     pub mod requests;
+    pub mod rpc;
+    pub mod script;
+    pub mod stealth;
     pub mod utxo;
+    pub mod utxo_commitment;
     pub mod validation;
+    pub mod verifier;
+    pub mod wallet;
     pub mod zk_proof;
 }
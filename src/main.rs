@@ -1,6 +1,7 @@
 mod modules;
 use crate::modules::requests;
 use crate::modules::validation;
+use crate::modules::constants::MIN_TRANSACTION_FEE;
 
 use std::env;
 
@@ -12,30 +13,41 @@ use std::env;
  *  
  *     An account can be made by running the following command: 
  *  
- *         cargo run make
+ *         cargo run make [account name]
  *  
  *     On the client side, a new private/public key pair will be generated using the secp256k1 elliptic curve
  *     over a finite field. The client will send a network request to all validators running nodes that a new 
  *     account creation is being requested. Validators will check that the account does not already exist in the
  *     merkel tree, and if not, it will be added and a new block will be created in the blockchain. Otherwise, 
- *     the account creation will be rejected. Additionally, in each block will be stored the hash of an elliptic 
- *     curve representation of the new user's private key will be stored for a zk proofs of knowledge verification 
- *     of the user's account balance when sending transactions.
+ *     the account creation will be rejected. Additionally, a Schnorr commitment derived from the new user's private 
+ *      key (signed with that key) will be stored in the merkle tree for zk proofs of knowledge when sending transactions.
+ * 
+ *     The private key is never printed: `make` stores it in the password-encrypted wallet (see 6.) under the account 
+ *     name, the same as `cargo run wallet new [account name]`.
  * 
  * 2.) Transaction: 
  * 
  *     To send a transaction provide the following arguments to the CLI:
  * 
- *     cargo run transaction [sender private key] [recipiant public key] [transaction amount]
+ *     cargo run transaction [sender account name or private key] [recipiant address or public key] [transaction amount] [fee]
+ *
+ *     The recipient is given by its address (see address.rs), or by its hex public key. A mistyped address fails its 
+ *     checksum and is rejected before anything is sent.
+ * 
+ *     If the sender is the name of a wallet account, its private key is unlocked with the wallet password.
  * 
- *     On the client side, the private key will be converted into an obfuscated representation as multiple ellitpic 
- *     curve points that sum to the elliptic curve representaiton of the original privte key (scalar multiplication). 
- *     This and the other provided details of the transaction (including the derived public key) will be packaged and 
- *     sent as a transaction request to all validators running nodes.
+ *     The fee is optional and defaults to constants::MIN_TRANSACTION_FEE. It is deducted from the sender and split 
+ *     among the validator set. The sender's nonce is fetched from the network automatically 
+ *     and the hash of the transaction is printed so its status can be queried.
  * 
- *     Validators will sum the elliptic curve points and hash the result. If this hash matches the hash recorded in 
- *     the merkle tree for the provded account, the user who sent the request will be assumed to have knowledge of the 
- *     private key. The account will be checked to have sufficient balance to send the transaciton.
+ *     On the client side, the private key is used to create a Schnorr proof of knowledge of the account secret that 
+ *     is bound to the chain id, recipient, amount, fee and nonce of the transfer. This and the other provided details 
+ *     of the transaction (including the derived public key) will be packaged and sent as a transaction request to all 
+ *     validators running nodes.
+ * 
+ *     Validators will check that the nonce is the sender's current nonce and verify the proof against the commitment 
+ *     recorded in the merkle tree for the provided account. The account will be checked to have sufficient balance 
+ *     to send the transaciton.
  *     
  *     Assuming validation is successful, the balance of the sender will be decreased by the transaction amount, and 
  *     vice versa for the recipiant. A new block will be added to the chain reflecting this change.
@@ -44,7 +56,11 @@ use std::env;
  * 
  *     A validator node can be run by providing the following arguments to the CLI:
  * 
- *     cargo run validate [private key]
+ *     cargo run validate
+ * 
+ *     The node signs its votes and heartbeats with the node key configured (key_file) for its port in accepted_ports.json. 
+ *     If that key is in the validator set of the network, the validator receives a share of the fees of every transaction 
+ *     it votes to accept at the reward address the set assigns to it.
  * 
  *     This will trigger the node software to send a network request to all other validator nodes that a new node is 
  *     requesting the current state of the blockchain and merkel tree. Each node will send their current state to the 
@@ -59,6 +75,86 @@ use std::env;
  *     
  *     Using the faucet command will send a network request to validator nodes to provide a given public key with a 
  *     small amount of tokens that can be used to send transactions with. This is for testing purposes.
+ * 
+ * 5.) Queries:
+ * 
+ *     Read-only queries are answered by a single validator node without going through consensus:
+ * 
+ *     cargo run account [public key]
+ *     cargo run status [transaction hash]
+ * 
+ *     The first prints the balance and nonce of an account, the second whether a transaction is pending, committed 
+ *     or rejected (along with the reason).
+ * 
+ * 6.) Wallet:
+ * 
+ *     Private keys can be kept in a password-encrypted keystore (wallet.json) under account names:
+ * 
+ *     cargo run wallet new [name]                                   creates and registers a new account
+ *     cargo run wallet list                                         prints the name and public key of each account
+ *     cargo run wallet import [name] [private key]                  encrypts an existing private key
+ *     cargo run wallet export [name]                                prints the private key of an account
+ *     cargo run wallet sign [name] [recipient] [amount] [fee] [nonce]  prints a signed transfer without sending it
+ * 
+ *     The password is prompted for, or read from the WALLET_PASSWORD environment variable.
+ * 
+ *     Keys can instead be derived from a single seed backed up by a mnemonic (see hd_keys.rs):
+ * 
+ *     cargo run wallet init                                         creates the seed and prints its mnemonic
+ *     cargo run wallet restore [count]                              restores the seed from its mnemonic
+ *     cargo run wallet receive                                      derives a fresh key to receive UTXO outputs
+ *     cargo run wallet xpub                                         prints the extended public key of the receive chain
+ * 
+ *     Once the wallet has a seed, wallet new derives the key of the new account from it.
+ * 
+ *     Outputs paid to the stealth address of an account go to one-time keys found by scanning UTXO blocks (see stealth.rs):
+ * 
+ *     cargo run wallet stealth [name]                               prints the stealth address of an account
+ *     cargo run wallet scan [name] [from height]                    prints unspent outputs paid to it and their keys
+ * 
+ *     Spends of M-of-N multisig outputs are co-signed offline in a partially signed transaction file (see multisig.rs):
+ * 
+ *     cargo run wallet multisig [M] [public key]...                 prints the lock to pay multisig outputs to
+ *     cargo run wallet psbt [file] [lock] [txid:vout,...] [recipient] [amount]...  writes a spend to co-sign
+ *     cargo run wallet timelock [file] [lock time] [input:blocks|seconds:N]...  time locks the spend before signing
+ *     cargo run wallet cosign [name] [file]                         adds the signatures of an account
+ *     cargo run wallet combine [file] [other file]                  merges signatures collected in parallel
+ *     cargo run wallet finalize [file]                              prints the signed UTXO transaction request
+ *     cargo run wallet script [asm]...                              prints the recipient of a locking script (see script.rs)
+ * 
+ *     Atomic swaps lock outputs in hash time-locked contracts (see htlc.rs):
+ * 
+ *     cargo run wallet htlc [sender] [receiver public key] [timeout] [hash]   prints the HTLC to pay, and a new secret without hash
+ *     cargo run wallet claim [name] [htlc] [txid:vout] [preimage] [recipient] [amount]...  prints the signed claim
+ *     cargo run wallet refund [name] [htlc] [txid:vout] [recipient] [amount]...  prints the refund, valid from the timeout
+ *     cargo run wallet preimage [input json]                        prints the secret revealed by a claim
+ * 
+ * 7.) Moving Value Between Accounts and UTXOs (see bridge.rs):
+ * 
+ *     cargo run to-utxo [sender account name or private key] [recipient] [amount] [fee]
+ * 
+ *     burns the amount and fee from the sender's balance and mints a UTXO of the amount paid to the recipient. The 
+ *     other way, UTXOs are spent to an output that credits an account, whose recipient is printed by:
+ * 
+ *     cargo run wallet to-account [public key]
+ * 
+ * 8.) UTXO Payments:
+ * 
+ *     cargo run send [sender account name or private key] [recipient] [amount] [strategy]
+ * 
+ *     pays the amount to the recipient from the UTXOs of the sender, fetched from the network with a GetUtxos query. 
+ *     The inputs are picked by the coin selection strategy (see coin_selection.rs): bnb (the default) looks for inputs 
+ *     that pay the amount and fee exactly and otherwise spends the largest first, largest-first spends as few inputs 
+ *     as possible and privacy avoids spending UTXOs of different addresses together. The fee is estimated from the 
+ *     size of the transaction, and change is paid back to the address of the sender.
+ * 
+ *     Amounts can be hidden in confidential outputs (see confidential.rs):
+ * 
+ *     cargo run wallet confidential [name] [txid:vout:amount[:blinding],...] [recipient] [amount] [fee]
+ * 
+ *     prints a signed payment of the amount in a confidential output, spending the given UTXOs (with the opening 
+ *     amount:blinding of confidential ones), and the openings of its outputs. The recipient needs the opening of the 
+ *     payment to spend it.
  */
 
 
@@ -72,29 +168,58 @@ async fn main() -> std::io::Result<()> {
 
     // Send Account Creation Request Specified  
     if args[1] == "make" { 
-        requests::send_account_creation_request().await;
+        requests::send_account_creation_request(args.get(2)).await;
+
+    } // Wallet Command Specified
+    else if args[1] == "wallet" {
+        requests::send_wallet_command(&args[2..]).await;
 
     } // Transaction Request Specified
     else if args[1] == "transaction" {  
 
-        // extract provided arguments:
-        let sender_private_key: String = args[2].to_string();
+        // extract provided arguments (the sender is a wallet account name or a private key):
+        let sender: String = args[2].to_string();
         let recipient_public_key: String = args[3].to_string();
         let transaction_amount: String = args[4].to_string();
+        let transaction_fee: String = args.get(5).cloned().unwrap_or(MIN_TRANSACTION_FEE.to_string());
 
         // send transaction request to validator nodes
         requests::send_transaction_request(
-            sender_private_key, recipient_public_key, transaction_amount
+            sender, recipient_public_key, transaction_amount, transaction_fee
         ).await;
  
     }// Run Validation Node Specified 
     else if args[1] == "validate" { 
         validation::run_validation().await;
 
+    } // UTXO Payment Specified
+    else if args[1] == "send" {
+        let sender: String = args[2].to_string();
+        let recipient: String = args[3].to_string();
+        let amount: String = args[4].to_string();
+        let strategy: String = args.get(5).cloned().unwrap_or("bnb".to_string());
+        requests::send_payment_request(sender, recipient, amount, strategy).await;
+
+    } // Account To UTXO Request Specified
+    else if args[1] == "to-utxo" {
+        let sender: String = args[2].to_string();
+        let recipient: String = args[3].to_string();
+        let amount: String = args[4].to_string();
+        let fee: String = args.get(5).cloned().unwrap_or(MIN_TRANSACTION_FEE.to_string());
+        requests::send_account_to_utxo_request(sender, recipient, amount, fee).await;
+
     } // Faucet Request Specified
     else if args[1] == "faucet"  {
         let public_key: String = args[2].to_string(); 
         requests::send_faucet_request(public_key).await;
+    } // Account Query Specified
+    else if args[1] == "account" {
+        let public_key: String = args[2].to_string();
+        requests::send_account_query(public_key).await;
+    } // Transaction Status Query Specified
+    else if args[1] == "status" {
+        let hash: String = args[2].to_string();
+        requests::send_tx_status_query(hash).await;
     }
     else { // Improper Command
         println!("ERROR! Unrecognized Command");
@@ -0,0 +1,250 @@
+use sha2::{Sha256, Digest};
+use ripemd::Ripemd160;
+use secp256k1::PublicKey;
+use bech32::{FromBase32, ToBase32, Variant};
+
+use crate::modules::blockchain;
+use crate::modules::bridge;
+use crate::modules::multisig::MultisigLock;
+use crate::modules::script::Script;
+use crate::modules::stealth::{self, StealthAddress};
+
+
+/**
+ * @notice address.rs contains the address format of the network. An address is the HASH160 (RIPEMD160 of SHA256) of
+ * a compressed secp256k1 public key, encoded with Bech32m under the address prefix of the network, e.g.
+ *
+ *   mbc1g975h6gdx5mryeac72h6lj2nzygugxhy5s223l
+ *
+ * The checksum catches any typo of up to 4 characters, so a mistyped address is rejected instead of sending funds to a
+ * key nobody holds. Since an address only commits to the hash of a public key, spending from it reveals the public key
+ * for the first time.
+ *
+ * Recipients may also still be given as hex encoded compressed public keys, and UTXO outputs may be paid to a hex
+ * encoded multisig lock (see multisig.rs) or locking script (see script.rs), or to a hex encoded account recipient that
+ * credits an account balance (see bridge.rs). Paying a stealth address (see stealth.rs) creates a new stealth recipient,
+ * which is paid to a one-time key only its receiver can find. All are parsed into a Recipient.
+ */
+// length of the public key hash an address encodes
+pub const PUBLIC_KEY_HASH_LENGTH: usize = 20;
+
+// length of a compressed secp256k1 public key
+pub const PUBLIC_KEY_LENGTH: usize = 33;
+
+/**
+ * @notice Recipient is a parsed recipient of a transfer or of a UTXO output.
+ * @param PublicKey - a compressed public key, given as hex
+ * @param PublicKeyHash - the HASH160 of a public key, given as an address
+ * @param Multisig - the encoding of a multisig lock, given as hex
+ * @param Script - a locking script prefixed with SCRIPT_TAG, given as hex
+ * @param Account - the public key of an account prefixed with ACCOUNT_TAG, given as hex
+ * @param Stealth - an ephemeral key and the hash of a one-time key prefixed with STEALTH_TAG, given as a stealth address
+ * (with a new ephemeral key every time it is parsed) or as hex
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recipient {
+    PublicKey(Vec<u8>),
+    PublicKeyHash(Vec<u8>),
+    Multisig(Vec<u8>),
+    Script(Vec<u8>),
+    Account(Vec<u8>),
+    Stealth(Vec<u8>),
+}
+
+impl Recipient {
+
+    // Returns the bytes stored as the recipient of a TxOutput
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Recipient::PublicKey(public_key) => public_key,
+            Recipient::PublicKeyHash(public_key_hash) => public_key_hash,
+            Recipient::Multisig(lock) => lock,
+            Recipient::Script(script) => script,
+            Recipient::Account(account) => account,
+            Recipient::Stealth(stealth) => stealth,
+        }
+    }
+}
+
+/**
+ * @notice hash160() returns RIPEMD160(SHA256(data)), the hash an address commits to.
+ */
+pub fn hash160(data: &[u8]) -> [u8; PUBLIC_KEY_HASH_LENGTH] {
+    let hash = Ripemd160::digest(Sha256::digest(data));
+    let mut public_key_hash = [0u8; PUBLIC_KEY_HASH_LENGTH];
+    public_key_hash.copy_from_slice(&hash);
+    public_key_hash
+}
+
+// Returns the human readable prefix of addresses on this network (see blockchain::GenesisConfig)
+pub fn address_prefix() -> &'static str {
+    &blockchain::genesis_config().address_prefix
+}
+
+/**
+ * @notice encode_address() encodes a public key hash as an address of this network.
+ */
+pub fn encode_address(public_key_hash: &[u8]) -> String {
+    bech32::encode(address_prefix(), public_key_hash.to_base32(), Variant::Bech32m)
+        .expect("the address prefix is a valid Bech32 prefix")
+}
+
+/**
+ * @notice address_of_public_key() returns the address of a compressed public key.
+ */
+pub fn address_of_public_key(public_key: &PublicKey) -> String {
+    encode_address(&hash160(&public_key.serialize()))
+}
+
+/**
+ * @notice decode_address() checks the checksum, prefix and length of an address and returns the public key hash it
+ * encodes.
+ */
+pub fn decode_address(address: &str) -> Result<Vec<u8>, String> {
+    let (prefix, data, variant) = bech32::decode(address).map_err(|e| format!("invalid address {}: {}", address, e))?;
+    if prefix != address_prefix() {
+        return Err(format!("address {} is not for this network (expected prefix {})", address, address_prefix()));
+    }
+    if variant != Variant::Bech32m { return Err(format!("address {} is not Bech32m encoded", address)); }
+
+    let public_key_hash: Vec<u8> = Vec::<u8>::from_base32(&data).map_err(|e| format!("invalid address {}: {}", address, e))?;
+    if public_key_hash.len() != PUBLIC_KEY_HASH_LENGTH {
+        return Err(format!("address {} must encode {} bytes", address, PUBLIC_KEY_HASH_LENGTH));
+    }
+    Ok(public_key_hash)
+}
+
+/**
+ * @notice parse_recipient() parses a recipient given as an address, a stealth address, a hex encoded compressed public
+ * key, a hex encoded multisig lock, a hex encoded script recipient, a hex encoded account recipient or a hex encoded
+ * stealth recipient.
+ */
+pub fn parse_recipient(recipient: &str) -> Result<Recipient, String> {
+    if let Ok(bytes) = hex::decode(recipient) {
+        if PublicKey::from_slice(&bytes).is_ok() { return Ok(Recipient::PublicKey(bytes)); }
+        if MultisigLock::from_bytes(&bytes).is_ok() { return Ok(Recipient::Multisig(bytes)); }
+        if Script::from_recipient(&bytes).is_ok() { return Ok(Recipient::Script(bytes)); }
+        if bridge::account_of_recipient(&bytes).is_some() { return Ok(Recipient::Account(bytes)); }
+        if stealth::parse_recipient(&bytes).is_some() { return Ok(Recipient::Stealth(bytes)); }
+    }
+    if let Ok(stealth_address) = recipient.parse::<StealthAddress>() { return Ok(Recipient::Stealth(stealth_address.pay())); }
+    decode_address(recipient).map(Recipient::PublicKeyHash)
+}
+
+/**
+ * @notice validate_recipient_bytes() checks the recipient of a TxOutput, which is either a public key hash, a
+ * compressed public key, a multisig lock, a well formed locking script, an account recipient or a stealth recipient.
+ */
+pub fn validate_recipient_bytes(recipient: &[u8]) -> Result<(), String> {
+    match recipient.len() {
+        PUBLIC_KEY_HASH_LENGTH => Ok(()),
+        PUBLIC_KEY_LENGTH if PublicKey::from_slice(recipient).is_ok() => Ok(()),
+        _ if MultisigLock::from_bytes(recipient).is_ok() => Ok(()),
+        _ if Script::from_recipient(recipient).is_ok() => Ok(()),
+        _ if bridge::account_of_recipient(recipient).is_some() => Ok(()),
+        _ if stealth::parse_recipient(recipient).is_some() => Ok(()),
+        _ => Err(format!("malformed recipient {}", hex::encode(recipient))),
+    }
+}
+
+/**
+ * @notice recipient_matches() returns true if the public key revealed by a spender owns outputs paid to the recipient,
+ * either because it is the recipient or because it hashes to it (or to the one-time key of a stealth recipient). A
+ * spender of a multisig or script output reveals its lock or script.
+ */
+pub fn recipient_matches(recipient: &[u8], public_key: &[u8]) -> bool {
+    match (recipient.len(), stealth::parse_recipient(recipient)) {
+        (_, Some((_, one_time_key_hash))) => hash160(public_key) == one_time_key_hash,
+        (PUBLIC_KEY_HASH_LENGTH, None) => hash160(public_key) == recipient,
+        _ => recipient == public_key,
+    }
+}
+
+/**
+ * @notice index_key() returns the key the UTXO set indexes outputs paid to a recipient under: the hash of the one-time
+ * key of a stealth recipient, so stealth outputs are found like outputs paid to an address, and the recipient itself
+ * otherwise.
+ */
+pub fn index_key(recipient: &[u8]) -> &[u8] {
+    match stealth::parse_recipient(recipient) {
+        Some((_, one_time_key_hash)) => one_time_key_hash,
+        None => recipient,
+    }
+}
+
+/**
+ * @notice display_recipient() formats the recipient of a TxOutput for display: an address for public key hashes, hex
+ * for public keys, the threshold and keys of multisig locks, the disassembly of scripts and the credited account.
+ */
+pub fn display_recipient(recipient: &[u8]) -> String {
+    match recipient.len() {
+        PUBLIC_KEY_HASH_LENGTH => encode_address(recipient),
+        PUBLIC_KEY_LENGTH => hex::encode(recipient),
+        _ => MultisigLock::from_bytes(recipient).map(|lock| lock.to_string())
+            .or_else(|_| Script::from_recipient(recipient).map(|script| format!("script [{}]", script)))
+            .or_else(|_| bridge::account_of_recipient(recipient).map(|account| format!("account {}", String::from_utf8_lossy(account))).ok_or(()))
+            .or_else(|_| stealth::parse_recipient(recipient).map(|(_, one_time_key_hash)| format!("stealth {}", encode_address(one_time_key_hash))).ok_or(()))
+            .unwrap_or_else(|_| hex::encode(recipient)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::zk_proof;
+
+    #[test]
+    fn test_address_round_trip_and_typos() {
+        let (_, public_key) = zk_proof::generate_keypair().unwrap();
+        let address = address_of_public_key(&public_key);
+        assert!(address.starts_with(&format!("{}1", address_prefix())));
+
+        let public_key_hash = decode_address(&address).unwrap();
+        assert!(recipient_matches(&public_key_hash, &public_key.serialize()));
+        assert_eq!(parse_recipient(&address).unwrap(), Recipient::PublicKeyHash(public_key_hash));
+        assert_eq!(parse_recipient(&public_key.to_string()).unwrap(), Recipient::PublicKey(public_key.serialize().to_vec()));
+
+        // a single mistyped character breaks the checksum
+        let last = address.chars().last().unwrap();
+        let typo = format!("{}{}", &address[..address.len() - 1], if last == 'q' { 'p' } else { 'q' });
+        assert!(parse_recipient(&typo).is_err());
+
+        // addresses of other networks and truncated public keys are rejected
+        let other_network = bech32::encode("tmbc", public_key_hash_of(&public_key).to_base32(), Variant::Bech32m).unwrap();
+        assert!(decode_address(&other_network).is_err());
+        assert!(parse_recipient(&public_key.to_string()[..64]).is_err());
+    }
+
+    #[test]
+    fn test_validate_recipient_bytes() {
+        let (_, public_key) = zk_proof::generate_keypair().unwrap();
+        assert!(validate_recipient_bytes(&public_key.serialize()).is_ok());
+        assert!(validate_recipient_bytes(&public_key_hash_of(&public_key)).is_ok());
+        assert!(validate_recipient_bytes(&[5u8; 33]).is_err());
+        assert!(validate_recipient_bytes(&[1, 2, 3]).is_err());
+        assert!(!recipient_matches(&[0u8; 20], &public_key.serialize()));
+    }
+
+    #[test]
+    fn test_stealth_recipients() {
+        let keys = stealth::StealthKeys::from_spend_key(secp256k1::SecretKey::from_slice(&[1; 32]).unwrap()).unwrap();
+        let stealth_address: String = keys.address().to_string();
+
+        // every payment to a stealth address gets a new recipient, which validates and is spent by its one-time key
+        let (Recipient::Stealth(first), Recipient::Stealth(second)) = (parse_recipient(&stealth_address).unwrap(), parse_recipient(&stealth_address).unwrap())
+            else { panic!("stealth addresses parse into stealth recipients") };
+        assert_ne!(first, second);
+        assert!(validate_recipient_bytes(&first).is_ok());
+        assert_eq!(parse_recipient(&hex::encode(&first)).unwrap(), Recipient::Stealth(first.clone()));
+
+        let one_time_key = PublicKey::from_secret_key(crate::modules::verifier::secp_context(), &keys.output_secret_key(&first).unwrap());
+        assert!(recipient_matches(&first, &one_time_key.serialize()));
+        assert_eq!(index_key(&first), hash160(&one_time_key.serialize()));
+        assert_eq!(display_recipient(&first), format!("stealth {}", address_of_public_key(&one_time_key)));
+    }
+
+    fn public_key_hash_of(public_key: &PublicKey) -> [u8; PUBLIC_KEY_HASH_LENGTH] {
+        hash160(&public_key.serialize())
+    }
+}
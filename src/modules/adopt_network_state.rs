@@ -4,8 +4,7 @@ use tokio::io::AsyncWriteExt;
 use sha2::{Digest, Sha256};
 
 use serde::{Serializer, Deserializer, Deserialize, Serialize};
-use serde::de::{self, Visitor, MapAccess};
-use serde::ser::SerializeMap;
+use serde::de::{self, Visitor};
 use serde_json::Value;
 
 use base64::{encode, decode};
@@ -14,17 +13,21 @@ use std::{fmt, collections::HashMap};
 use std::sync::Arc;
 
 use crate::modules::validation::ValidatorNode;
-use crate::modules::blockchain::{BlockChain, Block};
+use crate::modules::blockchain::{self, BlockChain, Block};
 use crate::modules::merkle_tree::{MerkleTree, Account};
 use crate::modules::requests;
+use crate::modules::utxo::UTXOSet;
+use crate::modules::utxo_commitment;
 
 
 /**
  * @notice adopt_network_state.rs contains the logic for validator nodes connecting to the network to adopt the majority
  * ledger state of the network. This file contains the logic for sending a request to all other nodes for a copy of their
- * blockchain, merkle tree, and all previously used zk-proofs. The validator node will wait for all peer responses (as per
+ * blockchain and merkle tree. The validator node will wait for all peer responses (as per
  * the time of the call to adopt_network_state()) and then determine the majority state of the network. The validator node
- * will then update its local blockchain and merkle tree to reflect the majority state of the network.
+ * will then update its local blockchain and merkle tree to reflect the majority state of the network. The UTXO set is not
+ * sent, it is rebuilt by replaying the adopted blockchain and checked against the UTXO set commitments its blocks record
+ * (see utxo_commitment.rs), so a peer whose chain does not match its commitments is never adopted.
 */
 
 /**
@@ -42,8 +45,7 @@ pub struct PeerLedgerResponse {
     accounts_vec: Vec<Account>,
     #[serde(serialize_with = "serialize_account_balances_map", deserialize_with = "deserialize_account_balances_map")] 
     accounts_map: HashMap<Vec<u8>, u64>,
-    #[serde(serialize_with = "serialize_used_zk_proofs_map", deserialize_with = "deserialize_used_zk_proofs_map")]
-    used_zk_proofs: HashMap<Vec<u8>, Vec<String>>,
+    chain_id: String,
 }
 
 
@@ -68,7 +70,7 @@ pub async fn adopt_network_state(validator_node: ValidatorNode) {
 
 /**
  * @notice handle_peer_ledger_request() is passed a serde_json::Value struct containing a request from the master 
- * event handler in network.rs for a copy of this node's ledger state (blockchain, merkle tree, used zk proofs). 
+ * event handler in network.rs for a copy of this node's ledger state (blockchain and merkle tree). 
  * This function will package the blockchain and merkle tree data into a PeerLedgerResponse struct and send it back 
  * to the requesting peer node.
  */
@@ -82,10 +84,6 @@ pub async fn adopt_network_state(validator_node: ValidatorNode) {
     let merkle_tree : Arc<Mutex<MerkleTree>> = validator_node.merkle_tree.clone();
     let merkle_tree_guard = merkle_tree.lock().await;
 
-    // lock the used zk proofs map
-    let used_zk_proofs: Arc<Mutex<HashMap<Vec<u8>, Vec<String>>>> = validator_node.used_zk_proofs.clone();
-    let used_zk_proofs: HashMap<Vec<u8>, Vec<String>> = used_zk_proofs.lock().await.clone();
-
     // retrieve blockchain and merkle tree data
     let blockchain: Vec<Block> = blockchain_guard.chain.clone();
     let accounts_vec: Vec<Account> = merkle_tree_guard.accounts_vec.clone();
@@ -97,7 +95,7 @@ pub async fn adopt_network_state(validator_node: ValidatorNode) {
         blockchain,
         accounts_vec,
         accounts_map,
-        used_zk_proofs
+        chain_id: blockchain::chain_id().to_string(),
     };
 
     // serialize the PeerLedgerResponse struct into a JSON string 
@@ -127,7 +125,7 @@ pub async fn adopt_network_state(validator_node: ValidatorNode) {
 
  /**
   * @handle_peer_ledger_response() is passed the serde::json repsonse sent back by peers using handle_peer_ledger_request() 
-  * from all  that takes a serde_json::Value struct containing the blockchain, merkle tree, and used zk-proofs of the peer node. 
+  * from all  that takes a serde_json::Value struct containing the blockchain and merkle tree of the peer node. 
   * This function will stores the data in the peer_ledger_state field of the client's ValidatorNode struct for majority consensus 
   * determination once the listening period has ended.
   * @dev As responses come in, the notify_all_ledgers_received field of the ValidatorNode struct is notified to check if all
@@ -154,10 +152,11 @@ pub async fn adopt_network_state(validator_node: ValidatorNode) {
  }
 
  /**
-  * @notice adopt_majority() determines the majority state of the collected peer_ledger_states and update the local blockchain, 
-  * merkle tree, and used zk proofs of the validator node to reflect the majority state of the network. 
+  * @notice adopt_majority() determines the majority state of the collected peer_ledger_states and update the local blockchain 
+  * and merkle tree of the validator node to reflect the majority state of the network. 
   * @dev The majority is determine by hashing the blockchain and merkle tree data of each peer_ledger_state and counting the 
   * number of occurences of each hash using a hash map. The hash with the most occurences is considered the majority state.
+  * @dev Peer states whose blockchain does not replay to the UTXO set commitments it records are left out of the count.
   */
   async fn adopt_majority(validator_node: ValidatorNode){
     println!("\nDetermining majority network state...");
@@ -166,8 +165,22 @@ pub async fn adopt_network_state(validator_node: ValidatorNode) {
     let peer_ledger_states: Arc<Mutex<Vec<PeerLedgerResponse>>> = validator_node.peer_ledger_states.clone();
     let peer_ledger_states_guard = peer_ledger_states.lock().await;
 
+    // Keep the peer_ledger_states whose blockchain replays to the UTXO set commitments it records, with the rebuilt UTXO set
+    let mut utxo_sets: Vec<UTXOSet> = Vec::new();
+    let mut consistent_states: Vec<PeerLedgerResponse> = Vec::new();
+    for peer_ledger_state in peer_ledger_states_guard.iter() {
+        match utxo_commitment::replay_chain(&peer_ledger_state.blockchain) {
+            Ok(utxo_set) => {
+                utxo_sets.push(utxo_set);
+                consistent_states.push(peer_ledger_state.clone());
+            },
+            Err(e) => { eprintln!("Ignoring peer ledger state: {}", e); }
+        }
+    }
+    drop(peer_ledger_states_guard);
+
     // If there are no peer_ledger_states to adopt, return (maintaining the empty local state)
-    if peer_ledger_states_guard.is_empty() { 
+    if consistent_states.is_empty() { 
         println!("No peer ledger states to adopt..."); 
         return; 
     }
@@ -176,7 +189,7 @@ pub async fn adopt_network_state(validator_node: ValidatorNode) {
     let mut ledger_hash_map: HashMap<Vec<u8>, u32> = HashMap::new();
 
     // Iterate through each peer_ledger_state and hash the blockchain and merkle tree data
-    for peer_ledger_state in peer_ledger_states_guard.iter() {
+    for peer_ledger_state in consistent_states.iter() {
 
         // hash the entire PeerLedgerResponse struct with a new SHA256 hasher
         let mut hasher = Sha256::new();
@@ -195,11 +208,11 @@ pub async fn adopt_network_state(validator_node: ValidatorNode) {
         .unwrap();  // Assuming there will be at least one entry
 
     // Find the peer_ledger_state with the majority hash
-    let majority_peer_ledger_state = ledger_hash_map.iter()
+    let majority_index: usize = ledger_hash_map.iter()
         .max_by_key(|entry| entry.1)
         .and_then(|(hash, _)| {
-            peer_ledger_states_guard.iter()
-                .find(|state| {
+            consistent_states.iter()
+                .position(|state| {
 
                     // hash the entire state
                     let mut hasher = Sha256::new();
@@ -207,25 +220,21 @@ pub async fn adopt_network_state(validator_node: ValidatorNode) {
                     hasher.finalize().to_vec() == *hash
                 })
         })
-        .unwrap_or_else(|| {
-            // Default to the first state if no majority is found (ie no consensus)
-            peer_ledger_states_guard.first().expect("There must be at least one state")
-        });
+        .unwrap_or(0); // Default to the first state if no majority is found (ie no consensus)
+    let majority_peer_ledger_state: &PeerLedgerResponse = &consistent_states[majority_index];
 
-    // lock the blockchain, merkle tree, and used zk proofs map
-    let mut blockchain_guard = validator_node.blockchain.lock().await;
+    // lock the merkle tree, UTXO set and blockchain
     let mut merkle_tree_guard = validator_node.merkle_tree.lock().await;
-    let mut used_zk_proofs_guard = validator_node.used_zk_proofs.lock().await;
+    let mut utxo_set_guard = validator_node.utxo_set.lock().await;
+    let mut blockchain_guard = validator_node.blockchain.lock().await;
 
-    // Update the local blockchain state to reflect the majority state
-    blockchain_guard.chain = majority_peer_ledger_state.blockchain.clone();
+    // Update the local blockchain and UTXO set to reflect the majority state
+    blockchain_guard.adopt_chain(majority_peer_ledger_state.blockchain.clone());
+    *utxo_set_guard = utxo_sets.swap_remove(majority_index);
 
     // update the local merkle tree state to reflect the network majority
     merkle_tree_guard.accounts_vec = majority_peer_ledger_state.accounts_vec.clone();
     merkle_tree_guard.accounts_map = majority_peer_ledger_state.accounts_map.clone();
-
-    // update the local used zk proofs map to reflect the network majority
-    *used_zk_proofs_guard = majority_peer_ledger_state.used_zk_proofs.clone();
         
     println!("\n\n--- Adopted majority network state ---\n\n");   
 }
@@ -289,62 +298,6 @@ where
 }
 
 
-/**
- * @notice The serialize_used_zk_proofs_map() function is a custom serialization function that serializes the used_zk_proofs
- * field of the PeerLedgerResponse struct. The used_zk_proofs field is a HashMap with keys of type Vec<u8> and values of type
- * Vec<String>. The keys are base64 encoded before serialization to JSON to ensure that the keys are valid JSON strings.
- */
-fn serialize_used_zk_proofs_map<S>(map: &HashMap<Vec<u8>, Vec<String>>, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    let mut map_ser = serializer.serialize_map(Some(map.len()))?;
-    for (key, value) in map {
-        map_ser.serialize_entry(&encode(key), value)?;
-    }
-    map_ser.end()
-}
-
-/**
- * @notice The deserialize_used_zk_proofs_map() function is a custom deserialization function that deserializes the used_zk_proofs
- * field of the PeerLedgerResponse struct. The used_zk_proofs field is a HashMap with keys of type Vec<u8> and values of type
- * Vec<String>. The keys are base64 encoded and must be decoded before being inserted into the HashMap.
- */
-fn deserialize_used_zk_proofs_map<'de, D>(deserializer: D) -> Result<HashMap<Vec<u8>, Vec<String>>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-
-    // Custom visitor for deserializing the used zk proofs map
-    struct UsedZkProofsVisitor;
-
-    // Implement the visitor trait for the custom visitor
-    impl<'de> Visitor<'de> for UsedZkProofsVisitor {
-        type Value = HashMap<Vec<u8>, Vec<String>>;
-
-        // error type for the visitor
-        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("a map of base64 strings to list of strings")
-        }
-
-        // Deserialize the map
-        fn visit_map<M>(self, mut access: M) -> Result<HashMap<Vec<u8>, Vec<String>>, M::Error>
-        where
-            M: MapAccess<'de>,
-        {
-            let mut map = HashMap::new();
-            while let Some((key, value)) = access.next_entry::<String, Vec<String>>()? {
-                map.insert(decode(&key).map_err(de::Error::custom)?, value);
-            }
-            return Ok(map);
-        }
-    }
-
-    // call the visitor to deserialize the map
-    deserializer.deserialize_map(UsedZkProofsVisitor)
-}
-
-
 #[cfg(test)]
 mod tests {
     use super::*;  // Import the necessary structs and functions from the parent module.
@@ -362,8 +315,10 @@ mod tests {
                     recipient: vec![4, 5, 6],
                     recipient_balance: 450,
                     amount: 50,
+                    fee: 1,
                     time: 1633046450,
                     sender_nonce: 1,
+                    validator_rewards: vec![(vec![7, 7, 7], 1)],
                     hash: vec![7, 8, 9],
                 },
             ],
@@ -373,6 +328,7 @@ mod tests {
                     public_key_hash: vec![4, 5, 6],
                     balance: 1000,
                     nonce: 0,
+                    schnorr_commitment: vec![],
                 },
             ],
             accounts_map: {
@@ -380,11 +336,7 @@ mod tests {
                 map.insert(vec![1, 2, 3], 1000u64);
                 map
             },
-            used_zk_proofs: {
-                let mut map = HashMap::new();
-                map.insert(vec![1, 2, 3], vec!["proof1".to_string(), "proof2".to_string()]);
-                map
-            },
+            chain_id: "devnet-00000000".to_string(),
         };
 
         // Serialize the response
@@ -402,15 +354,12 @@ mod tests {
 
     #[test]
     fn test_peer_ledger_response_serialization() {
-        let mut used_zk_proofs = HashMap::new();
-        used_zk_proofs.insert(vec![1, 2, 3], vec![String::from("example_proof")]);
-
         let response = PeerLedgerResponse {
             action: String::from("PeerLedgerResponse"),
             blockchain: vec![],
             accounts_vec: vec![],
             accounts_map: HashMap::new(),
-            used_zk_proofs,
+            chain_id: blockchain::chain_id().to_string(),
         };
 
         let serialized = serde_json::to_string(&response).unwrap();
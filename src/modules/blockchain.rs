@@ -1,9 +1,8 @@
 use sha2::{Sha256, Digest};
 
-use std::time::{SystemTime, UNIX_EPOCH};
 use std::collections::{HashMap, VecDeque};
 use std::fs::File;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
 use serde::{Serialize, Deserialize};
 
@@ -11,7 +10,12 @@ use tokio::sync::{Mutex, MutexGuard};
 
 
 use crate::modules::validation::ValidatorNode;
-use crate::modules::utxo::{UTXOTransaction, CoinbaseTransaction};
+use crate::modules::utxo::{UTXOTransaction, CoinbaseTransaction, OutPoint, TxOutput, UTXOSet, UtxoUndo};
+use crate::modules::merkle_tree::{MerkleTree, AccountUndo};
+use crate::modules::constants::{DEFAULT_NETWORK_NAME, DEFAULT_GENESIS_TIME, DEFAULT_ADDRESS_PREFIX};
+use crate::modules::network;
+use crate::modules::address;
+use crate::modules::encoding::{self, Encode, Encoder, BLOCK_TAG, BLOCK_CONTENTS_TAG, BLOCKCHAIN_TAG};
 
 /**
  * @notice blockchain.rs contains the structs and methods for creating and manipulating blocks in the blockchain.
@@ -25,11 +29,16 @@ use crate::modules::utxo::{UTXOTransaction, CoinbaseTransaction};
   * @dev The Block enum is used to store the data of the block and differentiate between the different types of blocks.
   * @dev All addresses are stored as UTF-8 encoded byte vectors.
   * @param Genesis - a block that contains only the timestamp of the block creation.
-  * @param Transaction - a block that contains the data of a single transaction between two users.
+  * @param Transaction - a block that contains the data of a single transaction between two users, the fee paid 
+  * and how that fee was distributed among the validator set.
   * @param NewAccount - a block that contains the data of a new account creation
   * @param Faucet - a block that contains the data of a faucet transaction.
-  * @param UTXOTransaction - a block that contains a UTXO-based transaction
+  * @param UTXOTransaction - a block that contains a UTXO-based transaction and the coinbase paying its fee to validators
+  * along with the commitment to the UTXO set once both were applied (see utxo_commitment.rs)
   * @param Coinbase - a block that creates new tokens (mining reward)
+  * @param AccountToUtxo - a block that burns an amount from an account and mints a UTXO of it at the outpoint (see 
+  * bridge.rs), along with the fee paid, how it was distributed among the validator set and the commitment
+  * to the UTXO set once the UTXO was minted
 */
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)] 
 pub enum Block {
@@ -42,8 +51,10 @@ pub enum Block {
         recipient: Vec<u8>, 
         recipient_balance: u64,
         amount: u64, 
+        fee: u64,
         time : u64, 
         sender_nonce: u64, 
+        validator_rewards: Vec<(Vec<u8>, u64)>,
         hash: Vec<u8>
     },
     NewAccount { 
@@ -61,15 +72,113 @@ pub enum Block {
     UTXOTransaction {
         transaction: UTXOTransaction,
         block_height: u64,
+        coinbase: CoinbaseTransaction,
+        utxo_commitment: Vec<u8>,
         hash: Vec<u8>
     },
     Coinbase {
         transaction: CoinbaseTransaction,
         hash: Vec<u8>
+    },
+    AccountToUtxo {
+        sender: Vec<u8>,
+        sender_balance: u64,
+        sender_nonce: u64,
+        outpoint: OutPoint,
+        output: TxOutput,
+        fee: u64,
+        time: u64,
+        validator_rewards: Vec<(Vec<u8>, u64)>,
+        utxo_commitment: Vec<u8>,
+        hash: Vec<u8>
     }
 }
 
 
+/**
+ * @notice GenesisConfig holds the parameters the genesis block of a network is built from. It is read once from the 
+ * optional "genesis" section of accepted_ports.json, so every node of a network starts from the same genesis block.
+ * Networks that run side by side (e.g. devnet, staging and demo) use a different name or time, which gives them a 
+ * different chain id.
+ * @param network - the name of the network, used as the readable prefix of the chain id
+ * @param time - the timestamp recorded in the genesis block
+ * @param address_prefix - the human readable prefix of addresses on the network (see address.rs)
+ * @param validators - the validator set of the network, only votes and heartbeats signed by their node keys are accepted
+ */
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GenesisConfig {
+    pub network: String,
+    pub time: u64,
+    #[serde(default = "default_address_prefix")]
+    pub address_prefix: String,
+    #[serde(default)]
+    pub validators: Vec<ValidatorConfig>,
+}
+
+/**
+ * @notice ValidatorConfig is a member of the validator set of a network.
+ * @param node_public_key - the hex encoded public key of the node key the validator signs its votes and heartbeats with
+ * @param reward_address - the public key of the account the validator collects its share of fees with
+ */
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ValidatorConfig {
+    pub node_public_key: String,
+    pub reward_address: String,
+}
+
+impl Default for GenesisConfig {
+    fn default() -> Self {
+        GenesisConfig { network: DEFAULT_NETWORK_NAME.to_string(), time: DEFAULT_GENESIS_TIME, address_prefix: default_address_prefix(), validators: Vec::new() }
+    }
+}
+
+// address prefix of networks whose genesis section does not set one
+fn default_address_prefix() -> String {
+    DEFAULT_ADDRESS_PREFIX.to_string()
+}
+
+impl GenesisConfig {
+
+    // Builds the genesis block of the network
+    pub fn genesis_block(&self) -> Block {
+        Block::Genesis { time: self.time }
+    }
+
+    // Derives the chain id from the network name, the hash of the genesis block and the validator set, e.g. "devnet-9f86d081"
+    pub fn chain_id(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(b"miniBlockChain/chain-id");
+        hasher.update(self.network.as_bytes());
+        hasher.update(encoding::canonical_bytes(BLOCK_TAG, &self.genesis_block()));
+        for field in self.validators.iter().flat_map(|validator| [&validator.node_public_key, &validator.reward_address]) {
+            hasher.update((field.len() as u64).to_le_bytes());
+            hasher.update(field.as_bytes());
+        }
+        format!("{}-{}", self.network, hex::encode(&hasher.finalize()[..4]))
+    }
+}
+
+// genesis parameters and chain id of the network this process belongs to, loaded on first use
+static GENESIS_CONFIG: OnceLock<GenesisConfig> = OnceLock::new();
+static CHAIN_ID: OnceLock<String> = OnceLock::new();
+
+/**
+ * @notice genesis_config() returns the genesis parameters of the network, loading them from accepted_ports.json the 
+ * first time it is called (see network::load_genesis_config()).
+ */
+pub fn genesis_config() -> &'static GenesisConfig {
+    GENESIS_CONFIG.get_or_init(network::load_genesis_config)
+}
+
+/**
+ * @notice chain_id() returns the id of the network this process belongs to. It is part of every signed message and 
+ * of every peer message, and validators reject anything carrying a different chain id.
+ */
+pub fn chain_id() -> &'static str {
+    CHAIN_ID.get_or_init(|| genesis_config().chain_id())
+}
+
+
 /**
  * @notice the Blockchain struct links Blocks in a linked list.
  * @param chain - a vector of Blocks that have been added to the blockchain.
@@ -79,12 +188,26 @@ pub enum Block {
  * @param joint_transactions_map - a hashmap that stores transactions that have not yet been added to the blockchain yet.
  * The keys are the addresses of the senders and the values are all transaction requests that have been made by that sender.
  * These transactions will be sorted in terms of lowest to highest nonce and validated processed in that order.
+ * @param undo_records - the undo record of each block applied by this node, indexed by block height. Blocks adopted from
+ * peers have none, so only blocks applied locally can be reverted (see revert_block()).
 */
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockChain {
     pub chain: Vec<Block>,                                         
     pending_request_queue: VecDeque<Vec<u8>>,          // queue of public keys
     joint_request_map: HashMap<Vec<u8>, Vec<Block>>, // map of public keys to transactions
+    undo_records: HashMap<u64, BlockUndo>,           // map of block heights to undo records
+}
+
+/**
+ * @notice BlockUndo holds what a block changed in the ledger state: the prior state of every outpoint of the UTXO set 
+ * and of every account of the merkle tree it changed, spent UTXOs and previous balances and nonces included. It is 
+ * stored alongside the block when the block is applied, and restores the exact prior state when it is reverted.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlockUndo {
+    pub utxos: UtxoUndo,
+    pub accounts: AccountUndo,
 }
 
 /**
@@ -102,6 +225,7 @@ impl BlockChain {
             chain: Vec::new(),
             pending_request_queue: VecDeque::new(),
             joint_request_map: HashMap::new(),
+            undo_records: HashMap::new(),
         };
 
         // Create a genesis block and return the blockchain
@@ -110,13 +234,11 @@ impl BlockChain {
 
     /**
      * @notice create_genesis_block() is a method that creates the first block in the blockchain.
-     * @dev the genesis block contains only the timestamp of the block creation.
+     * @dev the genesis block contains only the genesis time of the network (see GenesisConfig), so that all 
+     * nodes of a network share it.
      */
     fn create_genesis_block(&mut self) {
-
-        let time: u64  = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        let genesis_block = Block::Genesis {time: time};
-        self.chain.push(genesis_block);
+        self.chain.push(genesis_config().genesis_block());
     }
 
     /**
@@ -129,7 +251,7 @@ impl BlockChain {
 
         // Retrieve and clone relavant address from the request
         let address: Vec<u8> = match &new_block_request {
-            Block::Transaction { sender, .. } => sender,
+            Block::Transaction { sender, .. } | Block::AccountToUtxo { sender, .. } => sender,
             Block::NewAccount { address, .. } => address,
             Block::Faucet { address, .. } => address,
             Block::UTXOTransaction { transaction, .. } => {
@@ -165,7 +287,7 @@ impl BlockChain {
         
         // get address of sender
         let address: Vec<u8> = match &new_block {
-            Block::Transaction { sender, .. } => sender,
+            Block::Transaction { sender, .. } | Block::AccountToUtxo { sender, .. } => sender,
             Block::NewAccount { address, .. } => address,
             Block::Faucet { address, .. } => address,
             Block::UTXOTransaction { transaction, .. } => {
@@ -201,109 +323,117 @@ impl BlockChain {
         }
     }
 
-    // Sets the hash of a block based on its data
+    // Pushes a block applied by this node to the blockchain, storing the undo record of its changes alongside it
+    pub fn push_block_with_undo(&mut self, new_block: Block, undo: BlockUndo) {
+        self.undo_records.insert(self.chain.len() as u64, undo);
+        self.push_block_to_chain(new_block);
+    }
+
+    /**
+     * @notice revert_block() removes the last block from the blockchain and undoes its changes to the merkle tree and 
+     * UTXO set using the undo record stored alongside it, restoring the exact state from before the block was applied.
+     * Blocks must be reverted from the tip down, the returned block is the one reverted.
+     * @dev The genesis block and blocks adopted from peers have no undo record and cannot be reverted. Lock the merkle 
+     * tree, then the UTXO set, then the blockchain before calling this.
+     */
+    pub fn revert_block(&mut self, merkle_tree: &mut MerkleTree, utxo_set: &mut UTXOSet) -> Result<Block, String> {
+        let height: u64 = self.chain.len() as u64 - 1;
+        let undo: BlockUndo = self.undo_records.remove(&height)
+            .ok_or(format!("Block {} has no undo record and cannot be reverted", height))?;
+
+        // undo the changes to the UTXO set and accounts, then drop the block
+        utxo_set.undo(&undo.utxos);
+        merkle_tree.undo(&undo.accounts);
+        Ok(self.chain.pop().unwrap())
+    }
+
+    // Replaces the blockchain with one adopted from peers, whose blocks have no undo records on this node
+    pub fn adopt_chain(&mut self, chain: Vec<Block>) {
+        self.chain = chain;
+        self.undo_records.clear();
+    }
+
+    // Sets the hash of a block to the hash of the canonical encoding of its contents (see Block::encode_contents())
     fn set_block_hash(&mut self, block: &mut Block) {
+        let hash: Vec<u8> = block.compute_hash();
 
-        let mut hasher = Sha256::new(); // new SHA256 hasher
-    
-        match block { // Contribute block to hasher based on its type
-            Block::Genesis { time, .. } => {
-                hasher.update(&time.to_le_bytes());
-            }
-            Block::Transaction { sender, sender_balance, recipient, recipient_balance, amount, time, sender_nonce, .. } => {
-                hasher.update(sender);
-                hasher.update(&sender_balance.to_le_bytes());
-                hasher.update(recipient);
-                hasher.update(&recipient_balance.to_le_bytes());
-                hasher.update(&amount.to_le_bytes());
-                hasher.update(&time.to_le_bytes());
-                hasher.update(&sender_nonce.to_le_bytes());
-            }
-            Block::NewAccount { address, account_balance, time, .. } => {
-                hasher.update(address);
-                hasher.update(&account_balance.to_le_bytes());
-                hasher.update(&time.to_le_bytes());
-            }
-            Block::Faucet { address, account_balance, time, .. } => {
-                hasher.update(address);
-                hasher.update(&account_balance.to_le_bytes());
-                hasher.update(&time.to_le_bytes());
-            }
-            Block::UTXOTransaction { transaction, block_height, .. } => {
-                hasher.update(&transaction.hash);
-                hasher.update(&block_height.to_le_bytes());
-            }
-            Block::Coinbase { transaction, .. } => {
-                hasher.update(&transaction.hash);
-            }
-        }
-        
-        // Finalize the hash and return it as Vec<u8>
-        let hash = hasher.finalize().to_vec();
-        
         // Set the hash in the block
         match block {
             Block::Transaction { hash: block_hash, .. } | 
             Block::NewAccount { hash: block_hash, .. } | 
             Block::Faucet { hash: block_hash, .. } |
             Block::UTXOTransaction { hash: block_hash, .. } |
-            Block::Coinbase { hash: block_hash, .. } => {
-                *block_hash = hash.clone();
+            Block::Coinbase { hash: block_hash, .. } |
+            Block::AccountToUtxo { hash: block_hash, .. } => {
+                *block_hash = hash;
             }
             _ => (),
         }    
     }
     
-    /// Hashes the entire blockchain using SHA-256.
+    /// Hashes the entire blockchain: the SHA-256 hash of the canonical encoding of the list of blocks, hashes included.
     pub fn hash_blockchain(&self) -> Vec<u8> {
+        let mut encoder = Encoder::top_level(BLOCKCHAIN_TAG);
+        encoder.list(&self.chain);
+        encoder.hash().to_vec()
+    }
+}
 
-        // Create a new SHA256 hasher
-        let mut hasher = Sha256::new();
-
-        // Push each block's data to the hasher
-        for block in &self.chain {
+impl Block {
 
-            // Contribute block to hasher based on its type
-            match block {
-                Block::Genesis { time } => {
-                    hasher.update(&time.to_le_bytes());
-                }
-                Block::Transaction { sender, sender_balance, recipient, recipient_balance, amount, time, sender_nonce, hash  } => {
-                    hasher.update(sender);
-                    hasher.update(&sender_balance.to_be_bytes());
-                    hasher.update(recipient);
-                    hasher.update(&recipient_balance.to_be_bytes());
-                    hasher.update(&amount.to_be_bytes());
-                    hasher.update(&time.to_le_bytes());
-                    hasher.update(&sender_nonce.to_le_bytes());
-                    hasher.update(hash);
-                }
-                Block::NewAccount { address, account_balance, time, hash } => {
-                    hasher.update(address);
-                    hasher.update(&account_balance.to_le_bytes());
-                    hasher.update(&time.to_le_bytes());
-                    hasher.update(hash);
-                }
-                Block::Faucet { address, account_balance, time, hash } => {
-                    hasher.update(address);
-                    hasher.update(&account_balance.to_le_bytes());
-                    hasher.update(&time.to_le_bytes());
-                    hasher.update(hash);
-                }
-                Block::UTXOTransaction { transaction, block_height, hash } => {
-                    hasher.update(&transaction.hash);
-                    hasher.update(&block_height.to_le_bytes());
-                    hasher.update(hash);
-                }
-                Block::Coinbase { transaction, hash } => {
-                    hasher.update(&transaction.hash);
-                    hasher.update(hash);
-                }
+    /**
+     * @notice encode_contents() writes the canonical encoding (see encoding.rs) of everything in the block but its hash:
+     * the index of the variant followed by its fields in declaration order.
+     */
+    pub fn encode_contents(&self, encoder: &mut Encoder) {
+        match self {
+            Block::Genesis { time } => {
+                encoder.u8(0).u64(*time);
+            }
+            Block::Transaction { sender, sender_balance, recipient, recipient_balance, amount, fee, time, sender_nonce, validator_rewards, .. } => {
+                encoder.u8(1).bytes(sender).u64(*sender_balance).bytes(recipient).u64(*recipient_balance)
+                    .u64(*amount).u64(*fee).u64(*time).u64(*sender_nonce).list(validator_rewards);
+            }
+            Block::NewAccount { address, account_balance, time, .. } => {
+                encoder.u8(2).bytes(address).u64(*account_balance).u64(*time);
+            }
+            Block::Faucet { address, account_balance, time, .. } => {
+                encoder.u8(3).bytes(address).u64(*account_balance).u64(*time);
+            }
+            Block::UTXOTransaction { transaction, block_height, coinbase, utxo_commitment, .. } => {
+                encoder.u8(4).value(transaction).u64(*block_height).value(coinbase).bytes(utxo_commitment);
+            }
+            Block::Coinbase { transaction, .. } => {
+                encoder.u8(5).value(transaction);
+            }
+            Block::AccountToUtxo { sender, sender_balance, sender_nonce, outpoint, output, fee, time, validator_rewards, utxo_commitment, .. } => {
+                encoder.u8(6).bytes(sender).u64(*sender_balance).u64(*sender_nonce).value(outpoint).value(output)
+                    .u64(*fee).u64(*time).list(validator_rewards).bytes(utxo_commitment);
             }
         }
+    }
 
-        // Finalize the hash and return it
-        hasher.finalize().to_vec()
+    // Returns the hash of the block, computed over the canonical encoding of its contents
+    pub fn compute_hash(&self) -> Vec<u8> {
+        let mut encoder = Encoder::top_level(BLOCK_CONTENTS_TAG);
+        self.encode_contents(&mut encoder);
+        encoder.hash().to_vec()
+    }
+}
+
+// A block is encoded as its contents followed by its hash (the genesis block has none)
+impl Encode for Block {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.encode_contents(encoder);
+        match self {
+            Block::Transaction { hash, .. } |
+            Block::NewAccount { hash, .. } |
+            Block::Faucet { hash, .. } |
+            Block::UTXOTransaction { hash, .. } |
+            Block::Coinbase { hash, .. } |
+            Block::AccountToUtxo { hash, .. } => { encoder.bytes(hash); }
+            Block::Genesis { .. } => (),
+        }
     }
 }
 
@@ -323,8 +453,10 @@ pub enum BlockJson {
         recipient: String, 
         recipient_balance: u64,
         amount: u64, 
+        fee: u64,
         time : u64, 
         sender_nonce: u64, 
+        validator_rewards: Vec<(String, u64)>,
         hash: String
     },
     NewAccount { 
@@ -342,11 +474,25 @@ pub enum BlockJson {
     UTXOTransaction {
         transaction: UTXOTransaction,
         block_height: u64,
+        coinbase: CoinbaseTransaction,
+        utxo_commitment: String,
         hash: String
     },
     Coinbase {
         transaction: CoinbaseTransaction,
         hash: String
+    },
+    AccountToUtxo {
+        sender: String,
+        sender_balance: u64,
+        sender_nonce: u64,
+        outpoint: OutPoint,
+        output: TxOutput,
+        fee: u64,
+        time: u64,
+        validator_rewards: Vec<(String, u64)>,
+        utxo_commitment: String,
+        hash: String
     }
 }
 
@@ -406,7 +552,7 @@ async fn convert_vec_block_to_vec_blockjson(vec_block: Vec<Block>) -> Vec<BlockJ
  * @notice convert_block_to_blockjson() is an asynchronous function that converts a Block enum to a BlockJson enum.
  * @dev This function is used to convert the blockchain to JSON for saving and sending to other nodes.
  */
-async fn convert_block_to_blockjson(block: Block) -> BlockJson {
+pub async fn convert_block_to_blockjson(block: Block) -> BlockJson {
 
     let block_json: BlockJson;
 
@@ -416,15 +562,20 @@ async fn convert_block_to_blockjson(block: Block) -> BlockJson {
             // package genesis block data into BlockJson
             block_json = BlockJson::Genesis { time };
         },
-        Block::Transaction { sender, sender_balance, recipient, recipient_balance, amount, time, sender_nonce, hash } => {
+        Block::Transaction { sender, sender_balance, recipient, recipient_balance, amount, fee, time, sender_nonce, validator_rewards, hash } => {
 
-            // decode sender and recipient to strings
+            // decode sender, recipient and validator addresses to strings
             let sender = String::from_utf8(sender).unwrap();
             let recipient = String::from_utf8(recipient).unwrap();
+            let validator_rewards: Vec<(String, u64)> = validator_rewards.into_iter()
+                .map(|(validator, reward)| (String::from_utf8(validator).unwrap(), reward))
+                .collect();
             let hash = hex::encode(hash);
 
             // package transaction block data into BlockJson
-            block_json = BlockJson::Transaction { sender, sender_balance, recipient, recipient_balance, amount, time, sender_nonce, hash };
+            block_json = BlockJson::Transaction { 
+                sender, sender_balance, recipient, recipient_balance, amount, fee, time, sender_nonce, validator_rewards, hash 
+            };
         },
         Block::NewAccount { address, account_balance, time, hash } => {
 
@@ -442,11 +593,12 @@ async fn convert_block_to_blockjson(block: Block) -> BlockJson {
             // package faucet block data into BlockJson
             block_json = BlockJson::Faucet { address, account_balance, time, hash };
         },
-        Block::UTXOTransaction { transaction, block_height, hash } => {
+        Block::UTXOTransaction { transaction, block_height, coinbase, utxo_commitment, hash } => {
+            let utxo_commitment = hex::encode(utxo_commitment);
             let hash = hex::encode(hash);
 
             // package UTXO transaction block data into BlockJson
-            block_json = BlockJson::UTXOTransaction { transaction, block_height, hash };
+            block_json = BlockJson::UTXOTransaction { transaction, block_height, coinbase, utxo_commitment, hash };
         },
         Block::Coinbase { transaction, hash } => {
             let hash = hex::encode(hash);
@@ -454,6 +606,17 @@ async fn convert_block_to_blockjson(block: Block) -> BlockJson {
             // package coinbase block data into BlockJson
             block_json = BlockJson::Coinbase { transaction, hash };
         },
+        Block::AccountToUtxo { sender, sender_balance, sender_nonce, outpoint, output, fee, time, validator_rewards, utxo_commitment, hash } => {
+            let sender = String::from_utf8(sender).unwrap();
+            let validator_rewards: Vec<(String, u64)> = validator_rewards.into_iter()
+                .map(|(validator, reward)| (String::from_utf8(validator).unwrap(), reward))
+                .collect();
+            let utxo_commitment = hex::encode(utxo_commitment);
+            let hash = hex::encode(hash);
+
+            // package account to UTXO block data into BlockJson
+            block_json = BlockJson::AccountToUtxo { sender, sender_balance, sender_nonce, outpoint, output, fee, time, validator_rewards, utxo_commitment, hash };
+        },
     }
 
     block_json
@@ -483,7 +646,7 @@ pub async fn print_chain(blockchain: Arc<Mutex<BlockChain>>) {
                     i, address, account_balance, time, hash_hex
                 );
             },
-            Block::Transaction {sender, sender_balance, recipient, recipient_balance, amount, time, sender_nonce, hash} => {
+            Block::Transaction {sender, sender_balance, recipient, recipient_balance, amount, fee, time, sender_nonce, validator_rewards, hash} => {
 
                 // Directly use sender and recipient as they're already UTF-8 encoded hex strings
                 let hash_hex = hex::encode(hash); // Assuming hash is a Vec<u8> needing encoding
@@ -491,8 +654,13 @@ pub async fn print_chain(blockchain: Arc<Mutex<BlockChain>>) {
                 let recipient = String::from_utf8(recipient.clone()).unwrap();
 
                 println!(
-                    "\nBlock {}: \n\tSender: {}\n\tSender Balance: {}\n\tSender Nonce: {}\n\tRecipient: {}\n\tRecipient Balance: {}\n\tAmount: {}\n\tTime: {:}\n\tHash: {}", 
-                    i, sender, sender_balance, sender_nonce, recipient, recipient_balance, amount, time, hash_hex);
+                    "\nBlock {}: \n\tSender: {}\n\tSender Balance: {}\n\tSender Nonce: {}\n\tRecipient: {}\n\tRecipient Balance: {}\n\tAmount: {}\n\tFee: {}\n\tTime: {:}\n\tHash: {}", 
+                    i, sender, sender_balance, sender_nonce, recipient, recipient_balance, amount, fee, time, hash_hex);
+
+                // print how the fee was distributed among validators
+                for (validator, reward) in validator_rewards.iter() {
+                    println!("\tValidator Reward: {} -> {}", reward, String::from_utf8_lossy(validator));
+                }
             },
             Block::Genesis { time } => {
                 println!("\nBlock {}: \n\tGenesis Block\n\tTime: {:?}", i, time);
@@ -507,11 +675,12 @@ pub async fn print_chain(blockchain: Arc<Mutex<BlockChain>>) {
                     i, address, account_balance, time, hash_hex
                 );
             },
-            Block::UTXOTransaction { transaction, block_height, hash } => {
+            Block::UTXOTransaction { transaction, block_height, coinbase, utxo_commitment, hash } => {
                 let hash_hex = hex::encode(hash);
                 println!(
-                    "\nBlock {}: \n\tUTXO Transaction\n\tInputs: {}\n\tOutputs: {}\n\tTimestamp: {}\n\tBlock Height: {}\n\tHash: {}", 
-                    i, transaction.inputs.len(), transaction.outputs.len(), transaction.timestamp, block_height, hash_hex
+                    "\nBlock {}: \n\tUTXO Transaction\n\tInputs: {}\n\tOutputs: {}\n\tTimestamp: {}\n\tBlock Height: {}\n\tCoinbase Reward: {}\n\tUTXO Commitment: {}\n\tHash: {}", 
+                    i, transaction.inputs.len(), transaction.outputs.len(), transaction.timestamp, block_height, 
+                    coinbase.total_output_amount().unwrap_or_default(), hex::encode(utxo_commitment), hash_hex
                 );
             },
             Block::Coinbase { transaction, hash } => {
@@ -521,6 +690,18 @@ pub async fn print_chain(blockchain: Arc<Mutex<BlockChain>>) {
                     i, transaction.outputs.len(), transaction.block_height, transaction.timestamp, hash_hex
                 );
             },
+            Block::AccountToUtxo { sender, sender_balance, sender_nonce, outpoint, output, fee, time, validator_rewards, utxo_commitment, hash } => {
+                let hash_hex = hex::encode(hash);
+                let sender = String::from_utf8(sender.clone()).unwrap();
+                println!(
+                    "\nBlock {}: \n\tAccount To UTXO\n\tSender: {}\n\tSender Balance: {}\n\tSender Nonce: {}\n\tMinted: {} -> {} at {}:{}\n\tFee: {}\n\tTime: {}\n\tUTXO Commitment: {}\n\tHash: {}", 
+                    i, sender, sender_balance, sender_nonce, output.amount, address::display_recipient(&output.recipient), 
+                    hex::encode(&outpoint.txid), outpoint.vout, fee, time, hex::encode(utxo_commitment), hash_hex
+                );
+                for (validator, reward) in validator_rewards.iter() {
+                    println!("\tValidator Reward: {} -> {}", reward, String::from_utf8_lossy(validator));
+                }
+            },
         }
     }
 }
@@ -531,6 +712,7 @@ pub async fn print_chain(blockchain: Arc<Mutex<BlockChain>>) {
  */
 #[cfg(test)]mod tests {
     use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
 
     #[test]
     fn test_genesis_block_creation() {
@@ -543,6 +725,25 @@ pub async fn print_chain(blockchain: Arc<Mutex<BlockChain>>) {
         }
     }
 
+    #[test]
+    fn test_chain_id_derived_from_genesis() {
+        let devnet = GenesisConfig::default();
+        assert_eq!(devnet.chain_id(), devnet.clone().chain_id());
+        assert!(devnet.chain_id().starts_with("devnet-"));
+
+        // networks with a different name, genesis time or validator set have different chain ids
+        let staging = GenesisConfig { network: "staging".to_string(), ..devnet.clone() };
+        let later = GenesisConfig { time: devnet.time + 1, ..devnet.clone() };
+        let validator = ValidatorConfig { node_public_key: "02abcd".to_string(), reward_address: "02ef".to_string() };
+        let permissioned = GenesisConfig { validators: vec![validator], ..devnet.clone() };
+        assert_ne!(devnet.chain_id(), staging.chain_id());
+        assert_ne!(devnet.chain_id(), later.chain_id());
+        assert_ne!(devnet.chain_id(), permissioned.chain_id());
+
+        // every node builds the same genesis block
+        assert_eq!(BlockChain::new().chain[0], genesis_config().genesis_block());
+    }
+
     #[test]
     fn test_account_creation_block_addition() {
 
@@ -0,0 +1,105 @@
+use secp256k1::PublicKey;
+use std::str::FromStr;
+
+use crate::modules::encoding::{Encoder, BRIDGE_MINT_ID_TAG};
+use crate::modules::utxo::{UTXOTransaction, OutPoint};
+
+
+/**
+ * @notice bridge.rs moves value between the account model (balances in the MerkleTree) and the UTXO model (outputs in
+ * the UTXOSet). There are two transaction types:
+ *
+ *   - AccountToUtxo burns an amount (plus a fee) from an account balance and mints a UTXO of that amount. It is proven
+ *     like an account transfer, with a Schnorr proof bound to the sender's nonce (see zk_proof.rs). The minted output
+ *     sits at mint_outpoint() of the sender and nonce, which is unique because the nonce is incremented.
+ *   - A UTXO transaction with an output paid to an account recipient consumes its inputs and credits the account
+ *     instead of creating that output. The account recipient is signed over by every input like any other output:
+ *
+ *       ACCOUNT_TAG | hex encoded public key of the account (as stored in the merkle tree)
+ *
+ * Validators apply each of them under the locks of both the merkle tree and the UTXO set, checking everything before
+ * changing either, so an amount leaves one model exactly when it enters the other and the total supply is conserved.
+ */
+// first byte of the recipient of an output that credits an account
+pub const ACCOUNT_TAG: u8 = 0x41;
+
+/**
+ * @notice account_recipient() returns the recipient of an output that credits the account of a hex public key.
+ */
+pub fn account_recipient(public_key: &str) -> Result<Vec<u8>, String> {
+    PublicKey::from_str(public_key).map_err(|e| format!("Invalid account public key {}: {}", public_key, e))?;
+    Ok([&[ACCOUNT_TAG], public_key.to_lowercase().as_bytes()].concat())
+}
+
+/**
+ * @notice account_of_recipient() returns the merkle tree key of the account an output credits, if its recipient is an
+ * account recipient.
+ */
+pub fn account_of_recipient(recipient: &[u8]) -> Option<&[u8]> {
+    match recipient {
+        [ACCOUNT_TAG, account @ ..] => {
+            let public_key: &str = std::str::from_utf8(account).ok()?;
+            match PublicKey::from_str(public_key).is_ok() && public_key == public_key.to_lowercase() {
+                true => Some(account),
+                false => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/**
+ * @notice mint_outpoint() returns the outpoint of the UTXO minted by the AccountToUtxo transaction of an account at a
+ * nonce. Clients can compute it before the transaction is committed.
+ */
+pub fn mint_outpoint(sender: &[u8], nonce: u64) -> OutPoint {
+    let mut encoder = Encoder::top_level(BRIDGE_MINT_ID_TAG);
+    encoder.bytes(sender).u64(nonce);
+    OutPoint::new(encoder.hash().to_vec(), 0)
+}
+
+/**
+ * @notice account_credits() returns the total each account is credited by the outputs of a UTXO transaction, in order
+ * of first appearance. Fails if a total overflows.
+ */
+pub fn account_credits(transaction: &UTXOTransaction) -> Result<Vec<(Vec<u8>, u64)>, String> {
+    let mut credits: Vec<(Vec<u8>, u64)> = Vec::new();
+    for output in &transaction.outputs {
+        let Some(account) = account_of_recipient(&output.recipient) else { continue };
+        match credits.iter_mut().find(|(credited, _)| credited == account) {
+            Some((_, total)) => *total = total.checked_add(output.amount).ok_or("Account credit overflows".to_string())?,
+            None => credits.push((account.to_vec(), output.amount)),
+        }
+    }
+    Ok(credits)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::zk_proof;
+    use crate::modules::utxo::TxOutput;
+
+    #[test]
+    fn test_account_recipient() {
+        let (_, public_key) = zk_proof::generate_keypair().unwrap();
+        let recipient = account_recipient(&public_key.to_string()).unwrap();
+        assert_eq!(account_of_recipient(&recipient), Some(public_key.to_string().as_bytes()));
+        assert_eq!(account_of_recipient(&public_key.serialize()), None);
+        assert!(account_recipient("not a key").is_err());
+
+        // credits to the same account are summed, other outputs are left to the UTXO set
+        let transaction = UTXOTransaction::new(vec![], vec![
+            TxOutput::new(5, recipient.clone()), TxOutput::new(7, vec![1; 20]), TxOutput::new(3, recipient),
+        ], 0);
+        assert_eq!(account_credits(&transaction).unwrap(), vec![(public_key.to_string().into_bytes(), 8)]);
+    }
+
+    #[test]
+    fn test_mint_outpoints_are_unique() {
+        assert_ne!(mint_outpoint(b"alice", 0), mint_outpoint(b"alice", 1));
+        assert_ne!(mint_outpoint(b"alice", 0), mint_outpoint(b"bob", 0));
+        assert_eq!(mint_outpoint(b"alice", 0), mint_outpoint(b"alice", 0));
+    }
+}
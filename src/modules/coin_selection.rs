@@ -0,0 +1,275 @@
+use std::cmp::Reverse;
+use std::str::FromStr;
+
+use crate::modules::constants::{DUST_THRESHOLD, MIN_TRANSACTION_FEE, UTXO_FEE_RATE, BNB_MAX_TRIES};
+use crate::modules::encoding::{self, TRANSACTION_ID_TAG};
+use crate::modules::utxo::{OutPoint, UTXO, UTXOTransaction, INPUT_SIGNATURE_LENGTH};
+
+
+/**
+ * @notice coin_selection.rs picks the UTXOs a wallet spends to pay an amount. The fee of a UTXO transaction grows with
+ * its size (see estimate_fee()), so every strategy selects against a fee function of the number of inputs and outputs,
+ * and either leaves change of at least DUST_THRESHOLD to be paid back in a second output or adds the excess to the fee:
+ *
+ *   - LargestFirst spends the largest UTXOs until the amount and fee are covered, using as few inputs as possible.
+ *   - BranchAndBound searches for a set of UTXOs that pays the amount and fee without change (wasting less than the
+ *     change output would cost), and falls back to LargestFirst when there is none. Avoiding change keeps the UTXO set
+ *     small and does not reveal which output is the payment.
+ *   - Privacy never links UTXOs of different recipients (addresses) unless it has to: it spends the smallest group of
+ *     UTXOs paid to the same recipient that covers the payment, all of them, so that address is not linked again later.
+ *
+ * The send command of the CLI (see wallet::build_payment()) uses BranchAndBound unless it is given another strategy.
+ */
+/**
+ * @notice SelectionStrategy is the coin selection algorithm used by the wallet.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectionStrategy {
+    LargestFirst,
+    BranchAndBound,
+    Privacy,
+}
+
+impl FromStr for SelectionStrategy {
+    type Err = String;
+
+    fn from_str(strategy: &str) -> Result<Self, Self::Err> {
+        match strategy {
+            "largest-first" => Ok(SelectionStrategy::LargestFirst),
+            "bnb" | "branch-and-bound" => Ok(SelectionStrategy::BranchAndBound),
+            "privacy" => Ok(SelectionStrategy::Privacy),
+            _ => Err(format!("Unknown coin selection strategy {}, expected largest-first, bnb or privacy", strategy)),
+        }
+    }
+}
+
+/**
+ * @notice Selection is the result of coin selection.
+ * @param utxos - the UTXOs to spend
+ * @param fee - the fee paid by the transaction, including any excess too small to be paid back as change
+ * @param change - the amount paid back in a change output, 0 for none
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub utxos: Vec<(OutPoint, UTXO)>,
+    pub fee: u64,
+    pub change: u64,
+}
+
+/**
+ * @notice select_coins() selects UTXOs paying the target amount with the strategy. fee(inputs, outputs) returns the fee
+ * of a transaction with that many inputs and outputs, the outputs being the payment and possibly a change output.
+ */
+pub fn select_coins<F: Fn(usize, usize) -> u64>(utxos: &[(OutPoint, UTXO)], target: u64, strategy: SelectionStrategy, fee: F) -> Result<Selection, String> {
+    let selection: Option<Selection> = match strategy {
+        SelectionStrategy::LargestFirst => largest_first(utxos, target, &fee),
+        SelectionStrategy::BranchAndBound => branch_and_bound(utxos, target, &fee).or_else(|| largest_first(utxos, target, &fee)),
+        SelectionStrategy::Privacy => privacy(utxos, target, &fee),
+    };
+
+    selection.ok_or_else(|| {
+        let available: u128 = utxos.iter().map(|(_, utxo)| utxo.amount as u128).sum();
+        format!("Insufficient funds: {} available to pay {} plus the fee", available, target)
+    })
+}
+
+/**
+ * @notice estimate_fee() returns the fee of a transaction at UTXO_FEE_RATE, from the size of its canonical encoding once
+ * every unsigned input carries a single signature. It is never less than MIN_TRANSACTION_FEE.
+ */
+pub fn estimate_fee(transaction: &UTXOTransaction) -> u64 {
+    let mut signed: UTXOTransaction = transaction.clone();
+    for input in signed.inputs.iter_mut().filter(|input| input.signature.is_empty()) {
+        input.signature = "00".repeat(INPUT_SIGNATURE_LENGTH);
+    }
+    let size: u64 = encoding::canonical_bytes(TRANSACTION_ID_TAG, &signed).len() as u64;
+    (size * UTXO_FEE_RATE).div_ceil(1000).max(MIN_TRANSACTION_FEE)
+}
+
+// Completes a selection: pays back change if it is at least DUST_THRESHOLD, otherwise the excess goes to the fee
+fn finish<F: Fn(usize, usize) -> u64>(utxos: Vec<(OutPoint, UTXO)>, target: u64, fee: &F) -> Option<Selection> {
+    let total: u128 = utxos.iter().map(|(_, utxo)| utxo.amount as u128).sum();
+    let (with_change, without_change) = (fee(utxos.len(), 2) as u128, fee(utxos.len(), 1) as u128);
+
+    if total >= target as u128 + with_change + DUST_THRESHOLD as u128 {
+        let change: u64 = u64::try_from(total - target as u128 - with_change).ok()?;
+        Some(Selection { utxos, fee: with_change as u64, change })
+    } else if total >= target as u128 + without_change {
+        let fee: u64 = u64::try_from(total - target as u128).ok()?;
+        Some(Selection { utxos, fee, change: 0 })
+    } else {
+        None
+    }
+}
+
+// Spends the largest UTXOs first until the payment is covered
+fn largest_first<F: Fn(usize, usize) -> u64>(utxos: &[(OutPoint, UTXO)], target: u64, fee: &F) -> Option<Selection> {
+    let mut sorted: Vec<(OutPoint, UTXO)> = utxos.to_vec();
+    sorted.sort_by_key(|(_, utxo)| Reverse(utxo.amount));
+
+    (1..=sorted.len()).find_map(|count| finish(sorted[..count].to_vec(), target, fee))
+}
+
+/**
+ * @notice branch_and_bound() searches depth-first (largest UTXOs first) for the set of UTXOs paying the target and fee
+ * without change that wastes the least, where waste is the excess over the fee. Sets wasting more than a change output
+ * would cost (its fee plus DUST_THRESHOLD) are not changeless matches. Gives up after BNB_MAX_TRIES branches.
+ */
+fn branch_and_bound<F: Fn(usize, usize) -> u64>(utxos: &[(OutPoint, UTXO)], target: u64, fee: &F) -> Option<Selection> {
+    let mut sorted: Vec<(OutPoint, UTXO)> = utxos.to_vec();
+    sorted.sort_by_key(|(_, utxo)| Reverse(utxo.amount));
+
+    // amount left in the UTXOs from each index on, to cut branches that cannot reach the target
+    let mut remaining: Vec<u128> = vec![0; sorted.len() + 1];
+    for index in (0..sorted.len()).rev() {
+        remaining[index] = remaining[index + 1] + sorted[index].1.amount as u128;
+    }
+
+    let mut search = BranchAndBound { utxos: &sorted, remaining, target: target as u128, fee, tries: 0, best: None };
+    search.explore(0, &mut Vec::new(), 0);
+
+    let best: Vec<(OutPoint, UTXO)> = search.best?.1.into_iter().map(|index| sorted[index].clone()).collect();
+    finish(best, target, fee)
+}
+
+// State of the branch-and-bound search: the best match is kept as its waste and the indices of its UTXOs
+struct BranchAndBound<'a, F> {
+    utxos: &'a [(OutPoint, UTXO)],
+    remaining: Vec<u128>,
+    target: u128,
+    fee: &'a F,
+    tries: usize,
+    best: Option<(u128, Vec<usize>)>,
+}
+
+impl<F: Fn(usize, usize) -> u64> BranchAndBound<'_, F> {
+
+    fn explore(&mut self, index: usize, selected: &mut Vec<usize>, total: u128) {
+        self.tries += 1;
+        if self.tries > BNB_MAX_TRIES || self.best.as_ref().is_some_and(|(waste, _)| *waste == 0) { return; }
+
+        // once the payment is covered adding inputs only adds waste, so the branch ends here
+        if !selected.is_empty() {
+            let needed: u128 = self.target + (self.fee)(selected.len(), 1) as u128;
+            if total >= needed {
+                let cost_of_change: u128 = ((self.fee)(selected.len(), 2).saturating_sub((self.fee)(selected.len(), 1)) + DUST_THRESHOLD) as u128;
+                let waste: u128 = total - needed;
+                if waste < cost_of_change && self.best.as_ref().is_none_or(|(best_waste, _)| waste < *best_waste) {
+                    self.best = Some((waste, selected.clone()));
+                }
+                return;
+            }
+        }
+
+        // cut the branch if even spending every remaining UTXO cannot pay the target and fee
+        if index == self.utxos.len() || total + self.remaining[index] < self.target + (self.fee)(selected.len() + 1, 1) as u128 { return; }
+
+        // first spend the UTXO at the index, then skip it
+        selected.push(index);
+        self.explore(index + 1, selected, total + self.utxos[index].1.amount as u128);
+        selected.pop();
+        self.explore(index + 1, selected, total);
+    }
+}
+
+// Spends all UTXOs of the smallest recipient group covering the payment, or whole groups largest first if none does
+fn privacy<F: Fn(usize, usize) -> u64>(utxos: &[(OutPoint, UTXO)], target: u64, fee: &F) -> Option<Selection> {
+    let mut groups: Vec<Vec<(OutPoint, UTXO)>> = Vec::new();
+    for (outpoint, utxo) in utxos {
+        match groups.iter_mut().find(|group| group[0].1.recipient == utxo.recipient) {
+            Some(group) => group.push((outpoint.clone(), utxo.clone())),
+            None => groups.push(vec![(outpoint.clone(), utxo.clone())]),
+        }
+    }
+    let group_total = |group: &Vec<(OutPoint, UTXO)>| group.iter().map(|(_, utxo)| utxo.amount as u128).sum::<u128>();
+    groups.sort_by_key(group_total);
+
+    if let Some(selection) = groups.iter().find_map(|group| finish(group.clone(), target, fee)) {
+        return Some(selection);
+    }
+
+    let mut spent: Vec<(OutPoint, UTXO)> = Vec::new();
+    groups.into_iter().rev().find_map(|group| {
+        spent.extend(group);
+        finish(spent.clone(), target, fee)
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::utxo::{TxInput, TxOutput};
+
+    // UTXOs of the amounts, each paid to the recipient [recipient; 20]
+    fn utxos(amounts: &[(u64, u8)]) -> Vec<(OutPoint, UTXO)> {
+        amounts.iter().enumerate()
+            .map(|(index, (amount, recipient))| (OutPoint::new(vec![index as u8; 32], 0), UTXO::new(*amount, vec![*recipient; 20], 1, 12345)))
+            .collect()
+    }
+
+    // 2 tokens per input and 1 per output
+    fn fee(inputs: usize, outputs: usize) -> u64 {
+        (2 * inputs + outputs) as u64
+    }
+
+    fn amounts(selection: &Selection) -> Vec<u64> {
+        selection.utxos.iter().map(|(_, utxo)| utxo.amount).collect()
+    }
+
+    #[test]
+    fn test_largest_first() {
+        let selection = select_coins(&utxos(&[(10, 1), (50, 1), (30, 1)]), 60, SelectionStrategy::LargestFirst, fee).unwrap();
+        assert_eq!(amounts(&selection), vec![50, 30]);
+        assert_eq!((selection.fee, selection.change), (6, 14));
+    }
+
+    #[test]
+    fn test_change_below_dust_goes_to_the_fee() {
+        let selection = select_coins(&utxos(&[(70, 1)]), 65, SelectionStrategy::LargestFirst, fee).unwrap();
+        assert_eq!((selection.fee, selection.change), (5, 0));
+    }
+
+    #[test]
+    fn test_branch_and_bound_finds_changeless_match() {
+        // largest-first would spend 50 and 30 with change, 30 + 25 pays 50 and a fee of 5 exactly
+        let available = utxos(&[(50, 1), (30, 1), (25, 1), (4, 1)]);
+        let selection = select_coins(&available, 50, SelectionStrategy::BranchAndBound, fee).unwrap();
+        assert_eq!(amounts(&selection), vec![30, 25]);
+        assert_eq!((selection.fee, selection.change), (5, 0));
+
+        // without a changeless match it falls back to largest-first
+        let selection = select_coins(&available, 60, SelectionStrategy::BranchAndBound, fee).unwrap();
+        assert_eq!(selection, select_coins(&available, 60, SelectionStrategy::LargestFirst, fee).unwrap());
+    }
+
+    #[test]
+    fn test_privacy_spends_whole_recipient_groups() {
+        // the group of recipient 2 (20 + 20) is the smallest covering 30, recipient 1 is never linked to it
+        let selection = select_coins(&utxos(&[(100, 1), (20, 2), (20, 2), (10, 3)]), 30, SelectionStrategy::Privacy, fee).unwrap();
+        assert_eq!(amounts(&selection), vec![20, 20]);
+        assert!(selection.utxos.iter().all(|(_, utxo)| utxo.recipient == vec![2; 20]));
+
+        // when no group covers the payment whole groups are spent, largest first
+        let selection = select_coins(&utxos(&[(20, 1), (20, 2), (20, 2), (10, 3)]), 50, SelectionStrategy::Privacy, fee).unwrap();
+        assert_eq!(amounts(&selection), vec![20, 20, 20]);
+    }
+
+    #[test]
+    fn test_insufficient_funds() {
+        for strategy in [SelectionStrategy::LargestFirst, SelectionStrategy::BranchAndBound, SelectionStrategy::Privacy] {
+            assert!(select_coins(&utxos(&[(30, 1), (30, 2)]), 56, strategy, fee).is_err());
+            assert!(select_coins(&[], 1, strategy, fee).is_err());
+        }
+        assert!("random".parse::<SelectionStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_estimate_fee_grows_with_size() {
+        let input = TxInput::new(OutPoint::new(vec![1; 32], 0), String::new(), vec![2; 33]);
+        let one_input = UTXOTransaction::new(vec![input.clone()], vec![TxOutput::new(50, vec![3; 20])], 12345);
+        let many_inputs = UTXOTransaction::new(vec![input; 20], vec![TxOutput::new(50, vec![3; 20])], 12345);
+        assert!(estimate_fee(&one_input) >= MIN_TRANSACTION_FEE);
+        assert!(estimate_fee(&many_inputs) > estimate_fee(&one_input));
+    }
+}
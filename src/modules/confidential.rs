@@ -0,0 +1,484 @@
+use serde::{Serialize, Deserialize};
+use sha2::{Sha512, Digest};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{Identity, MultiscalarMul};
+use rand::{thread_rng, RngCore};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::modules::encoding::{Encode, Encoder};
+
+
+/**
+ * @notice confidential.rs contains confidential amounts: UTXO outputs whose amount is hidden in a Pedersen commitment on
+ * the Ristretto group (curve25519)
+ *
+ *   C = v·H + r·G
+ *
+ * of the amount v with a random blinding factor r, where G is the basepoint and H a second generator nobody knows the
+ * discrete log of. Commitments add up like the amounts they hide, so validators check that a transaction creates no
+ * tokens without learning any amount: the commitments of its inputs must equal those of its outputs plus fee·H (plain
+ * amounts count as v·H), which holds when the amounts balance and the blinding factors of the outputs add up to those of
+ * the inputs. The owner of an output keeps its Opening (v and r), which it needs to spend it.
+ *
+ * Amounts are only added modulo the group order, so every confidential output carries a range proof showing that v lies
+ * in [0, 2^64) without revealing it; otherwise a "negative" output could balance a larger one. The range proof is a
+ * Bulletproof (Bünz et al.): a commitment to the bits of v, checked with a logarithmic size inner product argument, made
+ * non-interactive with Fiat-Shamir challenges over everything sent before them. A proof is RANGE_PROOF_SIZE bytes.
+ */
+// domain separation tags of the generators and of the Fiat-Shamir transcript of range proofs
+const VALUE_GENERATOR_TAG: &[u8] = b"miniBlockChain/pedersen/value";
+const VECTOR_GENERATOR_TAG: &[u8] = b"miniBlockChain/bulletproofs/generator";
+const RANGE_PROOF_TAG: &[u8] = b"miniBlockChain/bulletproofs/range-proof";
+
+// number of bits of a proven amount, and the number of rounds of the inner product argument (log2 of the bits)
+const RANGE_PROOF_BITS: usize = 64;
+const INNER_PRODUCT_ROUNDS: usize = 6;
+
+// size of an encoded range proof: A, S, T1, T2, tau_x, mu, t_hat, the L and R of each round, and the final a and b
+pub const RANGE_PROOF_SIZE: usize = (9 + 2 * INNER_PRODUCT_ROUNDS) * 32;
+
+// the generators shared by every commitment and range proof, derived on first use
+static GENERATORS: OnceLock<Generators> = OnceLock::new();
+
+struct Generators {
+    value: RistrettoPoint,
+    g_vec: Vec<RistrettoPoint>,
+    h_vec: Vec<RistrettoPoint>,
+}
+
+// Derives a generator by hashing a tag to the group, so that no one knows its discrete log relative to any other
+fn hash_to_point(parts: &[&[u8]]) -> RistrettoPoint {
+    let mut hasher = Sha512::new();
+    for part in parts { hasher.update(part); }
+    let digest: [u8; 64] = hasher.finalize().into();
+    RistrettoPoint::from_uniform_bytes(&digest)
+}
+
+fn generators() -> &'static Generators {
+    GENERATORS.get_or_init(|| {
+        let vector_generator = |side: &[u8], index: usize| hash_to_point(&[VECTOR_GENERATOR_TAG, side, &(index as u64).to_le_bytes()]);
+        Generators {
+            value: hash_to_point(&[VALUE_GENERATOR_TAG]),
+            g_vec: (0..RANGE_PROOF_BITS).map(|index| vector_generator(b"G", index)).collect(),
+            h_vec: (0..RANGE_PROOF_BITS).map(|index| vector_generator(b"H", index)).collect(),
+        }
+    })
+}
+
+/**
+ * @notice random_blinding() returns a uniformly random blinding factor.
+ */
+pub fn random_blinding() -> Scalar {
+    let mut bytes = [0u8; 64];
+    thread_rng().fill_bytes(&mut bytes);
+    Scalar::from_bytes_mod_order_wide(&bytes)
+}
+
+/**
+ * @notice commit() returns the Pedersen commitment v·H + r·G of an amount and blinding factor.
+ */
+pub fn commit(amount: u64, blinding: &Scalar) -> RistrettoPoint {
+    generators().value * Scalar::from(amount) + RISTRETTO_BASEPOINT_POINT * blinding
+}
+
+/**
+ * @notice amount_commitment() returns the commitment of a plain amount (with no blinding), so that plain and
+ * confidential amounts can be balanced against each other.
+ */
+pub fn amount_commitment(amount: u64) -> RistrettoPoint {
+    generators().value * Scalar::from(amount)
+}
+
+/**
+ * @notice decode_commitment() parses a compressed commitment, rejecting invalid encodings.
+ */
+pub fn decode_commitment(commitment: &[u8]) -> Option<RistrettoPoint> {
+    if commitment.len() != 32 { return None; }
+    CompressedRistretto::from_slice(commitment).decompress()
+}
+
+/**
+ * @notice commitments_balance() checks that the input commitments equal the output commitments plus fee·H.
+ */
+pub fn commitments_balance(inputs: &[RistrettoPoint], outputs: &[RistrettoPoint], fee: u64) -> bool {
+    let input_sum: RistrettoPoint = inputs.iter().fold(RistrettoPoint::identity(), |sum, commitment| sum + commitment);
+    let output_sum: RistrettoPoint = outputs.iter().fold(amount_commitment(fee), |sum, commitment| sum + commitment);
+    input_sum == output_sum
+}
+
+/**
+ * @notice Opening is the secret behind a commitment: the amount and the blinding factor. It is written as
+ * amount:blinding (hex), the form the wallet prints and reads.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Opening {
+    pub amount: u64,
+    pub blinding: Scalar,
+}
+
+impl Opening {
+
+    // Returns the commitment the opening opens
+    pub fn commitment(&self) -> RistrettoPoint {
+        commit(self.amount, &self.blinding)
+    }
+}
+
+impl fmt::Display for Opening {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.amount, hex::encode(self.blinding.as_bytes()))
+    }
+}
+
+impl FromStr for Opening {
+    type Err = String;
+
+    fn from_str(opening: &str) -> Result<Self, Self::Err> {
+        let (amount, blinding) = opening.split_once(':').ok_or(format!("Openings must be given as amount:blinding, not {}", opening))?;
+        let blinding: [u8; 32] = hex::decode(blinding).ok().and_then(|blinding| blinding.try_into().ok())
+            .ok_or(format!("Invalid blinding factor {}", blinding))?;
+        Ok(Opening {
+            amount: amount.parse().map_err(|e| format!("Invalid amount {}: {}", amount, e))?,
+            blinding: Scalar::from_canonical_bytes(blinding).ok_or(format!("Invalid blinding factor {}", hex::encode(blinding)))?,
+        })
+    }
+}
+
+/**
+ * @notice ConfidentialAmount is the hidden amount of an output: the compressed commitment and its range proof.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfidentialAmount {
+    pub commitment: Vec<u8>,
+    pub range_proof: Vec<u8>,
+}
+
+impl ConfidentialAmount {
+
+    // Commits to the amount of the opening and proves it is in range
+    pub fn new(opening: &Opening) -> ConfidentialAmount {
+        ConfidentialAmount {
+            commitment: opening.commitment().compress().to_bytes().to_vec(),
+            range_proof: prove_range(opening.amount, &opening.blinding).to_bytes(),
+        }
+    }
+
+    /**
+     * @notice check_format() checks that the commitment is a valid point and the range proof is well formed, without
+     * verifying the proof.
+     */
+    pub fn check_format(&self) -> Result<(), String> {
+        decode_commitment(&self.commitment).ok_or("Invalid commitment".to_string())?;
+        RangeProof::from_bytes(&self.range_proof)?;
+        Ok(())
+    }
+
+    /**
+     * @notice verify() verifies the range proof of the commitment.
+     */
+    pub fn verify(&self) -> Result<(), String> {
+        let commitment = CompressedRistretto::from_slice(&self.commitment);
+        match self.commitment.len() == 32 && RangeProof::from_bytes(&self.range_proof)?.verify(&commitment) {
+            true => Ok(()),
+            false => Err("Invalid range proof".to_string()),
+        }
+    }
+}
+
+impl Encode for ConfidentialAmount {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.bytes(&self.commitment).bytes(&self.range_proof);
+    }
+}
+
+//------------------------------------ Range Proofs ------------------------------------//
+
+// Fiat-Shamir transcript of a range proof: every challenge hashes everything written before it, itself included
+struct Transcript {
+    bytes: Vec<u8>,
+}
+
+impl Transcript {
+
+    fn new(commitment: &CompressedRistretto) -> Transcript {
+        let mut transcript = Transcript { bytes: RANGE_PROOF_TAG.to_vec() };
+        transcript.append(commitment.as_bytes());
+        transcript
+    }
+
+    fn append(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn challenge(&mut self) -> Scalar {
+        let digest: [u8; 64] = Sha512::digest(&self.bytes).into();
+        let challenge = Scalar::from_bytes_mod_order_wide(&digest);
+        self.append(challenge.as_bytes());
+        challenge
+    }
+}
+
+/**
+ * @notice RangeProof is a Bulletproof that a commitment hides an amount in [0, 2^64).
+ * @param a, s - commitments to the bits of the amount and to the blinding vectors
+ * @param t1, t2 - commitments to the coefficients of the polynomial t(x) = <l(x), r(x)>
+ * @param tau_x, mu, t_hat - the blinding of t(x), the blinding of a + x·s and t(x) at the challenge x
+ * @param l_vec, r_vec, a_final, b_final - the inner product argument that t_hat = <l(x), r(x)>
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeProof {
+    a: CompressedRistretto,
+    s: CompressedRistretto,
+    t1: CompressedRistretto,
+    t2: CompressedRistretto,
+    tau_x: Scalar,
+    mu: Scalar,
+    t_hat: Scalar,
+    l_vec: Vec<CompressedRistretto>,
+    r_vec: Vec<CompressedRistretto>,
+    a_final: Scalar,
+    b_final: Scalar,
+}
+
+fn inner_product(a: &[Scalar], b: &[Scalar]) -> Scalar {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+// Returns 1, x, x^2, ... x^(n-1)
+fn powers(x: Scalar, n: usize) -> Vec<Scalar> {
+    std::iter::successors(Some(Scalar::one()), |power| Some(power * x)).take(n).collect()
+}
+
+/**
+ * @notice prove_range() proves that the commitment of an amount with a blinding factor hides a 64 bit amount.
+ */
+pub fn prove_range(amount: u64, blinding: &Scalar) -> RangeProof {
+    let generators = generators();
+    let (g, h) = (RISTRETTO_BASEPOINT_POINT, generators.value);
+    let commitment = commit(amount, blinding).compress();
+    let mut transcript = Transcript::new(&commitment);
+
+    // commit to the bits a_L of the amount and a_R = a_L - 1, and to random vectors s_L and s_R
+    let a_l: Vec<Scalar> = (0..RANGE_PROOF_BITS).map(|bit| Scalar::from((amount >> bit) & 1)).collect();
+    let a_r: Vec<Scalar> = a_l.iter().map(|bit| bit - Scalar::one()).collect();
+    let (s_l, s_r): (Vec<Scalar>, Vec<Scalar>) = (0..RANGE_PROOF_BITS).map(|_| (random_blinding(), random_blinding())).unzip();
+    let (alpha, rho) = (random_blinding(), random_blinding());
+
+    let vector_commitment = |blinding: &Scalar, left: &[Scalar], right: &[Scalar]| RistrettoPoint::multiscalar_mul(
+        std::iter::once(blinding).chain(left).chain(right),
+        std::iter::once(&g).chain(&generators.g_vec).chain(&generators.h_vec),
+    ).compress();
+    let a = vector_commitment(&alpha, &a_l, &a_r);
+    let s = vector_commitment(&rho, &s_l, &s_r);
+    transcript.append(a.as_bytes());
+    transcript.append(s.as_bytes());
+    let (y, z) = (transcript.challenge(), transcript.challenge());
+
+    // l(x) = l0 + l1·x and r(x) = r0 + r1·x, whose inner product t(x) has the amount in its constant term
+    let (y_powers, two_powers) = (powers(y, RANGE_PROOF_BITS), powers(Scalar::from(2u64), RANGE_PROOF_BITS));
+    let l0: Vec<Scalar> = a_l.iter().map(|bit| bit - z).collect();
+    let r0: Vec<Scalar> = (0..RANGE_PROOF_BITS).map(|i| y_powers[i] * (a_r[i] + z) + z * z * two_powers[i]).collect();
+    let r1: Vec<Scalar> = (0..RANGE_PROOF_BITS).map(|i| y_powers[i] * s_r[i]).collect();
+    let t1_coefficient = inner_product(&l0, &r1) + inner_product(&s_l, &r0);
+    let t2_coefficient = inner_product(&s_l, &r1);
+
+    let (tau1, tau2) = (random_blinding(), random_blinding());
+    let t1 = (h * t1_coefficient + g * tau1).compress();
+    let t2 = (h * t2_coefficient + g * tau2).compress();
+    transcript.append(t1.as_bytes());
+    transcript.append(t2.as_bytes());
+    let x = transcript.challenge();
+
+    // open l(x), r(x) and t(x) at the challenge
+    let tau_x = tau2 * x * x + tau1 * x + z * z * blinding;
+    let mu = alpha + rho * x;
+    let l: Vec<Scalar> = (0..RANGE_PROOF_BITS).map(|i| l0[i] + s_l[i] * x).collect();
+    let r: Vec<Scalar> = (0..RANGE_PROOF_BITS).map(|i| r0[i] + r1[i] * x).collect();
+    let t_hat = inner_product(&l, &r);
+    for scalar in [&tau_x, &mu, &t_hat] { transcript.append(scalar.as_bytes()); }
+    let q = h * transcript.challenge();
+
+    // prove <l, r> = t_hat over the generators G_i and H'_i = y^-i·H_i
+    let y_inverse = y.invert();
+    let h_prime: Vec<RistrettoPoint> = generators.h_vec.iter().zip(powers(y_inverse, RANGE_PROOF_BITS)).map(|(h_i, power)| h_i * power).collect();
+    let (mut a_vec, mut b_vec, mut g_vec, mut h_vec) = (l, r, generators.g_vec.clone(), h_prime);
+    let (mut l_vec, mut r_vec) = (Vec::new(), Vec::new());
+    while a_vec.len() > 1 {
+        let half = a_vec.len() / 2;
+        let (a_lo, a_hi) = a_vec.split_at(half);
+        let (b_lo, b_hi) = b_vec.split_at(half);
+        let (g_lo, g_hi) = g_vec.split_at(half);
+        let (h_lo, h_hi) = h_vec.split_at(half);
+
+        let (c_l, c_r) = (inner_product(a_lo, b_hi), inner_product(a_hi, b_lo));
+        let l_point = RistrettoPoint::multiscalar_mul(a_lo.iter().chain(b_hi).chain([&c_l]), g_hi.iter().chain(h_lo).chain([&q])).compress();
+        let r_point = RistrettoPoint::multiscalar_mul(a_hi.iter().chain(b_lo).chain([&c_r]), g_lo.iter().chain(h_hi).chain([&q])).compress();
+        transcript.append(l_point.as_bytes());
+        transcript.append(r_point.as_bytes());
+        l_vec.push(l_point);
+        r_vec.push(r_point);
+
+        let u = transcript.challenge();
+        let u_inverse = u.invert();
+        let fold_scalars = |lo: &[Scalar], hi: &[Scalar], lo_factor: Scalar, hi_factor: Scalar| -> Vec<Scalar> {
+            lo.iter().zip(hi).map(|(lo, hi)| lo * lo_factor + hi * hi_factor).collect()
+        };
+        let fold_points = |lo: &[RistrettoPoint], hi: &[RistrettoPoint], lo_factor: Scalar, hi_factor: Scalar| -> Vec<RistrettoPoint> {
+            lo.iter().zip(hi).map(|(lo, hi)| lo * lo_factor + hi * hi_factor).collect()
+        };
+        (a_vec, b_vec) = (fold_scalars(a_lo, a_hi, u, u_inverse), fold_scalars(b_lo, b_hi, u_inverse, u));
+        (g_vec, h_vec) = (fold_points(g_lo, g_hi, u_inverse, u), fold_points(h_lo, h_hi, u, u_inverse));
+    }
+
+    RangeProof { a, s, t1, t2, tau_x, mu, t_hat, l_vec, r_vec, a_final: a_vec[0], b_final: b_vec[0] }
+}
+
+impl RangeProof {
+
+    /**
+     * @notice verify() checks the proof against a compressed commitment.
+     */
+    pub fn verify(&self, commitment: &CompressedRistretto) -> bool {
+        let generators = generators();
+        let (g, h) = (RISTRETTO_BASEPOINT_POINT, generators.value);
+        let points: Option<Vec<RistrettoPoint>> = [commitment, &self.a, &self.s, &self.t1, &self.t2].iter()
+            .map(|point| point.decompress())
+            .collect();
+        let Some([v, a, s, t1, t2]) = points.and_then(|points| <[RistrettoPoint; 5]>::try_from(points).ok()) else { return false };
+
+        // replay the transcript of the prover
+        let mut transcript = Transcript::new(commitment);
+        transcript.append(self.a.as_bytes());
+        transcript.append(self.s.as_bytes());
+        let (y, z) = (transcript.challenge(), transcript.challenge());
+        transcript.append(self.t1.as_bytes());
+        transcript.append(self.t2.as_bytes());
+        let x = transcript.challenge();
+        for scalar in [&self.tau_x, &self.mu, &self.t_hat] { transcript.append(scalar.as_bytes()); }
+        let q = h * transcript.challenge();
+
+        // t(x) commits to z^2·v + delta(y, z), so t_hat·H + tau_x·G = z^2·V + delta·H + x·T1 + x^2·T2
+        let (y_powers, two_powers) = (powers(y, RANGE_PROOF_BITS), powers(Scalar::from(2u64), RANGE_PROOF_BITS));
+        let delta = (z - z * z) * y_powers.iter().sum::<Scalar>() - z * z * z * two_powers.iter().sum::<Scalar>();
+        if h * self.t_hat + g * self.tau_x != v * (z * z) + h * delta + t1 * x + t2 * (x * x) { return false; }
+
+        // P = A + x·S - z·sum(G_i) + sum((z·y^i + z^2·2^i)·H'_i) commits to l(x) and r(x) with blinding mu
+        let h_prime: Vec<RistrettoPoint> = generators.h_vec.iter().zip(powers(y.invert(), RANGE_PROOF_BITS)).map(|(h_i, power)| h_i * power).collect();
+        let h_scalars: Vec<Scalar> = (0..RANGE_PROOF_BITS).map(|i| z * y_powers[i] + z * z * two_powers[i]).collect();
+        let mut p: RistrettoPoint = a + s * x - RistrettoPoint::multiscalar_mul(std::iter::repeat_n(z, RANGE_PROOF_BITS), &generators.g_vec)
+            + RistrettoPoint::multiscalar_mul(&h_scalars, &h_prime) - g * self.mu + q * self.t_hat;
+
+        // fold the generators with the challenge of each round and check the final inner product
+        let (mut g_vec, mut h_vec) = (generators.g_vec.clone(), h_prime);
+        for (l_point, r_point) in self.l_vec.iter().zip(&self.r_vec) {
+            let (Some(l_decompressed), Some(r_decompressed)) = (l_point.decompress(), r_point.decompress()) else { return false };
+            transcript.append(l_point.as_bytes());
+            transcript.append(r_point.as_bytes());
+            let u = transcript.challenge();
+            let u_inverse = u.invert();
+            p += l_decompressed * (u * u) + r_decompressed * (u_inverse * u_inverse);
+
+            let half = g_vec.len() / 2;
+            g_vec = (0..half).map(|i| g_vec[i] * u_inverse + g_vec[half + i] * u).collect();
+            h_vec = (0..half).map(|i| h_vec[i] * u + h_vec[half + i] * u_inverse).collect();
+        }
+        p == g_vec[0] * self.a_final + h_vec[0] * self.b_final + q * (self.a_final * self.b_final)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(RANGE_PROOF_SIZE);
+        for point in [&self.a, &self.s, &self.t1, &self.t2] { bytes.extend_from_slice(point.as_bytes()); }
+        for scalar in [&self.tau_x, &self.mu, &self.t_hat] { bytes.extend_from_slice(scalar.as_bytes()); }
+        for point in self.l_vec.iter().chain(&self.r_vec) { bytes.extend_from_slice(point.as_bytes()); }
+        for scalar in [&self.a_final, &self.b_final] { bytes.extend_from_slice(scalar.as_bytes()); }
+        bytes
+    }
+
+    /**
+     * @notice from_bytes() parses an encoded proof, rejecting proofs of the wrong size and non-canonical scalars.
+     */
+    pub fn from_bytes(bytes: &[u8]) -> Result<RangeProof, String> {
+        if bytes.len() != RANGE_PROOF_SIZE { return Err(format!("Range proofs are {} bytes, not {}", RANGE_PROOF_SIZE, bytes.len())); }
+        let chunks: Vec<[u8; 32]> = bytes.chunks(32).map(|chunk| chunk.try_into().unwrap()).collect();
+        let point = |index: usize| CompressedRistretto(chunks[index]);
+        let scalar = |index: usize| Scalar::from_canonical_bytes(chunks[index]).ok_or("Invalid range proof scalar".to_string());
+
+        let rounds_end: usize = 7 + 2 * INNER_PRODUCT_ROUNDS;
+        Ok(RangeProof {
+            a: point(0),
+            s: point(1),
+            t1: point(2),
+            t2: point(3),
+            tau_x: scalar(4)?,
+            mu: scalar(5)?,
+            t_hat: scalar(6)?,
+            l_vec: (7..7 + INNER_PRODUCT_ROUNDS).map(point).collect(),
+            r_vec: (7 + INNER_PRODUCT_ROUNDS..rounds_end).map(point).collect(),
+            a_final: scalar(rounds_end)?,
+            b_final: scalar(rounds_end + 1)?,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_proofs() {
+        for amount in [0, 1, 42, u64::MAX] {
+            let opening = Opening { amount, blinding: random_blinding() };
+            let confidential = ConfidentialAmount::new(&opening);
+            assert_eq!(confidential.range_proof.len(), RANGE_PROOF_SIZE);
+            assert!(confidential.check_format().is_ok());
+            assert!(confidential.verify().is_ok());
+
+            // a proof does not verify for any other commitment
+            let other = ConfidentialAmount { commitment: commit(amount.wrapping_add(1), &opening.blinding).compress().to_bytes().to_vec(), ..confidential.clone() };
+            assert!(other.verify().is_err());
+        }
+    }
+
+    #[test]
+    fn test_out_of_range_amounts_cannot_be_proven() {
+        // a commitment to -1 (the group order minus one) with the proof made for a commitment to 2^64 - 1
+        let blinding = random_blinding();
+        let proof = prove_range(u64::MAX, &blinding);
+        let negative = (generators().value * -Scalar::one() + RISTRETTO_BASEPOINT_POINT * blinding).compress();
+        assert!(!proof.verify(&negative));
+
+        // tampering with any part of a proof breaks it
+        let commitment = commit(u64::MAX, &blinding).compress();
+        let mut bytes = proof.to_bytes();
+        bytes[RANGE_PROOF_SIZE - 1] ^= 1;
+        assert!(RangeProof::from_bytes(&bytes).map(|proof| !proof.verify(&commitment)).unwrap_or(true));
+        bytes[RANGE_PROOF_SIZE - 1] ^= 1;
+        bytes[0] ^= 1;
+        assert!(!RangeProof::from_bytes(&bytes).unwrap().verify(&commitment));
+        assert!(RangeProof::from_bytes(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn test_commitments_balance() {
+        let (input_blinding, payment_blinding) = (random_blinding(), random_blinding());
+        let input = commit(100, &input_blinding);
+        let payment = commit(70, &payment_blinding);
+        let change = commit(25, &(input_blinding - payment_blinding));
+
+        assert!(commitments_balance(&[input], &[payment, change], 5));
+        assert!(!commitments_balance(&[input], &[payment, change], 4));
+
+        // plain amounts balance against commitments with no blinding
+        assert!(commitments_balance(&[amount_commitment(100)], &[commit(95, &Scalar::zero())], 5));
+
+        // openings are printed and parsed as amount:blinding
+        let opening = Opening { amount: 70, blinding: payment_blinding };
+        assert_eq!(opening.to_string().parse::<Opening>().unwrap(), opening);
+        assert!("70".parse::<Opening>().is_err());
+    }
+}
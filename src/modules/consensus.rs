@@ -13,6 +13,8 @@ use std::collections::HashMap;
 
 use crate::modules::validation;
 use crate::modules::network;
+use crate::modules::blockchain;
+use crate::modules::zk_proof;
 
 
 /**
@@ -35,21 +37,44 @@ use crate::modules::network;
  * @param action: String - the action to be taken by the receiving node
  * @param request_hash: Vec<u8> - the hash of the request to be validated
  * @param response_port: String - the port to send the response to
+ * @param chain_id: String - the chain id of the responding validator, votes from other networks are ignored
+ * @param node_public_key: String - the public key of the node key of the responding validator
+ * @param signature: String - the signature of the vote (see zk_proof::vote_message()) with the node key
  */
  #[derive(Debug, Clone, Serialize, Deserialize)]
 struct BlockConsensusResponse {
     action: String,     
     request_hash: Vec<u8>,
     decision: bool,
+    chain_id: String,
+    node_public_key: String,
+    signature: String,
 }
 
 /**
- * @notice handle_block_consensus_request() is an asynchronous function that handles a block consensus request from 
- * another validator node. This function will retrieve the client decision from the request, package the response, 
- * and send the response back to the requesting node. The funnction is called within the validator module.
+ * @notice signed_consensus_response() packages the decision of this validator on a request and signs it with its node key 
+ * in the Vote domain, along with the commitment to the UTXO set the decision was checked against (empty for requests that 
+ * neither create nor spend UTXOs).
  */
-pub async fn handle_consensus_request(request: Value, validator_node: validation::ValidatorNode) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Handling request from peer for consensus..."); 
+fn signed_consensus_response(request_hash: Vec<u8>, decision: bool, utxo_commitment: &[u8], validator_node: &validation::ValidatorNode) -> BlockConsensusResponse {
+    let message: Vec<u8> = zk_proof::vote_message(&request_hash, decision, utxo_commitment);
+    BlockConsensusResponse {
+        action: "ConsensusResponse".to_string(), 
+        request_hash, 
+        decision,
+        chain_id: blockchain::chain_id().to_string(),
+        node_public_key: validator_node.node_public_key.clone(),
+        signature: zk_proof::sign_message(&validator_node.node_key, &message).unwrap(),
+    }
+}
+
+/**
+ * @notice consensus_vote() determines the vote of this validator on a consensus request: its client decision on the request, 
+ * or a vote to reject if it has not decided on the request (e.g. it received it before it matured, or not at all).
+ * @dev A consensus request for a request that creates or spends UTXOs carries the commitment to the UTXO set the requesting 
+ * node decided against. If this validator decided against another UTXO set the two sets have diverged, and it votes to reject.
+ */
+async fn consensus_vote(request: &Value, validator_node: &validation::ValidatorNode) -> BlockConsensusResponse {
 
     // retrieve request hash from request as a vector of u8
     let request_hash: Vec<u8> = request["request_hash"].as_array().unwrap()
@@ -57,17 +82,40 @@ pub async fn handle_consensus_request(request: Value, validator_node: validation
                                      .map(|x| x.as_u64().unwrap() as u8)
                                      .collect();
 
-    // lock mutex and get client decision from validator node
+    // lock mutex and get client decision from validator node, a request this node has not decided on is rejected
     let client_decisions: Arc<Mutex<HashMap<Vec<u8>, bool>>>= validator_node.client_decisions.clone();
     let client_decisions_guard = client_decisions.lock().await;
-    let client_decision: bool = client_decisions_guard.get(&request_hash).unwrap().clone();   
-
-    // Package responce in struct and serialize to JSON
-    let consensus_responce = BlockConsensusResponse {
-        action: "ConsensusResponse".to_string(), 
-        request_hash, 
-        decision: client_decision
+    let client_decision: bool = client_decisions_guard.get(&request_hash).copied().unwrap_or(false);
+    drop(client_decisions_guard);
+
+    // compare the UTXO set the requesting node decided against with the one this node decided against
+    let expected_commitment: Option<Vec<u8>> = serde_json::from_value(request["utxo_commitment"].clone()).unwrap_or_default();
+    let decision: bool = match &expected_commitment {
+        Some(expected_commitment) => {
+            let decision_commitment: Option<Vec<u8>> = validator_node.decision_commitments.lock().await.get(&request_hash).cloned();
+            let same_utxo_set: bool = decision_commitment.as_ref() == Some(expected_commitment);
+            if !same_utxo_set {
+                eprintln!("Decided against a UTXO set that diverged from the requesting node's, voting to reject. Expected commitment: {}, local commitment: {}",
+                    hex::encode(expected_commitment), hex::encode(decision_commitment.unwrap_or_default()));
+            }
+            client_decision && same_utxo_set
+        },
+        None => client_decision,
     };
+
+    signed_consensus_response(request_hash, decision, &expected_commitment.unwrap_or_default(), validator_node)
+}
+
+/**
+ * @notice handle_block_consensus_request() is an asynchronous function that handles a block consensus request from 
+ * another validator node. This function will determine the vote of this validator (see consensus_vote()), package the 
+ * response, and send the response back to the requesting node. The funnction is called within the validator module.
+ */
+pub async fn handle_consensus_request(request: Value, validator_node: validation::ValidatorNode) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Handling request from peer for consensus..."); 
+
+    // Package the signed responce in struct and serialize to JSON
+    let consensus_responce: BlockConsensusResponse = consensus_vote(&request, &validator_node).await;
     let json_msg: String = serde_json::to_string(&consensus_responce).unwrap();
 
     // retrieve response port from request
@@ -98,6 +146,10 @@ pub async fn handle_consensus_request(request: Value, validator_node: validation
  * @notice handle_block_consensus_response() is an asynchronous function that handles a block consensus response from 
  * another validator node. This function will retrieve the client decision from the response, update the peer decisions 
  * hash map, and trigger the notify to wake up the main thread. The funnction is called within the validator module.
+ * @dev Responses from another chain, from a node key outside the validator set, without a valid Vote signature from the 
+ * node key they carry, or from a validator that already voted on the request are rejected. The signature covers the 
+ * commitment to the UTXO set this node sent along with its consensus request, so a vote checked against another UTXO 
+ * set does not verify.
  */
 pub async fn handle_consensus_response(request: Value, validator_node: validation::ValidatorNode) -> Result<(), Box<dyn std::error::Error>> { 
     println!("Handling consensus request reponse from peer..."); 
@@ -108,8 +160,34 @@ pub async fn handle_consensus_response(request: Value, validator_node: validatio
                                      .map(|x| x.as_u64().unwrap() as u8)
                                      .collect();
 
-    // get client decision from request
+    // get client decision from request, and the UTXO set commitment this node sent with its consensus request
     let decision: bool = request["decision"].as_bool().unwrap();
+    let node_public_key: &str = request["node_public_key"].as_str().unwrap_or_default();
+    let utxo_commitment: Vec<u8> = validator_node.decision_commitments.lock().await.get(&request_hash).cloned().unwrap_or_default();
+
+    // only count votes of this network signed by the node key of a member of the validator set
+    if request["chain_id"].as_str() != Some(blockchain::chain_id()) {
+        return Err(format!("Vote from chain {:?}, this node is on chain {}", request["chain_id"], blockchain::chain_id()).into());
+    }
+    if validator_node.validator_reward_address(node_public_key).is_none() {
+        return Err(format!("Vote from {}, which is not in the validator set", node_public_key).into());
+    }
+    if !zk_proof::verify_message_signature(
+        node_public_key,
+        &zk_proof::vote_message(&request_hash, decision, &utxo_commitment),
+        request["signature"].as_str().unwrap_or_default(),
+    ) {
+        return Err("Invalid vote signature".into());
+    }
+
+    // record the vote of the validator, each validator votes once on a request
+    let mut consensus_voters_guard = validator_node.consensus_voters.lock().await;
+    let voters: &mut validation::RequestVoters = consensus_voters_guard.entry(request_hash.clone()).or_default();
+    if voters.contains_key(node_public_key) {
+        return Err(format!("Validator {} already voted on this request", node_public_key).into());
+    }
+    voters.insert(node_public_key.to_string(), decision);
+    drop(consensus_voters_guard);
 
     // get peer decisions from validator node
     let peer_decisions: Arc<Mutex<HashMap<Vec<u8>, (u32, u32)>>> = validator_node.peer_decisions.clone();
@@ -181,3 +259,142 @@ pub async fn determine_majority(request: Value, validator_node: validation::Vali
     
 }
 
+
+/**
+ * @notice split_validator_reward() divides a reward evenly between the provided validator addresses. Addresses are sorted
+ * and deduplicated first so that every node computes the same split regardless of the order responses arrived in. Any 
+ * remainder left by integer division is handed out one token at a time starting from the first address.
+ * @dev Addresses whose share is zero are left out. An empty vector is returned if there are no addresses to pay, in which 
+ * case the reward is burned.
+ */
+pub fn split_validator_reward(reward: u64, addresses: &[Vec<u8>]) -> Vec<(Vec<u8>, u64)> {
+
+    // sort and deduplicate addresses for a deterministic split
+    let mut recipients: Vec<Vec<u8>> = addresses.to_vec();
+    recipients.sort();
+    recipients.dedup();
+
+    if recipients.is_empty() || reward == 0 { return Vec::new(); }
+
+    // compute the even share and the remainder
+    let share: u64 = reward / recipients.len() as u64;
+    let remainder: u64 = reward % recipients.len() as u64;
+
+    recipients.into_iter()
+        .enumerate()
+        .map(|(i, address)| {
+            let bonus: u64 = if (i as u64) < remainder { 1 } else { 0 };
+            (address, share + bonus)
+        })
+        .filter(|(_, amount)| *amount > 0)
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::utxo::{OutPoint, UTXO};
+    use crate::modules::blockchain::ValidatorConfig;
+    use crate::modules::requests::NetworkRequest;
+
+    #[test]
+    fn test_split_validator_reward_even() {
+        let addresses = vec![vec![2u8], vec![1u8]];
+        let split = split_validator_reward(10, &addresses);
+        assert_eq!(split, vec![(vec![1u8], 5), (vec![2u8], 5)]);
+    }
+
+    #[test]
+    fn test_split_validator_reward_remainder_and_duplicates() {
+        let addresses = vec![vec![3u8], vec![1u8], vec![2u8], vec![1u8]];
+        let split = split_validator_reward(11, &addresses);
+
+        // duplicates are ignored and the remainder goes to the first sorted addresses
+        assert_eq!(split, vec![(vec![1u8], 4), (vec![2u8], 4), (vec![3u8], 3)]);
+        assert_eq!(split.iter().map(|(_, amount)| amount).sum::<u64>(), 11);
+    }
+
+    #[test]
+    fn test_split_validator_reward_no_recipients() {
+        assert!(split_validator_reward(10, &[]).is_empty());
+
+        // addresses left without a share are not paid
+        assert_eq!(split_validator_reward(1, &[vec![2u8], vec![1u8]]), vec![(vec![1u8], 1)]);
+    }
+
+    // validator set made of the node keys and reward addresses of the given nodes
+    fn validator_set(nodes: &[&validation::ValidatorNode]) -> Vec<ValidatorConfig> {
+        nodes.iter()
+            .map(|node| ValidatorConfig { node_public_key: node.node_public_key.clone(), reward_address: node.reward_address.clone() })
+            .collect()
+    }
+
+    // consensus request of the requesting node for a request, carrying the UTXO set commitment it decided against
+    fn consensus_request(request_hash: &[u8], utxo_commitment: Option<Vec<u8>>) -> Value {
+        serde_json::to_value(NetworkRequest::ConsensusRequest {
+            request_hash: request_hash.to_vec(),
+            response_port: "127.0.0.1:8080".to_string(),
+            utxo_commitment,
+            chain_id: blockchain::chain_id().to_string(),
+        }).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_only_signed_votes_of_validators_of_this_chain_are_counted() {
+        let peer = validation::ValidatorNode::new();
+        let outsider = validation::ValidatorNode::new();
+        let validator_node = validation::ValidatorNode { validators: validator_set(&[&peer]), ..validation::ValidatorNode::new() };
+        let request_hash: Vec<u8> = vec![7; 32];
+        peer.client_decisions.lock().await.insert(request_hash.clone(), true);
+        outsider.client_decisions.lock().await.insert(request_hash.clone(), true);
+        let vote: Value = serde_json::to_value(consensus_vote(&consensus_request(&request_hash, None), &peer).await).unwrap();
+
+        // a vote whose decision was changed, that comes from another chain, or from a key outside the validator set is rejected
+        let mut flipped = vote.clone();
+        flipped["decision"] = Value::Bool(false);
+        assert!(handle_consensus_response(flipped, validator_node.clone()).await.is_err());
+        let mut other_chain = vote.clone();
+        other_chain["chain_id"] = Value::String("other-chain".to_string());
+        assert!(handle_consensus_response(other_chain, validator_node.clone()).await.is_err());
+        let outsider_vote: Value = serde_json::to_value(consensus_vote(&consensus_request(&request_hash, None), &outsider).await).unwrap();
+        assert!(handle_consensus_response(outsider_vote, validator_node.clone()).await.is_err());
+        assert!(!validator_node.peer_decisions.lock().await.contains_key(&request_hash));
+
+        // the signed vote is counted once
+        handle_consensus_response(vote.clone(), validator_node.clone()).await.unwrap();
+        assert!(handle_consensus_response(vote, validator_node.clone()).await.is_err());
+        assert_eq!(validator_node.peer_decisions.lock().await.get(&request_hash), Some(&(1, 0)));
+        assert_eq!(validator_node.consensus_voters.lock().await[&request_hash].get(&peer.node_public_key), Some(&true));
+    }
+
+    #[tokio::test]
+    async fn test_votes_against_diverged_utxo_sets_count_as_rejects() {
+        let peer = validation::ValidatorNode::new();
+        let validator_node = validation::ValidatorNode { validators: validator_set(&[&peer]), ..validation::ValidatorNode::new() };
+        let local_commitment: Vec<u8> = validator_node.utxo_set.lock().await.commitment();
+
+        // the peer accepted the request against a UTXO set holding an output the requesting node does not know of
+        peer.utxo_set.lock().await.add_utxo(OutPoint::new(vec![1; 32], 0), UTXO::new(100, vec![2; 20], 1, 12345));
+        let diverged_commitment: Vec<u8> = peer.utxo_set.lock().await.commitment();
+        for request_hash in [vec![7; 32], vec![8; 32], vec![9; 32]] {
+            validator_node.decision_commitments.lock().await.insert(request_hash.clone(), local_commitment.clone());
+            peer.client_decisions.lock().await.insert(request_hash.clone(), true);
+        }
+        peer.decision_commitments.lock().await.insert(vec![7; 32], diverged_commitment.clone());
+        let request: Value = consensus_request(&[7; 32], Some(local_commitment.clone()));
+        handle_consensus_response(serde_json::to_value(consensus_vote(&request, &peer).await).unwrap(), validator_node.clone()).await.unwrap();
+        assert_eq!(validator_node.peer_decisions.lock().await.get(&vec![7; 32]), Some(&(0, 1)));
+
+        // a vote checked against another commitment than the one requested does not verify
+        let vote: Value = serde_json::to_value(signed_consensus_response(vec![8; 32], true, &diverged_commitment, &peer)).unwrap();
+        assert!(handle_consensus_response(vote, validator_node.clone()).await.is_err());
+
+        // a peer that decided against the same UTXO set is counted, even if the set of the requesting node changed since
+        peer.decision_commitments.lock().await.insert(vec![9; 32], local_commitment.clone());
+        let request: Value = consensus_request(&[9; 32], Some(local_commitment.clone()));
+        validator_node.utxo_set.lock().await.add_utxo(OutPoint::new(vec![3; 32], 0), UTXO::new(100, vec![2; 20], 1, 12345));
+        handle_consensus_response(serde_json::to_value(consensus_vote(&request, &peer).await).unwrap(), validator_node.clone()).await.unwrap();
+        assert_eq!(validator_node.peer_decisions.lock().await.get(&vec![9; 32]), Some(&(1, 0)));
+    }
+}
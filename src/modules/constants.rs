@@ -8,14 +8,79 @@ use std::time::Duration;
 // durations to wait before sending for consensus
 pub const PEER_STATE_RECEPTION_DURATION: Duration = Duration::from_secs(2);  
 
+// offset from a node's port to its JSON-RPC port when no rpc_port is set
+pub const RPC_PORT_OFFSET: u16 = 1000;
+
+// number of events buffered for event subscribers
+pub const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
 // Heartbeat durations
 pub const HEARTBEAT_PERIOD: Duration = Duration::from_secs(5);
 pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
 
-// TEST controls whether to save json block updates during integration testing
-pub const INTEGRATION_TEST: bool = true;
+// env var set by the integration test scripts for `make` to save new keys to new_account_details.json
+pub const TEST_ACCOUNT_DETAILS_ENV: &str = "SAVE_TEST_ACCOUNT_DETAILS";
 
 // amount of tokens to send to accounts when a faucet request is made
 pub const FAUCET_AMOUNT: u64 = 100; 
 
+// genesis parameters used when accepted_ports.json has none
+pub const DEFAULT_NETWORK_NAME: &str = "devnet";
+pub const DEFAULT_GENESIS_TIME: u64 = 1_713_547_800;
+pub const DEFAULT_ADDRESS_PREFIX: &str = "mbc";
+
+// minimum fee of an account transfer
+pub const MIN_TRANSACTION_FEE: u64 = 1;
+
+// tokens issued to validators by the coinbase of each UTXO block, 0 disables issuance
+pub const INITIAL_BLOCK_SUBSIDY: u64 = 0;
+
+// number of blocks between halvings of the block subsidy
+pub const SUBSIDY_HALVING_INTERVAL: u64 = 1_000;
+
+// wallet keystore file and the env var its password can be given in
+pub const KEYSTORE_PATH: &str = "wallet.json";
+pub const WALLET_PASSWORD_ENV: &str = "WALLET_PASSWORD";
+
+// scrypt cost parameters of new keystore accounts (N = 2^SCRYPT_LOG_N)
+pub const SCRYPT_LOG_N: u8 = 15;
+pub const SCRYPT_R: u32 = 8;
+pub const SCRYPT_P: u32 = 1;
+
+// BIP44 coin type of HD wallet keys and number of words of new mnemonics
+pub const HD_COIN_TYPE: u32 = 7777;
+pub const MNEMONIC_WORD_COUNT: usize = 12;
+
+// number of keys per chain re-derived by wallet restore by default
+pub const HD_RESTORE_COUNT: u32 = 5;
+
+// maximum number of keys of a multisig lock
+pub const MAX_MULTISIG_KEYS: usize = 15;
+
+// script interpreter limits: script bytes, opcodes run, stack items and bytes per item
+pub const MAX_SCRIPT_SIZE: usize = 1_000;
+pub const MAX_SCRIPT_OPS: usize = 200;
+pub const MAX_STACK_SIZE: usize = 100;
+pub const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+
+// lock times below this are block heights, the rest unix times
+pub const LOCK_TIME_THRESHOLD: u64 = 500_000_000;
+
+// maximum drift (in seconds) of a signed request timestamp from the validator's clock
+pub const MAX_TIMESTAMP_DRIFT: u64 = 600;
+
+// smallest amount of a UTXO transaction output
+pub const DUST_THRESHOLD: u64 = 5;
+
+// largest encoded size (in bytes) of a UTXO transaction
+pub const MAX_TRANSACTION_SIZE: usize = 100_000;
+
+// fee rate (in tokens per 1000 bytes) the wallet pays for UTXO transactions
+pub const UTXO_FEE_RATE: u64 = 10;
+
+// number of branches explored by branch-and-bound coin selection
+pub const BNB_MAX_TRIES: usize = 100_000;
 
+// number of immature transactions held, and how often they are checked
+pub const MAX_IMMATURE_TRANSACTIONS: usize = 1_000;
+pub const MATURITY_CHECK_PERIOD: Duration = Duration::from_secs(5);
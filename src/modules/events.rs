@@ -0,0 +1,223 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::modules::blockchain::{BlockChain, BlockJson, convert_block_to_blockjson};
+use crate::modules::validation::ValidatorNode;
+
+
+/**
+ * @notice events.rs contains the events a validator node pushes to subscribers as its local ledger changes. Events are
+ * published on a tokio broadcast channel held by the ValidatorNode (see ValidatorNode::emit()) by the add_*_to_ledger
+ * functions, by ValidatorNode::set_tx_status() and by the heartbeat handler.
+ *
+ * Clients subscribe through the JSON-RPC server (see rpc.rs) by sending a `subscribe` call over a raw TCP connection:
+ *
+ *   {"jsonrpc": "2.0", "method": "subscribe", "params": {"events": ["newBlock"], "public_keys": []}, "id": 1}
+ *
+ * Both params are optional, leaving them out subscribes to everything. The node answers with the subscription id and
+ * then writes one notification per line for every matching event until the client disconnects:
+ *
+ *   {"jsonrpc": "2.0", "method": "subscription", "params": {"subscription": 1, "result": {"event": "newBlock", ...}}}
+ */
+/**
+ * @notice NodeEvent is an event published by a validator node. The 'event' tag holds the camelCase event name.
+ * @param NewBlock - a block was written to the chain at the given height
+ * @param TxCommitted - a transaction (identified by the hex hash of its request) was written to the chain
+ * @param TxRejected - a transaction was rejected, along with the reason
+ * @param AccountChanged - the balance or nonce of an account changed (hex public key)
+ * @param PeerJoined - a peer sent its first heartbeat
+ * @param PeerLeft - a peer missed its heartbeat for longer than HEARTBEAT_TIMEOUT
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum NodeEvent {
+    NewBlock { height: u64, block: BlockJson },
+    TxCommitted { hash: String, block_height: u64 },
+    TxRejected { hash: String, reason: String },
+    AccountChanged { public_key: String },
+    PeerJoined { address: String },
+    PeerLeft { address: String },
+}
+
+// names of all events that can be subscribed to
+pub const EVENT_NAMES: [&str; 6] = ["newBlock", "txCommitted", "txRejected", "accountChanged", "peerJoined", "peerLeft"];
+
+// source of subscription ids
+static NEXT_SUBSCRIPTION_ID: AtomicU64 = AtomicU64::new(1);
+
+impl NodeEvent {
+
+    // Returns the name the event is subscribed to by
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeEvent::NewBlock { .. } => "newBlock",
+            NodeEvent::TxCommitted { .. } => "txCommitted",
+            NodeEvent::TxRejected { .. } => "txRejected",
+            NodeEvent::AccountChanged { .. } => "accountChanged",
+            NodeEvent::PeerJoined { .. } => "peerJoined",
+            NodeEvent::PeerLeft { .. } => "peerLeft",
+        }
+    }
+}
+
+/**
+ * @notice new_block_event() packages the most recent block of a blockchain into a NewBlock event.
+ * @dev Called right after push_block_to_chain() so that the event carries the hash set by the blockchain.
+ */
+pub async fn new_block_event(blockchain: &BlockChain) -> Option<NodeEvent> {
+    let block = blockchain.chain.last()?.clone();
+    Some(NodeEvent::NewBlock {
+        height: blockchain.chain.len() as u64 - 1,
+        block: convert_block_to_blockjson(block).await,
+    })
+}
+
+/**
+ * @notice EventFilter selects the events a subscription receives. An empty list of events or public keys matches all.
+ * @dev public_keys only restricts accountChanged events.
+ */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EventFilter {
+    pub events: Vec<String>,
+    pub public_keys: Vec<String>,
+}
+
+impl EventFilter {
+
+    // Builds a filter from the params of a subscribe call, rejecting unknown event names
+    pub fn from_params(params: &Value) -> Result<EventFilter, String> {
+        let strings = |name: &str| -> Result<Vec<String>, String> {
+            match &params[name] {
+                Value::Null => Ok(Vec::new()),
+                Value::Array(values) => values.iter()
+                    .map(|value| value.as_str().map(str::to_string).ok_or(format!("{} must be a list of strings", name)))
+                    .collect(),
+                _ => Err(format!("{} must be a list of strings", name)),
+            }
+        };
+
+        let filter = EventFilter { events: strings("events")?, public_keys: strings("public_keys")? };
+        if let Some(unknown) = filter.events.iter().find(|event| !EVENT_NAMES.contains(&event.as_str())) {
+            return Err(format!("unknown event: {}", unknown));
+        }
+        Ok(filter)
+    }
+
+    // Returns true if the event should be sent to the subscriber
+    pub fn matches(&self, event: &NodeEvent) -> bool {
+        if !self.events.is_empty() && !self.events.iter().any(|name| name == event.name()) { return false; }
+        match event {
+            NodeEvent::AccountChanged { public_key } => self.public_keys.is_empty() || self.public_keys.contains(public_key),
+            _ => true,
+        }
+    }
+}
+
+/**
+ * @notice stream_events() answers a subscribe call on a raw TCP connection and then writes every matching event to it as
+ * a JSON-RPC notification until the client disconnects. Events missed because the subscriber fell too far behind are
+ * skipped.
+ */
+pub async fn stream_events(socket: &mut TcpStream, id: Value, filter: EventFilter, validator_node: ValidatorNode) {
+
+    // subscribe before answering so no event is missed between the two
+    let mut receiver = validator_node.events.subscribe();
+    let subscription: u64 = NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed);
+
+    // confirm the subscription
+    let response: Value = json!({ "jsonrpc": "2.0", "result": { "subscription": subscription }, "id": id });
+    if socket.write_all(format!("{}\n", response).as_bytes()).await.is_err() { return; }
+
+    // forward matching events until the connection or the channel closes
+    loop {
+        let event: NodeEvent = match receiver.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(missed)) => { eprintln!("Subscription {} skipped {} events", subscription, missed); continue; },
+            Err(RecvError::Closed) => return,
+        };
+        if !filter.matches(&event) { continue; }
+
+        let notification: Value = json!({
+            "jsonrpc": "2.0",
+            "method": "subscription",
+            "params": { "subscription": subscription, "result": event },
+        });
+        if socket.write_all(format!("{}\n", notification).as_bytes()).await.is_err() { return; }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::{validation, requests, zk_proof};
+    use crate::modules::blockchain::ValidatorConfig;
+    use crate::modules::constants::HEARTBEAT_TIMEOUT;
+
+    #[test]
+    fn test_event_names_and_filter() {
+        let event = NodeEvent::AccountChanged { public_key: "abc".to_string() };
+        assert_eq!(serde_json::to_value(&event).unwrap()["event"], event.name());
+
+        let filter = EventFilter::from_params(&json!({"events": ["accountChanged"], "public_keys": ["abc"]})).unwrap();
+        assert!(filter.matches(&event));
+        assert!(!filter.matches(&NodeEvent::AccountChanged { public_key: "def".to_string() }));
+        assert!(!filter.matches(&NodeEvent::PeerJoined { address: "127.0.0.1:8080".to_string() }));
+
+        // no params subscribes to everything, unknown events are rejected
+        assert!(EventFilter::from_params(&Value::Null).unwrap().matches(&event));
+        assert!(EventFilter::from_params(&json!({"events": ["blocks"]})).is_err());
+    }
+
+    // heartbeat of the peer signed at the given time
+    fn heartbeat_at(peer: &ValidatorNode, timestamp: u64) -> Value {
+        let mut heartbeat: Value = serde_json::to_value(requests::heartbeat_request(peer)).unwrap();
+        let handshake: Vec<u8> = zk_proof::handshake_message(&peer.client_port_address, timestamp);
+        heartbeat["timestamp"] = json!(timestamp);
+        heartbeat["signature"] = json!(zk_proof::sign_message(&peer.node_key, &handshake).unwrap());
+        heartbeat
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_emits_peer_joined() {
+        let peer = ValidatorNode { client_port_address: "127.0.0.1:8081".to_string(), ..ValidatorNode::new() };
+        let outsider = ValidatorNode { client_port_address: "127.0.0.1:8082".to_string(), ..ValidatorNode::new() };
+        let mut validator_node = ValidatorNode::new();
+        let validators: Vec<ValidatorConfig> = [&peer, &validator_node].iter()
+            .map(|node| ValidatorConfig { node_public_key: node.node_public_key.clone(), reward_address: String::new() })
+            .collect();
+        validator_node.validators = validators;
+        let mut receiver = validator_node.events.subscribe();
+        let heartbeat: Value = serde_json::to_value(requests::heartbeat_request(&peer)).unwrap();
+        let timestamp: u64 = heartbeat["timestamp"].as_u64().unwrap();
+
+        // heartbeats of another chain, not signed by the key they carry, from a key outside the validator set, or signed 
+        // too long ago are rejected without a join
+        let mut other_chain = heartbeat.clone();
+        other_chain["chain_id"] = json!("other-chain");
+        assert!(validation::handle_heartbeat(other_chain, validator_node.clone()).await.is_err());
+        let mut forged = heartbeat.clone();
+        forged["port_address"] = json!("127.0.0.1:8082");
+        assert!(validation::handle_heartbeat(forged, validator_node.clone()).await.is_err());
+        let mut other_key = heartbeat.clone();
+        other_key["node_public_key"] = json!(validator_node.node_public_key);
+        assert!(validation::handle_heartbeat(other_key, validator_node.clone()).await.is_err());
+        let outsider_heartbeat: Value = serde_json::to_value(requests::heartbeat_request(&outsider)).unwrap();
+        assert!(validation::handle_heartbeat(outsider_heartbeat, validator_node.clone()).await.is_err());
+        let stale = heartbeat_at(&peer, timestamp - 2 * HEARTBEAT_TIMEOUT.as_secs());
+        assert!(validation::handle_heartbeat(stale, validator_node.clone()).await.is_err());
+        assert!(receiver.try_recv().is_err());
+
+        validation::handle_heartbeat(heartbeat.clone(), validator_node.clone()).await.unwrap();
+        assert_eq!(receiver.recv().await.unwrap(), NodeEvent::PeerJoined { address: "127.0.0.1:8081".to_string() });
+
+        // a replayed heartbeat is rejected, and a later heartbeat from the same peer is not a new join
+        assert!(validation::handle_heartbeat(heartbeat, validator_node.clone()).await.is_err());
+        validation::handle_heartbeat(heartbeat_at(&peer, timestamp + 1), validator_node.clone()).await.unwrap();
+        assert!(receiver.try_recv().is_err());
+    }
+}
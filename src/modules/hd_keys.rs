@@ -0,0 +1,299 @@
+use secp256k1::{Secp256k1, SecretKey, PublicKey};
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+use bip39::Mnemonic;
+use rand::RngCore;
+
+use crate::modules::address;
+use crate::modules::constants::HD_COIN_TYPE;
+
+
+/**
+ * @notice hd_keys.rs contains hierarchical deterministic key derivation on secp256k1 following BIP32, along with
+ * BIP39 mnemonics to back up the seed it starts from. Rather than every keypair being independent random bytes, all
+ * keys of a wallet are derived from a single 64 byte seed, so writing down the mnemonic backs up every key the wallet
+ * has handed out or will hand out.
+ *
+ * Keys are addressed by derivation paths such as m/44'/7777'/0'/0/5, where ' (or h) marks a hardened index. Hardened
+ * children can only be derived from the private key, normal children can also be derived from the extended public
+ * key, without knowing any private key. The wallet uses two BIP44 accounts:
+ *
+ *   m/44'/HD_COIN_TYPE'/0'/0/i   keys of account model accounts (wallet new)
+ *   m/44'/HD_COIN_TYPE'/1'/0/i   fresh receive keys for UTXO outputs (wallet receive)
+ *
+ * Extended keys serialize to the usual base58check xprv/xpub strings.
+ */
+// index from which child indices are hardened
+pub const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+// HMAC key used to derive the master key from a seed
+const MASTER_KEY_HMAC_KEY: &[u8] = b"Bitcoin seed";
+
+// version bytes of serialized extended keys (xprv / xpub)
+const XPRV_VERSION: [u8; 4] = [0x04, 0x88, 0xAD, 0xE4];
+const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xB2, 0x1E];
+
+type HmacSha512 = Hmac<Sha512>;
+
+/**
+ * @notice ExtendedPrivateKey is a secp256k1 private key extended with the chain code needed to derive its children,
+ * along with its position in the key tree.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtendedPrivateKey {
+    pub secret_key: SecretKey,
+    pub chain_code: [u8; 32],
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: u32,
+}
+
+/**
+ * @notice ExtendedPublicKey is the public half of an ExtendedPrivateKey. It can derive the public keys of all
+ * non-hardened children.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtendedPublicKey {
+    pub public_key: PublicKey,
+    pub chain_code: [u8; 32],
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: u32,
+}
+
+impl ExtendedPrivateKey {
+
+    /**
+     * @notice from_seed() derives the master key of the key tree from a seed (usually mnemonic_to_seed()).
+     */
+    pub fn from_seed(seed: &[u8]) -> Result<ExtendedPrivateKey, String> {
+        let (key, chain_code) = hmac_sha512(MASTER_KEY_HMAC_KEY, &[seed]);
+        let secret_key: SecretKey = SecretKey::from_slice(&key).map_err(|_| "Seed derives an invalid master key".to_string())?;
+        Ok(ExtendedPrivateKey { secret_key, chain_code, depth: 0, parent_fingerprint: [0u8; 4], child_number: 0 })
+    }
+
+    /**
+     * @notice derive_child() derives the child key at the given index. Indices from HARDENED_OFFSET up are hardened.
+     * @dev Fails in the (negligible) case the index derives an invalid key, BIP32 then skips to the next index.
+     */
+    pub fn derive_child(&self, index: u32) -> Result<ExtendedPrivateKey, String> {
+        let secp = Secp256k1::new();
+        let public_key: PublicKey = PublicKey::from_secret_key(&secp, &self.secret_key);
+
+        // hardened children commit to the private key, normal children to the public key
+        let (tweak, chain_code) = if index >= HARDENED_OFFSET {
+            hmac_sha512(&self.chain_code, &[&[0u8], &self.secret_key[..], &index.to_be_bytes()])
+        } else {
+            hmac_sha512(&self.chain_code, &[&public_key.serialize(), &index.to_be_bytes()])
+        };
+
+        // child key = parent key + tweak (mod n)
+        let mut secret_key: SecretKey = self.secret_key;
+        secret_key.add_assign(&tweak).map_err(|_| format!("Index {} derives an invalid key", index))?;
+
+        Ok(ExtendedPrivateKey {
+            secret_key,
+            chain_code,
+            depth: self.depth.checked_add(1).ok_or("Maximum derivation depth reached".to_string())?,
+            parent_fingerprint: fingerprint(&public_key),
+            child_number: index,
+        })
+    }
+
+    /**
+     * @notice derive_path() derives the key at a derivation path relative to this key, e.g. m/44'/7777'/0'/0/1.
+     */
+    pub fn derive_path(&self, path: &str) -> Result<ExtendedPrivateKey, String> {
+        parse_derivation_path(path)?.into_iter()
+            .try_fold(self.clone(), |key, index| key.derive_child(index))
+    }
+
+    // Returns the extended public key of this key
+    pub fn extended_public_key(&self) -> ExtendedPublicKey {
+        ExtendedPublicKey {
+            public_key: PublicKey::from_secret_key(&Secp256k1::new(), &self.secret_key),
+            chain_code: self.chain_code,
+            depth: self.depth,
+            parent_fingerprint: self.parent_fingerprint,
+            child_number: self.child_number,
+        }
+    }
+
+    // Serializes the key as a base58check xprv string
+    pub fn to_xprv(&self) -> String {
+        let mut key_data: Vec<u8> = vec![0u8];
+        key_data.extend_from_slice(&self.secret_key[..]);
+        serialize_extended_key(XPRV_VERSION, self.depth, self.parent_fingerprint, self.child_number, &self.chain_code, &key_data)
+    }
+}
+
+impl ExtendedPublicKey {
+
+    /**
+     * @notice derive_child() derives the public key of the non-hardened child at the given index.
+     */
+    pub fn derive_child(&self, index: u32) -> Result<ExtendedPublicKey, String> {
+        if index >= HARDENED_OFFSET { return Err("Hardened children cannot be derived from a public key".to_string()); }
+        let (tweak, chain_code) = hmac_sha512(&self.chain_code, &[&self.public_key.serialize(), &index.to_be_bytes()]);
+
+        // child key = parent key + tweak·G
+        let mut public_key: PublicKey = self.public_key;
+        public_key.add_exp_assign(&Secp256k1::new(), &tweak).map_err(|_| format!("Index {} derives an invalid key", index))?;
+
+        Ok(ExtendedPublicKey {
+            public_key,
+            chain_code,
+            depth: self.depth.checked_add(1).ok_or("Maximum derivation depth reached".to_string())?,
+            parent_fingerprint: fingerprint(&self.public_key),
+            child_number: index,
+        })
+    }
+
+    // Serializes the key as a base58check xpub string
+    pub fn to_xpub(&self) -> String {
+        serialize_extended_key(XPUB_VERSION, self.depth, self.parent_fingerprint, self.child_number, &self.chain_code, &self.public_key.serialize())
+    }
+}
+
+/**
+ * @notice parse_derivation_path() parses a path like m/44'/7777'/0'/0/1 into child indices. Hardened indices are marked
+ * with ' or h.
+ */
+pub fn parse_derivation_path(path: &str) -> Result<Vec<u32>, String> {
+    let mut components = path.split('/');
+    if components.next() != Some("m") { return Err(format!("Derivation path must start with m: {}", path)); }
+
+    components.map(|component| {
+        let (number, hardened) = match component.strip_suffix('\'').or(component.strip_suffix('h')) {
+            Some(number) => (number, true),
+            None => (component, false),
+        };
+        let index: u32 = number.parse().ok()
+            .filter(|index| *index < HARDENED_OFFSET)
+            .ok_or(format!("Invalid derivation path component: {}", component))?;
+        Ok(if hardened { index + HARDENED_OFFSET } else { index })
+    }).collect()
+}
+
+// Returns the derivation path of the i'th account model key of the wallet
+pub fn account_key_path(index: u32) -> String {
+    format!("m/44'/{}'/0'/0/{}", HD_COIN_TYPE, index)
+}
+
+// Returns the derivation path of the i'th UTXO receive key of the wallet
+pub fn receive_key_path(index: u32) -> String {
+    format!("m/44'/{}'/1'/0/{}", HD_COIN_TYPE, index)
+}
+
+// Returns the derivation path of the receive chain, whose xpub derives every receive key
+pub fn receive_chain_path() -> String {
+    format!("m/44'/{}'/1'/0", HD_COIN_TYPE)
+}
+
+//------------------------------------ Mnemonics ------------------------------------//
+
+/**
+ * @notice generate_mnemonic() generates a new English BIP39 mnemonic of 12, 15, 18, 21 or 24 words from fresh random
+ * entropy (4 bytes of entropy per 3 words).
+ */
+pub fn generate_mnemonic(word_count: usize) -> Result<String, String> {
+    if ![12, 15, 18, 21, 24].contains(&word_count) {
+        return Err(format!("A mnemonic has 12, 15, 18, 21 or 24 words, not {}", word_count));
+    }
+
+    let mut entropy: Vec<u8> = vec![0u8; word_count / 3 * 4];
+    rand::thread_rng().fill_bytes(&mut entropy);
+    let mnemonic: Mnemonic = Mnemonic::from_entropy(&entropy).map_err(|e| e.to_string())?;
+    Ok(mnemonic.to_string())
+}
+
+/**
+ * @notice mnemonic_to_seed() checks the words and checksum of a mnemonic and stretches it into the 64 byte seed of the
+ * key tree. The optional BIP39 passphrase is mixed into the seed, so a different passphrase gives a different wallet.
+ */
+pub fn mnemonic_to_seed(phrase: &str, passphrase: &str) -> Result<[u8; 64], String> {
+    let mnemonic: Mnemonic = Mnemonic::parse(phrase.trim()).map_err(|e| format!("Invalid mnemonic: {}", e))?;
+    Ok(mnemonic.to_seed(passphrase))
+}
+
+//------------------------------------ Helpers ------------------------------------//
+
+// HMAC-SHA512 over the concatenated data, split into its left and right 32 byte halves
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> ([u8; 32], [u8; 32]) {
+    let mut mac = HmacSha512::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for bytes in data { mac.update(bytes); }
+    let output = mac.finalize().into_bytes();
+
+    let mut left = [0u8; 32];
+    let mut right = [0u8; 32];
+    left.copy_from_slice(&output[..32]);
+    right.copy_from_slice(&output[32..]);
+    (left, right)
+}
+
+// First 4 bytes of HASH160 (RIPEMD160 of SHA256) of the compressed public key
+fn fingerprint(public_key: &PublicKey) -> [u8; 4] {
+    let hash = address::hash160(&public_key.serialize());
+    let mut fingerprint = [0u8; 4];
+    fingerprint.copy_from_slice(&hash[..4]);
+    fingerprint
+}
+
+// Serializes the fields of an extended key in the 78 byte BIP32 layout and base58check encodes it
+fn serialize_extended_key(version: [u8; 4], depth: u8, parent_fingerprint: [u8; 4], child_number: u32, chain_code: &[u8; 32], key_data: &[u8]) -> String {
+    let mut bytes: Vec<u8> = Vec::with_capacity(78);
+    bytes.extend_from_slice(&version);
+    bytes.push(depth);
+    bytes.extend_from_slice(&parent_fingerprint);
+    bytes.extend_from_slice(&child_number.to_be_bytes());
+    bytes.extend_from_slice(chain_code);
+    bytes.extend_from_slice(key_data);
+    bs58::encode(bytes).with_check().into_string()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bip32_test_vector_1() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = ExtendedPrivateKey::from_seed(&seed).unwrap();
+        assert_eq!(master.to_xprv(), "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi");
+        assert_eq!(master.extended_public_key().to_xpub(), "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8");
+
+        let child = master.derive_path("m/0'/1/2'/2/1000000000").unwrap();
+        assert_eq!(child.to_xprv(), "xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76");
+        assert_eq!(child.extended_public_key().to_xpub(), "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy");
+    }
+
+    #[test]
+    fn test_public_derivation_matches_private_derivation() {
+        let master = ExtendedPrivateKey::from_seed(&[7u8; 64]).unwrap();
+        let account = master.derive_path(&receive_chain_path()).unwrap();
+
+        // receive keys can be derived from the account xpub without any private key
+        let receive_key = account.extended_public_key().derive_child(3).unwrap();
+        assert_eq!(receive_key, master.derive_path(&receive_key_path(3)).unwrap().extended_public_key());
+        assert!(account.extended_public_key().derive_child(HARDENED_OFFSET).is_err());
+
+        assert!(parse_derivation_path("44'/0").is_err());
+        assert!(parse_derivation_path("m/2147483648").is_err());
+        assert_eq!(parse_derivation_path("m/0h/1").unwrap(), vec![HARDENED_OFFSET, 1]);
+    }
+
+    #[test]
+    fn test_mnemonic_to_seed() {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = mnemonic_to_seed(phrase, "TREZOR").unwrap();
+        assert_eq!(hex::encode(seed), "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04");
+
+        // a generated mnemonic round trips, a bad checksum is rejected
+        let generated = generate_mnemonic(24).unwrap();
+        assert_eq!(generated.split(' ').count(), 24);
+        assert!(mnemonic_to_seed(&generated, "").is_ok());
+        assert!(mnemonic_to_seed(&phrase.replace("about", "abandon"), "").is_err());
+        assert!(generate_mnemonic(13).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use secp256k1::{PublicKey, SecretKey};
+use sha2::{Sha256, Digest};
+use rand::RngCore;
+use std::fmt;
+
+use crate::modules::script::{Script, Instruction, OP_IF, OP_ELSE, OP_ENDIF, OP_SHA256, OP_EQUALVERIFY, OP_DROP, OP_CHECKSIG, OP_CHECKLOCKTIMEVERIFY};
+use crate::modules::utxo::{UTXOTransaction, TxInput, TxOutput, OutPoint, SIGHASH_ALL};
+
+
+/**
+ * @notice htlc.rs contains hash time-locked contracts (HTLCs), the building block of atomic swaps. An HTLC output can be
+ * claimed by the receiver by revealing the preimage of a SHA-256 hash, or refunded to the sender once a timeout (a lock
+ * time, see utxo::UTXOTransaction) has passed. It is a locking script (see script.rs):
+ *
+ *   OP_IF
+ *       OP_SHA256 <hash> OP_EQUALVERIFY <receiver>
+ *   OP_ELSE
+ *       <timeout> OP_CHECKLOCKTIMEVERIFY OP_DROP <sender>
+ *   OP_ENDIF
+ *   OP_CHECKSIG
+ *
+ * claimed with <signature> <preimage> 1 and refunded with <signature> 0 by a transaction locked until the timeout.
+ *
+ * To swap, Alice picks a secret and pays Bob an HTLC on one chain; Bob pays Alice an HTLC with the same hash and an
+ * earlier timeout on the other chain (or between two accounts of the same chain). Claiming Bob's HTLC reveals the
+ * secret in the unlocking script of the claim, which lets Bob claim Alice's. If either stops, both refund.
+ */
+/**
+ * @notice Htlc is the terms of a hash time-locked contract.
+ * @param hash - the SHA-256 hash whose preimage claims the output
+ * @param receiver - the key that can claim the output with the preimage
+ * @param sender - the key that can take the output back after the timeout
+ * @param timeout - the block height or unix time from which the sender can refund the output
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Htlc {
+    pub hash: [u8; 32],
+    pub receiver: PublicKey,
+    pub sender: PublicKey,
+    pub timeout: u64,
+}
+
+impl Htlc {
+
+    pub fn new(hash: [u8; 32], receiver: PublicKey, sender: PublicKey, timeout: u64) -> Result<Htlc, String> {
+        if timeout == 0 { return Err("The timeout of an HTLC must be a block height or unix time".to_string()); }
+        Ok(Htlc { hash, receiver, sender, timeout })
+    }
+
+    // Returns the locking script of the contract
+    pub fn script(&self) -> Script {
+        Script::new()
+            .push_opcode(OP_IF)
+            .push_opcode(OP_SHA256).push_data(&self.hash).push_opcode(OP_EQUALVERIFY).push_data(&self.receiver.serialize())
+            .push_opcode(OP_ELSE)
+            .push_number(self.timeout).push_opcode(OP_CHECKLOCKTIMEVERIFY).push_opcode(OP_DROP).push_data(&self.sender.serialize())
+            .push_opcode(OP_ENDIF)
+            .push_opcode(OP_CHECKSIG)
+    }
+
+    // Returns the recipient of outputs locked by the contract
+    pub fn to_recipient(&self) -> Vec<u8> {
+        self.script().to_recipient()
+    }
+
+    /**
+     * @notice from_recipient() recovers the terms of the contract locking an output, if its recipient is an HTLC.
+     */
+    pub fn from_recipient(recipient: &[u8]) -> Result<Htlc, String> {
+        let script: Script = Script::from_recipient(recipient)?;
+        let instructions = script.instructions()?;
+
+        // read the terms from their positions in the script, then check the script is exactly the template
+        let push = |index: usize| match instructions.get(index) {
+            Some(Instruction::Push(data)) => Ok(data.to_vec()),
+            _ => Err("Not an HTLC".to_string()),
+        };
+        let hash: [u8; 32] = push(2)?.try_into().map_err(|_| "Not an HTLC".to_string())?;
+        let receiver = PublicKey::from_slice(&push(4)?).map_err(|_| "Not an HTLC".to_string())?;
+        let sender = PublicKey::from_slice(&push(9)?).map_err(|_| "Not an HTLC".to_string())?;
+        let timeout: u64 = instructions.get(6).and_then(Instruction::as_number).ok_or("Not an HTLC".to_string())?;
+
+        let htlc = Htlc::new(hash, receiver, sender, timeout)?;
+        match htlc.script() == script {
+            true => Ok(htlc),
+            false => Err("Not an HTLC".to_string()),
+        }
+    }
+
+    /**
+     * @notice claim() returns the transaction spending the HTLC output at the outpoint to the outputs, signed by the
+     * receiver and revealing the preimage.
+     */
+    pub fn claim(&self, outpoint: OutPoint, outputs: Vec<TxOutput>, preimage: &[u8], secret_key: &SecretKey, timestamp: u64) -> Result<UTXOTransaction, String> {
+        if hash_preimage(preimage) != self.hash { return Err("The preimage does not match the hash of the HTLC".to_string()); }
+        self.spend(outpoint, outputs, secret_key, 0, Script::new().push_data(preimage).push_number(1), timestamp)
+    }
+
+    /**
+     * @notice refund() returns the transaction spending the HTLC output at the outpoint back to the outputs, signed by
+     * the sender and locked until the timeout.
+     */
+    pub fn refund(&self, outpoint: OutPoint, outputs: Vec<TxOutput>, secret_key: &SecretKey, timestamp: u64) -> Result<UTXOTransaction, String> {
+        self.spend(outpoint, outputs, secret_key, self.timeout, Script::new().push_number(0), timestamp)
+    }
+
+    // Signs the spend of the HTLC output, the branch taken is selected by the rest of the unlocking script
+    fn spend(&self, outpoint: OutPoint, outputs: Vec<TxOutput>, secret_key: &SecretKey, lock_time: u64, branch: Script, timestamp: u64) -> Result<UTXOTransaction, String> {
+        let mut transaction = UTXOTransaction::new(vec![TxInput::new(outpoint, String::new(), self.to_recipient())], outputs, timestamp)
+            .with_lock_time(lock_time);
+        let signature: Vec<u8> = transaction.input_signature(0, secret_key, SIGHASH_ALL)?;
+        let unlocking = Script::new().push_data(&signature);
+        transaction.inputs[0].signature = hex::encode([unlocking.as_bytes(), branch.as_bytes()].concat());
+        Ok(transaction)
+    }
+}
+
+impl fmt::Display for Htlc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "HTLC claimable by {} with the preimage of {}, refundable to {} from {}",
+            self.receiver, hex::encode(self.hash), self.sender, self.timeout)
+    }
+}
+
+/**
+ * @notice hash_preimage() returns the SHA-256 hash an HTLC locks to for a preimage.
+ */
+pub fn hash_preimage(preimage: &[u8]) -> [u8; 32] {
+    Sha256::digest(preimage).into()
+}
+
+/**
+ * @notice generate_preimage() returns a random 32 byte preimage (the secret of a swap).
+ */
+pub fn generate_preimage() -> [u8; 32] {
+    let mut preimage = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut preimage);
+    preimage
+}
+
+/**
+ * @notice revealed_preimage() returns the preimage revealed by an input claiming an HTLC, so that the other side of a
+ * swap can claim theirs with it.
+ */
+pub fn revealed_preimage(input: &TxInput) -> Option<Vec<u8>> {
+    let htlc = Htlc::from_recipient(&input.public_key).ok()?;
+    let unlocking = Script::from_bytes(hex::decode(&input.signature).ok()?);
+    let instructions = unlocking.instructions().ok()?;
+    instructions.iter().find_map(|instruction| match instruction {
+        Instruction::Push(data) if hash_preimage(data) == htlc.hash => Some(data.to_vec()),
+        _ => None,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::zk_proof;
+
+    fn test_htlc(timeout: u64) -> (Htlc, SecretKey, SecretKey) {
+        let ((receiver_key, receiver), (sender_key, sender)) = (zk_proof::generate_keypair().unwrap(), zk_proof::generate_keypair().unwrap());
+        (Htlc::new(hash_preimage(b"secret"), receiver, sender, timeout).unwrap(), receiver_key, sender_key)
+    }
+
+    #[test]
+    fn test_htlc_recipient_round_trip() {
+        for timeout in [5, 16, 17, 800_000, 1_700_000_000] {
+            let (htlc, _, _) = test_htlc(timeout);
+            assert_eq!(Htlc::from_recipient(&htlc.to_recipient()).unwrap(), htlc);
+        }
+        assert!(Htlc::from_recipient(&Script::from_asm("1").unwrap().to_recipient()).is_err());
+    }
+
+    #[test]
+    fn test_claim_and_refund() {
+        let (htlc, receiver_key, sender_key) = test_htlc(1_700_000_000);
+        let outputs = vec![TxOutput::new(90, vec![1; 20])];
+        let outpoint = OutPoint::new(vec![7; 32], 0);
+
+        let claim = htlc.claim(outpoint.clone(), outputs.clone(), b"secret", &receiver_key, 12345).unwrap();
+        assert!(claim.verify_input(0).is_ok());
+        assert_eq!(revealed_preimage(&claim.inputs[0]).unwrap(), b"secret".to_vec());
+        assert!(htlc.claim(outpoint.clone(), outputs.clone(), b"guess", &receiver_key, 12345).is_err());
+
+        // only the sender can refund, and the refund is locked until the timeout
+        let refund = htlc.refund(outpoint.clone(), outputs.clone(), &sender_key, 12345).unwrap();
+        assert!(refund.verify_input(0).is_ok());
+        assert_eq!(refund.lock_time, htlc.timeout);
+        assert!(htlc.refund(outpoint, outputs, &receiver_key, 12345).unwrap().verify_input(0).is_err());
+    }
+}
@@ -2,6 +2,8 @@ use sha2::{Sha256, Digest};
 use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
 
+use crate::modules::address;
+
 /**
  * @notice merkleTree.rs contains an implementation of a merkle tree for the purpose of retrieval of 
  * account balances from public keys. As well as for the validation of transactions and new blocks 
@@ -12,6 +14,9 @@ use serde::{Serialize, Deserialize};
  * merkle tree structure, which hashes together (account balance, pub key) pairs until a root hash is 
  * formed. This root hash is required during the consensus process to validate new blocks being written
  * to the blockchain.
+ *
+ * Changes to accounts overwrite their balances and nonces, so each block records the prior state of the accounts it 
+ * changes in an AccountUndo. MerkleTree::undo() restores them when the block is reverted (see BlockChain::revert_block()).
  */
 
  
@@ -21,7 +26,8 @@ use serde::{Serialize, Deserialize};
  * @param balance - the balance of the account.
  * @param nonce - the nonce of the account (amount of transactions sent from this account).
  * @param public_key_hash - hash of the public key for additional verification.
- * Digital signatures are used to verify ownership of the private key.
+ * @param schnorr_commitment - compressed Ristretto point P = x·G registered at account creation. Transfers from 
+ * the account carry a Schnorr proof of knowledge of x that is verified against it (see zk_proof.rs).
  */
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Account {
@@ -29,6 +35,8 @@ pub struct Account {
     pub public_key_hash: Vec<u8>,
     pub balance: u64,
     pub nonce: u64,
+    #[serde(default)]
+    pub schnorr_commitment: Vec<u8>,
 }
 
 /**
@@ -81,16 +89,37 @@ impl MerkleTree {
         self.accounts_vec.iter().find(|account| account.public_key == public_key).map(|account| account.public_key_hash.clone())
     }
 
+    // Returns the schnorr commitment registered for an account
+    pub fn get_schnorr_commitment(&self, public_key: &[u8]) -> Option<Vec<u8>> {
+        self.accounts_vec.iter().find(|account| account.public_key == public_key).map(|account| account.schnorr_commitment.clone())
+    }
+
     // Returns the nonce of a specific public key
     pub fn get_nonce(&self, public_key: &[u8]) -> Option<u64> {
         self.accounts_vec.iter().find(|account| account.public_key == public_key).map(|account| account.nonce)
     }
     
+    // Returns the public key of the account whose address encodes the given public key hash (see address.rs)
+    pub fn find_account_by_public_key_hash(&self, public_key_hash: &[u8]) -> Option<Vec<u8>> {
+        self.accounts_vec.iter()
+            .find(|account| std::str::from_utf8(&account.public_key).ok()
+                .and_then(|public_key| hex::decode(public_key).ok())
+                .is_some_and(|public_key| address::hash160(&public_key) == public_key_hash))
+            .map(|account| account.public_key.clone())
+    }
+
     // Checks if an account exists
     pub fn account_exists(&self, public_key: &[u8]) -> bool {
         self.accounts_map.contains_key(public_key)
     }
 
+    // Returns the root hash over the accounts in their current order, None for an empty tree
+    pub fn root_hash(&self) -> Option<Vec<u8>> {
+        let mut tree: MerkleTree = self.clone();
+        tree.generate_merkle_root();
+        tree.root.as_ref().map(|root| MerkleTree::extract_hash(root).to_vec())
+    }
+
     // Changes the balance of an account
     pub fn change_balance(&mut self, public_key: &[u8], new_balance: u64) {
         if let Some(balance) = self.accounts_map.get_mut(public_key) {
@@ -110,6 +139,36 @@ impl MerkleTree {
         }
     }
 
+    /**
+     * @notice undo() restores the accounts recorded in an AccountUndo to their prior state, in reverse order. An account 
+     * that did not exist before is removed, and one that did is put back at the index in accounts_vec it was recorded 
+     * at, so that the merkle root (which depends on the order of the accounts) is restored too.
+     */
+    pub fn undo(&mut self, undo: &AccountUndo) {
+        let mut missing: Vec<&(usize, Account)> = Vec::new();
+        for (public_key, prior) in undo.accounts.iter().rev() {
+            match prior {
+                Some(recorded) => {
+                    match self.accounts_vec.iter_mut().find(|existing| existing.public_key == *public_key) {
+                        Some(existing) => *existing = recorded.1.clone(),
+                        None => missing.push(recorded),
+                    }
+                    self.accounts_map.insert(public_key.clone(), recorded.1.balance);
+                }
+                None => {
+                    self.accounts_vec.retain(|existing| existing.public_key != *public_key);
+                    self.accounts_map.remove(public_key);
+                }
+            }
+        }
+
+        // put missing accounts back in ascending order of index, so that each one lands where it was
+        missing.sort_by_key(|(index, _)| *index);
+        for (index, account) in missing {
+            self.accounts_vec.insert((*index).min(self.accounts_vec.len()), account.clone());
+        }
+    }
+
     /**
      * @notice generate_merkle_root() is a method that generates the root hash of the merkle tree. It is called during the consensus 
      * process to validate new blocks being written to the blockchain. The method transforms accounts into leaf nodes and hashes them 
@@ -150,6 +209,7 @@ impl MerkleTree {
         hasher.update(&account.balance.to_be_bytes());
         hasher.update(&account.nonce.to_be_bytes());    
         hasher.update(&account.public_key_hash);
+        hasher.update(&account.schnorr_commitment);
 
         // return the hash
         hasher.finalize().to_vec()
@@ -174,6 +234,30 @@ impl MerkleTree {
     }
 }
 
+// An account recorded by an AccountUndo: its index in accounts_vec and its state, None if it did not exist yet
+pub type RecordedAccount = Option<(usize, Account)>;
+
+/**
+ * @notice AccountUndo records the state of each account a block changes before the first change, along with its index 
+ * in accounts_vec, or None for an account that did not exist yet. It is stored alongside the block and restored with 
+ * MerkleTree::undo().
+ */
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountUndo {
+    accounts: Vec<(Vec<u8>, RecordedAccount)>,
+}
+
+impl AccountUndo {
+
+    // Records the current state and index of an account, unless it was already recorded
+    pub fn record(&mut self, merkle_tree: &MerkleTree, public_key: &[u8]) {
+        if self.accounts.iter().any(|(recorded, _)| recorded == public_key) { return; }
+        let account: RecordedAccount = merkle_tree.accounts_vec.iter().position(|account| account.public_key == public_key)
+            .map(|index| (index, merkle_tree.accounts_vec[index].clone()));
+        self.accounts.push((public_key.to_vec(), account));
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -190,6 +274,7 @@ mod tests {
             public_key_hash: vec![1, 2, 3, 4],
             balance: 100,
             nonce: 1,
+            schnorr_commitment: vec![],
         };
         tree.insert_account(account.clone());
         
@@ -210,6 +295,7 @@ mod tests {
             public_key_hash: vec![1, 2, 3, 4],
             balance: 100,
             nonce: 1,
+            schnorr_commitment: vec![],
         };
         tree.insert_account(account.clone());  // add the account to the tree
 
@@ -229,6 +315,7 @@ mod tests {
             public_key_hash: vec![1, 2, 3, 4],   
             balance: 100,
             nonce: 1,
+            schnorr_commitment: vec![],
         };  
 
         // add the account to the tree and generate the merkle root
@@ -253,6 +340,7 @@ mod tests {
             public_key_hash: vec![1, 2, 3, 4],
             balance: 100,
             nonce: 1,
+            schnorr_commitment: vec![],
         };
 
         tree.insert_account(account.clone());
@@ -267,6 +355,7 @@ mod tests {
             public_key_hash: vec![1, 2, 3, 4],
             balance: 100,
             nonce: 1,
+            schnorr_commitment: vec![],
         };
         
         // add 25 account clones to the tree
@@ -305,6 +394,7 @@ mod tests {
             public_key_hash: vec![1, 2, 3, 4],
             balance: 100,
             nonce: 1,
+            schnorr_commitment: vec![],
         };  
 
         // insert account into the tree
@@ -329,4 +419,56 @@ mod tests {
         // adopt_network_state.rs since HashMap<Vec<u8>, u64> cannot be directly
         // serialized to JSON (byte arrays can't be JSON object keys)
     }
-}
\ No newline at end of file
+
+    /**
+     * @test test_undo_restores_accounts() checks that MerkleTree::undo() restores changed balances and nonces and removes
+     * accounts created after the undo record was started.
+     */
+    #[test]
+    fn test_undo_restores_accounts() {
+        let mut tree = MerkleTree::new();
+        for public_key in [vec![1], vec![2]] {
+            tree.insert_account(Account { public_key: public_key.clone(), public_key_hash: public_key, balance: 50, nonce: 0, schnorr_commitment: vec![] });
+        }
+        let before: MerkleTree = tree.clone();
+
+        // change both accounts (one twice) and create a third, recording each before its first change
+        let mut undo = AccountUndo::default();
+        for public_key in [vec![1], vec![2], vec![1], vec![3]] { undo.record(&tree, &public_key); }
+        tree.change_balance(&[1], 10);
+        tree.increment_nonce(&[1]);
+        tree.change_balance(&[2], 90);
+        tree.change_balance(&[1], 5);
+        tree.insert_account(Account { public_key: vec![3], public_key_hash: vec![3], balance: 7, nonce: 0, schnorr_commitment: vec![] });
+        assert_ne!(tree, before);
+
+        // undoing restores the exact prior tree, account order included
+        tree.undo(&undo);
+        assert_eq!(tree, before);
+        assert!(!tree.account_exists(&[3]));
+    }
+
+    /**
+     * @test test_undo_puts_accounts_back_in_place() checks that MerkleTree::undo() puts an account missing from the tree 
+     * back at the index it was recorded at, restoring the merkle root.
+     */
+    #[test]
+    fn test_undo_puts_accounts_back_in_place() {
+        let mut tree = MerkleTree::new();
+        for public_key in [vec![1], vec![2], vec![3]] {
+            tree.insert_account(Account { public_key: public_key.clone(), public_key_hash: public_key, balance: 50, nonce: 0, schnorr_commitment: vec![] });
+        }
+        let before: MerkleTree = tree.clone();
+
+        // record the first two accounts, then drop them from the tree
+        let mut undo = AccountUndo::default();
+        for public_key in [vec![1], vec![2]] { undo.record(&tree, &public_key); }
+        tree.accounts_vec.drain(..2);
+        tree.accounts_map.retain(|public_key, _| *public_key == vec![3]);
+
+        // undoing inserts them back in front of the third one
+        tree.undo(&undo);
+        assert_eq!(tree, before);
+        assert_eq!(tree.root_hash(), before.root_hash());
+    }
+}
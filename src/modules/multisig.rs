@@ -0,0 +1,255 @@
+use serde::{Serialize, Deserialize};
+use secp256k1::{PublicKey, SecretKey};
+use std::collections::BTreeMap;
+
+use crate::modules::address::PUBLIC_KEY_LENGTH;
+use crate::modules::constants::MAX_MULTISIG_KEYS;
+use crate::modules::utxo::{UTXOTransaction, SIGHASH_ALL};
+use crate::modules::verifier::{self, secp_context};
+
+
+/**
+ * @notice multisig.rs contains M-of-N multi-signature outputs. A MultisigLock locks a UTXO output to N public keys, any
+ * M of which must sign to spend it. The lock is stored as the recipient of the output:
+ *
+ *   MULTISIG_LOCK_TAG | M | N | N compressed public keys
+ *
+ * Its length never equals that of a public key hash or a public key, so the three kinds of recipient never collide. The
+ * input spending a multisig output reveals the lock as its public key, and its signature holds the signatures of M of
+ * the keys (each a compact signature followed by the sighash flag byte) in the order of the keys of the lock. It is
+ * verified by running the multisig template of script.rs.
+ *
+ * The keys of a lock are usually held by different people, so a spend is co-signed offline: a PartiallySignedTransaction
+ * is passed from signer to signer (or signed in parallel and combined), and finalized into a transaction once M keys
+ * have signed every input.
+ */
+// first byte of the encoding of a multisig lock
+pub const MULTISIG_LOCK_TAG: u8 = 0x4d;
+
+/**
+ * @notice MultisigLock is a threshold of public keys locking an output.
+ * @param threshold - the number of keys that must sign (M)
+ * @param public_keys - the keys that may sign (N), in the order their signatures must be given
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultisigLock {
+    pub threshold: u8,
+    pub public_keys: Vec<PublicKey>,
+}
+
+impl MultisigLock {
+
+    // Creates a lock, checking that 1 <= M <= N <= MAX_MULTISIG_KEYS and that no key is repeated
+    pub fn new(threshold: u8, public_keys: Vec<PublicKey>) -> Result<MultisigLock, String> {
+        if public_keys.is_empty() || public_keys.len() > MAX_MULTISIG_KEYS {
+            return Err(format!("A multisig lock must have between 1 and {} keys", MAX_MULTISIG_KEYS));
+        }
+        if threshold == 0 || threshold as usize > public_keys.len() {
+            return Err(format!("The threshold must be between 1 and {}", public_keys.len()));
+        }
+        if public_keys.iter().enumerate().any(|(index, key)| public_keys[..index].contains(key)) {
+            return Err("A multisig lock cannot repeat a key".to_string());
+        }
+        Ok(MultisigLock { threshold, public_keys })
+    }
+
+    // Returns the encoding of the lock stored as the recipient of an output
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![MULTISIG_LOCK_TAG, self.threshold, self.public_keys.len() as u8];
+        for public_key in &self.public_keys {
+            bytes.extend_from_slice(&public_key.serialize());
+        }
+        bytes
+    }
+
+    // Parses and validates the encoding of a lock
+    pub fn from_bytes(bytes: &[u8]) -> Result<MultisigLock, String> {
+        let (threshold, key_count, keys) = match bytes {
+            [MULTISIG_LOCK_TAG, threshold, key_count, keys @ ..] => (*threshold, *key_count as usize, keys),
+            _ => return Err("Not a multisig lock".to_string()),
+        };
+        if keys.len() != key_count * PUBLIC_KEY_LENGTH {
+            return Err(format!("A multisig lock of {} keys must hold {} bytes of keys", key_count, key_count * PUBLIC_KEY_LENGTH));
+        }
+        let public_keys = keys.chunks(PUBLIC_KEY_LENGTH)
+            .map(|key| PublicKey::from_slice(key).map_err(|e| format!("Invalid key in multisig lock: {}", e)))
+            .collect::<Result<Vec<PublicKey>, String>>()?;
+        MultisigLock::new(threshold, public_keys)
+    }
+
+    // Returns the position of a key in the lock
+    pub fn key_index(&self, public_key: &PublicKey) -> Option<usize> {
+        self.public_keys.iter().position(|key| key == public_key)
+    }
+}
+
+impl std::fmt::Display for MultisigLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let keys: Vec<String> = self.public_keys.iter().map(|key| key.to_string()).collect();
+        write!(f, "{}-of-{} multisig [{}]", self.threshold, self.public_keys.len(), keys.join(", "))
+    }
+}
+
+/**
+ * @notice PartiallySignedTransaction is a UTXO transaction being co-signed. It is serialized to JSON and passed between
+ * signers, each adding the signatures of the keys they hold.
+ * @param transaction - the transaction being signed, its inputs hold no signatures until it is finalized
+ * @param partial_signatures - for each input, the hex signatures collected so far by hex public key of the signer
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartiallySignedTransaction {
+    pub transaction: UTXOTransaction,
+    pub partial_signatures: Vec<BTreeMap<String, String>>,
+}
+
+impl PartiallySignedTransaction {
+
+    // Starts co-signing a transaction. Each input holds the public key or the multisig lock of the output it spends.
+    pub fn new(mut transaction: UTXOTransaction) -> PartiallySignedTransaction {
+        for input in &mut transaction.inputs { input.signature.clear(); }
+        let partial_signatures = vec![BTreeMap::new(); transaction.inputs.len()];
+        PartiallySignedTransaction { transaction, partial_signatures }
+    }
+
+    /**
+     * @notice sign() adds a SIGHASH_ALL signature of the key to every input it can sign, i.e. every input spending an
+     * output locked to the key or to a multisig lock containing it. Returns the number of inputs signed.
+     */
+    pub fn sign(&mut self, secret_key: &SecretKey) -> Result<usize, String> {
+        let public_key: String = PublicKey::from_secret_key(secp_context(), secret_key).to_string();
+        let mut signed: usize = 0;
+        for input_index in 0..self.transaction.inputs.len() {
+            if let Ok(signature) = self.transaction.input_signature(input_index, secret_key, SIGHASH_ALL) {
+                self.partial_signatures[input_index].insert(public_key.clone(), hex::encode(signature));
+                signed += 1;
+            }
+        }
+        match signed {
+            0 => Err("The key cannot sign any input of the transaction".to_string()),
+            signed => Ok(signed),
+        }
+    }
+
+    /**
+     * @notice combine() adds the signatures collected by another signer of the same transaction.
+     */
+    pub fn combine(&mut self, other: &PartiallySignedTransaction) -> Result<(), String> {
+        if other.transaction.compute_hash() != self.transaction.compute_hash() {
+            return Err("Cannot combine signatures of different transactions".to_string());
+        }
+        for (signatures, other_signatures) in self.partial_signatures.iter_mut().zip(&other.partial_signatures) {
+            signatures.extend(other_signatures.iter().map(|(key, signature)| (key.clone(), signature.clone())));
+        }
+        Ok(())
+    }
+
+    // Returns the indexes of the inputs that still lack signatures
+    pub fn unsigned_inputs(&self) -> Vec<usize> {
+        (0..self.transaction.inputs.len()).filter(|input_index| self.final_signature(*input_index).is_err()).collect()
+    }
+
+    // Assembles the final signature of an input from the partial signatures of its keys
+    fn final_signature(&self, input_index: usize) -> Result<String, String> {
+        let input = &self.transaction.inputs[input_index];
+        let signatures = &self.partial_signatures[input_index];
+
+        // a single key input is signed by its key
+        let lock = match MultisigLock::from_bytes(&input.public_key) {
+            Ok(lock) => lock,
+            Err(_) => return signatures.get(&hex::encode(&input.public_key)).cloned()
+                .ok_or_else(|| format!("Input {} is not signed", input_index)),
+        };
+
+        // a multisig input is signed by the first M keys that signed, in the order of the lock
+        let lock_signatures: Vec<&String> = lock.public_keys.iter()
+            .filter_map(|public_key| signatures.get(&public_key.to_string()))
+            .take(lock.threshold as usize)
+            .collect();
+        if lock_signatures.len() < lock.threshold as usize {
+            return Err(format!("Input {} has {} of {} signatures", input_index, lock_signatures.len(), lock.threshold));
+        }
+        Ok(lock_signatures.into_iter().map(String::as_str).collect())
+    }
+
+    /**
+     * @notice finalize() returns the signed transaction once every input has enough signatures, after verifying them.
+     */
+    pub fn finalize(&self) -> Result<UTXOTransaction, String> {
+        let mut transaction: UTXOTransaction = self.transaction.clone();
+        for input_index in 0..transaction.inputs.len() {
+            transaction.inputs[input_index].signature = self.final_signature(input_index)?;
+        }
+        verifier::verify_transaction_signatures(&transaction)?;
+        Ok(transaction)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::utxo::{OutPoint, TxInput, TxOutput};
+
+    fn keys(count: u8) -> (Vec<SecretKey>, Vec<PublicKey>) {
+        let secret_keys: Vec<SecretKey> = (1..=count).map(|i| SecretKey::from_slice(&[i; 32]).unwrap()).collect();
+        let public_keys = secret_keys.iter().map(|key| PublicKey::from_secret_key(secp_context(), key)).collect();
+        (secret_keys, public_keys)
+    }
+
+    #[test]
+    fn test_multisig_lock_encoding() {
+        let (_, public_keys) = keys(3);
+        let lock = MultisigLock::new(2, public_keys.clone()).unwrap();
+        let bytes = lock.to_bytes();
+        assert_eq!(bytes.len(), 3 + 3 * PUBLIC_KEY_LENGTH);
+        assert_eq!(MultisigLock::from_bytes(&bytes).unwrap(), lock);
+
+        assert!(MultisigLock::new(0, public_keys.clone()).is_err());
+        assert!(MultisigLock::new(4, public_keys.clone()).is_err());
+        assert!(MultisigLock::new(1, vec![public_keys[0], public_keys[0]]).is_err());
+        assert!(MultisigLock::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(MultisigLock::from_bytes(&public_keys[0].serialize()).is_err());
+    }
+
+    #[test]
+    fn test_co_signing_a_multisig_spend() {
+        let (secret_keys, public_keys) = keys(3);
+        let lock = MultisigLock::new(2, public_keys).unwrap();
+        let transaction = UTXOTransaction::new(
+            vec![TxInput::new(OutPoint::new(vec![1; 32], 0), String::new(), lock.to_bytes())],
+            vec![TxOutput::new(90, vec![7; 20])],
+            12345,
+        );
+
+        // the first and last keys sign separately and their signatures are combined
+        let mut first = PartiallySignedTransaction::new(transaction.clone());
+        let mut last = first.clone();
+        assert_eq!(first.sign(&secret_keys[0]).unwrap(), 1);
+        assert!(first.finalize().is_err());
+        assert_eq!(first.unsigned_inputs(), vec![0]);
+        last.sign(&secret_keys[2]).unwrap();
+        first.combine(&last).unwrap();
+        assert!(first.unsigned_inputs().is_empty());
+
+        let signed = first.finalize().unwrap();
+        assert!(signed.verify_input(0).is_ok());
+
+        // a key outside the lock cannot sign, and one signature repeated does not reach the threshold
+        assert!(first.clone().sign(&SecretKey::from_slice(&[9; 32]).unwrap()).is_err());
+        let mut repeated = signed.clone();
+        let signature = first.partial_signatures[0].values().next().unwrap().clone();
+        repeated.inputs[0].signature = format!("{}{}", signature, signature);
+        assert!(repeated.verify_input(0).is_err());
+
+        // signatures given out of key order are rejected
+        let mut reordered = signed.clone();
+        let signatures = &signed.inputs[0].signature;
+        let half = signatures.len() / 2;
+        reordered.inputs[0].signature = format!("{}{}", &signatures[half..], &signatures[..half]);
+        assert!(reordered.verify_input(0).is_err());
+
+        // signatures of another transaction cannot be combined
+        let other = PartiallySignedTransaction::new(UTXOTransaction::new(transaction.inputs.clone(), vec![], 1));
+        assert!(first.combine(&other).is_err());
+    }
+}
@@ -1,44 +1,81 @@
 use std::io::Error as IoError;
 use std::fs;
 use tokio::net::TcpListener;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::time;
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
 use sha2::{Digest, Sha256};
 use crate::modules::validation;
 use crate::modules::validation::ValidatorNode;
-use crate::modules::constants::HEARTBEAT_PERIOD;
+use crate::modules::constants::{HEARTBEAT_PERIOD, MATURITY_CHECK_PERIOD};
 use crate::modules::consensus;
-use crate::modules::blockchain::{print_chain, save_chain_json};
+use crate::modules::blockchain::{print_chain, save_chain_json, chain_id, GenesisConfig};
 use crate::modules::requests;
+use crate::modules::encoding::{self, NETWORK_REQUEST_TAG};
 use crate::modules::adopt_network_state;
+use crate::modules::query;
+use crate::modules::rpc;
+use crate::modules::zk_proof;
 
 
 /**
  * @notice network.rs contains the main logic for the network listenening, as well as the master event 
  * handler for all incoming traffic into the network. The network will listen for incoming transactions,
  * account creations, and consensus requests. The network will also send heartbeats to the network every
- * HEARTBEAT_PERIOD seconds.
+ * HEARTBEAT_PERIOD seconds, and handles held time locked UTXO transactions once they mature. Read-only queries (see query.rs) are answered directly on the connection they arrive on.
  */
 
 
  /**
  * @notice the following structs are used to load in the accepted_ports.json file which contains a list
  * of accepted ports for the network. When a node is booted up, if the port cannot connnect to the network,
- * an excpetion will be thrown and handled by attempting to connect to the next port in the list.
+ * an excpetion will be thrown and handled by attempting to connect to the next port in the list. The optional
+ * genesis section selects the network (and therefore the chain id) the nodes and clients belong to.
  */
 #[derive(Debug, Serialize, Deserialize)]
-pub struct NetworkConfig { pub nodes: Vec<PortConfig>,}
+pub struct NetworkConfig { 
+    pub nodes: Vec<PortConfig>,
+    #[serde(default)]
+    pub genesis: GenesisConfig,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PortConfig {
    pub id: String,
    pub address: String,
    pub port: u16,
+   #[serde(default)]
+   pub rpc_port: Option<u16>, // JSON-RPC port (defaults to port + RPC_PORT_OFFSET)
+   #[serde(default)]
+   pub key_file: Option<String>, // file holding the node key of the validator on this port
 }
 
 
+/**
+ * @notice load_genesis_config() reads the genesis section of accepted_ports.json. The default devnet genesis is used 
+ * if the file cannot be read or has no genesis section.
+ */
+pub fn load_genesis_config() -> GenesisConfig {
+    fs::read_to_string("accepted_ports.json").ok()
+        .and_then(|config_data| serde_json::from_str::<NetworkConfig>(&config_data).ok())
+        .map(|config| config.genesis)
+        .unwrap_or_default()
+}
+
+/**
+ * @notice load_node_key() reads the node key configured (as key_file in accepted_ports.json) for the node bound to 
+ * client_port_address. None is returned if no key file is configured for it.
+ */
+pub fn load_node_key(client_port_address: &str) -> Result<Option<(String, String)>, String> {
+    let config_data: String = fs::read_to_string("accepted_ports.json").map_err(|e| e.to_string())?;
+    let config: NetworkConfig = serde_json::from_str(&config_data).map_err(|e| e.to_string())?;
+    let node: &PortConfig = config.nodes.iter()
+        .find(|node| format!("{}:{}", node.address, node.port) == client_port_address)
+        .ok_or(format!("{} is not in accepted_ports.json", client_port_address))?;
+    node.key_file.as_deref().map(zk_proof::read_node_key).transpose()
+}
+
  /**
  * @notice try_bind_to_ports() is an asynchronous function that attempts to bind to the ports specified in the
  * accepted_ports.json file. If the function is successful, it will return a TcpListener that is bound to the
@@ -99,16 +136,42 @@ pub async fn start_listening(validator_node: ValidatorNode) {
     let mut validator_node: ValidatorNode = validator_node;
     validator_node.client_port_address = client_port_address.clone();
 
-    // clone mutable validator node structs for use in the 3 below spawned tasks
+    // Sign votes and heartbeats with the node key configured for the port, and collect fees with its reward address
+    match load_node_key(&client_port_address) {
+        Ok(Some((node_key, node_public_key))) => {
+            validator_node.reward_address = validator_node.validator_reward_address(&node_public_key).unwrap_or_default().to_string();
+            if validator_node.reward_address.is_empty() {
+                eprintln!("Node key {} is not in the validator set, peers will ignore the votes and heartbeats of this node", node_public_key);
+            }
+            validator_node.node_key = node_key;
+            validator_node.node_public_key = node_public_key;
+        },
+        Ok(None) => eprintln!("No key_file configured for {}, peers will ignore the votes and heartbeats of this node", client_port_address),
+        Err(e) => { eprintln!("Failed to load the node key: {}", e); return; }
+    }
+
+    // clone mutable validator node structs for use in the below spawned tasks
     let validator_node_clone_1: ValidatorNode = validator_node.clone();
     let validator_node_clone_2: ValidatorNode = validator_node.clone();
     let validator_node_clone_3: ValidatorNode = validator_node.clone();
+    let validator_node_clone_4: ValidatorNode = validator_node.clone();
+    let validator_node_clone_5: ValidatorNode = validator_node.clone();
 
     // Spawn a task for sending heartbeat signals 
     tokio::spawn(async move {
         send_heartbeat_periodically(validator_node_clone_1).await;
     });
 
+    // Spawn a task for handling time locked UTXO transactions once they mature
+    tokio::spawn(async move {
+        handle_matured_transactions_periodically(validator_node_clone_5).await;
+    });
+
+    // Spawn the JSON-RPC server for tools and dashboards
+    tokio::spawn(async move {
+        rpc::start_rpc_server(validator_node_clone_4).await;
+    });
+
     // Spawn a delayed request for peer ledger states so to adopt the majority of the network
     tokio::spawn(async move {
 
@@ -131,10 +194,19 @@ pub async fn start_listening(validator_node: ValidatorNode) {
         // Spawn a new task to handle the incoming message
         tokio::spawn(async move {
             
-            // Read the incoming message into a buffer and pass into the master event handler
+            // Read the incoming message into a buffer 
             let mut buffer: Vec<u8> = Vec::new();
             if socket.read_to_end(&mut buffer).await.is_ok() && !buffer.is_empty() {
-                master_events_handler(&buffer, validator_node_clone).await;
+
+                // Read-only queries are answered on the same socket, everything else goes to the master event handler
+                match serde_json::from_slice::<Value>(&buffer) {
+                    Ok(request) if query::is_query(&request) => {
+                        let response: query::QueryResponse = query::handle_query(request, validator_node_clone).await;
+                        let response_json: String = serde_json::to_string(&response).unwrap();
+                        let _ = socket.write_all(response_json.as_bytes()).await;
+                    },
+                    _ => master_events_handler(&buffer, validator_node_clone).await,
+                }
             }
         });
     }
@@ -156,6 +228,12 @@ async fn master_events_handler( buffer: &[u8], validator_node: ValidatorNode ) {
     if let Ok(request) = serde_json::from_str::<Value>(&msg) {
         
         let request_action: Option<&str> = request["action"].as_str();     
+
+        // Drop peer messages from nodes of another network
+        if is_peer_message(request_action) && request["chain_id"].as_str() != Some(chain_id()) {
+            eprintln!("Ignoring {:?} from chain {:?}, this node is on chain {}", request_action, request["chain_id"], chain_id());
+            return;
+        }
         
         // Determine the action to take based on the request
         match request_action {
@@ -184,6 +262,15 @@ async fn master_events_handler( buffer: &[u8], validator_node: ValidatorNode ) {
                     Err(e) => { eprintln!("UTXO Transaction Validation Error: {}", e); }
                 }
             },
+            Some("AccountToUtxo") => { 
+                match validation::handle_account_to_utxo_request(request, validator_node.clone()).await {
+                    Ok(success) => { 
+                        if success { println!("Account To UTXO Transaction Validated..."); } 
+                        else { println!("Account To UTXO Transaction Failed Validation"); }
+                    },
+                    Err(e) => { eprintln!("Account To UTXO Transaction Validation Error: {}", e); }
+                }
+            },
             Some("Faucet") => { 
                 match validation::handle_faucet_request(request, validator_node.clone()).await {
                     Ok(_) => { println!("Faucet Request Validated..."); },
@@ -231,6 +318,15 @@ async fn master_events_handler( buffer: &[u8], validator_node: ValidatorNode ) {
     } else {eprintln!("Failed to parse message: {}", msg);}
 }
 
+/**
+ * @notice is_peer_message() returns true for the actions exchanged between validator nodes (votes and handshakes).
+ * These carry the chain id of the sending node, see requests::NetworkRequest.
+ */
+fn is_peer_message(request_action: Option<&str>) -> bool {
+    matches!(request_action, 
+        Some("ConsensusRequest") | Some("ConsensusResponse") | Some("HeartBeat") | Some("PeerLedgerRequest") | Some("PeerLedgerResponse"))
+}
+
 /**
  * @notice send_heartbeat_periodically() is an asynchronous function that 
  * sends a heartbeat signal to the network every HEARTBEAT_PERIOD seconds.
@@ -244,11 +340,32 @@ async fn send_heartbeat_periodically(validator_node: ValidatorNode) {
 }
 
 /**
- * @notice hash_network_request() uses Sha256 to hash a serde_json::Value 
- * that contains that contains network request information
+ * @notice handle_matured_transactions_periodically() is an asynchronous function that every MATURITY_CHECK_PERIOD 
+ * seconds handles the held UTXO transactions whose time locks have passed, as if they had just been received.
+ */
+async fn handle_matured_transactions_periodically(validator_node: ValidatorNode) {
+    let mut interval = time::interval(MATURITY_CHECK_PERIOD);
+    loop {
+        interval.tick().await;
+        for request in validator_node.take_matured_transactions().await {
+            match validation::handle_utxo_transaction_request(request, validator_node.clone()).await {
+                Ok(success) => println!("Matured UTXO Transaction Validated: {}", success),
+                Err(e) => eprintln!("Matured UTXO Transaction Validation Error: {}", e),
+            }
+            save_chain_json(validator_node.clone()).await;
+        }
+    }
+}
+
+/**
+ * @notice hash_network_request() returns the identity of a network request: the Sha256 hash of the canonical encoding 
+ * of the NetworkRequest (see encoding.rs), so every node derives the same hash however the JSON was formatted.
+ * @dev A request that does not parse as a NetworkRequest is rejected by its handler; it is identified by the hash of 
+ * its JSON text.
  */
 pub async fn hash_network_request(request_struct_json: Value) -> Vec<u8> {
-    let mut hasher = Sha256::new();
-    hasher.update(request_struct_json.to_string());
-    hasher.finalize().to_vec() 
+    match serde_json::from_value::<requests::NetworkRequest>(request_struct_json.clone()) {
+        Ok(request) => encoding::canonical_hash(NETWORK_REQUEST_TAG, &request),
+        Err(_) => Sha256::digest(request_struct_json.to_string()).to_vec(),
+    }
 }
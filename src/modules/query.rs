@@ -0,0 +1,250 @@
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::modules::validation::ValidatorNode;
+use crate::modules::address::{self, Recipient};
+use crate::modules::utxo::{UTXOSet, OutPoint, UTXO};
+use crate::modules::blockchain::Block;
+
+
+/**
+ * @notice query.rs contains the read-only query protocol of the network. Unlike the other requests handled by the
+ * network module, queries do not go through consensus and never change the ledger. A client connects to a single
+ * validator node, writes the query and shuts down its write half. The node answers on the same socket with a
+ * serialized QueryResponse and closes the connection.
+ *
+ * Four queries are supported: GetAccount returns the balance and nonce of an account (used by the CLI to fill in the
+ * nonce of a transfer), GetTxStatus returns whether a transaction is pending, committed or rejected, GetUtxos returns
+ * the UTXOs a recipient can spend (used by the wallet to select the inputs of a payment) and GetUtxoBlocks returns the
+ * UTXO transaction blocks from a height on (scanned by the wallet for outputs paid to its stealth address).
+ */
+/**
+ * @notice TxStatus tracks the lifecycle of a transaction request seen by this validator node. The key it is stored
+ * under in ValidatorNode::tx_statuses is network::hash_network_request() of the request.
+ * @dev Unknown is only ever returned to clients, it is never stored.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "status")]
+pub enum TxStatus {
+    Pending,
+    Committed { block_height: u64 },
+    Rejected { reason: String },
+    Unknown,
+}
+
+/**
+ * @notice QueryResponse is the serialized reply written back to the client that sent a query.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "response")]
+pub enum QueryResponse {
+    Account {
+        public_key: String,
+        exists: bool,
+        balance: u64,
+        nonce: u64,
+    },
+    TxStatus {
+        hash: String,
+        status: TxStatus,
+    },
+    Utxos {
+        recipient: String,
+        utxos: Vec<(OutPoint, UTXO)>,
+    },
+    UtxoBlocks {
+        blocks: Vec<Block>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/**
+ * @notice is_query() returns true if the action of a request is one of the read-only queries. This is used by the
+ * network module to decide whether a reply should be written back on the connection.
+ */
+pub fn is_query(request: &Value) -> bool {
+    matches!(request["action"].as_str(), Some("GetAccount") | Some("GetTxStatus") | Some("GetUtxos") | Some("GetUtxoBlocks"))
+}
+
+/**
+ * @notice handle_query() answers a read-only query using the local ledger state of the validator node.
+ */
+pub async fn handle_query(request: Value, validator_node: ValidatorNode) -> QueryResponse {
+    match request["action"].as_str() {
+        Some("GetAccount") => {
+            let public_key: String = request["public_key"].as_str().unwrap_or_default().to_string();
+            handle_account_query(public_key, validator_node).await
+        },
+        Some("GetTxStatus") => {
+            let hash: String = request["hash"].as_str().unwrap_or_default().to_string();
+            handle_tx_status_query(hash, validator_node).await
+        },
+        Some("GetUtxos") => {
+            let recipient: String = request["recipient"].as_str().unwrap_or_default().to_string();
+            handle_utxos_query(recipient, validator_node).await
+        },
+        Some("GetUtxoBlocks") => {
+            let from_height: u64 = request["from_height"].as_u64().unwrap_or(0);
+            handle_utxo_blocks_query(from_height, validator_node).await
+        },
+        action => QueryResponse::Error { message: format!("Unrecognized query: {:?}", action) },
+    }
+}
+
+/**
+ * @notice handle_account_query() looks up the balance and nonce of an account in the merkle tree. Accounts that do
+ * not exist are reported with exists = false and a zero balance and nonce.
+ */
+pub async fn handle_account_query(public_key: String, validator_node: ValidatorNode) -> QueryResponse {
+
+    // accounts are keyed by the UTF-8 bytes of the hex encoded public key
+    let address: &[u8] = public_key.as_bytes();
+
+    // Lock the merkle tree while reading the account
+    let merkle_tree_guard = validator_node.merkle_tree.lock().await;
+
+    QueryResponse::Account {
+        exists: merkle_tree_guard.account_exists(address),
+        balance: merkle_tree_guard.get_account_balance(address).unwrap_or(0),
+        nonce: merkle_tree_guard.get_nonce(address).unwrap_or(0),
+        public_key,
+    }
+}
+
+/**
+ * @notice handle_tx_status_query() looks up the status of a transaction by the hex encoded hash of its request.
+ */
+pub async fn handle_tx_status_query(hash: String, validator_node: ValidatorNode) -> QueryResponse {
+
+    // decode the transaction hash
+    let request_hash: Vec<u8> = match hex::decode(&hash) {
+        Ok(request_hash) => request_hash,
+        Err(e) => return QueryResponse::Error { message: format!("Invalid transaction hash: {}", e) },
+    };
+
+    // look up the status, defaulting to unknown for transactions never seen by this node
+    let status: TxStatus = validator_node.tx_statuses.lock().await
+        .get(&request_hash)
+        .cloned()
+        .unwrap_or(TxStatus::Unknown);
+
+    QueryResponse::TxStatus { hash, status }
+}
+
+/**
+ * @notice handle_utxos_query() looks up the UTXOs a recipient (an address, hex public key, multisig lock or script) can
+ * spend.
+ */
+pub async fn handle_utxos_query(recipient: String, validator_node: ValidatorNode) -> QueryResponse {
+    let utxo_set_guard = validator_node.utxo_set.lock().await;
+    match owned_utxos(&utxo_set_guard, &recipient) {
+        Ok(utxos) => QueryResponse::Utxos { recipient, utxos },
+        Err(message) => QueryResponse::Error { message },
+    }
+}
+
+/**
+ * @notice handle_utxo_blocks_query() returns the UTXO transaction blocks of the chain at or above a block height.
+ */
+pub async fn handle_utxo_blocks_query(from_height: u64, validator_node: ValidatorNode) -> QueryResponse {
+    let blockchain_guard = validator_node.blockchain.lock().await;
+    let blocks: Vec<Block> = blockchain_guard.chain.iter()
+        .filter(|block| matches!(block, Block::UTXOTransaction { block_height, .. } if *block_height >= from_height))
+        .cloned()
+        .collect();
+    QueryResponse::UtxoBlocks { blocks }
+}
+
+/**
+ * @notice owned_utxos() returns the UTXOs a recipient can spend: those paid to a public key and to its address, those
+ * locked to a multisig lock or script, or those paid to the one-time key of a stealth recipient.
+ */
+pub fn owned_utxos(utxo_set: &UTXOSet, recipient: &str) -> Result<Vec<(OutPoint, UTXO)>, String> {
+    let owned = match address::parse_recipient(recipient)? {
+        Recipient::PublicKey(public_key) => utxo_set.get_utxos_for_public_key(&public_key),
+        Recipient::PublicKeyHash(public_key_hash) => utxo_set.get_utxos_for_recipient(&public_key_hash),
+        Recipient::Multisig(lock) | Recipient::Script(lock) => utxo_set.get_utxos_for_recipient(&lock),
+        Recipient::Stealth(stealth) => utxo_set.get_utxos_for_recipient(address::index_key(&stealth)),
+        Recipient::Account(_) => return Err("accounts hold a balance, not UTXOs (see getAccount)".to_string()),
+    };
+    Ok(owned.into_iter().map(|(outpoint, utxo)| (outpoint, utxo.clone())).collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::modules::merkle_tree::Account;
+
+    #[tokio::test]
+    async fn test_account_query_returns_balance_and_nonce() {
+        let validator_node = ValidatorNode::new();
+        {
+            let mut merkle_tree_guard = validator_node.merkle_tree.lock().await;
+            merkle_tree_guard.insert_account(Account { public_key: b"abc".to_vec(), public_key_hash: vec![], balance: 42, nonce: 3, schnorr_commitment: vec![] });
+        }
+
+        let response = handle_query(json!({"action": "GetAccount", "public_key": "abc"}), validator_node.clone()).await;
+        assert_eq!(response, QueryResponse::Account { public_key: "abc".to_string(), exists: true, balance: 42, nonce: 3 });
+
+        let response = handle_query(json!({"action": "GetAccount", "public_key": "missing"}), validator_node).await;
+        assert_eq!(response, QueryResponse::Account { public_key: "missing".to_string(), exists: false, balance: 0, nonce: 0 });
+    }
+
+    #[tokio::test]
+    async fn test_tx_status_query() {
+        let validator_node = ValidatorNode::new();
+        validator_node.set_tx_status(vec![1, 2], TxStatus::Rejected { reason: "insufficient balance".to_string() }).await;
+
+        let response = handle_query(json!({"action": "GetTxStatus", "hash": "0102"}), validator_node.clone()).await;
+        assert_eq!(response, QueryResponse::TxStatus {
+            hash: "0102".to_string(),
+            status: TxStatus::Rejected { reason: "insufficient balance".to_string() },
+        });
+
+        let response = handle_query(json!({"action": "GetTxStatus", "hash": "ff"}), validator_node.clone()).await;
+        assert_eq!(response, QueryResponse::TxStatus { hash: "ff".to_string(), status: TxStatus::Unknown });
+
+        let response = handle_query(json!({"action": "GetTxStatus", "hash": "zz"}), validator_node).await;
+        assert!(matches!(response, QueryResponse::Error { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_utxos_query() {
+        let validator_node = ValidatorNode::new();
+        let public_key: Vec<u8> = crate::modules::zk_proof::generate_keypair().unwrap().1.serialize().to_vec();
+        let paid_to_address = (OutPoint::new(vec![1; 32], 0), UTXO::new(40, address::hash160(&public_key).to_vec(), 1, 12345));
+        validator_node.utxo_set.lock().await.add_utxo(paid_to_address.0.clone(), paid_to_address.1.clone());
+
+        let response = handle_query(json!({"action": "GetUtxos", "recipient": hex::encode(&public_key)}), validator_node.clone()).await;
+        assert_eq!(response, QueryResponse::Utxos { recipient: hex::encode(&public_key), utxos: vec![paid_to_address] });
+
+        let response = handle_query(json!({"action": "GetUtxos", "recipient": "mbc1notanaddress"}), validator_node).await;
+        assert!(matches!(response, QueryResponse::Error { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_stealth_utxos_and_blocks_queries() {
+        let validator_node = ValidatorNode::new();
+        let keys = crate::modules::stealth::StealthKeys::from_spend_key(secp256k1::SecretKey::from_slice(&[1; 32]).unwrap()).unwrap();
+        let transaction = crate::modules::utxo::UTXOTransaction::new(vec![], vec![crate::modules::utxo::TxOutput::new(40, keys.address().pay())], 12345);
+        let coinbase = crate::modules::utxo::CoinbaseTransaction::new(vec![], 4, 12345);
+        let block = Block::UTXOTransaction { transaction: transaction.clone(), block_height: 4, coinbase, utxo_commitment: vec![], hash: vec![] };
+        validator_node.blockchain.lock().await.chain.push(block.clone());
+        validator_node.utxo_set.lock().await.apply_transaction(&transaction, 4).unwrap();
+
+        // the block is found from its height on, and its output is found by the one-time key the receiver recovers
+        let response = handle_query(json!({"action": "GetUtxoBlocks", "from_height": 4}), validator_node.clone()).await;
+        assert_eq!(response, QueryResponse::UtxoBlocks { blocks: vec![block.clone()] });
+        let response = handle_query(json!({"action": "GetUtxoBlocks", "from_height": 5}), validator_node.clone()).await;
+        assert_eq!(response, QueryResponse::UtxoBlocks { blocks: vec![] });
+
+        let received = keys.scan(&block);
+        let one_time_key = secp256k1::PublicKey::from_secret_key(crate::modules::verifier::secp_context(), &received[0].secret_key);
+        let utxos = owned_utxos(&*validator_node.utxo_set.lock().await, &one_time_key.to_string()).unwrap();
+        assert_eq!(utxos.iter().map(|(outpoint, utxo)| (outpoint.clone(), utxo.amount)).collect::<Vec<_>>(), vec![(received[0].outpoint.clone(), 40)]);
+    }
+}
@@ -1,4 +1,4 @@
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use serde::{Serialize, Deserialize};
 use serde_json;
@@ -6,15 +6,27 @@ use serde_json::Value;
 use std::{io, fs};
 extern crate secp256k1;
 use secp256k1::{SecretKey, PublicKey};
+use std::str::FromStr;
+use std::path::Path;
 extern crate rand;
-use crate::modules::constants::INTEGRATION_TEST;
+use crate::modules::constants::{TEST_ACCOUNT_DETAILS_ENV, MIN_TRANSACTION_FEE, HD_RESTORE_COUNT};
 extern crate hex;
 
+use crate::modules::address;
+use crate::modules::encoding::{Encode, Encoder};
 use crate::modules::zk_proof;
+use crate::modules::wallet;
 use crate::modules::network::NetworkConfig;
 use crate::modules::network;
 use crate::modules::validation::ValidatorNode;
-use crate::modules::utxo::{TxInput, TxOutput};
+use crate::modules::utxo::{TxInput, TxOutput, UTXOTransaction, OutPoint, UTXO};
+use crate::modules::coin_selection::SelectionStrategy;
+use crate::modules::script::Script;
+use crate::modules::query::QueryResponse;
+use crate::modules::blockchain::{chain_id, Block};
+use crate::modules::stealth::StealthKeys;
+use crate::modules::verifier::secp_context;
+use crate::modules::bridge;
 
 
 /**
@@ -27,7 +39,10 @@ use crate::modules::utxo::{TxInput, TxOutput};
  /**
  * @notice NetworkRequest is an enum that encapsulates the different types of requests that can be sent to the network.
  * The enum is serialized and deserialized to JSON for transmission over the network. The different types of requests
- * include AccountCreation, Transaction, Faucet, ConsensusRequest, HeartBeat, and PeerLedgerRequest.
+ * include AccountCreation, Transaction, Faucet, ConsensusRequest, HeartBeat, and PeerLedgerRequest, as well as the
+ * read-only GetAccount, GetTxStatus, GetUtxos and GetUtxoBlocks queries (see query.rs) and AccountToUtxo, which moves an account balance into a 
+ * UTXO (see bridge.rs). Transfers and all messages between validator nodes carry
+ * the chain id of the network (see blockchain::chain_id()) and are rejected by nodes of any other network.
  * @dev the 'action' tag is used to specify the type of request based on the 'action' field. This is used by the
  * network::master_event_handler() to filter the recieved, serialized version of this struct into the correct variant
  * event handler.    
@@ -38,18 +53,27 @@ use crate::modules::utxo::{TxInput, TxOutput};
      AccountCreation {
          public_key: String,
          public_key_hash: String,
+         schnorr_commitment: String,
+         commitment_signature: String,
      },
      Transaction {
          sender_public_key: String,
-         signature: String,
+         proof: String,
          recipient_public_key: String,
          amount: String,
+         fee: String,
          nonce: u64,
+         chain_id: String,
      },
      UTXOTransaction {
          inputs: Vec<TxInput>,
          outputs: Vec<TxOutput>,
          timestamp: u64,
+         #[serde(default)]
+         lock_time: u64,
+         #[serde(default)]
+         explicit_fee: u64,
+         chain_id: String,
      },
      Faucet {
          public_key: String,
@@ -57,16 +81,90 @@ use crate::modules::utxo::{TxInput, TxOutput};
      ConsensusRequest{ 
         request_hash: Vec<u8>,
         response_port: String,
+        utxo_commitment: Option<Vec<u8>>,
+        chain_id: String,
     },
     HeartBeat{
         port_address: String,
+        timestamp: u64,
+        node_public_key: String,
+        signature: String,
+        chain_id: String,
     },
     PeerLedgerRequest{
         response_port: String,
-    }
+        chain_id: String,
+    },
+    GetAccount{
+        public_key: String,
+    },
+    GetTxStatus{
+        hash: String,
+    },
+    AccountToUtxo {
+        sender_public_key: String,
+        proof: String,
+        recipient: String,
+        amount: String,
+        fee: String,
+        nonce: u64,
+        timestamp: u64,
+        chain_id: String,
+    },
+    GetUtxos{
+        recipient: String,
+    },
+    GetUtxoBlocks{
+        from_height: u64,
+    },
  }
 
 
+// Canonical encoding of a request (see encoding.rs): the index of the variant followed by its fields in declaration order
+impl Encode for NetworkRequest {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            NetworkRequest::AccountCreation { public_key, public_key_hash, schnorr_commitment, commitment_signature } => {
+                encoder.u8(0).str(public_key).str(public_key_hash).str(schnorr_commitment).str(commitment_signature);
+            },
+            NetworkRequest::Transaction { sender_public_key, proof, recipient_public_key, amount, fee, nonce, chain_id } => {
+                encoder.u8(1).str(sender_public_key).str(proof).str(recipient_public_key).str(amount).str(fee).u64(*nonce).str(chain_id);
+            },
+            NetworkRequest::UTXOTransaction { inputs, outputs, timestamp, lock_time, explicit_fee, chain_id } => {
+                encoder.u8(2).list(inputs).list(outputs).u64(*timestamp).u64(*lock_time).u64(*explicit_fee).str(chain_id);
+            },
+            NetworkRequest::Faucet { public_key } => {
+                encoder.u8(3).str(public_key);
+            },
+            NetworkRequest::ConsensusRequest { request_hash, response_port, utxo_commitment, chain_id } => {
+                encoder.u8(4).bytes(request_hash).str(response_port).value(utxo_commitment).str(chain_id);
+            },
+            NetworkRequest::HeartBeat { port_address, timestamp, node_public_key, signature, chain_id } => {
+                encoder.u8(5).str(port_address).u64(*timestamp).str(node_public_key).str(signature).str(chain_id);
+            },
+            NetworkRequest::PeerLedgerRequest { response_port, chain_id } => {
+                encoder.u8(6).str(response_port).str(chain_id);
+            },
+            NetworkRequest::GetAccount { public_key } => {
+                encoder.u8(7).str(public_key);
+            },
+            NetworkRequest::GetTxStatus { hash } => {
+                encoder.u8(8).str(hash);
+            },
+            NetworkRequest::AccountToUtxo { sender_public_key, proof, recipient, amount, fee, nonce, timestamp, chain_id } => {
+                encoder.u8(9).str(sender_public_key).str(proof).str(recipient).str(amount).str(fee).u64(*nonce).u64(*timestamp).str(chain_id);
+            },
+            NetworkRequest::GetUtxos { recipient } => {
+                encoder.u8(10).str(recipient);
+            },
+            NetworkRequest::GetUtxoBlocks { from_height } => {
+                encoder.u8(11).u64(*from_height);
+            },
+        }
+    }
+}
+
+
 /**
  * @notice NewAccountDetailsTestOutput encapsulate the details of a new account created on the blockchain 
  * for integration testing. The struct is serialized and deserialized to JSON for saving the account details.
@@ -78,23 +176,28 @@ struct NewAccountDetailsTestOutput {
 }
    
 /**
- * @notice send_account_creation_msg() asynchonously creates a new private/public keypair, obfuscates the private key 
- * using elliptic curve cryptography, and sends the public key and obfuscated private key hash to the network.
+ * @notice send_account_creation_request() creates a new account in the keystore under the given name and registers it 
+ * with the network, like `wallet new`. The private key is never printed.
+ * @dev When TEST_ACCOUNT_DETAILS_ENV is set (by the integration test scripts only) the keystore is skipped and the new 
+ * keypair is written in plaintext to new_account_details.json instead, so that the scripts can use the account.
  */
-pub async fn send_account_creation_request(){
+pub async fn send_account_creation_request(name: Option<&String>){
     println!("Sending Account Creation Request...");
 
+    // Store the new key in the keystore unless running under the integration test scripts
+    if std::env::var(TEST_ACCOUNT_DETAILS_ENV).is_err() {
+        match name {
+            Some(name) => send_wallet_command(&["new".to_string(), name.clone()]).await,
+            None => eprintln!("Usage: cargo run make <account name>"),
+        }
+        return;
+    }
+
     // Generate a new keypair
     let (secret_key, public_key) = zk_proof::generate_keypair().unwrap();
 
-    // Hash the public key for storage
-    let public_key_hash: Vec<u8> = zk_proof::get_public_key_hash(&public_key);
-
     // Package account creation request
-    let request = NetworkRequest::AccountCreation {
-        public_key: public_key.to_string(),
-        public_key_hash: hex::encode(public_key_hash),
-    };
+    let request: NetworkRequest = account_creation_request(&secret_key, &public_key).unwrap();
 
     // Serialize request to JSON
     let request_json = serde_json::to_string(&request).map_err(|e| io::Error::new(io::ErrorKind::Other, e)).unwrap();
@@ -102,50 +205,233 @@ pub async fn send_account_creation_request(){
     // Send the account creation request to the network
     send_json_request_to_all_ports(request_json).await;
 
-    // print hunman readable account details
-    print_human_readable_account_details(&secret_key, &public_key);
-    if INTEGRATION_TEST { 
-        save_new_account_details_json(&secret_key.to_string(), &public_key.to_string()); 
-    }
+    // print hunman readable account details and save the keypair for the test scripts
+    print_human_readable_account_details(&public_key);
+    save_new_account_details_json(&secret_key.to_string(), &public_key.to_string());
+}
+
+/**
+ * @notice send_wallet_command() runs a wallet subcommand of the CLI:
+ * new <name> creates an account in the keystore and registers it with the network, list prints the accounts, 
+ * import <name> <private key> and export <name> move keys in and out of the keystore, 
+ * sign <name> <recipient> <amount> [fee] [nonce] prints a signed transfer request without sending it,
+ * init and restore [count] create or restore the HD seed of the keystore, receive derives a fresh receive key and 
+ * xpub prints the extended public key of the receive chain.
+ * multisig <threshold> <public key>... prints an M-of-N multisig lock, and psbt, cosign, combine and finalize build, 
+ * co-sign and finalize a spend of UTXO outputs in a partially signed transaction file (see multisig.rs). timelock sets 
+ * the lock time and relative input locks of such a file before it is signed.
+ * script <asm>... assembles a locking script and prints the recipient to pay outputs locked by it to (see script.rs).
+ * htlc, claim and refund create, claim and refund hash time-locked contracts, and preimage extracts the secret revealed 
+ * by a claim (see htlc.rs). to-account <public key> prints the recipient of outputs that credit an account (see bridge.rs).
+ * confidential <name> <txid:vout:amount[:blinding],...> <recipient> <amount> [fee] prints a signed payment in a 
+ * confidential output and the openings of its outputs (see confidential.rs).
+ * stealth <name> prints the stealth address of an account, and scan <name> [from height] prints the unspent outputs paid 
+ * to it (see stealth.rs).
+ */
+pub async fn send_wallet_command(args: &[String]) {
+    let arg = |index: usize| args.get(index).cloned().unwrap_or_default();
+
+    let result: Result<(), String> = match args.first().map(String::as_str) {
+        Some("new") => match wallet::create_account(&arg(1)) {
+            Ok((secret_key, public_key)) => {
+
+                // register the new account with the network
+                let request: NetworkRequest = account_creation_request(&secret_key, &public_key).unwrap();
+                send_json_request_to_all_ports(serde_json::to_string(&request).unwrap()).await;
+                println!("Created account {} with address {}", arg(1), address::address_of_public_key(&public_key));
+                Ok(())
+            },
+            Err(e) => Err(e),
+        },
+        Some("list") => wallet::list_accounts().map(|accounts| {
+            for account in accounts {
+                let address: String = PublicKey::from_str(&account.public_key).map(|public_key| address::address_of_public_key(&public_key)).unwrap_or_default();
+                println!("{}\t{}\t{}\t{}", account.name, address, account.public_key, account.derivation_path.unwrap_or_default());
+            }
+        }),
+        Some("import") => wallet::import_account(&arg(1), &arg(2))
+            .map(|public_key| println!("Imported account {} with public key {}", arg(1), public_key)),
+        Some("export") => wallet::export_account(&arg(1))
+            .map(|private_key| println!("{}", private_key)),
+        Some("init") => wallet::init_seed().map(|mnemonic| {
+            println!("Write down this mnemonic, it restores every key derived by the wallet:\n\n    {}\n", mnemonic);
+        }),
+        Some("restore") => match arg(1).parse::<u32>() {
+            Ok(count) => wallet::restore_seed(count),
+            Err(_) if arg(1).is_empty() => wallet::restore_seed(HD_RESTORE_COUNT),
+            Err(e) => Err(format!("Invalid count: {}", e)),
+        }.map(|accounts| {
+            for account in accounts { println!("Restored {}\t{}", account.name, account.public_key); }
+        }),
+        Some("receive") => wallet::new_receive_key()
+            .map(|account| {
+                let public_key: PublicKey = PublicKey::from_str(&account.public_key).unwrap();
+                println!("Receive with {} ({})", address::address_of_public_key(&public_key), account.name);
+            }),
+        Some("xpub") => wallet::receive_chain_xpub().map(|xpub| println!("{}", xpub)),
+        Some("sign") => sign_wallet_transaction(&arg(1), arg(2), arg(3), args.get(4).cloned(), args.get(5)).await,
+        Some("multisig") => wallet::multisig_lock(&arg(1), args.get(2..).unwrap_or_default())
+            .map(|lock| println!("{}\n{}", hex::encode(lock.to_bytes()), lock)),
+        Some("psbt") => wallet::create_psbt(Path::new(&arg(1)), &arg(2), &arg(3), args.get(4..).unwrap_or_default())
+            .map(|txid| println!("Wrote transaction {} to {} for signing", txid, arg(1))),
+        Some("timelock") => wallet::timelock_psbt(Path::new(&arg(1)), &arg(2), args.get(3..).unwrap_or_default())
+            .map(|txid| println!("Locked transaction {} in {}", txid, arg(1))),
+        Some("cosign") => wallet::cosign_psbt(&arg(1), Path::new(&arg(2)))
+            .map(|(signed, unsigned_inputs)| println!("Signed {} inputs, inputs still lacking signatures: {:?}", signed, unsigned_inputs)),
+        Some("combine") => wallet::combine_psbt(Path::new(&arg(1)), Path::new(&arg(2)))
+            .map(|unsigned_inputs| println!("Combined signatures, inputs still lacking signatures: {:?}", unsigned_inputs)),
+        Some("finalize") => wallet::finalize_psbt(Path::new(&arg(1))).map(print_utxo_transaction_request),
+        Some("htlc") => wallet::create_htlc(&arg(1), &arg(2), &arg(3), args.get(4)).map(|(htlc, preimage)| {
+            println!("{}\n{}", hex::encode(htlc.to_recipient()), htlc);
+            if let Some(preimage) = preimage { println!("Keep this preimage secret until you claim the other side of the swap: {}", hex::encode(preimage)); }
+        }),
+        Some("claim") => wallet::claim_htlc(&arg(1), &arg(2), &arg(3), &arg(4), args.get(5..).unwrap_or_default())
+            .map(print_utxo_transaction_request),
+        Some("refund") => wallet::refund_htlc(&arg(1), &arg(2), &arg(3), args.get(4..).unwrap_or_default())
+            .map(print_utxo_transaction_request),
+        Some("confidential") => wallet::confidential_payment(&arg(1), &arg(2), &arg(3), &arg(4), args.get(5))
+            .map(|(transaction, openings)| {
+                let txid: String = hex::encode(&transaction.hash);
+                print_utxo_transaction_request(transaction);
+                for (vout, opening) in openings.iter().enumerate() { println!("Opening of {}:{}: {}", txid, vout, opening); }
+            }),
+        Some("stealth") => wallet::stealth_keys(&arg(1)).map(|keys| println!("{}", keys.address())),
+        Some("scan") => scan_stealth_outputs(&arg(1), &arg(2)).await,
+        Some("preimage") => wallet::claimed_preimage(&arg(1)).map(|preimage| println!("{}", preimage)),
+        Some("to-account") => bridge::account_recipient(&arg(1)).map(|recipient| println!("{}", hex::encode(recipient))),
+        Some("script") => Script::from_asm(&args.get(1..).unwrap_or_default().join(" "))
+            .map(|script| println!("{}\n{}", hex::encode(script.to_recipient()), script)),
+        command => Err(format!("Unrecognized wallet command: {:?}", command)),
+    };
+
+    if let Err(e) = result { eprintln!("{}", e); }
+}
+
+/**
+ * @notice print_utxo_transaction_request() prints the request of a signed UTXO transaction, which can then be submitted 
+ * by any client (e.g. with the sendUtxoTransaction JSON-RPC method).
+ */
+fn print_utxo_transaction_request(transaction: UTXOTransaction) {
+    let request = NetworkRequest::UTXOTransaction {
+        inputs: transaction.inputs,
+        outputs: transaction.outputs,
+        timestamp: transaction.timestamp,
+        lock_time: transaction.lock_time,
+        explicit_fee: transaction.explicit_fee,
+        chain_id: chain_id().to_string(),
+    };
+    println!("{}", serde_json::to_string(&request).unwrap());
+}
+
+/**
+ * @notice sign_wallet_transaction() signs a transfer with a wallet account and prints the request, which can then be 
+ * submitted by any client (e.g. with the sendTransaction JSON-RPC method). The nonce is fetched from the network if 
+ * it is not given.
+ */
+async fn sign_wallet_transaction(name: &str, recipient_public_key: String, amount: String, fee: Option<String>, nonce: Option<&String>) -> Result<(), String> {
+    let private_key: String = wallet::resolve_private_key(name)?;
+    let nonce: u64 = match nonce {
+        Some(nonce) => nonce.parse().map_err(|e| format!("Invalid nonce: {}", e))?,
+        None => fetch_account_nonce(&zk_proof::derive_public_key_from_private_key(&private_key)).await?,
+    };
+    let fee: String = fee.unwrap_or(MIN_TRANSACTION_FEE.to_string());
+
+    let request: NetworkRequest = transaction_request(&private_key, recipient_public_key, amount, fee, nonce)?;
+    println!("{}", serde_json::to_string(&request).unwrap());
+    Ok(())
+}
+
+/**
+ * @notice account_creation_request() packages the account creation request for a keypair. The Schnorr commitment of 
+ * the account is derived from the private key and signed with it, so only the key owner can register it.
+ */
+pub fn account_creation_request(secret_key: &SecretKey, public_key: &PublicKey) -> Result<NetworkRequest, String> {
+
+    // Hash the public key for storage
+    let public_key_hash: Vec<u8> = zk_proof::get_public_key_hash(public_key);
+
+    // derive and sign the commitment the account's transfer proofs are verified against
+    let schnorr_commitment: Vec<u8> = zk_proof::derive_schnorr_commitment(&secret_key.to_string())?;
+    let commitment_signature: String = zk_proof::sign_account_commitment(&secret_key.to_string(), &public_key.to_string(), &schnorr_commitment)?;
+
+    Ok(NetworkRequest::AccountCreation {
+        public_key: public_key.to_string(),
+        public_key_hash: hex::encode(public_key_hash),
+        schnorr_commitment: hex::encode(schnorr_commitment),
+        commitment_signature,
+    })
 }
 
 
  /**
  * @notice send_transcation_request() sends a request to the network to transfer a given amount of tokens from one account to another.
- * The request includes the public key of the sender, the public key of the recipient, and the amount of tokens to transfer.
- * @dev The sender's private key is used to derive the sender's public key and sign the transaction.
- * The signature proves ownership of the private key without revealing it.
+ * The request includes the public key of the sender, the public key of the recipient, the amount of tokens to transfer,
+ * and the fee paid to the validator set.
+ * @dev The sender is either the name of a wallet account (unlocked with its password, see wallet.rs) or a hex private key.
+ * The sender's current nonce is fetched from the network with a GetAccount query before signing, validators reject any 
+ * other nonce.
  */
-pub async fn send_transaction_request(sender_private_key: String, recipient_public_key: String, amount: String ) {
+pub async fn send_transaction_request(sender: String, recipient_public_key: String, amount: String, fee: String) {
     println!("Sending Transaction Request...");
 
-    // derive the public key from the private key
+    // load the private key of the sender
+    let sender_private_key: String = match wallet::resolve_private_key(&sender) {
+        Ok(private_key) => private_key,
+        Err(e) => { eprintln!("{}", e); return; }
+    };
+
+    // fetch the sender's current nonce from the network
     let sender_public_key: String = zk_proof::derive_public_key_from_private_key(&sender_private_key);
+    let nonce: u64 = match fetch_account_nonce(&sender_public_key).await {
+        Ok(nonce) => nonce,
+        Err(e) => { eprintln!("Failed to fetch sender nonce: {}", e); return; }
+    };
+
+    // Package the message
+    let request: NetworkRequest = transaction_request(&sender_private_key, recipient_public_key, amount, fee, nonce)
+        .expect("Failed to create transaction proof");
+    let request_json: String = serde_json::to_string(&request).unwrap();    
+
+    // Send the transaction request to the network
+    send_json_request_to_all_ports(request_json).await;
+
+    // print the hash used to query the status of the transaction
+    print_transaction_hash(&request).await;
+}
+
+/**
+ * @notice transaction_request() packages a transfer without sending it. The sender's private key is used to derive the 
+ * sender's public key and a Schnorr proof of knowledge of the account secret that is bound to the transfer. The proof 
+ * reveals nothing that could be reused to authorise another transfer, and is bound to the chain id so that it is not 
+ * valid on any other network.
+ */
+pub fn transaction_request(sender_private_key: &String, recipient_public_key: String, amount: String, fee: String, nonce: u64) -> Result<NetworkRequest, String> {
+
+    // reject malformed recipients (e.g. a mistyped address) before anything is signed
+    address::parse_recipient(&recipient_public_key)?;
 
-    // For simplicity, use nonce 0. In production, this should be fetched from the network
-    let nonce: u64 = 0;
+    // derive the public key from the private key
+    let sender_public_key: String = zk_proof::derive_public_key_from_private_key(sender_private_key);
 
-    // Sign the transaction
-    let signature = zk_proof::sign_transaction(
-        &sender_private_key,
+    // Prove knowledge of the account secret for this transfer
+    let proof: String = zk_proof::create_transaction_proof(
+        sender_private_key,
         &sender_public_key,
         &recipient_public_key,
         &amount,
+        &fee,
         nonce
-    ).expect("Failed to sign transaction");
+    )?;
 
-    // Package the message
-    let request = NetworkRequest::Transaction {
+    Ok(NetworkRequest::Transaction {
         sender_public_key,
-        signature,
+        proof,
         recipient_public_key,
         amount,
+        fee,
         nonce,
-    };
-    let request_json: String = serde_json::to_string(&request).unwrap();    
-
-    // Send the transaction request to the network
-    send_json_request_to_all_ports(request_json).await;
+        chain_id: chain_id().to_string(),
+    })
 }
 
 /**
@@ -164,12 +450,89 @@ pub async fn send_utxo_transaction_request(inputs: Vec<TxInput>, outputs: Vec<Tx
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs(),
+        lock_time: 0,
+        explicit_fee: 0,
+        chain_id: chain_id().to_string(),
     };
     
     let request_json: String = serde_json::to_string(&request).unwrap();
 
     // Send the UTXO transaction request to the network
     send_json_request_to_all_ports(request_json).await;
+
+    // print the hash used to query the status of the transaction
+    print_transaction_hash(&request).await;
+}
+
+/**
+ * @notice send_payment_request() pays an amount to a recipient from the UTXOs of the sender, a wallet account name or a 
+ * hex private key. The UTXOs of the sender are fetched from the network with a GetUtxos query, and the wallet selects 
+ * the inputs with the coin selection strategy, adds the fee and change and signs the transaction (see 
+ * wallet::build_payment()).
+ */
+pub async fn send_payment_request(sender: String, recipient: String, amount: String, strategy: String) {
+    println!("Sending UTXO Payment...");
+
+    let transaction: Result<(UTXOTransaction, u64), String> = async {
+        let private_key: String = wallet::resolve_private_key(&sender)?;
+        let secret_key: SecretKey = SecretKey::from_str(&private_key).map_err(|e| e.to_string())?;
+        let amount: u64 = amount.parse().map_err(|e| format!("Invalid amount: {}", e))?;
+        let strategy: SelectionStrategy = strategy.parse()?;
+
+        let utxos: Vec<(OutPoint, UTXO)> = fetch_utxos(&zk_proof::derive_public_key_from_private_key(&private_key)).await?;
+        wallet::build_payment(&secret_key, &utxos, &recipient, amount, strategy)
+    }.await;
+
+    match transaction {
+        Ok((transaction, fee)) => {
+            println!("Spending {} UTXOs with a fee of {}", transaction.inputs.len(), fee);
+            let request = NetworkRequest::UTXOTransaction {
+                inputs: transaction.inputs,
+                outputs: transaction.outputs,
+                timestamp: transaction.timestamp,
+                lock_time: transaction.lock_time,
+                explicit_fee: transaction.explicit_fee,
+                chain_id: chain_id().to_string(),
+            };
+            send_json_request_to_all_ports(serde_json::to_string(&request).unwrap()).await;
+            print_transaction_hash(&request).await;
+        },
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+/**
+ * @notice send_account_to_utxo_request() sends a request to the network to burn an amount (plus the fee) from the 
+ * sender's account balance and mint a UTXO of that amount paid to the recipient (see bridge.rs). The sender is a wallet 
+ * account name or a hex private key, and the request is proven like a transfer at the sender's current nonce and the 
+ * current time, which the UTXO is minted with. The outpoint the UTXO will be minted at is printed along with the hash of the request.
+ */
+pub async fn send_account_to_utxo_request(sender: String, recipient: String, amount: String, fee: String) {
+    println!("Sending Account To UTXO Request...");
+
+    let request: Result<NetworkRequest, String> = async {
+        let private_key: String = wallet::resolve_private_key(&sender)?;
+        let sender_public_key: String = zk_proof::derive_public_key_from_private_key(&private_key);
+        let nonce: u64 = fetch_account_nonce(&sender_public_key).await?;
+
+        // reject malformed recipients before anything is signed
+        address::parse_recipient(&recipient)?;
+        let timestamp: u64 = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let message: Vec<u8> = zk_proof::account_to_utxo_message(&sender_public_key, &recipient, &amount, &fee, nonce, timestamp);
+        let proof: String = zk_proof::create_schnorr_proof(&private_key, &message)?;
+        let outpoint = bridge::mint_outpoint(sender_public_key.as_bytes(), nonce);
+        println!("Minting UTXO {}:{}", hex::encode(&outpoint.txid), outpoint.vout);
+
+        Ok(NetworkRequest::AccountToUtxo { sender_public_key, proof, recipient, amount, fee, nonce, timestamp, chain_id: chain_id().to_string() })
+    }.await;
+
+    match request {
+        Ok(request) => {
+            send_json_request_to_all_ports(serde_json::to_string(&request).unwrap()).await;
+            print_transaction_hash(&request).await;
+        },
+        Err(e) => eprintln!("{}", e),
+    }
 }
 
 /**
@@ -201,10 +564,15 @@ pub async fn send_consensus_request( request: Value, validator_node: ValidatorNo
     // get hash of request recieved by client, (used as key)
     let client_request_hash: Vec<u8> = network::hash_network_request(request.clone()).await;
 
+    // requests that create or spend UTXOs carry the commitment to the UTXO set the client decided against
+    let utxo_commitment: Option<Vec<u8>> = validator_node.decision_commitments.lock().await.get(&client_request_hash).cloned();
+
     // Package peer request in struct and serialize to JSON
     let consensus_request = NetworkRequest::ConsensusRequest {
         request_hash: client_request_hash.clone(),
-        response_port: client_port.clone()
+        response_port: client_port.clone(),
+        utxo_commitment,
+        chain_id: chain_id().to_string(),
     };
 
     // Serialize request to JSON
@@ -226,7 +594,8 @@ pub async fn send_peer_ledger_request(validator_node: ValidatorNode){
 
     // Package peer request in struct and serialize to JSON
     let peer_ledger_request = NetworkRequest::PeerLedgerRequest {
-        response_port: client_port.clone()
+        response_port: client_port.clone(),
+        chain_id: chain_id().to_string(),
     }; 
 
     // Serialize request to JSON
@@ -245,19 +614,168 @@ pub async fn send_peer_ledger_request(validator_node: ValidatorNode){
 pub async fn send_heartbeat_request(validator_node: ValidatorNode) {
     println!("\nSending HeartBeat...");
 
-    // get client port and outbound ports
-    let client_port: String = validator_node.client_port_address.clone();
-
     // package and serialize the heartbeat signal
-    let heartbeat = NetworkRequest::HeartBeat { port_address: client_port.clone() };
-    let heartbeat_json: String = serde_json::to_string(&heartbeat).unwrap();
+    let heartbeat_json: String = serde_json::to_string(&heartbeat_request(&validator_node)).unwrap();
 
     // Send the heartbeat signal to all outbound ports
     send_json_request_to_other_nodes(heartbeat_json, validator_node).await
 }
 
+/**
+ * @notice heartbeat_request() packages the heartbeat of a validator: its port address and the current time, signed with 
+ * its node key in the Handshake domain so that peers on other networks, or anyone without the key, cannot announce it 
+ * and so that it cannot be replayed later.
+ */
+pub fn heartbeat_request(validator_node: &ValidatorNode) -> NetworkRequest {
+    let port_address: String = validator_node.client_port_address.clone();
+    let timestamp: u64 = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    let signature: String = zk_proof::sign_message(&validator_node.node_key, &zk_proof::handshake_message(&port_address, timestamp)).unwrap();
+    NetworkRequest::HeartBeat {
+        port_address,
+        timestamp,
+        node_public_key: validator_node.node_public_key.clone(),
+        signature,
+        chain_id: chain_id().to_string(),
+    }
+}
+
+/**
+ * @notice send_account_query() queries the network for the balance and nonce of an account and prints the result.
+ */
+pub async fn send_account_query(public_key: String) {
+    println!("Querying Account...");
+
+    let request = NetworkRequest::GetAccount { public_key };
+    match send_query_request(serde_json::to_string(&request).unwrap()).await {
+        Ok(QueryResponse::Account { public_key, exists, balance, nonce }) => {
+            if !exists { println!("Account {} does not exist", public_key); return; }
+            println!("Public Key: {}", public_key);
+            println!("Balance: {}", balance);
+            println!("Nonce: {}", nonce);
+        },
+        Ok(response) => eprintln!("Unexpected query response: {:?}", response),
+        Err(e) => eprintln!("Account query failed: {}", e),
+    }
+}
+
+/**
+ * @notice send_tx_status_query() queries the network for the status of a transaction by the hex encoded hash printed when 
+ * the transaction was sent, and prints the result.
+ */
+pub async fn send_tx_status_query(hash: String) {
+    println!("Querying Transaction Status...");
+
+    let request = NetworkRequest::GetTxStatus { hash };
+    match send_query_request(serde_json::to_string(&request).unwrap()).await {
+        Ok(QueryResponse::TxStatus { hash, status }) => println!("Transaction {}: {:?}", hash, status),
+        Ok(response) => eprintln!("Unexpected query response: {:?}", response),
+        Err(e) => eprintln!("Transaction status query failed: {}", e),
+    }
+}
+
+/**
+ * @notice fetch_account_nonce() queries the network for the current nonce of an account. The nonce must be included
+ * in the signed message of a transaction, so it is fetched before every transfer.
+ */
+pub async fn fetch_account_nonce(public_key: &str) -> Result<u64, String> {
+    let request = NetworkRequest::GetAccount { public_key: public_key.to_string() };
+    match send_query_request(serde_json::to_string(&request).unwrap()).await? {
+        QueryResponse::Account { exists: true, nonce, .. } => Ok(nonce),
+        QueryResponse::Account { exists: false, .. } => Err("account does not exist".to_string()),
+        QueryResponse::Error { message } => Err(message),
+        response => Err(format!("unexpected query response: {:?}", response)),
+    }
+}
+
+/**
+ * @notice fetch_utxos() queries the network for the UTXOs a recipient can spend, to select the inputs of a payment from.
+ */
+pub async fn fetch_utxos(recipient: &str) -> Result<Vec<(OutPoint, UTXO)>, String> {
+    let request = NetworkRequest::GetUtxos { recipient: recipient.to_string() };
+    match send_query_request(serde_json::to_string(&request).unwrap()).await? {
+        QueryResponse::Utxos { utxos, .. } => Ok(utxos),
+        QueryResponse::Error { message } => Err(message),
+        response => Err(format!("unexpected query response: {:?}", response)),
+    }
+}
+
+/**
+ * @notice fetch_utxo_blocks() queries the network for the UTXO transaction blocks from a block height on, to scan for 
+ * outputs paid to a stealth address.
+ */
+pub async fn fetch_utxo_blocks(from_height: u64) -> Result<Vec<Block>, String> {
+    let request = NetworkRequest::GetUtxoBlocks { from_height };
+    match send_query_request(serde_json::to_string(&request).unwrap()).await? {
+        QueryResponse::UtxoBlocks { blocks } => Ok(blocks),
+        QueryResponse::Error { message } => Err(message),
+        response => Err(format!("unexpected query response: {:?}", response)),
+    }
+}
+
+/**
+ * @notice scan_stealth_outputs() scans the UTXO transaction blocks from a block height on for outputs paid to the 
+ * stealth address of a wallet account or private key, and prints those still unspent with the one-time private key 
+ * that spends them (e.g. as the sender of the send command).
+ */
+async fn scan_stealth_outputs(name: &str, from_height: &str) -> Result<(), String> {
+    let keys: StealthKeys = wallet::stealth_keys(name)?;
+    let from_height: u64 = match from_height {
+        "" => 0,
+        from_height => from_height.parse().map_err(|e| format!("Invalid block height: {}", e))?,
+    };
+
+    println!("Scanning UTXO Blocks...");
+    for block in fetch_utxo_blocks(from_height).await? {
+        for received in keys.scan(&block) {
+            let one_time_key: PublicKey = PublicKey::from_secret_key(secp_context(), &received.secret_key);
+            let unspent: Vec<(OutPoint, UTXO)> = fetch_utxos(&one_time_key.to_string()).await?;
+            if unspent.iter().any(|(outpoint, _)| *outpoint == received.outpoint) {
+                println!("{}:{}	amount {}	block {}	key {}", hex::encode(&received.outpoint.txid), received.outpoint.vout,
+                    received.output.amount, received.block_height, received.secret_key);
+            }
+        }
+    }
+    Ok(())
+}
+
 //------------------------------------ Helper Functions ------------------------------------//
 
+/**
+ * @notice send_query_request() sends a read-only query to the first reachable validator node in accepted_ports.json and
+ * returns its reply. The write half of the connection is shut down so the node knows the query is complete.
+ */
+pub async fn send_query_request(request_json: String) -> Result<QueryResponse, String> {
+
+    // Load accepted ports configuration
+    let config_data: String = fs::read_to_string("accepted_ports.json").map_err(|e| e.to_string())?;
+    let config: NetworkConfig = serde_json::from_str(&config_data).map_err(|e| e.to_string())?;
+
+    // Query the first node that accepts the connection
+    for node in config.nodes.iter() {
+        let addr: String = format!("{}:{}", node.address, node.port);
+        if let Ok(mut stream) = TcpStream::connect(&addr).await {
+            stream.write_all(request_json.as_bytes()).await.map_err(|e| e.to_string())?;
+            stream.shutdown().await.map_err(|e| e.to_string())?;
+
+            // read and parse the reply
+            let mut buffer: Vec<u8> = Vec::new();
+            stream.read_to_end(&mut buffer).await.map_err(|e| e.to_string())?;
+            return serde_json::from_slice(&buffer).map_err(|e| e.to_string());
+        }
+    }
+
+    Err("no validator node reachable".to_string())
+}
+
+/**
+ * @notice print_transaction_hash() prints the hash a transaction request is tracked under by validator nodes. 
+ */
+async fn print_transaction_hash(request: &NetworkRequest) {
+    let request_hash: Vec<u8> = network::hash_network_request(serde_json::to_value(request).unwrap()).await;
+    println!("Transaction Hash: {}", hex::encode(request_hash));
+}
+
+
 /**
  * @notice send_json_request() sends a json request to all accepted ports on the network
  */
@@ -304,17 +822,17 @@ pub async fn send_json_request_to_other_nodes(request_json: String, validator_no
 
 /**
  * @notice print_human_readable_account_details() prints the details of a new account created on the blockchain
- * network in human readable format.
+ * network in human readable format. The secret key is not printed.
  */
-fn print_human_readable_account_details(secret_key: &SecretKey, public_key: &PublicKey) {
+fn print_human_readable_account_details(public_key: &PublicKey) {
     println!("Account details sucessfully created: ");
-    println!("Secret Key: {:?}", secret_key.to_string());
     println!("Public Key: {:?}", public_key.to_string());
+    println!("Address: {:?}", address::address_of_public_key(public_key));
 }
 
 /**
  * @notice save_new_account_details_json() saves a json string of the details of a new account created on the blockchain
- * network to the terminal. This is used during integration testing to save the output of the account creation process.
+ * network, private key included in plaintext. This is only used by the integration test scripts (see TEST_ACCOUNT_DETAILS_ENV).
  */
 fn save_new_account_details_json(private_key: &String, public_key: &String) {
 
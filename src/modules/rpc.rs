@@ -0,0 +1,564 @@
+use std::fs;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::modules::validation::ValidatorNode;
+use crate::modules::network::{self, NetworkConfig};
+use crate::modules::blockchain::{self, Block, convert_block_to_blockjson};
+use crate::modules::requests::{self, NetworkRequest};
+use crate::modules::query::{self, QueryResponse};
+use crate::modules::constants::RPC_PORT_OFFSET;
+use crate::modules::events::{self, EventFilter};
+
+
+/**
+ * @notice rpc.rs contains a JSON-RPC 2.0 server that runs alongside every validator node. It gives tools and dashboards
+ * a request/response API over the local state of the node, as opposed to the fire-and-forget requests used between
+ * nodes. The server listens on 127.0.0.1 at the rpc_port configured for the node in accepted_ports.json, or at the
+ * node's port + constants::RPC_PORT_OFFSET if none is set.
+ *
+ * Two transports are accepted on the same port: newline-delimited JSON (one request or batch per line, one response
+ * per line) and a single HTTP POST whose body is the request.
+ *
+ * Methods: getBlock, getBlockByHash, getAccount, getUtxos, sendTransaction, sendUtxoTransaction, sendAccountToUtxo, getPeers 
+ * and getChainInfo.
+ * Parameters may be given by name (object) or by position (array). Over a raw TCP connection a client may also call
+ * subscribe, which turns the connection into a stream of events (see events.rs).
+ */
+// standard JSON-RPC 2.0 error codes
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+
+// largest HTTP request body accepted by the server (in bytes)
+const MAX_HTTP_BODY_SIZE: usize = 1 << 20;
+
+// server defined error codes
+pub const NOT_FOUND: i64 = -32001;
+pub const SUBMISSION_FAILED: i64 = -32002;
+
+
+/**
+ * @notice RpcError is the structured error object returned in the error field of a JSON-RPC response.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: &str) -> RpcError {
+        RpcError { code, message: message.to_string(), data: None }
+    }
+}
+
+/**
+ * @notice RpcResponse is a JSON-RPC 2.0 response. Exactly one of result and error is set.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+    pub id: Value,
+}
+
+impl RpcResponse {
+    fn new(id: Value, outcome: Result<Value, RpcError>) -> RpcResponse {
+        match outcome {
+            Ok(result) => RpcResponse { jsonrpc: "2.0".to_string(), result: Some(result), error: None, id },
+            Err(error) => RpcResponse { jsonrpc: "2.0".to_string(), result: None, error: Some(error), id },
+        }
+    }
+}
+
+
+/**
+ * @notice rpc_address_for() determines the local address the JSON-RPC server of the node bound to client_port_address
+ * should listen on, using the accepted_ports.json configuration.
+ */
+pub fn rpc_address_for(client_port_address: &str) -> Result<String, String> {
+
+    // Load the accepted ports configuration file
+    let config_data: String = fs::read_to_string("accepted_ports.json").map_err(|e| e.to_string())?;
+    let config: NetworkConfig = serde_json::from_str(&config_data).map_err(|e| e.to_string())?;
+
+    // find the node this validator is bound to and derive its rpc port
+    let node = config.nodes.iter()
+        .find(|node| format!("{}:{}", node.address, node.port) == client_port_address)
+        .ok_or(format!("{} is not in accepted_ports.json", client_port_address))?;
+    let rpc_port: u16 = node.rpc_port.unwrap_or(node.port.saturating_add(RPC_PORT_OFFSET));
+
+    Ok(format!("127.0.0.1:{}", rpc_port))
+}
+
+/**
+ * @notice start_rpc_server() binds the JSON-RPC server of the validator node and spawns a task for each connection.
+ * @dev Called by network::start_listening() once the node knows which port it is bound to.
+ */
+pub async fn start_rpc_server(validator_node: ValidatorNode) {
+
+    // determine and bind the rpc address
+    let rpc_address: String = match rpc_address_for(&validator_node.client_port_address) {
+        Ok(rpc_address) => rpc_address,
+        Err(e) => { eprintln!("JSON-RPC server disabled: {}", e); return; }
+    };
+    let listener: TcpListener = match TcpListener::bind(&rpc_address).await {
+        Ok(listener) => { println!("JSON-RPC server listening on `{}`...", rpc_address); listener },
+        Err(e) => { eprintln!("Failed to bind JSON-RPC server to {}: {}", rpc_address, e); return; }
+    };
+
+    // serve each connection in its own task
+    while let Ok((socket, _)) = listener.accept().await {
+        let validator_node_clone: ValidatorNode = validator_node.clone();
+        tokio::spawn(async move {
+            handle_rpc_connection(socket, validator_node_clone).await;
+        });
+    }
+}
+
+/**
+ * @notice handle_rpc_connection() serves a single connection, either as one HTTP POST request or as a stream of
+ * newline-delimited JSON-RPC messages.
+ */
+async fn handle_rpc_connection(socket: TcpStream, validator_node: ValidatorNode) {
+    let mut reader = BufReader::new(socket);
+    let mut line = String::new();
+
+    // the first line tells whether this is an HTTP request
+    if reader.read_line(&mut line).await.unwrap_or(0) == 0 { return; }
+    if line.starts_with("POST ") {
+        handle_http_request(reader, validator_node).await;
+        return;
+    }
+
+    // otherwise answer each non-blank line until the client disconnects
+    loop {
+        let response: Option<String> = match line.trim() {
+            "" => None,
+            message if is_subscribe_call(message) => {
+                handle_subscribe(reader.into_inner(), message, validator_node).await;
+                return;
+            },
+            message => process_rpc_message(message, validator_node.clone()).await,
+        };
+        if let Some(response) = response {
+            if reader.get_mut().write_all(format!("{}\n", response).as_bytes()).await.is_err() { return; }
+        }
+        line.clear();
+        if reader.read_line(&mut line).await.unwrap_or(0) == 0 { return; }
+    }
+}
+
+/**
+ * @notice handle_http_request() reads the headers and body of an HTTP POST request (the request line has already been
+ * consumed) and writes the JSON-RPC response back as the body of an HTTP response.
+ */
+async fn handle_http_request(mut reader: BufReader<TcpStream>, validator_node: ValidatorNode) {
+
+    // read headers until the blank line, keeping the content length
+    let mut content_length: usize = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await.unwrap_or(0) == 0 { return; }
+        let header = header.trim();
+        if header.is_empty() { break; }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    // read the body and process it
+    if content_length > MAX_HTTP_BODY_SIZE { return; }
+    let mut body: Vec<u8> = vec![0; content_length];
+    if reader.read_exact(&mut body).await.is_err() { return; }
+    let response: String = process_rpc_message(&String::from_utf8_lossy(&body), validator_node).await.unwrap_or_default();
+
+    // write the HTTP response (an empty body means every call was a notification)
+    let status: &str = if response.is_empty() { "204 No Content" } else { "200 OK" };
+    let http_response: String = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, response.len(), response
+    );
+    let _ = reader.get_mut().write_all(http_response.as_bytes()).await;
+}
+
+/**
+ * @notice process_rpc_message() handles a raw JSON-RPC message, which may be a single request or a batch. Returns the
+ * serialized response, or None if nothing should be sent back (notifications only).
+ */
+pub async fn process_rpc_message(message: &str, validator_node: ValidatorNode) -> Option<String> {
+
+    // parse the message, replying with a parse error if it is not valid JSON
+    let request: Value = match serde_json::from_str(message) {
+        Ok(request) => request,
+        Err(_) => {
+            let response = RpcResponse::new(Value::Null, Err(RpcError::new(PARSE_ERROR, "Parse error")));
+            return Some(serde_json::to_string(&response).unwrap());
+        }
+    };
+
+    match request {
+        // an empty batch is an invalid request
+        Value::Array(calls) if calls.is_empty() => {
+            let response = RpcResponse::new(Value::Null, Err(RpcError::new(INVALID_REQUEST, "Invalid Request")));
+            Some(serde_json::to_string(&response).unwrap())
+        },
+        // a batch is answered with an array of the non-notification responses
+        Value::Array(calls) => {
+            let mut responses: Vec<RpcResponse> = Vec::new();
+            for call in calls {
+                if let Some(response) = process_rpc_call(call, validator_node.clone()).await {
+                    responses.push(response);
+                }
+            }
+            if responses.is_empty() { None } else { Some(serde_json::to_string(&responses).unwrap()) }
+        },
+        call => process_rpc_call(call, validator_node).await
+            .map(|response| serde_json::to_string(&response).unwrap()),
+    }
+}
+
+/**
+ * @notice process_rpc_call() validates and dispatches a single JSON-RPC call. Calls without an id are notifications
+ * and produce no response.
+ */
+async fn process_rpc_call(call: Value, validator_node: ValidatorNode) -> Option<RpcResponse> {
+
+    // the id is echoed back, a missing id marks a notification
+    let id: Option<Value> = call.get("id").cloned();
+
+    // check the envelope of the call
+    let method: Option<&str> = call["method"].as_str();
+    let params: &Value = &call["params"];
+    let valid_params: bool = params.is_null() || params.is_object() || params.is_array();
+    if call["jsonrpc"] != "2.0" || method.is_none() || !valid_params {
+        return Some(RpcResponse::new(id.unwrap_or(Value::Null), Err(RpcError::new(INVALID_REQUEST, "Invalid Request"))));
+    }
+
+    // dispatch to the method handler
+    let outcome: Result<Value, RpcError> = dispatch(method.unwrap(), params, validator_node).await;
+    id.map(|id| RpcResponse::new(id, outcome))
+}
+
+/**
+ * @notice dispatch() calls the handler of a JSON-RPC method.
+ */
+async fn dispatch(method: &str, params: &Value, validator_node: ValidatorNode) -> Result<Value, RpcError> {
+    match method {
+        "getBlock" => get_block(params, validator_node).await,
+        "getBlockByHash" => get_block_by_hash(params, validator_node).await,
+        "getAccount" => get_account(params, validator_node).await,
+        "getUtxos" => get_utxos(params, validator_node).await,
+        "sendTransaction" => send_request(params, "Transaction").await,
+        "sendUtxoTransaction" => send_request(params, "UTXOTransaction").await,
+        "sendAccountToUtxo" => send_request(params, "AccountToUtxo").await,
+        "getPeers" => get_peers(validator_node).await,
+        "getChainInfo" => get_chain_info(validator_node).await,
+        "subscribe" => Err(RpcError::new(INVALID_REQUEST, "subscribe is only available as a single call over a raw TCP connection")),
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, "Method not found")),
+    }
+}
+
+//------------------------------------ Method Handlers ------------------------------------//
+
+/**
+ * @notice is_subscribe_call() returns true if a raw message is a single call of the subscribe method.
+ */
+fn is_subscribe_call(message: &str) -> bool {
+    serde_json::from_str::<Value>(message).is_ok_and(|call| call["jsonrpc"] == "2.0" && call["method"] == "subscribe")
+}
+
+/**
+ * @notice handle_subscribe() validates the params of a subscribe call and hands the connection over to the event stream.
+ * Params: events (optional list of event names), public_keys (optional list restricting accountChanged events)
+ */
+async fn handle_subscribe(mut socket: TcpStream, message: &str, validator_node: ValidatorNode) {
+    let call: Value = serde_json::from_str(message).unwrap();
+    let id: Value = call.get("id").cloned().unwrap_or(Value::Null);
+
+    match EventFilter::from_params(&call["params"]) {
+        Ok(filter) => events::stream_events(&mut socket, id, filter, validator_node).await,
+        Err(e) => {
+            let response = RpcResponse::new(id, Err(RpcError::new(INVALID_PARAMS, &e)));
+            let _ = socket.write_all(format!("{}\n", serde_json::to_string(&response).unwrap()).as_bytes()).await;
+        }
+    }
+}
+
+/**
+ * @notice get_block() returns the block at a given height. Params: height
+ */
+async fn get_block(params: &Value, validator_node: ValidatorNode) -> Result<Value, RpcError> {
+    let height: u64 = param(params, "height", 0)?.as_u64()
+        .ok_or(RpcError::new(INVALID_PARAMS, "height must be a non-negative integer"))?;
+
+    let block: Option<Block> = validator_node.blockchain.lock().await.chain.get(height as usize).cloned();
+    match block {
+        Some(block) => Ok(serde_json::to_value(convert_block_to_blockjson(block).await).unwrap()),
+        None => Err(RpcError::new(NOT_FOUND, "Block not found")),
+    }
+}
+
+/**
+ * @notice get_block_by_hash() returns the block with a given hex encoded hash along with its height. Params: hash
+ */
+async fn get_block_by_hash(params: &Value, validator_node: ValidatorNode) -> Result<Value, RpcError> {
+    let hash: Vec<u8> = param(params, "hash", 0)?.as_str()
+        .and_then(|hash| hex::decode(hash).ok())
+        .ok_or(RpcError::new(INVALID_PARAMS, "hash must be a hex string"))?;
+
+    // search the chain for a block with a matching hash
+    let found: Option<(usize, Block)> = validator_node.blockchain.lock().await.chain.iter()
+        .enumerate()
+        .find(|(_, block)| block_hash(block) == Some(&hash))
+        .map(|(height, block)| (height, block.clone()));
+
+    match found {
+        Some((height, block)) => Ok(json!({
+            "height": height,
+            "block": convert_block_to_blockjson(block).await,
+        })),
+        None => Err(RpcError::new(NOT_FOUND, "Block not found")),
+    }
+}
+
+/**
+ * @notice get_account() returns the balance and nonce of an account. Params: public_key
+ */
+async fn get_account(params: &Value, validator_node: ValidatorNode) -> Result<Value, RpcError> {
+    let public_key: String = param(params, "public_key", 0)?.as_str()
+        .ok_or(RpcError::new(INVALID_PARAMS, "public_key must be a string"))?
+        .to_string();
+
+    match query::handle_account_query(public_key, validator_node).await {
+        QueryResponse::Account { public_key, exists, balance, nonce } => Ok(json!({
+            "public_key": public_key, "exists": exists, "balance": balance, "nonce": nonce,
+        })),
+        _ => Err(RpcError::new(NOT_FOUND, "Account not found")),
+    }
+}
+
+/**
+ * @notice get_utxos() returns the unspent outputs owned by a recipient, with the hex commitment of confidential ones.
+ * Params: recipient (an address, a hex encoded multisig lock, or a hex encoded public key which also owns the outputs
+ * paid to its address)
+ */
+async fn get_utxos(params: &Value, validator_node: ValidatorNode) -> Result<Value, RpcError> {
+    let recipient: &str = param(params, "recipient", 0)?.as_str()
+        .ok_or(RpcError::new(INVALID_PARAMS, "recipient must be a string"))?;
+
+    let utxo_set_guard = validator_node.utxo_set.lock().await;
+    let owned_utxos = query::owned_utxos(&utxo_set_guard, recipient).map_err(|e| RpcError::new(INVALID_PARAMS, &e))?;
+    let utxos: Vec<Value> = owned_utxos.into_iter()
+        .map(|(outpoint, utxo)| json!({
+            "txid": hex::encode(&outpoint.txid),
+            "vout": outpoint.vout,
+            "amount": utxo.amount,
+            "commitment": utxo.commitment.map(hex::encode),
+            "block_height": utxo.block_height,
+            "timestamp": utxo.timestamp,
+        }))
+        .collect();
+
+    Ok(Value::Array(utxos))
+}
+
+/**
+ * @notice send_request() broadcasts a transaction to every validator node in the network and returns the hash it can
+ * be tracked under (see query::TxStatus). The params must hold the fields of the matching NetworkRequest variant.
+ */
+async fn send_request(params: &Value, action: &str) -> Result<Value, RpcError> {
+
+    // build and check the network request from the params
+    let mut fields = params.as_object().cloned()
+        .ok_or(RpcError::new(INVALID_PARAMS, "params must be an object"))?;
+    fields.insert("action".to_string(), Value::String(action.to_string()));
+    let request: NetworkRequest = serde_json::from_value(Value::Object(fields))
+        .map_err(|e| RpcError { code: INVALID_PARAMS, message: "Invalid params".to_string(), data: Some(Value::String(e.to_string())) })?;
+
+    // broadcast the request to all nodes
+    let request_json: String = serde_json::to_string(&request)
+        .map_err(|e| RpcError::new(SUBMISSION_FAILED, &e.to_string()))?;
+    requests::send_json_request_to_all_ports(request_json).await;
+
+    let request_hash: Vec<u8> = network::hash_network_request(serde_json::to_value(&request).unwrap()).await;
+    Ok(json!({ "hash": hex::encode(request_hash) }))
+}
+
+/**
+ * @notice get_peers() returns the active peers of the node and the time of their last heartbeat.
+ */
+async fn get_peers(validator_node: ValidatorNode) -> Result<Value, RpcError> {
+    let peers: Vec<Value> = validator_node.active_peers.lock().await.iter()
+        .map(|(address, last_heartbeat)| json!({ "address": address, "last_heartbeat": last_heartbeat }))
+        .collect();
+
+    Ok(Value::Array(peers))
+}
+
+/**
+ * @notice get_chain_info() returns a summary of the local ledger state of the node.
+ */
+async fn get_chain_info(validator_node: ValidatorNode) -> Result<Value, RpcError> {
+
+    // read the blockchain summary, releasing the lock before touching other state
+    let blockchain_guard = validator_node.blockchain.lock().await;
+    let block_count: usize = blockchain_guard.chain.len();
+    let latest_hash: Option<String> = blockchain_guard.chain.last().and_then(block_hash).map(hex::encode);
+    let chain_hash: String = hex::encode(blockchain_guard.hash_blockchain());
+    drop(blockchain_guard);
+
+    let utxo_count: usize = validator_node.utxo_set.lock().await.len();
+    let active_peers: usize = validator_node.active_peers.lock().await.len();
+
+    Ok(json!({
+        "chain_id": blockchain::chain_id(),
+        "node_address": validator_node.client_port_address,
+        "block_count": block_count,
+        "latest_block_hash": latest_hash,
+        "chain_hash": chain_hash,
+        "utxo_count": utxo_count,
+        "active_peers": active_peers,
+    }))
+}
+
+//------------------------------------ Helper Functions ------------------------------------//
+
+/**
+ * @notice param() retrieves a parameter by name from an object or by position from an array.
+ */
+fn param<'a>(params: &'a Value, name: &str, index: usize) -> Result<&'a Value, RpcError> {
+    let value: Option<&Value> = match params {
+        Value::Object(map) => map.get(name),
+        Value::Array(values) => values.get(index),
+        _ => None,
+    };
+    value.ok_or(RpcError::new(INVALID_PARAMS, &format!("missing param: {}", name)))
+}
+
+/**
+ * @notice block_hash() returns the hash of a block, the genesis block has none.
+ */
+fn block_hash(block: &Block) -> Option<&Vec<u8>> {
+    match block {
+        Block::Genesis { .. } => None,
+        Block::Transaction { hash, .. } => Some(hash),
+        Block::NewAccount { hash, .. } => Some(hash),
+        Block::Faucet { hash, .. } => Some(hash),
+        Block::UTXOTransaction { hash, .. } => Some(hash),
+        Block::Coinbase { hash, .. } => Some(hash),
+        Block::AccountToUtxo { hash, .. } => Some(hash),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // parses the response to a single message
+    async fn call(message: &str, validator_node: ValidatorNode) -> Value {
+        serde_json::from_str(&process_rpc_message(message, validator_node).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_structured_errors() {
+        let validator_node = ValidatorNode::new();
+
+        let response = call("{not json", validator_node.clone()).await;
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+
+        let response = call(r#"{"jsonrpc": "1.0", "method": "getPeers", "id": 1}"#, validator_node.clone()).await;
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+
+        let response = call(r#"{"jsonrpc": "2.0", "method": "mine", "id": 2}"#, validator_node.clone()).await;
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(response["id"], 2);
+
+        let response = call(r#"{"jsonrpc": "2.0", "method": "getBlock", "params": {"height": "x"}, "id": 3}"#, validator_node.clone()).await;
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+
+        let response = call(r#"{"jsonrpc": "2.0", "method": "getBlock", "params": [5], "id": 4}"#, validator_node).await;
+        assert_eq!(response["error"]["code"], NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_block_and_chain_info() {
+        let validator_node = ValidatorNode::new();
+
+        let response = call(r#"{"jsonrpc": "2.0", "method": "getBlock", "params": [0], "id": 1}"#, validator_node.clone()).await;
+        assert!(response["result"]["Genesis"].is_object());
+        assert!(response.get("error").is_none());
+
+        let response = call(r#"{"jsonrpc": "2.0", "method": "getChainInfo", "id": "info"}"#, validator_node).await;
+        assert_eq!(response["result"]["block_count"], 1);
+        assert_eq!(response["id"], "info");
+    }
+
+    #[tokio::test]
+    async fn test_batch_and_notifications() {
+        let validator_node = ValidatorNode::new();
+
+        // notifications are executed but not answered
+        assert!(process_rpc_message(r#"{"jsonrpc": "2.0", "method": "getPeers"}"#, validator_node.clone()).await.is_none());
+
+        let response = call(r#"[
+            {"jsonrpc": "2.0", "method": "getPeers", "id": 1},
+            {"jsonrpc": "2.0", "method": "getPeers"},
+            {"jsonrpc": "2.0", "method": "getAccount", "params": {"public_key": "abc"}, "id": 2}
+        ]"#, validator_node.clone()).await;
+        assert_eq!(response.as_array().unwrap().len(), 2);
+        assert_eq!(response[1]["result"]["exists"], false);
+
+        let response = call("[]", validator_node).await;
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_streams_events() {
+        let validator_node = ValidatorNode::new();
+
+        // serve a single connection on an ephemeral port
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server_node = validator_node.clone();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            handle_rpc_connection(socket, server_node).await;
+        });
+
+        // subscribe to peer events only
+        let mut client = BufReader::new(TcpStream::connect(address).await.unwrap());
+        let subscribe = r#"{"jsonrpc": "2.0", "method": "subscribe", "params": {"events": ["peerJoined"]}, "id": 7}"#;
+        client.get_mut().write_all(format!("{}\n", subscribe).as_bytes()).await.unwrap();
+
+        let mut line = String::new();
+        client.read_line(&mut line).await.unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["id"], 7);
+        let subscription = response["result"]["subscription"].clone();
+
+        // filtered out events are not sent, matching ones are
+        validator_node.emit(events::NodeEvent::PeerLeft { address: "127.0.0.1:8081".to_string() });
+        validator_node.emit(events::NodeEvent::PeerJoined { address: "127.0.0.1:8082".to_string() });
+
+        line.clear();
+        client.read_line(&mut line).await.unwrap();
+        let notification: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(notification["params"]["subscription"], subscription);
+        assert_eq!(notification["params"]["result"]["event"], "peerJoined");
+        assert_eq!(notification["params"]["result"]["address"], "127.0.0.1:8082");
+    }
+}
@@ -0,0 +1,524 @@
+use sha2::{Sha256, Digest};
+use std::fmt;
+
+use crate::modules::address::{self, PUBLIC_KEY_LENGTH, PUBLIC_KEY_HASH_LENGTH};
+use crate::modules::constants::{MAX_SCRIPT_SIZE, MAX_SCRIPT_OPS, MAX_STACK_SIZE, MAX_SCRIPT_ELEMENT_SIZE, MAX_MULTISIG_KEYS};
+use crate::modules::multisig::MultisigLock;
+use crate::modules::utxo::INPUT_SIGNATURE_LENGTH;
+
+
+/**
+ * @notice script.rs contains the locking scripts of UTXO outputs: a small stack language in which an output states the
+ * conditions under which it can be spent. An input spending it provides an unlocking script, which may only push data
+ * (signatures, public keys, hash preimages). The unlocking script is run first, then the locking script on the stack
+ * it left, and the spend is valid if the locking script ends with exactly one true value on the stack.
+ *
+ * Opcodes follow the numbering of Bitcoin script:
+ *
+ *   OP_0, OP_1 .. OP_16, pushes        push data or a small number
+ *   OP_IF, OP_NOTIF, OP_ELSE, OP_ENDIF run a branch depending on the top of the stack
+ *   OP_VERIFY, OP_EQUAL(VERIFY)        fail unless the top of the stack is true, compare the top two items
+ *   OP_DUP, OP_DROP, OP_NOP            stack manipulation
+ *   OP_SHA256, OP_HASH160              hash the top of the stack
+ *   OP_CHECKSIG(VERIFY)                check a signature (compact signature and sighash flag byte) against a public key
+ *   OP_CHECKMULTISIG                   check M signatures against N public keys, in the order of the keys
+ *   OP_CHECKLOCKTIMEVERIFY             fail unless the transaction is time locked at least until the top of the stack
+ *
+ * Numbers are unsigned little-endian integers of at most 8 bytes, and an item is true unless all of its bytes are zero.
+ * The size of scripts, the number of opcodes run, the size of the stack and of its items are limited (see constants.rs).
+ *
+ * The existing kinds of recipient are standard templates of this language, so every input is verified by the
+ * interpreter (see UTXOTransaction::verify_input()):
+ *
+ *   public key         <public key> OP_CHECKSIG                        unlocked by <signature>
+ *   multisig lock      OP_M <public key>... OP_N OP_CHECKMULTISIG      unlocked by <signature>...
+ *
+ * A public key hash recipient is spent like a public key once the input's public key is checked to hash to it. Any
+ * other locking script is stored as the recipient of the output, prefixed with SCRIPT_TAG. The input spending it reveals
+ * that recipient as its public key, and holds the hex encoded unlocking script as its signature.
+ */
+// first byte of a recipient holding a locking script
+pub const SCRIPT_TAG: u8 = 0x53;
+
+// opcodes
+pub const OP_0: u8 = 0x00;
+pub const OP_PUSHDATA1: u8 = 0x4c;
+pub const OP_1: u8 = 0x51;
+pub const OP_16: u8 = 0x60;
+pub const OP_NOP: u8 = 0x61;
+pub const OP_IF: u8 = 0x63;
+pub const OP_NOTIF: u8 = 0x64;
+pub const OP_ELSE: u8 = 0x67;
+pub const OP_ENDIF: u8 = 0x68;
+pub const OP_VERIFY: u8 = 0x69;
+pub const OP_DROP: u8 = 0x75;
+pub const OP_DUP: u8 = 0x76;
+pub const OP_EQUAL: u8 = 0x87;
+pub const OP_EQUALVERIFY: u8 = 0x88;
+pub const OP_SHA256: u8 = 0xa8;
+pub const OP_HASH160: u8 = 0xa9;
+pub const OP_CHECKSIG: u8 = 0xac;
+pub const OP_CHECKSIGVERIFY: u8 = 0xad;
+pub const OP_CHECKMULTISIG: u8 = 0xae;
+pub const OP_CHECKLOCKTIMEVERIFY: u8 = 0xb1;
+
+// names of the opcodes in script assembly
+const OPCODE_NAMES: [(u8, &str); 18] = [
+    (OP_0, "OP_0"), (OP_NOP, "OP_NOP"), (OP_IF, "OP_IF"), (OP_NOTIF, "OP_NOTIF"), (OP_ELSE, "OP_ELSE"),
+    (OP_ENDIF, "OP_ENDIF"), (OP_VERIFY, "OP_VERIFY"), (OP_DROP, "OP_DROP"), (OP_DUP, "OP_DUP"), (OP_EQUAL, "OP_EQUAL"),
+    (OP_EQUALVERIFY, "OP_EQUALVERIFY"), (OP_SHA256, "OP_SHA256"), (OP_HASH160, "OP_HASH160"), (OP_CHECKSIG, "OP_CHECKSIG"),
+    (OP_CHECKSIGVERIFY, "OP_CHECKSIGVERIFY"), (OP_CHECKMULTISIG, "OP_CHECKMULTISIG"),
+    (OP_CHECKLOCKTIMEVERIFY, "OP_CHECKLOCKTIMEVERIFY"), (OP_PUSHDATA1, "OP_PUSHDATA1"),
+];
+
+/**
+ * @notice Instruction is a parsed step of a script: either data to push or an opcode.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction<'a> {
+    Push(&'a [u8]),
+    Op(u8),
+}
+
+impl Instruction<'_> {
+
+    // Returns the number pushed by the instruction, if it pushes one
+    pub fn as_number(&self) -> Option<u64> {
+        match self {
+            Instruction::Push(data) => decode_number(data).ok(),
+            Instruction::Op(OP_0) => Some(0),
+            Instruction::Op(opcode) if (OP_1..=OP_16).contains(opcode) => Some((opcode - OP_1 + 1) as u64),
+            Instruction::Op(_) => None,
+        }
+    }
+}
+
+/**
+ * @notice SignatureChecker gives the interpreter access to the transaction being verified.
+ */
+pub trait SignatureChecker {
+    // Returns true if the signature (compact signature and sighash flag byte) signs the input with the public key
+    fn check_signature(&self, signature: &[u8], public_key: &[u8]) -> bool;
+
+    // Returns true if the transaction is not valid before the given lock time
+    fn check_lock_time(&self, lock_time: u64) -> bool;
+}
+
+/**
+ * @notice Script is a serialized locking or unlocking script.
+ */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Script {
+    bytes: Vec<u8>,
+}
+
+impl Script {
+
+    pub fn new() -> Script {
+        Script { bytes: Vec::new() }
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Script {
+        Script { bytes }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    // Appends an opcode
+    pub fn push_opcode(mut self, opcode: u8) -> Script {
+        self.bytes.push(opcode);
+        self
+    }
+
+    // Appends a push of data, with OP_PUSHDATA1 for data longer than 75 bytes
+    pub fn push_data(mut self, data: &[u8]) -> Script {
+        if data.len() >= OP_PUSHDATA1 as usize {
+            self.bytes.push(OP_PUSHDATA1);
+        }
+        self.bytes.push(data.len() as u8);
+        self.bytes.extend_from_slice(data);
+        self
+    }
+
+    // Appends a push of a number, using OP_0 .. OP_16 for small numbers
+    pub fn push_number(self, number: u64) -> Script {
+        match number {
+            0 => self.push_opcode(OP_0),
+            1..=16 => self.push_opcode(OP_1 + number as u8 - 1),
+            _ => self.push_data(&encode_number(number)),
+        }
+    }
+
+    // The standard template of a public key recipient: <public key> OP_CHECKSIG
+    pub fn pay_to_public_key(public_key: &[u8]) -> Script {
+        Script::new().push_data(public_key).push_opcode(OP_CHECKSIG)
+    }
+
+    // The standard template of a multisig lock: OP_M <public key>... OP_N OP_CHECKMULTISIG
+    pub fn multisig(lock: &MultisigLock) -> Script {
+        let script = lock.public_keys.iter()
+            .fold(Script::new().push_number(lock.threshold as u64), |script, public_key| script.push_data(&public_key.serialize()));
+        script.push_number(lock.public_keys.len() as u64).push_opcode(OP_CHECKMULTISIG)
+    }
+
+    /**
+     * @notice to_recipient() returns the recipient of an output locked by this script.
+     * @dev A recipient of 20 or 33 bytes is a public key hash or a public key, so a script that would encode to either
+     * length is padded with an OP_NOP.
+     */
+    pub fn to_recipient(&self) -> Vec<u8> {
+        let mut recipient = vec![SCRIPT_TAG];
+        recipient.extend_from_slice(&self.bytes);
+        if recipient.len() == PUBLIC_KEY_HASH_LENGTH || recipient.len() == PUBLIC_KEY_LENGTH {
+            recipient.push(OP_NOP);
+        }
+        recipient
+    }
+
+    // Parses the locking script stored as a recipient, checking that it is well formed
+    pub fn from_recipient(recipient: &[u8]) -> Result<Script, String> {
+        match recipient {
+            [SCRIPT_TAG, bytes @ ..] if recipient.len() != PUBLIC_KEY_HASH_LENGTH && recipient.len() != PUBLIC_KEY_LENGTH => {
+                let script = Script::from_bytes(bytes.to_vec());
+                if script.bytes.len() > MAX_SCRIPT_SIZE {
+                    return Err(format!("Scripts are limited to {} bytes", MAX_SCRIPT_SIZE));
+                }
+                script.instructions()?;
+                Ok(script)
+            }
+            _ => Err("Not a script recipient".to_string()),
+        }
+    }
+
+    /**
+     * @notice locking_script() returns the locking script an input must satisfy, from the public key it reveals: the
+     * standard template of a public key or multisig lock, or the script of a script recipient.
+     */
+    pub fn locking_script(revealed: &[u8]) -> Result<Script, String> {
+        if revealed.len() == PUBLIC_KEY_LENGTH {
+            return Ok(Script::pay_to_public_key(revealed));
+        }
+        if let Ok(lock) = MultisigLock::from_bytes(revealed) {
+            return Ok(Script::multisig(&lock));
+        }
+        Script::from_recipient(revealed)
+    }
+
+    /**
+     * @notice unlocking_script() returns the unlocking script of an input from its signature bytes. Inputs of the
+     * standard templates hold their signatures, which are pushed one after the other; inputs of script recipients hold
+     * the unlocking script itself.
+     */
+    pub fn unlocking_script(revealed: &[u8], signature: &[u8]) -> Result<Script, String> {
+        if revealed.len() != PUBLIC_KEY_LENGTH && MultisigLock::from_bytes(revealed).is_err() {
+            return Ok(Script::from_bytes(signature.to_vec()));
+        }
+        if signature.is_empty() || !signature.len().is_multiple_of(INPUT_SIGNATURE_LENGTH) {
+            return Err("Missing signature".to_string());
+        }
+        Ok(signature.chunks(INPUT_SIGNATURE_LENGTH).fold(Script::new(), |script, signature| script.push_data(signature)))
+    }
+
+    // Parses the script into its instructions
+    pub fn instructions(&self) -> Result<Vec<Instruction<'_>>, String> {
+        let mut instructions = Vec::new();
+        let mut rest: &[u8] = &self.bytes;
+        while let Some((&opcode, tail)) = rest.split_first() {
+            let (length, tail) = match opcode {
+                1..=0x4b => (opcode as usize, tail),
+                OP_PUSHDATA1 => match tail.split_first() {
+                    Some((&length, tail)) => (length as usize, tail),
+                    None => return Err("OP_PUSHDATA1 without a length".to_string()),
+                },
+                _ => {
+                    instructions.push(Instruction::Op(opcode));
+                    rest = tail;
+                    continue;
+                }
+            };
+            if tail.len() < length {
+                return Err("Push past the end of the script".to_string());
+            }
+            instructions.push(Instruction::Push(&tail[..length]));
+            rest = &tail[length..];
+        }
+        Ok(instructions)
+    }
+
+    // Returns true if the script only pushes data
+    pub fn is_push_only(&self) -> bool {
+        self.instructions().map(|instructions| instructions.iter().all(|instruction| match instruction {
+            Instruction::Push(_) => true,
+            Instruction::Op(opcode) => *opcode == OP_0 || (OP_1..=OP_16).contains(opcode),
+        })).unwrap_or(false)
+    }
+
+    // Returns true if the script pushes the given data, e.g. a public key that can sign for it
+    pub fn pushes(&self, data: &[u8]) -> bool {
+        self.instructions().map(|instructions| instructions.contains(&Instruction::Push(data))).unwrap_or(false)
+    }
+
+    /**
+     * @notice from_asm() parses script assembly: opcode names (e.g. OP_CHECKSIG), decimal numbers and hex data, separated
+     * by spaces. Data is written as <hex> to tell it apart from numbers.
+     */
+    pub fn from_asm(asm: &str) -> Result<Script, String> {
+        asm.split_whitespace().try_fold(Script::new(), |script, token| {
+            if let Some(data) = token.strip_prefix('<').and_then(|token| token.strip_suffix('>')) {
+                return hex::decode(data).map(|data| script.push_data(&data)).map_err(|e| format!("Invalid data {}: {}", token, e));
+            }
+            if let Some((opcode, _)) = OPCODE_NAMES.iter().find(|(_, name)| *name == token) {
+                return Ok(script.push_opcode(*opcode));
+            }
+            token.parse::<u64>().map(|number| script.push_number(number)).map_err(|_| format!("Unknown token {}", token))
+        })
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let instructions = match self.instructions() {
+            Ok(instructions) => instructions,
+            Err(_) => return write!(f, "<invalid script {}>", hex::encode(&self.bytes)),
+        };
+        let tokens: Vec<String> = instructions.iter().map(|instruction| match instruction {
+            Instruction::Push(data) => format!("<{}>", hex::encode(data)),
+            Instruction::Op(opcode) if (OP_1..=OP_16).contains(opcode) => (opcode - OP_1 + 1).to_string(),
+            Instruction::Op(opcode) => OPCODE_NAMES.iter().find(|(code, _)| code == opcode)
+                .map(|(_, name)| name.to_string())
+                .unwrap_or(format!("OP_UNKNOWN_{:02x}", opcode)),
+        }).collect();
+        write!(f, "{}", tokens.join(" "))
+    }
+}
+
+// Encodes a number as the shortest little-endian byte string, the empty string for 0
+fn encode_number(number: u64) -> Vec<u8> {
+    let bytes = number.to_le_bytes();
+    let length = bytes.iter().rposition(|byte| *byte != 0).map_or(0, |last| last + 1);
+    bytes[..length].to_vec()
+}
+
+// Decodes a number of at most 8 bytes
+fn decode_number(bytes: &[u8]) -> Result<u64, String> {
+    if bytes.len() > 8 { return Err("Numbers are limited to 8 bytes".to_string()); }
+    let mut padded = [0u8; 8];
+    padded[..bytes.len()].copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(padded))
+}
+
+// An item is true unless all of its bytes are zero
+fn is_true(item: &[u8]) -> bool {
+    item.iter().any(|byte| *byte != 0)
+}
+
+fn pop(stack: &mut Vec<Vec<u8>>) -> Result<Vec<u8>, String> {
+    stack.pop().ok_or("Stack underflow".to_string())
+}
+
+/**
+ * @notice verify_script() runs an unlocking script followed by the locking script it spends. The spend is valid if the
+ * unlocking script only pushes data and the locking script leaves exactly one true value on the stack.
+ */
+pub fn verify_script(unlocking: &Script, locking: &Script, checker: &impl SignatureChecker) -> Result<(), String> {
+    if !unlocking.is_push_only() {
+        return Err("Unlocking scripts may only push data".to_string());
+    }
+
+    let mut stack: Vec<Vec<u8>> = Vec::new();
+    execute(unlocking, &mut stack, checker)?;
+    execute(locking, &mut stack, checker)?;
+
+    match stack.as_slice() {
+        [result] if is_true(result) => Ok(()),
+        [_] => Err("Script evaluated to false".to_string()),
+        _ => Err(format!("Script left {} items on the stack instead of 1", stack.len())),
+    }
+}
+
+/**
+ * @notice execute() runs one script on the stack, within the resource limits of constants.rs.
+ */
+fn execute(script: &Script, stack: &mut Vec<Vec<u8>>, checker: &impl SignatureChecker) -> Result<(), String> {
+    if script.as_bytes().len() > MAX_SCRIPT_SIZE {
+        return Err(format!("Scripts are limited to {} bytes", MAX_SCRIPT_SIZE));
+    }
+
+    // one entry per open OP_IF, true while its taken branch is running
+    let mut branches: Vec<bool> = Vec::new();
+    let mut op_count: usize = 0;
+
+    for instruction in script.instructions()? {
+        let executing: bool = branches.iter().all(|taken| *taken);
+        let opcode: u8 = match instruction {
+            Instruction::Push(data) if data.len() > MAX_SCRIPT_ELEMENT_SIZE => {
+                return Err(format!("Pushes are limited to {} bytes", MAX_SCRIPT_ELEMENT_SIZE));
+            }
+            Instruction::Push(data) => {
+                if executing { stack.push(data.to_vec()); }
+                continue;
+            }
+            Instruction::Op(opcode) => opcode,
+        };
+
+        // count every opcode that is not a push, taken or not
+        if opcode > OP_16 {
+            op_count += 1;
+            if op_count > MAX_SCRIPT_OPS { return Err(format!("Scripts are limited to {} opcodes", MAX_SCRIPT_OPS)); }
+        }
+
+        match opcode {
+            OP_IF | OP_NOTIF => {
+                let taken: bool = executing && (is_true(&pop(stack)?) == (opcode == OP_IF));
+                branches.push(taken);
+            }
+            OP_ELSE => {
+                let taken = branches.last_mut().ok_or("OP_ELSE without OP_IF".to_string())?;
+                *taken = !*taken;
+            }
+            OP_ENDIF => {
+                branches.pop().ok_or("OP_ENDIF without OP_IF".to_string())?;
+            }
+            _ if !executing => {}
+            OP_0 => stack.push(Vec::new()),
+            OP_1..=OP_16 => stack.push(encode_number((opcode - OP_1 + 1) as u64)),
+            OP_NOP => {}
+            OP_VERIFY => {
+                if !is_true(&pop(stack)?) { return Err("OP_VERIFY failed".to_string()); }
+            }
+            OP_DROP => {
+                pop(stack)?;
+            }
+            OP_DUP => {
+                let top: Vec<u8> = stack.last().cloned().ok_or("Stack underflow".to_string())?;
+                stack.push(top);
+            }
+            OP_EQUAL | OP_EQUALVERIFY => {
+                let equal: bool = pop(stack)? == pop(stack)?;
+                if opcode == OP_EQUAL { stack.push(encode_number(equal as u64)); }
+                else if !equal { return Err("OP_EQUALVERIFY failed".to_string()); }
+            }
+            OP_SHA256 => {
+                let item: Vec<u8> = pop(stack)?;
+                stack.push(Sha256::digest(&item).to_vec());
+            }
+            OP_HASH160 => {
+                let item: Vec<u8> = pop(stack)?;
+                stack.push(address::hash160(&item).to_vec());
+            }
+            OP_CHECKSIG | OP_CHECKSIGVERIFY => {
+                let public_key: Vec<u8> = pop(stack)?;
+                let signature: Vec<u8> = pop(stack)?;
+                let valid: bool = checker.check_signature(&signature, &public_key);
+                if opcode == OP_CHECKSIG { stack.push(encode_number(valid as u64)); }
+                else if !valid { return Err("OP_CHECKSIGVERIFY failed".to_string()); }
+            }
+            OP_CHECKMULTISIG => {
+                let key_count = decode_number(&pop(stack)?)? as usize;
+                if key_count > MAX_MULTISIG_KEYS { return Err(format!("OP_CHECKMULTISIG is limited to {} keys", MAX_MULTISIG_KEYS)); }
+                op_count += key_count;
+                if op_count > MAX_SCRIPT_OPS { return Err(format!("Scripts are limited to {} opcodes", MAX_SCRIPT_OPS)); }
+                let mut public_keys: Vec<Vec<u8>> = (0..key_count).map(|_| pop(stack)).collect::<Result<_, _>>()?;
+                public_keys.reverse();
+
+                let signature_count = decode_number(&pop(stack)?)? as usize;
+                if signature_count > key_count { return Err("OP_CHECKMULTISIG needs more signatures than keys".to_string()); }
+                let mut signatures: Vec<Vec<u8>> = (0..signature_count).map(|_| pop(stack)).collect::<Result<_, _>>()?;
+                signatures.reverse();
+
+                // each signature must match a key after the key matched by the previous one, so no key counts twice
+                let mut keys = public_keys.iter();
+                let valid: bool = signatures.iter()
+                    .all(|signature| keys.by_ref().any(|public_key| checker.check_signature(signature, public_key)));
+                stack.push(encode_number(valid as u64));
+            }
+            OP_CHECKLOCKTIMEVERIFY => {
+                let lock_time: u64 = decode_number(stack.last().ok_or("Stack underflow".to_string())?)?;
+                if !checker.check_lock_time(lock_time) { return Err(format!("Locked until {}", lock_time)); }
+            }
+            _ => return Err(format!("Unknown opcode {:#04x}", opcode)),
+        }
+
+        if stack.len() > MAX_STACK_SIZE {
+            return Err(format!("The stack is limited to {} items", MAX_STACK_SIZE));
+        }
+    }
+
+    match branches.is_empty() {
+        true => Ok(()),
+        false => Err("OP_IF without OP_ENDIF".to_string()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Accepts the signature "good" for any key and lock times up to 100
+    struct TestChecker;
+
+    impl SignatureChecker for TestChecker {
+        fn check_signature(&self, signature: &[u8], _public_key: &[u8]) -> bool { signature == b"good" }
+        fn check_lock_time(&self, lock_time: u64) -> bool { lock_time <= 100 }
+    }
+
+    fn run(unlocking: &str, locking: &str) -> Result<(), String> {
+        verify_script(&Script::from_asm(unlocking).unwrap(), &Script::from_asm(locking).unwrap(), &TestChecker)
+    }
+
+    #[test]
+    fn test_asm_round_trip_and_recipients() {
+        let script = Script::from_asm("OP_DUP OP_HASH160 <0102> OP_EQUALVERIFY 300 OP_CHECKLOCKTIMEVERIFY OP_DROP 2").unwrap();
+        assert_eq!(script.to_string(), "OP_DUP OP_HASH160 <0102> OP_EQUALVERIFY <2c01> OP_CHECKLOCKTIMEVERIFY OP_DROP 2");
+        assert_eq!(Script::from_recipient(&script.to_recipient()).unwrap(), script);
+
+        // scripts never encode to the length of a public key hash or public key
+        let short = Script::from_bytes(vec![OP_NOP; PUBLIC_KEY_HASH_LENGTH - 1]);
+        assert_eq!(short.to_recipient().len(), PUBLIC_KEY_HASH_LENGTH + 1);
+        assert!(Script::from_recipient(&[SCRIPT_TAG, 0x05, 1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_hash_lock_with_branches() {
+        let hash = hex::encode(Sha256::digest(b"secret"));
+        let locking = format!("OP_IF OP_SHA256 <{}> OP_EQUALVERIFY <aa> OP_ELSE 100 OP_CHECKLOCKTIMEVERIFY OP_DROP <bb> OP_ENDIF OP_CHECKSIG", hash);
+
+        assert!(run(&format!("<{}> <{}> 1", hex::encode(b"good"), hex::encode(b"secret")), &locking).is_ok());
+        assert!(run(&format!("<{}> <{}> 1", hex::encode(b"good"), hex::encode(b"wrong")), &locking).is_err());
+        assert!(run(&format!("<{}> 0", hex::encode(b"good")), &locking).is_ok());
+        assert!(run(&format!("<{}> 0", hex::encode(b"bad")), &locking).is_err());
+
+        // a later lock time is not yet satisfied
+        assert!(run(&format!("<{}> 0", hex::encode(b"good")), &locking.replace(" 100 ", " 101 ")).is_err());
+    }
+
+    #[test]
+    fn test_checkmultisig_and_clean_stack() {
+        let (good, bad) = (hex::encode(b"good"), hex::encode(b"bad"));
+        let locking = "2 <01> <02> <03> 3 OP_CHECKMULTISIG";
+        assert!(run(&format!("<{}> <{}>", good, good), locking).is_ok());
+        assert!(run(&format!("<{}> <{}>", good, bad), locking).is_err());
+        assert!(run(&format!("<{}>", good), locking).is_err());
+
+        // leftover items fail the spend, and unlocking scripts cannot run opcodes
+        assert!(run(&format!("<{}> <{}> <{}>", good, good, good), locking).is_err());
+        assert!(run("1 OP_DUP", "OP_DROP").is_err());
+    }
+
+    #[test]
+    fn test_resource_limits() {
+        let many_ops = vec!["OP_NOP"; MAX_SCRIPT_OPS + 1].join(" ");
+        assert!(run("1", &many_ops).is_err());
+        assert!(run("1", &vec!["OP_NOP"; MAX_SCRIPT_OPS].join(" ")).is_ok());
+
+        let deep_stack = vec!["1"; MAX_STACK_SIZE + 1].join(" ");
+        assert!(run(&deep_stack, "OP_DROP").is_err());
+
+        assert!(run("1", "OP_IF 1").is_err());
+        assert!(run("1", "OP_ENDIF").is_err());
+        assert!(run("1", "OP_CHECKSIG").is_err());
+    }
+}
@@ -0,0 +1,262 @@
+use secp256k1::{PublicKey, SecretKey};
+use sha2::{Sha256, Digest};
+use bech32::{FromBase32, ToBase32, Variant};
+use rand::RngCore;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::modules::address::{self, PUBLIC_KEY_HASH_LENGTH, PUBLIC_KEY_LENGTH};
+use crate::modules::blockchain::Block;
+use crate::modules::utxo::{OutPoint, TxOutput};
+use crate::modules::verifier::secp_context;
+
+
+/**
+ * @notice stealth.rs contains stealth addresses, which let a user publish one address and still receive every UTXO
+ * output at a different one-time key, so that payments to the same user cannot be linked on the chain. A stealth
+ * address holds two secp256k1 public keys: a scan key A = a·G and a spend key B = b·G. To pay it, the sender picks an
+ * ephemeral key r and derives the one-time key
+ *
+ *   P = B + H(r·A)·G
+ *
+ * from the ECDH secret r·A, and pays the output to a stealth recipient holding R = r·G and the hash of P:
+ *
+ *   STEALTH_TAG | ephemeral public key R (33 bytes) | HASH160 of P (20 bytes)
+ *
+ * The receiver computes the same secret as a·R, so the scan key alone finds the outputs paid to an address (by scanning
+ * the outputs of Block::UTXOTransaction blocks), while spending one takes the one-time secret key b + H(a·R). A spend
+ * reveals P like a spend from an address, and the UTXO set indexes stealth outputs under the hash of P, so their balance
+ * and UTXOs are looked up like those of an address.
+ *
+ * Stealth addresses are encoded with Bech32m under the address prefix of the network followed by "s". The scan key is
+ * derived from the spend key, so a wallet account is also a stealth address.
+ */
+// first byte of the recipient of an output paid to a stealth address
+pub const STEALTH_TAG: u8 = 0x50;
+
+// length of a stealth recipient: the tag, the ephemeral public key and the hash of the one-time key
+pub const STEALTH_RECIPIENT_LENGTH: usize = 1 + PUBLIC_KEY_LENGTH + PUBLIC_KEY_HASH_LENGTH;
+
+// domain separation tags of the scan key derivation and of the one-time key tweak
+const SCAN_KEY_TAG: &[u8] = b"miniBlockChain/stealth/scan-key";
+const TWEAK_TAG: &[u8] = b"miniBlockChain/stealth/tweak";
+
+// Returns the human readable prefix of stealth addresses on this network
+pub fn stealth_prefix() -> String {
+    format!("{}s", address::address_prefix())
+}
+
+/**
+ * @notice StealthAddress is the public scan and spend key a stealth payment is derived from.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StealthAddress {
+    pub scan_key: PublicKey,
+    pub spend_key: PublicKey,
+}
+
+impl StealthAddress {
+
+    /**
+     * @notice pay() returns the recipient of a new output paid to the address, with a fresh ephemeral key.
+     */
+    pub fn pay(&self) -> Vec<u8> {
+        let mut ephemeral = [0u8; 32];
+        loop {
+            rand::thread_rng().fill_bytes(&mut ephemeral);
+            if let Ok(ephemeral_key) = SecretKey::from_slice(&ephemeral) {
+                if let Ok(recipient) = self.recipient(&ephemeral_key) { return recipient; }
+            }
+        }
+    }
+
+    /**
+     * @notice recipient() returns the recipient of an output paid to the address with the given ephemeral key.
+     */
+    pub fn recipient(&self, ephemeral_key: &SecretKey) -> Result<Vec<u8>, String> {
+        let tweak: [u8; 32] = shared_tweak(&self.scan_key, ephemeral_key)?;
+        let mut one_time_key: PublicKey = self.spend_key;
+        one_time_key.add_exp_assign(secp_context(), &tweak).map_err(|e| format!("Invalid one-time key: {}", e))?;
+
+        let ephemeral_public_key = PublicKey::from_secret_key(secp_context(), ephemeral_key);
+        Ok([&[STEALTH_TAG][..], &ephemeral_public_key.serialize(), &address::hash160(&one_time_key.serialize())].concat())
+    }
+}
+
+impl fmt::Display for StealthAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keys: Vec<u8> = [self.scan_key.serialize(), self.spend_key.serialize()].concat();
+        let encoded = bech32::encode(&stealth_prefix(), keys.to_base32(), Variant::Bech32m).map_err(|_| fmt::Error)?;
+        write!(f, "{}", encoded)
+    }
+}
+
+impl FromStr for StealthAddress {
+    type Err = String;
+
+    fn from_str(stealth_address: &str) -> Result<Self, Self::Err> {
+        let (prefix, data, variant) = bech32::decode(stealth_address).map_err(|e| format!("invalid stealth address {}: {}", stealth_address, e))?;
+        if prefix != stealth_prefix() || variant != Variant::Bech32m {
+            return Err(format!("{} is not a stealth address of this network (expected prefix {})", stealth_address, stealth_prefix()));
+        }
+        let keys: Vec<u8> = Vec::<u8>::from_base32(&data).map_err(|e| format!("invalid stealth address {}: {}", stealth_address, e))?;
+        if keys.len() != 2 * PUBLIC_KEY_LENGTH { return Err(format!("stealth address {} must encode two public keys", stealth_address)); }
+
+        let public_key = |bytes: &[u8]| PublicKey::from_slice(bytes).map_err(|e| format!("invalid stealth address {}: {}", stealth_address, e));
+        Ok(StealthAddress {
+            scan_key: public_key(&keys[..PUBLIC_KEY_LENGTH])?,
+            spend_key: public_key(&keys[PUBLIC_KEY_LENGTH..])?,
+        })
+    }
+}
+
+// Returns H(secret·public), the tweak of the one-time key, from either side of the ECDH exchange
+fn shared_tweak(public_key: &PublicKey, secret_key: &SecretKey) -> Result<[u8; 32], String> {
+    let mut shared: PublicKey = *public_key;
+    shared.mul_assign(secp_context(), &secret_key[..]).map_err(|e| format!("Invalid shared secret: {}", e))?;
+    Ok(Sha256::new().chain_update(TWEAK_TAG).chain_update(shared.serialize()).finalize().into())
+}
+
+/**
+ * @notice parse_recipient() splits a stealth recipient into its ephemeral public key and the hash of its one-time key.
+ */
+pub fn parse_recipient(recipient: &[u8]) -> Option<(PublicKey, &[u8])> {
+    match recipient {
+        [STEALTH_TAG, rest @ ..] if recipient.len() == STEALTH_RECIPIENT_LENGTH => {
+            let (ephemeral_key, one_time_key_hash) = rest.split_at(PUBLIC_KEY_LENGTH);
+            Some((PublicKey::from_slice(ephemeral_key).ok()?, one_time_key_hash))
+        }
+        _ => None,
+    }
+}
+
+/**
+ * @notice ReceivedOutput is an output paid to a stealth address found by scanning a block, with the one-time secret key
+ * that spends it.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedOutput {
+    pub outpoint: OutPoint,
+    pub output: TxOutput,
+    pub block_height: u64,
+    pub secret_key: SecretKey,
+}
+
+/**
+ * @notice StealthKeys are the secret scan and spend keys of a stealth address.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StealthKeys {
+    pub scan_secret: SecretKey,
+    pub spend_secret: SecretKey,
+}
+
+impl StealthKeys {
+
+    // Derives the scan key from the spend key, so that any key (e.g. of a wallet account) is a stealth address
+    pub fn from_spend_key(spend_secret: SecretKey) -> Result<StealthKeys, String> {
+        let scan_secret: [u8; 32] = Sha256::new().chain_update(SCAN_KEY_TAG).chain_update(&spend_secret[..]).finalize().into();
+        Ok(StealthKeys {
+            scan_secret: SecretKey::from_slice(&scan_secret).map_err(|e| format!("Invalid scan key: {}", e))?,
+            spend_secret,
+        })
+    }
+
+    pub fn address(&self) -> StealthAddress {
+        StealthAddress {
+            scan_key: PublicKey::from_secret_key(secp_context(), &self.scan_secret),
+            spend_key: PublicKey::from_secret_key(secp_context(), &self.spend_secret),
+        }
+    }
+
+    /**
+     * @notice output_secret_key() returns the one-time secret key of an output recipient if it is paid to this address.
+     */
+    pub fn output_secret_key(&self, recipient: &[u8]) -> Option<SecretKey> {
+        let (ephemeral_key, one_time_key_hash) = parse_recipient(recipient)?;
+        let tweak: [u8; 32] = shared_tweak(&ephemeral_key, &self.scan_secret).ok()?;
+
+        let mut secret_key: SecretKey = self.spend_secret;
+        secret_key.add_assign(&tweak).ok()?;
+        let one_time_key = PublicKey::from_secret_key(secp_context(), &secret_key);
+        (address::hash160(&one_time_key.serialize()) == one_time_key_hash).then_some(secret_key)
+    }
+
+    /**
+     * @notice scan() returns the outputs of a UTXO transaction block, and of its coinbase, paid to this address. Other
+     * blocks hold no UTXO outputs and yield none.
+     */
+    pub fn scan(&self, block: &Block) -> Vec<ReceivedOutput> {
+        let Block::UTXOTransaction { transaction, block_height, coinbase, .. } = block else { return Vec::new() };
+
+        let outputs = transaction.outputs.iter().enumerate().map(|(vout, output)| (&transaction.hash, vout, output))
+            .chain(coinbase.outputs.iter().enumerate().map(|(vout, output)| (&coinbase.hash, vout, output)));
+        outputs
+            .filter_map(|(txid, vout, output)| self.output_secret_key(&output.recipient).map(|secret_key| ReceivedOutput {
+                outpoint: OutPoint::new(txid.clone(), vout as u32),
+                output: output.clone(),
+                block_height: *block_height,
+                secret_key,
+            }))
+            .collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::utxo::{CoinbaseTransaction, UTXOTransaction};
+
+    fn stealth_keys(seed: u8) -> StealthKeys {
+        StealthKeys::from_spend_key(SecretKey::from_slice(&[seed; 32]).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_stealth_address_round_trip() {
+        let address = stealth_keys(1).address();
+        let encoded = address.to_string();
+        assert!(encoded.starts_with(&format!("{}1", stealth_prefix())));
+        assert_eq!(encoded.parse::<StealthAddress>().unwrap(), address);
+
+        // plain addresses and mistyped stealth addresses are rejected
+        assert!(address::address_of_public_key(&address.spend_key).parse::<StealthAddress>().is_err());
+        let typo = format!("{}{}", &encoded[..encoded.len() - 1], if encoded.ends_with('q') { 'p' } else { 'q' });
+        assert!(typo.parse::<StealthAddress>().is_err());
+    }
+
+    #[test]
+    fn test_one_time_keys_are_unlinkable_and_spendable() {
+        let keys = stealth_keys(1);
+        let (first, second) = (keys.address().pay(), keys.address().pay());
+        assert_eq!(first.len(), STEALTH_RECIPIENT_LENGTH);
+        assert_ne!(first[1 + PUBLIC_KEY_LENGTH..], second[1 + PUBLIC_KEY_LENGTH..]);
+
+        // the receiver recovers the secret key of each one-time key, other keys find nothing
+        for recipient in [&first, &second] {
+            let secret_key = keys.output_secret_key(recipient).unwrap();
+            assert!(address::recipient_matches(recipient, &PublicKey::from_secret_key(secp_context(), &secret_key).serialize()));
+            assert_eq!(stealth_keys(2).output_secret_key(recipient), None);
+        }
+        assert!(!address::recipient_matches(&first, &keys.address().spend_key.serialize()));
+    }
+
+    #[test]
+    fn test_scan_block() {
+        let keys = stealth_keys(1);
+        let transaction = UTXOTransaction::new(vec![], vec![
+            TxOutput::new(10, vec![7; 20]),
+            TxOutput::new(20, keys.address().pay()),
+            TxOutput::new(30, stealth_keys(2).address().pay()),
+        ], 12345);
+        let coinbase = CoinbaseTransaction::new(vec![TxOutput::new(5, keys.address().pay())], 3, 12345);
+        let block = Block::UTXOTransaction { transaction: transaction.clone(), block_height: 3, coinbase: coinbase.clone(), utxo_commitment: vec![], hash: vec![] };
+
+        let received: Vec<ReceivedOutput> = keys.scan(&block);
+        assert_eq!(received.iter().map(|output| output.outpoint.clone()).collect::<Vec<OutPoint>>(),
+            vec![OutPoint::new(transaction.hash.clone(), 1), OutPoint::new(coinbase.hash.clone(), 0)]);
+        assert_eq!(received[0].output.amount, 20);
+        assert_eq!(received[1].block_height, 3);
+        assert!(keys.scan(&Block::Genesis { time: 12345 }).is_empty());
+    }
+}
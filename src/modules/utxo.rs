@@ -1,6 +1,19 @@
 use serde::{Serialize, Deserialize};
-use std::collections::{HashMap, BTreeMap};
-use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet, BTreeMap};
+use std::fmt;
+use secp256k1::{SecretKey, PublicKey, Message, Signature};
+use curve25519_dalek::ristretto::RistrettoPoint;
+
+use crate::modules::address;
+use crate::modules::bridge;
+use crate::modules::confidential::{self, ConfidentialAmount, Opening};
+use crate::modules::constants::{INITIAL_BLOCK_SUBSIDY, SUBSIDY_HALVING_INTERVAL, LOCK_TIME_THRESHOLD, MAX_TIMESTAMP_DRIFT, DUST_THRESHOLD, MAX_TRANSACTION_SIZE};
+use crate::modules::encoding::{self, Encode, Encoder, TRANSACTION_ID_TAG, COINBASE_ID_TAG};
+use crate::modules::multisig::MultisigLock;
+use crate::modules::script::{self, Script, SignatureChecker};
+use crate::modules::utxo_commitment::UtxoCommitment;
+use crate::modules::verifier::secp_context;
+use crate::modules::zk_proof::{signed_message, SignatureDomain};
 
 /**
  * UTXO (Unspent Transaction Output) Model Implementation
@@ -26,7 +39,14 @@ use sha2::{Digest, Sha256};
  * - TxInput: References a UTXO being spent with signature
  * - TxOutput: Creates new UTXOs with amounts and recipients
  * - UTXOTransaction: Contains inputs and outputs for a transaction
- * - UTXOSet: Manages all unspent outputs with optimized lookups
+ * - CoinbaseTransaction: Pays a block's fees (plus any subsidy) to validators
+ * - Sighash: The digest each input signature commits to, selected by SIGHASH_* flags
+ * - Multisig outputs: Outputs locked to M of N public keys (see multisig.rs)
+ * - Time locks: An absolute lock time per transaction and a RelativeLock per input, checked by validators
+ * - Consensus rules: The checks every UTXO transaction must pass, each rejecting it with a TxRejection
+ * - Confidential outputs: Outputs whose amount is hidden in a Pedersen commitment (see confidential.rs)
+ * - UTXOSet: Manages all unspent outputs with optimized lookups and a commitment to them (see utxo_commitment.rs)
+ * - UtxoUndo: The prior state of the outpoints a block changed, which restores the UTXOSet when the block is reverted
  */
 
 /// Unique identifier for a transaction output
@@ -55,6 +75,9 @@ pub struct UTXO {
     pub block_height: u64,
     /// Time when this UTXO was created
     pub timestamp: u64,
+    /// Commitment to the hidden amount of a confidential output, whose `amount` is then 0
+    #[serde(default)]
+    pub commitment: Option<Vec<u8>>,
 }
 
 impl UTXO {
@@ -64,19 +87,44 @@ impl UTXO {
             recipient,
             block_height,
             timestamp,
+            commitment: None,
+        }
+    }
+
+    /// The commitment to the amount of the UTXO, v·H for plain amounts (see confidential.rs)
+    pub fn commitment_point(&self) -> Option<RistrettoPoint> {
+        match &self.commitment {
+            Some(commitment) => confidential::decode_commitment(commitment),
+            None => Some(confidential::amount_commitment(self.amount)),
         }
     }
 }
 
+/// Sign all inputs and all outputs
+pub const SIGHASH_ALL: u8 = 0x01;
+/// Sign all inputs but none of the outputs
+pub const SIGHASH_NONE: u8 = 0x02;
+/// Sign all inputs and only the output with the same index as the signed input
+pub const SIGHASH_SINGLE: u8 = 0x03;
+/// Combined with one of the above, signs only the input being signed so others can add inputs
+pub const SIGHASH_ANYONECANPAY: u8 = 0x80;
+
+/// Length of one input signature: a compact signature followed by the sighash flag byte
+pub const INPUT_SIGNATURE_LENGTH: usize = 65;
+
 /// A transaction input that spends a UTXO
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TxInput {
     /// Reference to the UTXO being spent
     pub outpoint: OutPoint,
-    /// Digital signature proving ownership of the UTXO
+    /// Hex encoded compact signature over the sighash followed by the sighash flag byte. An input spending a multisig
+    /// output holds one such signature per required key, in the order of the keys of the lock
     pub signature: String,
-    /// Public key of the spender (for verification)
+    /// Public key of the spender (for verification), or the multisig lock of the output being spent
     pub public_key: Vec<u8>,
+    /// How old the UTXO being spent must be before this input is valid
+    #[serde(default)]
+    pub relative_lock: Option<RelativeLock>,
 }
 
 impl TxInput {
@@ -85,8 +133,52 @@ impl TxInput {
             outpoint,
             signature,
             public_key,
+            relative_lock: None,
         }
     }
+
+    /// Only allow the input once the UTXO it spends has aged by the lock
+    pub fn with_relative_lock(mut self, relative_lock: RelativeLock) -> Self {
+        self.relative_lock = Some(relative_lock);
+        self
+    }
+}
+
+/// A relative lock on an input, measured from the creation of the UTXO it spends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelativeLock {
+    /// Blocks since the block that created the UTXO
+    Blocks(u64),
+    /// Seconds since the timestamp of the transaction that created the UTXO
+    Seconds(u64),
+}
+
+impl RelativeLock {
+    /// Check the lock against the UTXO being spent, at the height of the block the spend would be included in and the
+    /// current time
+    pub fn check(&self, utxo: &UTXO, block_height: u64, time: u64) -> Result<(), String> {
+        let (unlocked_at, now, unit) = match self {
+            RelativeLock::Blocks(blocks) => (utxo.block_height.saturating_add(*blocks), block_height, "block"),
+            RelativeLock::Seconds(seconds) => (utxo.timestamp.saturating_add(*seconds), time, "time"),
+        };
+        match now >= unlocked_at {
+            true => Ok(()),
+            false => Err(format!("Input locked until {} {}", unit, unlocked_at)),
+        }
+    }
+}
+
+/// Check the timestamp a UTXO is created with against the current time. Under the lock time rules it must be a unix
+/// time, and it must be within `MAX_TIMESTAMP_DRIFT` of the clock so that relative locks in seconds on the UTXO count
+/// from when it was actually created
+pub fn check_creation_timestamp(timestamp: u64, time: u64) -> Result<(), String> {
+    if timestamp < LOCK_TIME_THRESHOLD {
+        return Err(format!("Timestamp {} is not a unix time", timestamp));
+    }
+    match timestamp.abs_diff(time) <= MAX_TIMESTAMP_DRIFT {
+        true => Ok(()),
+        false => Err(format!("Timestamp {} is more than {} seconds from the current time", timestamp, MAX_TIMESTAMP_DRIFT)),
+    }
 }
 
 /// A transaction output that creates a new UTXO
@@ -94,13 +186,35 @@ impl TxInput {
 pub struct TxOutput {
     /// Amount of tokens in this output
     pub amount: u64,
-    /// Public key of the recipient
+    /// Recipient of the output: the 20 byte public key hash of an address, a 33 byte compressed public key, a multisig
+    /// lock (see multisig.rs) or a locking script (see script.rs)
     pub recipient: Vec<u8>,
+    /// The hidden amount of a confidential output (see confidential.rs), whose `amount` is then 0
+    #[serde(default)]
+    pub confidential: Option<ConfidentialAmount>,
 }
 
 impl TxOutput {
     pub fn new(amount: u64, recipient: Vec<u8>) -> Self {
-        TxOutput { amount, recipient }
+        TxOutput { amount, recipient, confidential: None }
+    }
+
+    /// Creates a confidential output committing to the amount of the opening, with its range proof
+    pub fn confidential(opening: &Opening, recipient: Vec<u8>) -> Self {
+        TxOutput { amount: 0, recipient, confidential: Some(ConfidentialAmount::new(opening)) }
+    }
+
+    /// The commitment to the amount of the output, v·H for plain amounts (see confidential.rs)
+    pub fn commitment_point(&self) -> Option<RistrettoPoint> {
+        match &self.confidential {
+            Some(confidential) => confidential::decode_commitment(&confidential.commitment),
+            None => Some(confidential::amount_commitment(self.amount)),
+        }
+    }
+
+    /// Creates an output paying an address (or a hex encoded public key), rejecting malformed recipients
+    pub fn pay_to(amount: u64, recipient: &str) -> Result<Self, String> {
+        Ok(TxOutput::new(amount, address::parse_recipient(recipient)?.into_bytes()))
     }
 }
 
@@ -113,6 +227,13 @@ pub struct UTXOTransaction {
     pub outputs: Vec<TxOutput>,
     /// Transaction timestamp
     pub timestamp: u64,
+    /// Block height (below LOCK_TIME_THRESHOLD) or unix time before which the transaction is not valid, 0 for none
+    #[serde(default)]
+    pub lock_time: u64,
+    /// Fee of a transaction with confidential inputs or outputs, whose fee can not be computed from the amounts; 0 for
+    /// plain transactions
+    #[serde(default)]
+    pub explicit_fee: u64,
     /// Transaction hash (computed from inputs/outputs)
     pub hash: Vec<u8>,
 }
@@ -123,31 +244,63 @@ impl UTXOTransaction {
             inputs,
             outputs,
             timestamp,
+            lock_time: 0,
+            explicit_fee: 0,
             hash: Vec::new(),
         };
         tx.hash = tx.compute_hash();
         tx
     }
 
-    /// Compute the transaction hash
-    pub fn compute_hash(&self) -> Vec<u8> {
-        let mut hasher = Sha256::new();
-        
-        // Hash all inputs
+    /// Pay the given fee explicitly, as confidential transactions must
+    pub fn with_explicit_fee(mut self, explicit_fee: u64) -> Self {
+        self.explicit_fee = explicit_fee;
+        self.hash = self.compute_hash();
+        self
+    }
+
+    /// Only allow the transaction from the given block height or unix time on
+    pub fn with_lock_time(mut self, lock_time: u64) -> Self {
+        self.lock_time = lock_time;
+        self.hash = self.compute_hash();
+        self
+    }
+
+    /// Check the lock time of the transaction and the relative locks of its inputs, at the height of the block the
+    /// transaction would be included in and the current time. Inputs spending UTXOs missing from the set are left to
+    /// the other checks of the validator.
+    pub fn check_time_locks(&self, utxo_set: &UTXOSet, block_height: u64, time: u64) -> Result<(), String> {
+        match self.lock_time {
+            0 => {}
+            height if height < LOCK_TIME_THRESHOLD && block_height < height => {
+                return Err(format!("Transaction locked until block {}", height));
+            }
+            lock_time if lock_time >= LOCK_TIME_THRESHOLD && time < lock_time => {
+                return Err(format!("Transaction locked until time {}", lock_time));
+            }
+            _ => {}
+        }
+
         for input in &self.inputs {
-            hasher.update(&input.outpoint.txid);
-            hasher.update(&input.outpoint.vout.to_le_bytes());
-            hasher.update(&input.public_key);
+            if let (Some(relative_lock), Some(utxo)) = (&input.relative_lock, utxo_set.get_utxo(&input.outpoint)) {
+                relative_lock.check(utxo, block_height, time)?;
+            }
         }
-        
-        // Hash all outputs
-        for output in &self.outputs {
-            hasher.update(&output.amount.to_le_bytes());
-            hasher.update(&output.recipient);
+        Ok(())
+    }
+
+    /// Compute the transaction hash (txid) over the canonical encoding of the transaction without its signatures
+    pub fn compute_hash(&self) -> Vec<u8> {
+        let mut encoder = Encoder::top_level(TRANSACTION_ID_TAG);
+
+        // Encode all inputs, leaving out the signatures so that signing does not change the txid
+        encoder.u64(self.inputs.len() as u64);
+        for input in &self.inputs {
+            encoder.value(&input.outpoint).bytes(&input.public_key).value(&input.relative_lock);
         }
-        
-        hasher.update(&self.timestamp.to_le_bytes());
-        hasher.finalize().to_vec()
+
+        encoder.list(&self.outputs).u64(self.timestamp).u64(self.lock_time).u64(self.explicit_fee);
+        encoder.hash().to_vec()
     }
 
     /// Get total input amount (for validation)
@@ -163,17 +316,257 @@ impl UTXOTransaction {
         Some(total)
     }
 
-    /// Get total output amount
-    pub fn total_output_amount(&self) -> u64 {
-        self.outputs.iter().map(|output| output.amount).sum()
+    /// Get total output amount, None on overflow
+    pub fn total_output_amount(&self) -> Option<u64> {
+        self.outputs.iter().try_fold(0u64, |total, output| total.checked_add(output.amount))
     }
 
     /// Calculate transaction fee (input_amount - output_amount)
     pub fn fee(&self, utxo_set: &UTXOSet) -> Option<u64> {
         let input_amount = self.total_input_amount(utxo_set)?;
-        let output_amount = self.total_output_amount();
+        let output_amount = self.total_output_amount()?;
         input_amount.checked_sub(output_amount)
     }
+
+    /// Check the consensus rules that do not depend on the UTXO set: at least one input and one output, a canonical
+    /// encoding of at most `MAX_TRANSACTION_SIZE` bytes, no outpoint spent twice, no plain output below `DUST_THRESHOLD`,
+    /// well formed confidential outputs (no plain amount, a valid commitment and a range proof of the right size, not paying
+    /// an account), well formed recipients and an output total that does not overflow
+    pub fn check_structure(&self) -> Result<(), TxRejection> {
+        if self.inputs.is_empty() { return Err(TxRejection::NoInputs); }
+        if self.outputs.is_empty() { return Err(TxRejection::NoOutputs); }
+
+        let size: usize = encoding::canonical_bytes(TRANSACTION_ID_TAG, self).len();
+        if size > MAX_TRANSACTION_SIZE { return Err(TxRejection::Oversized { size }); }
+
+        let mut spent: HashSet<&OutPoint> = HashSet::new();
+        if let Some(input) = self.inputs.iter().find(|input| !spent.insert(&input.outpoint)) {
+            return Err(TxRejection::DuplicateInput(input.outpoint.clone()));
+        }
+
+        for (index, output) in self.outputs.iter().enumerate() {
+            match &output.confidential {
+                Some(confidential) => {
+                    let invalid = |reason: &str| TxRejection::InvalidConfidentialOutput { index, reason: reason.to_string() };
+                    if output.amount != 0 { return Err(invalid("Confidential outputs have no plain amount")); }
+                    if bridge::account_of_recipient(&output.recipient).is_some() { return Err(invalid("Confidential outputs cannot pay an account")); }
+                    confidential.check_format().map_err(|reason| invalid(&reason))?;
+                }
+                None => {
+                    if output.amount == 0 { return Err(TxRejection::ZeroOutput { index }); }
+                    if output.amount < DUST_THRESHOLD { return Err(TxRejection::DustOutput { index, amount: output.amount }); }
+                }
+            }
+            address::validate_recipient_bytes(&output.recipient)
+                .map_err(|reason| TxRejection::MalformedRecipient { index, reason })?;
+        }
+
+        self.total_output_amount().ok_or(TxRejection::OutputOverflow)?;
+        Ok(())
+    }
+
+    /// Check every consensus rule against the UTXO set (see `check_structure`): additionally, every input must exist
+    /// and the inputs must add up, without overflowing, to at least the outputs. Returns the fee.
+    ///
+    /// Transactions with a confidential input or output instead pay an explicit fee, and the commitments of their inputs
+    /// must equal those of their outputs plus the fee (see confidential.rs). Their range proofs are left to the verifier.
+    pub fn check_consensus_rules(&self, utxo_set: &UTXOSet) -> Result<u64, TxRejection> {
+        self.check_structure()?;
+
+        if let Some(input) = self.inputs.iter().find(|input| !utxo_set.contains(&input.outpoint)) {
+            return Err(TxRejection::MissingInput(input.outpoint.clone()));
+        }
+
+        if self.is_confidential(utxo_set) {
+            let inputs: Option<Vec<RistrettoPoint>> = self.inputs.iter()
+                .map(|input| utxo_set.get_utxo(&input.outpoint).and_then(UTXO::commitment_point))
+                .collect();
+            let outputs: Option<Vec<RistrettoPoint>> = self.outputs.iter().map(TxOutput::commitment_point).collect();
+            return match inputs.zip(outputs) {
+                Some((inputs, outputs)) if confidential::commitments_balance(&inputs, &outputs, self.explicit_fee) => Ok(self.explicit_fee),
+                _ => Err(TxRejection::UnbalancedCommitments),
+            };
+        }
+        if self.explicit_fee != 0 { return Err(TxRejection::UnexpectedExplicitFee { fee: self.explicit_fee }); }
+
+        // every input exists, so the input amount is only missing on overflow
+        let input_amount = self.total_input_amount(utxo_set).ok_or(TxRejection::InputOverflow)?;
+        let output_amount = self.total_output_amount().ok_or(TxRejection::OutputOverflow)?;
+        self.fee(utxo_set).ok_or(TxRejection::InsufficientInputs { input_amount, output_amount })
+    }
+
+    /// Whether any output of the transaction, or any UTXO it spends, is confidential
+    pub fn is_confidential(&self, utxo_set: &UTXOSet) -> bool {
+        self.outputs.iter().any(|output| output.confidential.is_some())
+            || self.inputs.iter().any(|input| utxo_set.get_utxo(&input.outpoint).is_some_and(|utxo| utxo.commitment.is_some()))
+    }
+
+    /// Compute the digest signed by the input at `input_index` under the given sighash flags.
+    ///
+    /// The digest is tagged with the UtxoInput signature domain and the chain id, and always commits to the flags, the
+    /// input index, the signed input, the timestamp, the lock times and the explicit fee. The other
+    /// inputs are left out with SIGHASH_ANYONECANPAY, and the outputs covered are all of them (ALL), none (NONE)
+    /// or only the one at `input_index` (SINGLE).
+    pub fn sighash(&self, input_index: usize, sighash_type: u8) -> Result<[u8; 32], String> {
+        let input = self.inputs.get(input_index)
+            .ok_or_else(|| format!("Input {} does not exist", input_index))?;
+
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+        let base_type = sighash_type & !SIGHASH_ANYONECANPAY;
+
+        let mut message = signed_message(SignatureDomain::UtxoInput);
+        message.u8(sighash_type).u32(input_index as u32);
+
+        // Encode the signed inputs without their signatures
+        let signed_inputs: &[TxInput] = if anyone_can_pay { std::slice::from_ref(input) } else { &self.inputs };
+        message.u64(signed_inputs.len() as u64);
+        for signed_input in signed_inputs {
+            message.value(&signed_input.outpoint).bytes(&signed_input.public_key).value(&signed_input.relative_lock);
+        }
+
+        // Hash the outputs selected by the base type
+        let signed_outputs: &[TxOutput] = match base_type {
+            SIGHASH_ALL => &self.outputs,
+            SIGHASH_NONE => &[],
+            SIGHASH_SINGLE => match self.outputs.get(input_index) {
+                Some(output) => std::slice::from_ref(output),
+                None => return Err(format!("SIGHASH_SINGLE input {} has no matching output", input_index)),
+            },
+            _ => return Err(format!("Invalid sighash type: {:#04x}", sighash_type)),
+        };
+        message.list(signed_outputs).u64(self.timestamp).u64(self.lock_time).u64(self.explicit_fee);
+        Ok(message.hash())
+    }
+
+    /// Sign the input at `input_index` with the secret key of the UTXO it spends.
+    ///
+    /// The signature is stored on the input as the hex encoded compact signature followed by the sighash flag byte.
+    pub fn sign_input(&mut self, input_index: usize, secret_key: &SecretKey, sighash_type: u8) -> Result<(), String> {
+        let signature = self.input_signature(input_index, secret_key, sighash_type)?;
+        self.inputs[input_index].signature = hex::encode(signature);
+        Ok(())
+    }
+
+    /// Create the signature of one key for the input at `input_index`: the compact signature over the sighash followed
+    /// by the sighash flag byte. The key must be the public key of the input, one of the keys of its multisig lock or a key
+    /// pushed by its locking script.
+    pub fn input_signature(&self, input_index: usize, secret_key: &SecretKey, sighash_type: u8) -> Result<Vec<u8>, String> {
+        let secp = secp_context();
+        let input = self.inputs.get(input_index)
+            .ok_or_else(|| format!("Input {} does not exist", input_index))?;
+
+        // Only the owner of the input's public key (or of a key of its lock) can produce a valid signature
+        let public_key = PublicKey::from_secret_key(secp, secret_key);
+        let can_sign = match (MultisigLock::from_bytes(&input.public_key), Script::from_recipient(&input.public_key)) {
+            (Ok(lock), _) => lock.key_index(&public_key).is_some(),
+            (_, Ok(locking)) => locking.pushes(&public_key.serialize()),
+            _ => input.public_key == public_key.serialize(),
+        };
+        if !can_sign {
+            return Err(format!("Secret key does not belong to input {}", input_index));
+        }
+
+        let message = Message::from_slice(&self.sighash(input_index, sighash_type)?)
+            .map_err(|e| format!("Failed to create message: {}", e))?;
+
+        let mut signature = secp.sign(&message, secret_key).serialize_compact().to_vec();
+        signature.push(sighash_type);
+        Ok(signature)
+    }
+
+    /// Verify the input at `input_index` by running its unlocking script against the locking script of the output it
+    /// spends (see script.rs). Inputs spending a public key or a multisig lock hold their signatures, each checked
+    /// against the sighash selected by the flag byte at its end; inputs spending a script hold the unlocking script.
+    pub fn verify_input(&self, input_index: usize) -> Result<(), String> {
+        let input = self.inputs.get(input_index)
+            .ok_or_else(|| format!("Input {} does not exist", input_index))?;
+        let signature_bytes = hex::decode(&input.signature)
+            .map_err(|e| format!("Failed to decode signature: {}", e))?;
+
+        let locking = Script::locking_script(&input.public_key)?;
+        let unlocking = Script::unlocking_script(&input.public_key, &signature_bytes)?;
+        script::verify_script(&unlocking, &locking, &InputChecker { transaction: self, input_index })
+    }
+
+    /// Verify one signature of the input at `input_index` (a compact signature followed by the sighash flag byte)
+    fn verify_signature(&self, input_index: usize, signature_bytes: &[u8], public_key: &PublicKey) -> Result<(), String> {
+
+        // Split the signature into the compact signature and the sighash flags
+        let (sighash_type, compact) = match signature_bytes.split_last() {
+            Some((sighash_type, compact)) => (*sighash_type, compact),
+            None => return Err("Missing signature".to_string()),
+        };
+        let signature = Signature::from_compact(compact)
+            .map_err(|e| format!("Invalid signature: {}", e))?;
+
+        let message = Message::from_slice(&self.sighash(input_index, sighash_type)?)
+            .map_err(|e| format!("Failed to create message: {}", e))?;
+
+        secp_context().verify(&message, &signature, public_key)
+            .map_err(|_| "Signature does not match the transaction".to_string())
+    }
+}
+
+/// The consensus rule a UTXO transaction breaks, the reason validators reject it for
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxRejection {
+    NoInputs,
+    NoOutputs,
+    Oversized { size: usize },
+    DuplicateInput(OutPoint),
+    ZeroOutput { index: usize },
+    DustOutput { index: usize, amount: u64 },
+    MalformedRecipient { index: usize, reason: String },
+    OutputOverflow,
+    MissingInput(OutPoint),
+    InputOverflow,
+    InsufficientInputs { input_amount: u64, output_amount: u64 },
+    InvalidConfidentialOutput { index: usize, reason: String },
+    UnbalancedCommitments,
+    UnexpectedExplicitFee { fee: u64 },
+}
+
+impl fmt::Display for TxRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TxRejection::NoInputs => write!(f, "Transaction has no inputs"),
+            TxRejection::NoOutputs => write!(f, "Transaction has no outputs"),
+            TxRejection::Oversized { size } => write!(f, "Transaction is {} bytes, the maximum is {}", size, MAX_TRANSACTION_SIZE),
+            TxRejection::DuplicateInput(outpoint) => write!(f, "Input spent twice: {}:{}", hex::encode(&outpoint.txid), outpoint.vout),
+            TxRejection::ZeroOutput { index } => write!(f, "Output {} has no amount", index),
+            TxRejection::DustOutput { index, amount } => write!(f, "Output {} of {} is below the dust threshold of {}", index, amount, DUST_THRESHOLD),
+            TxRejection::MalformedRecipient { index, reason } => write!(f, "Output {}: {}", index, reason),
+            TxRejection::OutputOverflow => write!(f, "Transaction output amount overflows"),
+            TxRejection::MissingInput(outpoint) => write!(f, "Referenced UTXO does not exist: {}:{}", hex::encode(&outpoint.txid), outpoint.vout),
+            TxRejection::InputOverflow => write!(f, "Transaction input amount overflows"),
+            TxRejection::InsufficientInputs { input_amount, output_amount } => {
+                write!(f, "Transaction outputs exceed inputs: {} < {}", input_amount, output_amount)
+            }
+            TxRejection::InvalidConfidentialOutput { index, reason } => write!(f, "Confidential output {}: {}", index, reason),
+            TxRejection::UnbalancedCommitments => write!(f, "Input commitments do not equal output commitments plus the fee"),
+            TxRejection::UnexpectedExplicitFee { fee } => write!(f, "Transaction without confidential amounts pays an explicit fee of {}", fee),
+        }
+    }
+}
+
+/// Gives the script interpreter access to the input being verified
+struct InputChecker<'a> {
+    transaction: &'a UTXOTransaction,
+    input_index: usize,
+}
+
+impl SignatureChecker for InputChecker<'_> {
+    fn check_signature(&self, signature: &[u8], public_key: &[u8]) -> bool {
+        PublicKey::from_slice(public_key)
+            .map(|public_key| self.transaction.verify_signature(self.input_index, signature, &public_key).is_ok())
+            .unwrap_or(false)
+    }
+
+    // A script lock time is met by transactions locked at least as late, in the same unit (block height or time)
+    fn check_lock_time(&self, lock_time: u64) -> bool {
+        let transaction_lock_time: u64 = self.transaction.lock_time;
+        (lock_time < LOCK_TIME_THRESHOLD) == (transaction_lock_time < LOCK_TIME_THRESHOLD) && lock_time <= transaction_lock_time
+    }
 }
 
 /// Special transaction type for coinbase (creating new coins)
@@ -202,19 +595,99 @@ impl CoinbaseTransaction {
     }
 
     pub fn compute_hash(&self) -> Vec<u8> {
-        let mut hasher = Sha256::new();
-        
-        for output in &self.outputs {
-            hasher.update(&output.amount.to_le_bytes());
-            hasher.update(&output.recipient);
+        let mut encoder = Encoder::top_level(COINBASE_ID_TAG);
+        encoder.list(&self.outputs).u64(self.block_height).u64(self.timestamp);
+        encoder.hash().to_vec()
+    }
+
+    /// Get total amount created by this coinbase, None on overflow
+    pub fn total_output_amount(&self) -> Option<u64> {
+        self.outputs.iter().try_fold(0u64, |total, output| total.checked_add(output.amount))
+    }
+
+    /// Check that the coinbase pays out no more than the block's fees plus the allowed subsidy, in plain amounts
+    pub fn verify_amount(&self, fees: u64) -> Result<(), String> {
+        if self.outputs.iter().any(|output| output.confidential.is_some()) {
+            return Err("Coinbase outputs cannot be confidential".to_string());
         }
-        
-        hasher.update(&self.block_height.to_le_bytes());
-        hasher.update(&self.timestamp.to_le_bytes());
-        hasher.finalize().to_vec()
+        let paid = self.total_output_amount()
+            .ok_or_else(|| "Coinbase output amount overflows".to_string())?;
+        let allowed = fees.checked_add(block_subsidy(self.block_height))
+            .ok_or_else(|| "Coinbase allowance overflows".to_string())?;
+
+        if paid > allowed {
+            return Err(format!("Coinbase pays {} but only {} is allowed", paid, allowed));
+        }
+        Ok(())
+    }
+}
+
+// Canonical encodings (see encoding.rs). Transactions are encoded in full, signatures included; the txid is computed
+// over the unsigned fields only (see UTXOTransaction::compute_hash()).
+
+impl Encode for OutPoint {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.bytes(&self.txid).u32(self.vout);
+    }
+}
+
+impl Encode for UTXO {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.u64(self.amount).bytes(&self.recipient).u64(self.block_height).u64(self.timestamp).value(&self.commitment);
+    }
+}
+
+impl Encode for TxInput {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.value(&self.outpoint).str(&self.signature).bytes(&self.public_key).value(&self.relative_lock);
+    }
+}
+
+impl Encode for RelativeLock {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            RelativeLock::Blocks(blocks) => encoder.u8(0).u64(*blocks),
+            RelativeLock::Seconds(seconds) => encoder.u8(1).u64(*seconds),
+        };
+    }
+}
+
+impl Encode for TxOutput {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.u64(self.amount).bytes(&self.recipient).value(&self.confidential);
     }
 }
 
+impl Encode for UTXOTransaction {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.list(&self.inputs).list(&self.outputs).u64(self.timestamp).u64(self.lock_time).u64(self.explicit_fee);
+    }
+}
+
+impl Encode for CoinbaseTransaction {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.list(&self.outputs).u64(self.block_height).u64(self.timestamp);
+    }
+}
+
+/// New tokens issued to validators at a given block height.
+///
+/// Follows `subsidy_schedule()` starting from `INITIAL_BLOCK_SUBSIDY`.
+pub fn block_subsidy(block_height: u64) -> u64 {
+    subsidy_schedule(INITIAL_BLOCK_SUBSIDY, block_height)
+}
+
+/// Subsidy at a given block height of a schedule starting at `initial_subsidy`.
+///
+/// The subsidy halves every `SUBSIDY_HALVING_INTERVAL` blocks until it reaches zero.
+pub fn subsidy_schedule(initial_subsidy: u64, block_height: u64) -> u64 {
+    let halvings = block_height / SUBSIDY_HALVING_INTERVAL;
+    if halvings >= 64 {
+        return 0;
+    }
+    initial_subsidy >> halvings
+}
+
 /// The UTXO set - tracks all unspent transaction outputs
 /// 
 /// This is the core data structure that maintains the current state of all
@@ -226,20 +699,26 @@ impl CoinbaseTransaction {
 /// 
 /// Performance optimizations:
 /// - BTreeMap for primary storage: Better cache locality than HashMap
-/// - Recipient index: O(1) balance calculation instead of O(n) scan
+/// - Recipient index: O(1) balance calculation instead of O(n) scan, with stealth
+///   outputs indexed under the hash of their one-time key (see address::index_key())
 /// - Cached count: O(1) size queries
+/// - Commitment: a multiset hash of all UTXOs, updated as they are added and
+///   removed (see utxo_commitment.rs)
 /// 
-/// The recipient_index is marked with #[serde(skip)] and rebuilt after
-/// deserialization to avoid binary key serialization issues.
+/// The recipient_index and commitment are marked with #[serde(skip)] and rebuilt
+/// after deserialization to avoid binary key serialization issues.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UTXOSet {
     /// Primary storage: BTreeMap for better cache locality and ordered iteration
     utxos: BTreeMap<OutPoint, UTXO>,
-    /// Index by recipient for fast balance lookups
+    /// Index by recipient (see address::index_key()) for fast balance lookups
     #[serde(skip)]
     recipient_index: HashMap<Vec<u8>, Vec<OutPoint>>,
     /// Track total number of UTXOs for quick size queries
     count: usize,
+    /// Running commitment to every UTXO in the set
+    #[serde(skip)]
+    commitment: UtxoCommitment,
 }
 
 impl UTXOSet {
@@ -248,18 +727,22 @@ impl UTXOSet {
             utxos: BTreeMap::new(),
             recipient_index: HashMap::new(),
             count: 0,
+            commitment: UtxoCommitment::default(),
         }
     }
 
     /// Add a new UTXO to the set with optimized indexing
     pub fn add_utxo(&mut self, outpoint: OutPoint, utxo: UTXO) {
-        // Add to primary storage
+        // Add to primary storage and the commitment
+        self.commitment.insert(&outpoint, &utxo);
         if let Some(old_utxo) = self.utxos.insert(outpoint.clone(), utxo.clone()) {
-            // Remove old entry from recipient index if it was a replacement
-            if let Some(outpoints) = self.recipient_index.get_mut(&old_utxo.recipient) {
+            // Remove old entry from recipient index and commitment if it was a replacement
+            self.commitment.remove(&outpoint, &old_utxo);
+            let old_key: &[u8] = address::index_key(&old_utxo.recipient);
+            if let Some(outpoints) = self.recipient_index.get_mut(old_key) {
                 outpoints.retain(|op| op != &outpoint);
                 if outpoints.is_empty() {
-                    self.recipient_index.remove(&old_utxo.recipient);
+                    self.recipient_index.remove(old_key);
                 }
             }
         } else {
@@ -268,7 +751,7 @@ impl UTXOSet {
         
         // Update recipient index
         self.recipient_index
-            .entry(utxo.recipient.clone())
+            .entry(address::index_key(&utxo.recipient).to_vec())
             .or_insert_with(Vec::new)
             .push(outpoint);
     }
@@ -277,12 +760,14 @@ impl UTXOSet {
     pub fn remove_utxo(&mut self, outpoint: &OutPoint) -> Option<UTXO> {
         if let Some(utxo) = self.utxos.remove(outpoint) {
             self.count -= 1;
+            self.commitment.remove(outpoint, &utxo);
             
             // Clean up recipient index
-            if let Some(outpoints) = self.recipient_index.get_mut(&utxo.recipient) {
+            let key: &[u8] = address::index_key(&utxo.recipient);
+            if let Some(outpoints) = self.recipient_index.get_mut(key) {
                 outpoints.retain(|op| op != outpoint);
                 if outpoints.is_empty() {
-                    self.recipient_index.remove(&utxo.recipient);
+                    self.recipient_index.remove(key);
                 }
             }
             
@@ -316,6 +801,13 @@ impl UTXOSet {
         }
     }
 
+    /// Get all UTXOs a public key can spend: those paid to the key itself and those paid to its address
+    pub fn get_utxos_for_public_key(&self, public_key: &[u8]) -> Vec<(OutPoint, &UTXO)> {
+        let mut utxos = self.get_utxos_for_recipient(public_key);
+        utxos.extend(self.get_utxos_for_recipient(&address::hash160(public_key)));
+        utxos
+    }
+
     /// Calculate total balance for a recipient using optimized index
     pub fn get_balance(&self, recipient: &[u8]) -> u64 {
         if let Some(outpoints) = self.recipient_index.get(recipient) {
@@ -329,8 +821,9 @@ impl UTXOSet {
         }
     }
 
-    /// Apply a transaction to the UTXO set
-    pub fn apply_transaction(&mut self, tx: &UTXOTransaction, block_height: u64) -> Result<(), String> {
+    /// Apply a transaction to the UTXO set, outputs paid to an account recipient are credited to the account instead
+    /// (see bridge.rs) and create no UTXO. Returns the undo record of the changes, spent UTXOs included
+    pub fn apply_transaction(&mut self, tx: &UTXOTransaction, block_height: u64) -> Result<UtxoUndo, String> {
         // First verify all inputs exist
         for input in &tx.inputs {
             if !self.contains(&input.outpoint) {
@@ -339,27 +832,47 @@ impl UTXOSet {
         }
 
         // Remove spent UTXOs
+        let mut undo = UtxoUndo::default();
         for input in &tx.inputs {
+            undo.record(self, &input.outpoint);
             self.remove_utxo(&input.outpoint);
         }
 
         // Add new UTXOs
         for (index, output) in tx.outputs.iter().enumerate() {
+            if bridge::account_of_recipient(&output.recipient).is_some() { continue; }
             let outpoint = OutPoint::new(tx.hash.clone(), index as u32);
-            let utxo = UTXO::new(output.amount, output.recipient.clone(), block_height, tx.timestamp);
+            let utxo = UTXO {
+                commitment: output.confidential.as_ref().map(|confidential| confidential.commitment.clone()),
+                ..UTXO::new(output.amount, output.recipient.clone(), block_height, tx.timestamp)
+            };
+            undo.record(self, &outpoint);
             self.add_utxo(outpoint, utxo);
         }
 
-        Ok(())
+        Ok(undo)
     }
 
-    /// Apply a coinbase transaction to the UTXO set
-    pub fn apply_coinbase(&mut self, tx: &CoinbaseTransaction) {
+    /// Apply a coinbase transaction to the UTXO set, returning the undo record of the changes
+    pub fn apply_coinbase(&mut self, tx: &CoinbaseTransaction) -> UtxoUndo {
+        let mut undo = UtxoUndo::default();
         for (index, output) in tx.outputs.iter().enumerate() {
             let outpoint = OutPoint::new(tx.hash.clone(), index as u32);
             let utxo = UTXO::new(output.amount, output.recipient.clone(), tx.block_height, tx.timestamp);
+            undo.record(self, &outpoint);
             self.add_utxo(outpoint, utxo);
         }
+        undo
+    }
+
+    /// Undo changes to the UTXO set, restoring every recorded outpoint to its prior state in reverse order
+    pub fn undo(&mut self, undo: &UtxoUndo) {
+        for (outpoint, prior) in undo.changes.iter().rev() {
+            match prior {
+                Some(utxo) => { self.add_utxo(outpoint.clone(), utxo.clone()); }
+                None => { self.remove_utxo(outpoint); }
+            }
+        }
     }
 
     /// Get the total number of UTXOs using cached count
@@ -372,14 +885,21 @@ impl UTXOSet {
         self.count == 0
     }
 
-    /// Rebuild recipient index (for use after deserialization)
+    /// Digest of the commitment to the UTXO set, equal on every node holding the same UTXOs
+    pub fn commitment(&self) -> Vec<u8> {
+        self.commitment.digest()
+    }
+
+    /// Rebuild recipient index and commitment (for use after deserialization)
     pub fn rebuild_index(&mut self) {
         self.recipient_index.clear();
+        self.commitment = UtxoCommitment::default();
         self.count = self.utxos.len();
         
         for (outpoint, utxo) in &self.utxos {
+            self.commitment.insert(outpoint, utxo);
             self.recipient_index
-                .entry(utxo.recipient.clone())
+                .entry(address::index_key(&utxo.recipient).to_vec())
                 .or_insert_with(Vec::new)
                 .push(outpoint.clone());
         }
@@ -392,6 +912,35 @@ impl Default for UTXOSet {
     }
 }
 
+/// Two UTXO sets are equal when they hold the same UTXOs, the index and commitment are derived from them
+impl PartialEq for UTXOSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.utxos == other.utxos
+    }
+}
+
+/// The undo record of changes to the UTXO set
+/// 
+/// Holds every outpoint that was changed along with the UTXO it held before the
+/// change (None if it held none), in the order of the changes. Spent UTXOs are
+/// kept in full, so UTXOSet::undo() restores the set exactly.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UtxoUndo {
+    changes: Vec<(OutPoint, Option<UTXO>)>,
+}
+
+impl UtxoUndo {
+    /// Record the state of an outpoint before it is changed
+    pub fn record(&mut self, utxo_set: &UTXOSet, outpoint: &OutPoint) {
+        self.changes.push((outpoint.clone(), utxo_set.get_utxo(outpoint).cloned()));
+    }
+
+    /// Append the changes made after the ones already recorded
+    pub fn extend(&mut self, later: UtxoUndo) {
+        self.changes.extend(later.changes);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -405,6 +954,22 @@ mod tests {
         assert_eq!(utxo.timestamp, 12345);
     }
 
+    #[test]
+    fn test_outputs_paid_to_an_address() {
+        let secret_key = SecretKey::from_slice(&[3u8; 32]).unwrap();
+        let public_key = PublicKey::from_secret_key(secp_context(), &secret_key).serialize().to_vec();
+        let output = TxOutput::pay_to(100, &address::encode_address(&address::hash160(&public_key))).unwrap();
+        assert_eq!(output.recipient, address::hash160(&public_key).to_vec());
+        assert!(TxOutput::pay_to(100, "mbc1notanaddress").is_err());
+
+        // the public key finds outputs paid to it directly and to its address
+        let mut utxo_set = UTXOSet::new();
+        utxo_set.add_utxo(OutPoint::new(vec![1], 0), UTXO::new(100, output.recipient.clone(), 1, 12345));
+        utxo_set.add_utxo(OutPoint::new(vec![2], 0), UTXO::new(50, public_key.clone(), 1, 12345));
+        assert_eq!(utxo_set.get_utxos_for_public_key(&public_key).len(), 2);
+        assert!(address::recipient_matches(&output.recipient, &public_key));
+    }
+
     #[test]
     fn test_outpoint() {
         let outpoint = OutPoint::new(vec![1, 2, 3], 0);
@@ -457,7 +1022,302 @@ mod tests {
         let tx = UTXOTransaction::new(vec![input], vec![output], 12345);
 
         assert_eq!(tx.total_input_amount(&utxo_set), Some(100));
-        assert_eq!(tx.total_output_amount(), 90);
+        assert_eq!(tx.total_output_amount(), Some(90));
         assert_eq!(tx.fee(&utxo_set), Some(10));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_coinbase_amount_limited_to_fees_and_subsidy() {
+        let allowed = 10 + block_subsidy(5);
+        let coinbase = CoinbaseTransaction::new(vec![TxOutput::new(allowed, vec![1, 2, 3])], 5, 12345);
+        assert!(coinbase.verify_amount(10).is_ok());
+
+        let coinbase = CoinbaseTransaction::new(vec![TxOutput::new(allowed + 1, vec![1, 2, 3])], 5, 12345);
+        assert!(coinbase.verify_amount(10).is_err());
+
+        let coinbase = CoinbaseTransaction::new(
+            vec![TxOutput::new(u64::MAX, vec![1, 2, 3]), TxOutput::new(1, vec![4, 5, 6])], 5, 12345
+        );
+        assert!(coinbase.verify_amount(10).is_err());
+    }
+
+    #[test]
+    fn test_time_locks() {
+        let mut utxo_set = UTXOSet::new();
+        let outpoint = OutPoint::new(vec![1; 32], 0);
+        utxo_set.add_utxo(outpoint.clone(), UTXO::new(100, vec![1; 20], 10, 1_000));
+        let spend = |relative_lock: RelativeLock, lock_time: u64| UTXOTransaction::new(
+            vec![TxInput::new(outpoint.clone(), String::new(), vec![2; 33]).with_relative_lock(relative_lock)],
+            vec![TxOutput::new(90, vec![3; 20])],
+            12345,
+        ).with_lock_time(lock_time);
+
+        // relative locks count from the block and time the UTXO was created
+        assert!(spend(RelativeLock::Blocks(5), 0).check_time_locks(&utxo_set, 14, 0).is_err());
+        assert!(spend(RelativeLock::Blocks(5), 0).check_time_locks(&utxo_set, 15, 0).is_ok());
+        assert!(spend(RelativeLock::Seconds(60), 0).check_time_locks(&utxo_set, 15, 1_059).is_err());
+        assert!(spend(RelativeLock::Seconds(60), 0).check_time_locks(&utxo_set, 15, 1_060).is_ok());
+
+        // lock times below LOCK_TIME_THRESHOLD are block heights, others are unix times
+        assert!(spend(RelativeLock::Blocks(0), 20).check_time_locks(&utxo_set, 19, u64::MAX).is_err());
+        assert!(spend(RelativeLock::Blocks(0), 20).check_time_locks(&utxo_set, 20, 0).is_ok());
+        assert!(spend(RelativeLock::Blocks(0), LOCK_TIME_THRESHOLD + 1).check_time_locks(&utxo_set, u64::MAX, LOCK_TIME_THRESHOLD).is_err());
+        assert!(spend(RelativeLock::Blocks(0), LOCK_TIME_THRESHOLD + 1).check_time_locks(&utxo_set, 10, LOCK_TIME_THRESHOLD + 1).is_ok());
+
+        // the locks are part of the transaction id
+        assert_ne!(spend(RelativeLock::Blocks(5), 0).hash, spend(RelativeLock::Blocks(6), 0).hash);
+        assert_ne!(spend(RelativeLock::Blocks(5), 0).hash, spend(RelativeLock::Blocks(5), 1).hash);
+    }
+
+    #[test]
+    fn test_creation_timestamp() {
+        let time: u64 = LOCK_TIME_THRESHOLD + 10_000;
+
+        // a timestamp must be a unix time within MAX_TIMESTAMP_DRIFT of the clock, before or after it
+        assert!(check_creation_timestamp(time, time).is_ok());
+        assert!(check_creation_timestamp(time - MAX_TIMESTAMP_DRIFT, time).is_ok());
+        assert!(check_creation_timestamp(time + MAX_TIMESTAMP_DRIFT, time).is_ok());
+        assert!(check_creation_timestamp(time - MAX_TIMESTAMP_DRIFT - 1, time).is_err());
+        assert!(check_creation_timestamp(time + MAX_TIMESTAMP_DRIFT + 1, time).is_err());
+        assert!(check_creation_timestamp(LOCK_TIME_THRESHOLD - 1, LOCK_TIME_THRESHOLD - 1).is_err());
+    }
+
+    #[test]
+    fn test_block_subsidy_halving() {
+        // the subsidy halves at each interval and is constant within one
+        assert_eq!(subsidy_schedule(5_000, 0), 5_000);
+        assert_eq!(subsidy_schedule(5_000, SUBSIDY_HALVING_INTERVAL - 1), 5_000);
+        assert_eq!(subsidy_schedule(5_000, SUBSIDY_HALVING_INTERVAL), 2_500);
+        assert_eq!(subsidy_schedule(5_000, SUBSIDY_HALVING_INTERVAL * 2), 1_250);
+        assert_eq!(subsidy_schedule(5_000, SUBSIDY_HALVING_INTERVAL * 13), 0);
+        assert_eq!(subsidy_schedule(u64::MAX, SUBSIDY_HALVING_INTERVAL * 63), 1);
+        assert_eq!(subsidy_schedule(u64::MAX, SUBSIDY_HALVING_INTERVAL * 64), 0);
+
+        // the shipped schedule starts at INITIAL_BLOCK_SUBSIDY
+        assert_eq!(block_subsidy(0), INITIAL_BLOCK_SUBSIDY);
+        assert_eq!(block_subsidy(SUBSIDY_HALVING_INTERVAL), subsidy_schedule(INITIAL_BLOCK_SUBSIDY, SUBSIDY_HALVING_INTERVAL));
+    }
+
+    // Builds a transaction spending one UTXO of each key to two outputs
+    fn two_input_transaction(alice: &SecretKey, bob: &SecretKey) -> UTXOTransaction {
+        let input = |key: &SecretKey, vout: u32| {
+            TxInput::new(OutPoint::new(vec![1; 32], vout), String::new(), PublicKey::from_secret_key(secp_context(), key).serialize().to_vec())
+        };
+        UTXOTransaction::new(
+            vec![input(alice, 0), input(bob, 1)],
+            vec![TxOutput::new(60, vec![7, 8, 9]), TxOutput::new(30, vec![4, 5, 6])],
+            12345,
+        )
+    }
+
+    #[test]
+    fn test_sign_input_commits_to_outputs() {
+        let alice = SecretKey::from_slice(&[1; 32]).unwrap();
+        let bob = SecretKey::from_slice(&[2; 32]).unwrap();
+        let mut tx = two_input_transaction(&alice, &bob);
+
+        tx.sign_input(0, &alice, SIGHASH_ALL).unwrap();
+        tx.sign_input(1, &bob, SIGHASH_ALL).unwrap();
+        assert!(tx.verify_input(0).is_ok());
+        assert!(tx.verify_input(1).is_ok());
+
+        // a key can only sign its own input
+        assert!(tx.clone().sign_input(1, &alice, SIGHASH_ALL).is_err());
+
+        // redirecting an output invalidates the signatures
+        let mut redirected = tx.clone();
+        redirected.outputs[0].recipient = vec![6, 6, 6];
+        assert!(redirected.verify_input(0).is_err());
+        assert!(redirected.verify_input(1).is_err());
+
+        // so does changing the flag byte of the signature
+        let mut reflagged = tx.clone();
+        let signature = &reflagged.inputs[0].signature;
+        reflagged.inputs[0].signature = format!("{}{:02x}", &signature[..signature.len() - 2], SIGHASH_NONE);
+        assert!(reflagged.verify_input(0).is_err());
+    }
+
+    #[test]
+    fn test_sighash_single_and_anyonecanpay() {
+        let alice = SecretKey::from_slice(&[1; 32]).unwrap();
+        let bob = SecretKey::from_slice(&[2; 32]).unwrap();
+        let mut tx = two_input_transaction(&alice, &bob);
+
+        // SINGLE only covers the output with the same index as the input
+        tx.sign_input(0, &alice, SIGHASH_SINGLE).unwrap();
+        tx.outputs[1].amount = 10;
+        assert!(tx.verify_input(0).is_ok());
+        tx.outputs[0].amount = 10;
+        assert!(tx.verify_input(0).is_err());
+
+        // ANYONECANPAY leaves out the other inputs
+        let mut tx = two_input_transaction(&alice, &bob);
+        tx.sign_input(0, &alice, SIGHASH_ALL | SIGHASH_ANYONECANPAY).unwrap();
+        tx.inputs.truncate(1);
+        assert!(tx.verify_input(0).is_ok());
+
+        // unknown flags and SINGLE without a matching output are rejected
+        assert!(tx.sighash(0, 0x04).is_err());
+        tx.outputs.clear();
+        assert!(tx.sighash(0, SIGHASH_SINGLE).is_err());
+    }
+
+    // A UTXO set holding 100 at [1; 32]:0 and a transaction spending it to one output of 90
+    fn rule_test_transaction() -> (UTXOSet, UTXOTransaction) {
+        let mut utxo_set = UTXOSet::new();
+        let outpoint = OutPoint::new(vec![1; 32], 0);
+        utxo_set.add_utxo(outpoint.clone(), UTXO::new(100, vec![4; 20], 1, 12345));
+        let input = TxInput::new(outpoint, "signature".to_string(), vec![4; 33]);
+        (utxo_set, UTXOTransaction::new(vec![input], vec![TxOutput::new(90, vec![7; 20])], 12345))
+    }
+
+    #[test]
+    fn test_rule_valid_transaction() {
+        let (utxo_set, tx) = rule_test_transaction();
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Ok(10));
+    }
+
+    #[test]
+    fn test_rule_no_inputs() {
+        let (utxo_set, mut tx) = rule_test_transaction();
+        tx.inputs.clear();
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::NoInputs));
+    }
+
+    #[test]
+    fn test_rule_no_outputs() {
+        let (utxo_set, mut tx) = rule_test_transaction();
+        tx.outputs.clear();
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::NoOutputs));
+    }
+
+    #[test]
+    fn test_rule_oversized() {
+        let (utxo_set, mut tx) = rule_test_transaction();
+        tx.inputs[0].signature = "00".repeat(MAX_TRANSACTION_SIZE);
+        assert!(matches!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::Oversized { size }) if size > MAX_TRANSACTION_SIZE));
+    }
+
+    #[test]
+    fn test_rule_duplicate_input() {
+        let (utxo_set, mut tx) = rule_test_transaction();
+        tx.inputs.push(tx.inputs[0].clone());
+        tx.outputs[0].amount = 190;
+        let outpoint = tx.inputs[0].outpoint.clone();
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::DuplicateInput(outpoint)));
+    }
+
+    #[test]
+    fn test_rule_zero_output() {
+        let (utxo_set, mut tx) = rule_test_transaction();
+        tx.outputs.push(TxOutput::new(0, vec![7; 20]));
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::ZeroOutput { index: 1 }));
+    }
+
+    #[test]
+    fn test_rule_dust_output() {
+        let (utxo_set, mut tx) = rule_test_transaction();
+        tx.outputs[0].amount = DUST_THRESHOLD - 1;
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::DustOutput { index: 0, amount: DUST_THRESHOLD - 1 }));
+        tx.outputs[0].amount = DUST_THRESHOLD;
+        assert!(tx.check_consensus_rules(&utxo_set).is_ok());
+    }
+
+    #[test]
+    fn test_rule_malformed_recipient() {
+        let (utxo_set, mut tx) = rule_test_transaction();
+        tx.outputs[0].recipient = vec![7, 8, 9];
+        assert!(matches!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::MalformedRecipient { index: 0, .. })));
+    }
+
+    #[test]
+    fn test_rule_output_overflow() {
+        let (utxo_set, mut tx) = rule_test_transaction();
+        tx.outputs = vec![TxOutput::new(u64::MAX, vec![7; 20]), TxOutput::new(DUST_THRESHOLD, vec![7; 20])];
+        assert_eq!(tx.total_output_amount(), None);
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::OutputOverflow));
+    }
+
+    #[test]
+    fn test_rule_missing_input() {
+        let (utxo_set, mut tx) = rule_test_transaction();
+        tx.inputs[0].outpoint = OutPoint::new(vec![2; 32], 0);
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::MissingInput(OutPoint::new(vec![2; 32], 0))));
+    }
+
+    #[test]
+    fn test_rule_input_overflow() {
+        let (mut utxo_set, mut tx) = rule_test_transaction();
+        let outpoint = OutPoint::new(vec![2; 32], 0);
+        utxo_set.add_utxo(outpoint.clone(), UTXO::new(u64::MAX, vec![4; 20], 1, 12345));
+        tx.inputs.push(TxInput::new(outpoint, "signature".to_string(), vec![4; 33]));
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::InputOverflow));
+    }
+
+    #[test]
+    fn test_rule_insufficient_inputs() {
+        let (utxo_set, mut tx) = rule_test_transaction();
+        tx.outputs[0].amount = 101;
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::InsufficientInputs { input_amount: 100, output_amount: 101 }));
+    }
+
+    // A confidential output of the amount with the blinding factor
+    fn confidential_output(amount: u64, blinding: curve25519_dalek::scalar::Scalar) -> TxOutput {
+        TxOutput::confidential(&Opening { amount, blinding }, vec![7; 20])
+    }
+
+    #[test]
+    fn test_rule_balanced_commitments() {
+        let (mut utxo_set, mut tx) = rule_test_transaction();
+        let blinding = confidential::random_blinding();
+        tx.outputs = vec![confidential_output(60, blinding), confidential_output(30, -blinding)];
+        tx.explicit_fee = 10;
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Ok(10));
+
+        // the hidden amounts are only created in commitments, and can be spent back into plain outputs
+        tx.hash = tx.compute_hash();
+        utxo_set.apply_transaction(&tx, 2).unwrap();
+        assert_eq!(utxo_set.get_balance(&[7; 20]), 0);
+        let input = TxInput::new(OutPoint::new(tx.hash.clone(), 0), "signature".to_string(), vec![7; 33]);
+        let spend = UTXOTransaction::new(vec![input], vec![TxOutput::new(55, vec![8; 20])], 12345).with_explicit_fee(5);
+        assert_eq!(spend.check_consensus_rules(&utxo_set), Err(TxRejection::UnbalancedCommitments));
+
+        let reveal = UTXOTransaction::new(spend.inputs.clone(), vec![TxOutput::new(55, vec![8; 20]), confidential_output(0, blinding)], 12345)
+            .with_explicit_fee(5);
+        assert_eq!(reveal.check_consensus_rules(&utxo_set), Ok(5));
+    }
+
+    #[test]
+    fn test_rule_unbalanced_commitments() {
+        let (utxo_set, mut tx) = rule_test_transaction();
+        let blinding = confidential::random_blinding();
+
+        // an output worth more than the inputs, or blinding factors that do not cancel out
+        tx.outputs = vec![confidential_output(61, blinding), confidential_output(30, -blinding)];
+        tx.explicit_fee = 10;
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::UnbalancedCommitments));
+        tx.outputs = vec![confidential_output(60, blinding), confidential_output(30, blinding)];
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::UnbalancedCommitments));
+    }
+
+    #[test]
+    fn test_rule_invalid_confidential_output() {
+        let (utxo_set, mut tx) = rule_test_transaction();
+        tx.outputs = vec![TxOutput { amount: 90, ..confidential_output(90, confidential::random_blinding()) }];
+        assert!(matches!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::InvalidConfidentialOutput { index: 0, .. })));
+
+        tx.outputs[0].amount = 0;
+        tx.outputs[0].confidential.as_mut().unwrap().range_proof.pop();
+        assert!(matches!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::InvalidConfidentialOutput { index: 0, .. })));
+
+        tx.outputs[0] = TxOutput { recipient: bridge::account_recipient(&PublicKey::from_secret_key(secp_context(), &SecretKey::from_slice(&[1; 32]).unwrap()).to_string()).unwrap(), ..confidential_output(90, confidential::random_blinding()) };
+        assert!(matches!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::InvalidConfidentialOutput { index: 0, .. })));
+    }
+
+    #[test]
+    fn test_rule_unexpected_explicit_fee() {
+        let (utxo_set, mut tx) = rule_test_transaction();
+        tx.explicit_fee = 10;
+        assert_eq!(tx.check_consensus_rules(&utxo_set), Err(TxRejection::UnexpectedExplicitFee { fee: 10 }));
+    }
+}
@@ -0,0 +1,150 @@
+use sha2::{Sha512, Digest};
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::traits::Identity;
+
+use crate::modules::blockchain::Block;
+use crate::modules::encoding::{self, UTXO_COMMITMENT_TAG};
+use crate::modules::utxo::{OutPoint, UTXO, UTXOSet};
+
+
+/**
+ * @notice utxo_commitment.rs contains the commitment to the UTXO set, a short digest two validators compare to check
+ * that their UTXO sets agree without exchanging them. It is a multiset hash (ECMH) on the Ristretto group: every unspent
+ * output (OutPoint, UTXO) is hashed to a point of the group and the commitment is the sum of these points
+ *
+ *   C = Σ hash_to_point(outpoint, utxo)
+ *
+ * Addition is commutative, so the commitment does not depend on the order outputs were created or spent in, and it is
+ * updated incrementally: UTXOSet::add_utxo() adds the point of the new output and UTXOSet::remove_utxo() subtracts the
+ * point of the spent one. The digest is the 32 byte compressed point. Finding a second set with the same commitment is
+ * as hard as computing discrete logs in the group.
+ *
+ * Every block that changes the UTXO set records the commitment after it was applied. Validators send the commitment they
+ * decided against along with consensus requests for requests that change the UTXO set, and a node adopting the ledger of its peers replays each chain (see replay_chain()) and only adopts
+ * one whose UTXO set matches every commitment it records.
+ */
+/**
+ * @notice UtxoCommitment is the running sum of the points of every output in a UTXO set, the identity when it is empty.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct UtxoCommitment {
+    sum: RistrettoPoint,
+}
+
+impl UtxoCommitment {
+
+    // Adds an output to the committed set
+    pub fn insert(&mut self, outpoint: &OutPoint, utxo: &UTXO) {
+        self.sum += element_point(outpoint, utxo);
+    }
+
+    // Removes an output from the committed set
+    pub fn remove(&mut self, outpoint: &OutPoint, utxo: &UTXO) {
+        self.sum -= element_point(outpoint, utxo);
+    }
+
+    // Returns the 32 byte digest of the commitment
+    pub fn digest(&self) -> Vec<u8> {
+        self.sum.compress().to_bytes().to_vec()
+    }
+}
+
+impl Default for UtxoCommitment {
+    fn default() -> Self {
+        UtxoCommitment { sum: RistrettoPoint::identity() }
+    }
+}
+
+// Hashes an output to a point of the group, over the canonical encoding of the outpoint and the UTXO
+fn element_point(outpoint: &OutPoint, utxo: &UTXO) -> RistrettoPoint {
+    let encoding: Vec<u8> = encoding::canonical_bytes(UTXO_COMMITMENT_TAG, &(outpoint.clone(), utxo.clone()));
+    let digest: [u8; 64] = Sha512::digest(&encoding).into();
+    RistrettoPoint::from_uniform_bytes(&digest)
+}
+
+/**
+ * @notice recorded_commitment() returns the UTXO set commitment recorded in a block, None for blocks that do not change
+ * the UTXO set.
+ */
+pub fn recorded_commitment(block: &Block) -> Option<&[u8]> {
+    match block {
+        Block::UTXOTransaction { utxo_commitment, .. } | Block::AccountToUtxo { utxo_commitment, .. } => Some(utxo_commitment),
+        _ => None,
+    }
+}
+
+/**
+ * @notice replay_chain() rebuilds the UTXO set of a chain by applying the blocks that change it in order, and checks the
+ * commitment recorded in each of them against the rebuilt set. An error is returned at the first block that cannot be
+ * applied, whose coinbase pays more than the fee of its transaction plus the block subsidy, or whose commitment differs.
+ * @dev The height of a block is its index in the chain, which is the height its outputs were created at.
+ */
+pub fn replay_chain(chain: &[Block]) -> Result<UTXOSet, String> {
+    let mut utxo_set = UTXOSet::new();
+    for (height, block) in chain.iter().enumerate() {
+        match block {
+            Block::UTXOTransaction { transaction, block_height, coinbase, .. } => {
+
+                // the fee is determined against the set the transaction spends from, before its inputs are removed
+                let fee: u64 = transaction.check_consensus_rules(&utxo_set)
+                    .map_err(|rejection| format!("Block {} cannot be applied: {}", height, rejection))?;
+                coinbase.verify_amount(fee)
+                    .map_err(|e| format!("Block {} has an invalid coinbase: {}", height, e))?;
+                utxo_set.apply_transaction(transaction, *block_height)
+                    .map_err(|e| format!("Block {} cannot be applied: {}", height, e))?;
+                utxo_set.apply_coinbase(coinbase);
+            }
+            Block::AccountToUtxo { outpoint, output, time, .. } => {
+                utxo_set.add_utxo(outpoint.clone(), UTXO::new(output.amount, output.recipient.clone(), height as u64, *time));
+            }
+            _ => continue,
+        }
+
+        // the set after the block must match the commitment the block recorded
+        if recorded_commitment(block) != Some(utxo_set.commitment().as_slice()) {
+            return Err(format!("Block {} records a UTXO set commitment that does not match its chain", height));
+        }
+    }
+    Ok(utxo_set)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commitment_is_order_independent_and_incremental() {
+        let outputs: Vec<(OutPoint, UTXO)> = (0..4u8)
+            .map(|index| (OutPoint::new(vec![index; 32], index as u32), UTXO::new(100 * index as u64, vec![index; 20], 1, 12345)))
+            .collect();
+
+        // adding the same outputs in any order gives the same commitment
+        let mut forward = UTXOSet::new();
+        let mut backward = UTXOSet::new();
+        for (outpoint, utxo) in outputs.iter() { forward.add_utxo(outpoint.clone(), utxo.clone()); }
+        for (outpoint, utxo) in outputs.iter().rev() { backward.add_utxo(outpoint.clone(), utxo.clone()); }
+        assert_eq!(forward.commitment(), backward.commitment());
+        assert_ne!(forward.commitment(), UTXOSet::new().commitment());
+
+        // spending an output is the same as never having created it
+        forward.remove_utxo(&outputs[2].0);
+        let mut without = UTXOSet::new();
+        for (outpoint, utxo) in outputs.iter().filter(|(outpoint, _)| outpoint != &outputs[2].0) {
+            without.add_utxo(outpoint.clone(), utxo.clone());
+        }
+        assert_eq!(forward.commitment(), without.commitment());
+
+        // replacing an output commits to the new one only, and any change to an output changes the commitment
+        let mut replaced = without.clone();
+        replaced.add_utxo(outputs[0].0.clone(), UTXO::new(1, vec![9; 20], 1, 12345));
+        assert_ne!(replaced.commitment(), without.commitment());
+        replaced.add_utxo(outputs[0].0.clone(), outputs[0].1.clone());
+        assert_eq!(replaced.commitment(), without.commitment());
+
+        // the commitment survives a rebuild of the set
+        let mut rebuilt = without.clone();
+        rebuilt.rebuild_index();
+        assert_eq!(rebuilt.commitment(), without.commitment());
+    }
+}
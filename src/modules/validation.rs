@@ -1,18 +1,23 @@
-use tokio::sync::{Mutex, MutexGuard, Notify};
+use tokio::sync::{broadcast, Mutex, MutexGuard, Notify};
 use serde_json::Value;
 use std::sync::Arc;
 use std::time::UNIX_EPOCH;
 use std::collections::HashMap;
 
-use crate::modules::blockchain::{BlockChain, Block};
+use crate::modules::address::{self, Recipient};
+use crate::modules::blockchain::{self, BlockChain, Block, BlockUndo, ValidatorConfig};
+use crate::modules::bridge;
 use crate::modules::merkle_tree::{MerkleTree, Account};
-use crate::modules::constants::{FAUCET_AMOUNT, HEARTBEAT_TIMEOUT};
+use crate::modules::constants::{FAUCET_AMOUNT, HEARTBEAT_TIMEOUT, MIN_TRANSACTION_FEE, EVENT_CHANNEL_CAPACITY, MAX_IMMATURE_TRANSACTIONS};
 use crate::modules::consensus;
 use crate::modules::zk_proof;
 use crate::modules::network;
 use crate::modules::requests;
 use crate::modules::adopt_network_state::PeerLedgerResponse;
-use crate::modules::utxo::{UTXOSet, UTXOTransaction, TxInput, TxOutput, OutPoint};
+use crate::modules::query::TxStatus;
+use crate::modules::events::{self, NodeEvent};
+use crate::modules::utxo::{UTXOSet, UTXOTransaction, CoinbaseTransaction, TxInput, TxOutput, UTXO, block_subsidy, check_creation_timestamp};
+use crate::modules::verifier;
 
 /**
  * @protocol validation.rs contains the data structures and event handler logic for running a validator node. 
@@ -28,6 +33,9 @@ use crate::modules::utxo::{UTXOSet, UTXOTransaction, TxInput, TxOutput, OutPoint
  */
 
 
+// node keys of the validators that voted on a request, mapped to their decisions
+pub type RequestVoters = HashMap<String, bool>;
+
 /**
  * @struct The ValidatorNode struct contains the local ledger state of this validator node. A well as other
  * datastructures used to fascilitate the validation process. All datastructures are wrapperd in Arc<Mutex<>> 
@@ -47,6 +55,11 @@ use crate::modules::utxo::{UTXOSet, UTXOTransaction, TxInput, TxOutput, OutPoint
  * boolean representing the decision made by the client. This datastructure is updated following the result of 
  * the independent validation of a request by the client.
  * 
+ * @param decision_commitments: Arc<Mutex<HashMap<Vec<u8>, Vec<u8>>>> - This hashmap stores the commitment to the 
+ * UTXO set (see utxo_commitment.rs) at the time the client decided on a request that creates or spends UTXOs. The key 
+ * is the hash of the request. It is sent along with consensus requests, and peers that decided against another UTXO 
+ * set vote to reject.
+ * 
  * @param peer_consensus_decisions: Arc<Mutex<HashMap<Vec<u8>, (u32, u32)>> - This hashmap stores the decisions 
  * made by each peer validator node on the network regarding the validity of a given request. The key is the hash 
  * of the request and the value is a tuple of u32 integers representing the number of yays vs nays for the request 
@@ -57,15 +70,29 @@ use crate::modules::utxo::{UTXOSet, UTXOTransaction, TxInput, TxOutput, OutPoint
  * @param client_port_address: String - The port address that the client is listening on for incoming connections. 
  * This is used to establish a connection with the client from the network.
  * 
- * @param used_zk_proofs: Arc<Mutex<Vec<u8>, String>> - A hashmap that stores the zk-proofs that hahses of the 
- * zk-proofs have been used by a requester to verify transactions. The key is the address of the client and the 
- * value is a hash of the zk-proof. This datastructure is used to prevent replay attacks with zk-proofs. This 
- * is because the same curve points will always add to the third curve point (the obscured private key) so
- * allowing the resuse of the same zk-proof would enable a listener to replay the same transaction multiple times.
+ * @param reward_address: String - The public key of the account this validator collects its share of transaction fees 
+ * with, as configured for its node key in the validator set.
+ * 
+ * @param node_key: String - The hex encoded secp256k1 private key this validator signs its consensus responses and 
+ * heartbeats with (see the Vote and Handshake domains in zk_proof.rs). It is loaded from the key_file configured for the 
+ * port of the node in accepted_ports.json (see network::load_node_key()).
+ * 
+ * @param node_public_key: String - The hex encoded public key of node_key, sent along with every signed message.
+ * 
+ * @param validators: Vec<ValidatorConfig> - The validator set of the network (see blockchain::GenesisConfig). Votes 
+ * and heartbeats are only accepted from its node keys, and fees are split among its reward addresses.
+ * 
+ * @param consensus_voters: Arc<Mutex<HashMap<Vec<u8>, RequestVoters>>> - This hashmap stores the node keys of the 
+ * validators that voted on a given request along with their decision. The key is the hash of the request. It 
+ * keeps a validator from voting twice on a request.
+ * 
+ * @param tx_statuses: Arc<Mutex<HashMap<Vec<u8>, TxStatus>>> - This hashmap stores the status of every transaction 
+ * request seen by this node (pending, committed at a block height, or rejected with a reason). The key is the hash 
+ * of the request. It is read by the GetTxStatus query (see query.rs).
  * 
  * @param active_peers: Arc<Mutex<Vec<(String, u64)>>> - A vector of (String, u64) tuples containing the addresses 
- * of all active peers (as represented by their port address) on the network and the timestamp of the last recieved 
- * heartbeat from this peer. This datastructure is maintained by the validator node via a blocked on heartbeat 
+ * of all active peers (as represented by their port address) on the network and the signed timestamp of the last 
+ * recieved heartbeat from this peer. This datastructure is maintained by the validator node via a blocked on heartbeat 
  * protocol that listens for the periodic heartbeat of other nodes on the network. Nodes that fail to send a 
  * heartbeat within a given time frame are removed from the active peers list.
  * 
@@ -78,6 +105,12 @@ use crate::modules::utxo::{UTXOSet, UTXOTransaction, TxInput, TxOutput, OutPoint
  * of peer nodes and determine the majority state of the network when updating the local ledger state of the 
  * validator node.
  * 
+ * @param events: broadcast::Sender<NodeEvent> - The channel events are published on as the local ledger changes. 
+ * Subscribers of the JSON-RPC server receive them through their own receiver (see events.rs).
+ * 
+ * @param immature_transactions: Arc<Mutex<Vec<Value>>> - UTXO transaction requests held because their time locks have
+ * not passed yet. They are handled again once they mature (see network::handle_matured_transactions_periodically()).
+ * 
  * @param notify: Arc<Notify> - A tokio sync Notify struct that is used to notify the validator node when all
  * responses have been recieved for a given request. This is used to break out of the loop that waits for responses
  * from the network.
@@ -93,19 +126,28 @@ pub struct ValidatorNode {
     // Datastructures for Validation
     pub peer_decisions: Arc<Mutex<HashMap<Vec<u8>, (u32, u32)>>>, 
     pub client_decisions: Arc<Mutex<HashMap<Vec<u8>, bool>>>,
+    pub decision_commitments: Arc<Mutex<HashMap<Vec<u8>, Vec<u8>>>>,
     pub client_port_address: String,    
-    pub used_zk_proofs: Arc<Mutex<HashMap<Vec<u8>, Vec<String>>>>, // address -> vec of hashes of zk-proofs
+    pub reward_address: String,
+    pub node_key: String,
+    pub node_public_key: String,
+    pub validators: Vec<ValidatorConfig>,
+    pub consensus_voters: Arc<Mutex<HashMap<Vec<u8>, RequestVoters>>>,
+    pub tx_statuses: Arc<Mutex<HashMap<Vec<u8>, TxStatus>>>,
     pub active_peers: Arc<Mutex<Vec<(String, u64)>>>, 
     pub total_peers: Arc<Mutex<usize>>, 
     pub peer_ledger_states: Arc<Mutex<Vec<PeerLedgerResponse>>>,
     pub notify_consensus: Arc<Notify>, 
     pub notify_all_ledgers_received: Arc<Notify>,
+    pub events: broadcast::Sender<NodeEvent>,
+    pub immature_transactions: Arc<Mutex<Vec<Value>>>,
 }
 
 impl ValidatorNode { // initializes datastructures
     pub fn new() -> ValidatorNode {
         let mut utxo_set = UTXOSet::new();
         utxo_set.rebuild_index(); // Initialize the index
+        let (node_key, node_public_key) = zk_proof::generate_keypair().unwrap();
         
         ValidatorNode { 
             blockchain: Arc::new(Mutex::new(BlockChain::new())),
@@ -113,13 +155,21 @@ impl ValidatorNode { // initializes datastructures
             utxo_set: Arc::new(Mutex::new(utxo_set)),
             peer_decisions: Arc::new(Mutex::new(HashMap::new())),
             client_decisions: Arc::new(Mutex::new(HashMap::new())),
+            decision_commitments: Arc::new(Mutex::new(HashMap::new())),
             client_port_address: String::new(),
-            used_zk_proofs: Arc::new(Mutex::new(HashMap::new())),
+            reward_address: String::new(),
+            node_key: node_key.to_string(),
+            node_public_key: node_public_key.to_string(),
+            validators: blockchain::genesis_config().validators.clone(),
+            consensus_voters: Arc::new(Mutex::new(HashMap::new())),
+            tx_statuses: Arc::new(Mutex::new(HashMap::new())),
             active_peers: Arc::new(Mutex::new(Vec::new())),
             total_peers: Arc::new(Mutex::new(0)), // Init to zero, will be set when peers are know
             peer_ledger_states: Arc::new(Mutex::new(Vec::new())),
             notify_consensus: Arc::new(Notify::new()),
             notify_all_ledgers_received: Arc::new(Notify::new()),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            immature_transactions: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -190,6 +240,72 @@ impl ValidatorNode { // initializes datastructures
         println!("All local ledger states from peers...");
     }
 
+    // Returns the reward address the validator set assigns to a node key, None if the key is not a validator
+    pub fn validator_reward_address(&self, node_public_key: &str) -> Option<&str> {
+        self.validators.iter()
+            .find(|validator| validator.node_public_key == node_public_key)
+            .map(|validator| validator.reward_address.as_str())
+    }
+
+    // Returns the reward addresses of the validator set, which every node splits fees among in the same way
+    pub fn validator_reward_addresses(&self) -> Vec<Vec<u8>> {
+        self.validators.iter()
+            .filter(|validator| !validator.reward_address.is_empty())
+            .map(|validator| validator.reward_address.as_bytes().to_vec())
+            .collect()
+    }
+
+    // Holds a time locked UTXO transaction request until it matures, returns false if too many are held already
+    pub async fn hold_immature_transaction(&self, request: Value) -> bool {
+        let mut immature_transactions = self.immature_transactions.lock().await;
+        if immature_transactions.contains(&request) { return true; }
+        if immature_transactions.len() >= MAX_IMMATURE_TRANSACTIONS { return false; }
+        immature_transactions.push(request);
+        true
+    }
+
+    // Removes and returns the held UTXO transaction requests whose time locks have passed
+    pub async fn take_matured_transactions(&self) -> Vec<Value> {
+        let held: Vec<Value> = self.immature_transactions.lock().await.clone();
+        let mut matured: Vec<Value> = Vec::new();
+        for request in held {
+            let is_mature: bool = match parse_utxo_transaction(&request) {
+                Ok(utxo_transaction) => check_utxo_time_locks(&utxo_transaction, self).await.is_ok(),
+                Err(_) => true,
+            };
+            if is_mature { matured.push(request); }
+        }
+        self.immature_transactions.lock().await.retain(|request| !matured.contains(request));
+        matured
+    }
+
+    // Records the latest status of a transaction request for the GetTxStatus query and publishes its outcome
+    pub async fn set_tx_status(&self, request_hash: Vec<u8>, status: TxStatus) {
+        let hash: String = hex::encode(&request_hash);
+        match &status {
+            TxStatus::Committed { block_height } => self.emit(NodeEvent::TxCommitted { hash, block_height: *block_height }),
+            TxStatus::Rejected { reason } => self.emit(NodeEvent::TxRejected { hash, reason: reason.clone() }),
+            _ => {}
+        }
+        self.tx_statuses.lock().await.insert(request_hash, status);
+    }
+
+    // Publishes an event to all subscribers (events are dropped when nobody is subscribed)
+    pub fn emit(&self, event: NodeEvent) {
+        let _ = self.events.send(event);
+    }
+
+    // Publishes the block most recently pushed to the chain
+    pub async fn emit_new_block(&self, blockchain: &BlockChain) {
+        if let Some(event) = events::new_block_event(blockchain).await { self.emit(event); }
+    }
+
+    // Publishes an accountChanged event for each account-model address (UTF-8 encoded public key)
+    pub fn emit_accounts_changed(&self, addresses: &[&Vec<u8>]) {
+        for address in addresses {
+            self.emit(NodeEvent::AccountChanged { public_key: String::from_utf8_lossy(address).to_string() });
+        }
+    }
 
 }
 
@@ -261,9 +377,13 @@ async fn verify_account_creation_independently( request: Value, validator_node:
     let merkle_tree: Arc<Mutex<MerkleTree>> = validator_node.merkle_tree.clone();
     let merkle_tree_guard: MutexGuard<MerkleTree> = merkle_tree.lock().await;
 
-    // make decision upone whether the account already exist in the tree
-    let decision: bool;
-    if merkle_tree_guard.account_exists(&public_key) { decision = false; }else { decision = true; }
+    // get the schnorr commitment and the owner's signature over it
+    let schnorr_commitment: Vec<u8> = hex::decode(request["schnorr_commitment"].as_str().unwrap_or_default()).unwrap_or_default();
+    let commitment_signature: &str = request["commitment_signature"].as_str().unwrap_or_default();
+
+    // make decision upon whether the account already exist in the tree and the commitment was signed by the key owner
+    let decision: bool = !merkle_tree_guard.account_exists(&public_key)
+        && zk_proof::verify_account_commitment(request["public_key"].as_str().unwrap_or_default(), &schnorr_commitment, commitment_signature);
     println!("Client decision: {}", decision);
 
     // use SHA256 to hash the request
@@ -288,15 +408,18 @@ async fn add_account_creation_to_ledger( request: Value, validator_node: Validat
     // Retrieve public key and public key hash from request
     let public_key: Vec<u8> = request["public_key"].as_str().unwrap_or_default().as_bytes().to_vec();
     let public_key_hash: Vec<u8> = hex::decode(request["public_key_hash"].as_str().unwrap_or_default()).unwrap();
+    let schnorr_commitment: Vec<u8> = hex::decode(request["schnorr_commitment"].as_str().unwrap_or_default()).unwrap_or_default();
 
     // Lock merkle tree for writing
     let merkel_tree: Arc<Mutex<MerkleTree>> = validator_node.merkle_tree.clone();
     let mut merkel_tree_guard: MutexGuard<MerkleTree> = merkel_tree.lock().await;
 
     // Package account details in merkle_tree::Account struct and insert into merkle tree
-    let account = Account { public_key: public_key.clone(), public_key_hash,  balance: 0, nonce: 0,};
+    let account = Account { public_key: public_key.clone(), public_key_hash,  balance: 0, nonce: 0, schnorr_commitment };
 
-    // Insert the account into the merkle tree
+    // Insert the account into the merkle tree, recording that it did not exist before
+    let mut undo = BlockUndo::default();
+    undo.accounts.record(&merkel_tree_guard, &public_key);
     merkel_tree_guard.insert_account(account);
     assert!(merkel_tree_guard.account_exists(&public_key));
 
@@ -305,7 +428,7 @@ async fn add_account_creation_to_ledger( request: Value, validator_node: Validat
 
     // Package request details in Request enum 
     let new_account_request = Block::NewAccount { 
-        address: public_key, 
+        address: public_key.clone(), 
         account_balance: 0,
         time: time, 
         hash: Vec::new(),
@@ -317,8 +440,11 @@ async fn add_account_creation_to_ledger( request: Value, validator_node: Validat
 
     // Write the  acount creation in the blockchain
     blockchain_guard.store_incoming_requests(&new_account_request);
-    blockchain_guard.push_block_to_chain(new_account_request);   
+    blockchain_guard.push_block_with_undo(new_account_request, undo);   
 
+    // publish the new block and account
+    validator_node.emit_new_block(&blockchain_guard).await;
+    validator_node.emit_accounts_changed(&[&public_key]);
 }
 
 // ------------------------------------------------------------------------------------------------------------------------------------------------------------------------ // Transaction Verification Logic
@@ -332,8 +458,12 @@ async fn add_account_creation_to_ledger( request: Value, validator_node: Validat
 pub async fn handle_transaction_request(request: Value, validator_node: ValidatorNode) -> Result<bool, String> { 
     println!("vHandling transaction request..."); 
 
+    // mark the transaction as pending so that clients can query its status
+    let request_hash: Vec<u8> = network::hash_network_request(request.clone()).await;
+    validator_node.set_tx_status(request_hash.clone(), TxStatus::Pending).await;
+
     // verify the transaction independently 
-    verify_transaction_independently(request.clone(), validator_node.clone()).await;
+    let client_verdict: Result<(), String> = verify_transaction_independently(request.clone(), validator_node.clone()).await;
 
     // Prepare for responses by updating the count of active peers
     validator_node.update_active_peer_count().await;
@@ -342,9 +472,7 @@ pub async fn handle_transaction_request(request: Value, validator_node: Validato
     requests::send_consensus_request( request.clone(), validator_node.clone() ).await;
 
     // await responses from all peers (checks that num peers matches num responses)
-    validator_node.await_all_block_decisions(
-        &network::hash_network_request(request.clone()).await
-    ).await;
+    validator_node.await_all_block_decisions(&request_hash).await;
 
     // Determine if the client's decision is the majority decision
     let peer_majority_decision: bool = consensus::determine_majority(request.clone(), validator_node.clone()).await;
@@ -352,11 +480,16 @@ pub async fn handle_transaction_request(request: Value, validator_node: Validato
     // print peer majority decision
     println!("Majority Decision: {}", peer_majority_decision);
 
-    // return false if network consensus not reached
-    if peer_majority_decision == false { return Ok(false);}
+    // record the rejection and return false if network consensus not reached
+    if peer_majority_decision == false { 
+        let reason: String = client_verdict.err().unwrap_or("rejected by network majority".to_string());
+        validator_node.set_tx_status(request_hash, TxStatus::Rejected { reason }).await;
+        return Ok(false);
+    }
 
     // add the transaction to the ledger
-    add_transaction_to_ledger(request.clone(), validator_node.clone()).await;
+    let block_height: u64 = add_transaction_to_ledger(request.clone(), validator_node.clone()).await;
+    validator_node.set_tx_status(request_hash, TxStatus::Committed { block_height }).await;
 
     Ok(true) 
 }
@@ -365,19 +498,22 @@ pub async fn handle_transaction_request(request: Value, validator_node: Validato
  * @notice verify_transaction_independently() is an asynchronous function that performs the independent verification of a transaction
  * request recieved by a validator node. The decision of whether to accept the transaction is based on the information that was recieved by 
  * this particular node in isolation. The resulting decision will be sent to all other validator nodes to determine a majority decision. 
- * @dev the checks this function performs include: verifying the sender's private key (using zk_proof module), ensuruing 
- * the sender and recipient accounts both exist in the merkle tree, and that the sender has sufficient balance to send the
- * transaction.
+ * @dev the checks this function performs include: ensuruing the sender and recipient accounts both exist in the merkle tree
+ * (the recipient is given as a hex public key or as an address, see resolve_recipient()),
+ * that the request is for this network (blockchain::chain_id()), that its nonce is exactly the sender's current nonce (which rejects replays),
+ * verifying the sender's Schnorr proof (using zk_proof module), that the fee meets MIN_TRANSACTION_FEE, and that the 
+ * sender has sufficient balance to pay both the transaction amount and the fee. A rejected transaction returns the reason.
  */
-async fn verify_transaction_independently(request: Value, validator_node: ValidatorNode)-> bool {
+async fn verify_transaction_independently(request: Value, validator_node: ValidatorNode)-> Result<(), String> {
     println!("Performing Independent Validation of Transaction Request...");
   
     // retrieve request information (avoid unnecessary allocations)
     let sender_key_str = request["sender_public_key"].as_str().unwrap_or_default();
     let sender_address: Vec<u8> = sender_key_str.as_bytes().to_vec();
     let recipient_key_str = request["recipient_public_key"].as_str().unwrap_or_default();
-    let recipient_address: Vec<u8> = recipient_key_str.as_bytes().to_vec();
     let transaction_amount: u64 = request["amount"].as_str().unwrap_or_default().parse().unwrap_or_default();
+    let transaction_fee: Option<u64> = request["fee"].as_str().unwrap_or_default().parse().ok();
+    let transaction_nonce: Option<u64> = request["nonce"].as_u64();
  
     // lock client decisions map
     let mut client_decisions_guard: MutexGuard<HashMap<Vec<u8>, bool>> = validator_node.client_decisions.lock().await;
@@ -385,74 +521,106 @@ async fn verify_transaction_independently(request: Value, validator_node: Valida
     // Lock the merkle tree while accessing sender account info
     let merkle_tree_guard: MutexGuard<MerkleTree> = validator_node.merkle_tree.lock().await;
 
+    // resolve the account the recipient refers to
+    let recipient_address: Result<Vec<u8>, String> = resolve_recipient(&merkle_tree_guard, recipient_key_str);
+
     // declare decision
-    let decision: bool;
+    let decision: Result<(), String>;
 
     // Reject decision if the sender account does not exist in the merkle tree
     if !merkle_tree_guard.account_exists(&sender_address) { 
-        decision = false;
-    }
-     // Reject decision if the recipient account does not exist in the merkle tree
-    else if !merkle_tree_guard.account_exists(&recipient_address) { 
-        decision = false;
-    }
-    // Reject decision if the signature verification fails
-    else if {
-        let signature = request["signature"].as_str().unwrap_or_default();
-        let sender_public_key = request["sender_public_key"].as_str().unwrap_or_default();
-        let recipient_public_key = request["recipient_public_key"].as_str().unwrap_or_default();
-        let amount = request["amount"].as_str().unwrap_or_default();
-        let _nonce = request["nonce"].as_u64().unwrap_or_default();
-        
-        // Get the actual nonce from the merkle tree
-        let actual_nonce = merkle_tree_guard.get_nonce(&sender_address).unwrap_or(0);
-        
-        !zk_proof::verify_transaction_signature(
-            signature,
-            sender_public_key,
-            recipient_public_key,
-            amount,
-            actual_nonce,
-            validator_node.clone()
-        ).await
-    } {
-        decision = false;
-    }
-    // Reject decision if the sender does not have enough balance to send the transaction
-    else if transaction_amount > merkle_tree_guard.get_account_balance(&sender_address).unwrap(){
-        decision = false;
+        decision = Err("sender account does not exist".to_string());
+    }
+     // Reject decision if the recipient is malformed or its account does not exist in the merkle tree
+    else if let Err(reason) = recipient_address { 
+        decision = Err(reason);
+    }
+    // Reject decision if the transaction was made for a different network
+    else if request["chain_id"].as_str() != Some(blockchain::chain_id()) {
+        decision = Err(format!("chain id must be {}", blockchain::chain_id()));
+    }
+    // Reject decision if the nonce is not the sender's current nonce (replayed or out of order)
+    else if transaction_nonce != merkle_tree_guard.get_nonce(&sender_address) {
+        decision = Err(format!("nonce must be {}", merkle_tree_guard.get_nonce(&sender_address).unwrap_or_default()));
+    }
+    // Reject decision if the proof of knowledge of the sender's account secret fails
+    else if !zk_proof::verify_transaction_proof(
+        request["proof"].as_str().unwrap_or_default(),
+        &merkle_tree_guard.get_schnorr_commitment(&sender_address).unwrap_or_default(),
+        sender_key_str,
+        recipient_key_str,
+        request["amount"].as_str().unwrap_or_default(),
+        request["fee"].as_str().unwrap_or_default(),
+        transaction_nonce.unwrap_or_default(),
+    ) {
+        decision = Err("invalid proof".to_string());
+    }
+    // Reject decision if the fee is missing or below the minimum accepted by validators
+    else if transaction_fee.is_none_or(|fee| fee < MIN_TRANSACTION_FEE) {
+        decision = Err(format!("fee must be at least {}", MIN_TRANSACTION_FEE));
+    }
+    // Reject decision if the sender does not have enough balance to pay the amount and the fee
+    else if transaction_amount.checked_add(transaction_fee.unwrap_or_default())
+        .is_none_or(|total| total > merkle_tree_guard.get_account_balance(&sender_address).unwrap()) {
+        decision = Err("insufficient balance".to_string());
     }
     // Accept decision if all checks pass
-    else { decision = true; }
+    else { decision = Ok(()); }
         
     // insert the decision in the client decision map 
     client_decisions_guard.insert(
-        network::hash_network_request(request.clone()).await, decision
+        network::hash_network_request(request.clone()).await, decision.is_ok()
     );
 
-    // return true if all checks pass
+    // return the decision along with the reason for a rejection
     decision
 } // TODO issue #7 to be implemented here
 
+/**
+ * @notice resolve_recipient() returns the merkle tree key of the recipient of an account transfer. The recipient is 
+ * given either as a hex encoded public key or as an address, in which case the account whose public key hashes to 
+ * it is looked up. Malformed recipients (e.g. an address with a typo) are rejected.
+ */
+fn resolve_recipient(merkle_tree: &MerkleTree, recipient: &str) -> Result<Vec<u8>, String> {
+    let recipient_address: Vec<u8> = match address::parse_recipient(recipient).map_err(|e| format!("malformed recipient: {}", e))? {
+        Recipient::PublicKey(_) => recipient.as_bytes().to_vec(),
+        Recipient::PublicKeyHash(public_key_hash) => merkle_tree.find_account_by_public_key_hash(&public_key_hash).unwrap_or_default(),
+        Recipient::Multisig(_) | Recipient::Script(_) | Recipient::Stealth(_) => {
+            return Err("multisig locks, scripts and stealth addresses can only receive UTXO outputs".to_string());
+        }
+        Recipient::Account(account_recipient) => bridge::account_of_recipient(&account_recipient).unwrap_or_default().to_vec(),
+    };
+    match merkle_tree.account_exists(&recipient_address) {
+        true => Ok(recipient_address),
+        false => Err("recipient account does not exist".to_string()),
+    }
+}
+
 
 /**
  * @notice add_transaction_to_ledger() is an asynchronous function that adds a transaction after it has been verified by the 
  * entire network to both the merkle tree and the blockchain. 
+ * @dev The fee is deducted from the sender alongside the amount and split evenly among the reward addresses of the validator 
+ * set, so that every node applies the same split whichever votes it received (see consensus::split_validator_reward()). Only 
+ * reward addresses that are existing accounts are paid; if none qualify the fee is burned. The distribution is recorded in the transaction block. Returns the height of the new block.
 */
-async fn add_transaction_to_ledger(request: Value, validator_node: ValidatorNode) {
+async fn add_transaction_to_ledger(request: Value, validator_node: ValidatorNode) -> u64 {
     println!("Adding transaction to ledger...");
 
-    // get the sender and recipient addresses from the request
+    // get the sender address from the request
     let sender_address: Vec<u8> = request["sender_public_key"].as_str().unwrap_or_default().as_bytes().to_vec();
-    let recipient_address: Vec<u8> = request["recipient_public_key"].as_str().unwrap_or_default().as_bytes().to_vec();
     
-    // get the transaction amount from the request
+    // get the transaction amount and fee from the request
     let amount: u64 = request["amount"].as_str().unwrap_or_default().parse().unwrap_or_default();
+    let fee: u64 = request["fee"].as_str().unwrap_or_default().parse().unwrap_or_default();
 
     // retrieve and lock the merkle tree 
     let merkle_tree: Arc<Mutex<MerkleTree>> = validator_node.merkle_tree.clone();
     let mut merkle_tree_guard: MutexGuard<MerkleTree> = merkle_tree.lock().await;
 
+    // resolve the recipient account, which was checked to exist during validation
+    let recipient_address: Vec<u8> = resolve_recipient(&merkle_tree_guard, request["recipient_public_key"].as_str().unwrap_or_default()).unwrap();
+
     // retrieve the sender and recipient balances from the merkle tree
     let mut sender_balance: u64 = merkle_tree_guard.get_account_balance(&sender_address).unwrap();
     let mut recipient_balance: u64 = merkle_tree_guard.get_account_balance(&recipient_address).unwrap();
@@ -462,12 +630,35 @@ async fn add_transaction_to_ledger(request: Value, validator_node: ValidatorNode
     let time: u64 = std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
 
     // determine new account balances
-    sender_balance -= amount; recipient_balance += amount;
+    sender_balance -= amount + fee; recipient_balance += amount;
 
-    // Change account balances in the merkle tree to the ones updated
+    // Record the sender and recipient before they change, then change their balances in the merkle tree to the ones updated
+    let mut undo = BlockUndo::default();
+    undo.accounts.record(&merkle_tree_guard, &sender_address);
+    undo.accounts.record(&merkle_tree_guard, &recipient_address);
     merkle_tree_guard.change_balance(&sender_address, sender_balance);
     merkle_tree_guard.increment_nonce(&sender_address);
     merkle_tree_guard.change_balance(&recipient_address, recipient_balance);
+
+    // split the fee among the validators of the set that hold an account and credit their balances
+    let eligible_validators: Vec<Vec<u8>> = validator_node.validator_reward_addresses().into_iter()
+        .filter(|validator| merkle_tree_guard.account_exists(validator))
+        .collect();
+    let validator_rewards: Vec<(Vec<u8>, u64)> = consensus::split_validator_reward(fee, &eligible_validators);
+    for (validator, reward) in validator_rewards.iter() {
+        undo.accounts.record(&merkle_tree_guard, validator);
+        let validator_balance: u64 = merkle_tree_guard.get_account_balance(validator).unwrap();
+        merkle_tree_guard.change_balance(validator, validator_balance + reward);
+    }
+
+    // publish the accounts whose balance or nonce changed
+    let mut changed_accounts: Vec<&Vec<u8>> = vec![&sender_address, &recipient_address];
+    changed_accounts.extend(validator_rewards.iter().map(|(validator, _)| validator));
+    validator_node.emit_accounts_changed(&changed_accounts);
+
+    // re-read balances in case the sender or recipient also received a validator reward
+    let sender_balance: u64 = merkle_tree_guard.get_account_balance(&sender_address).unwrap();
+    let recipient_balance: u64 = merkle_tree_guard.get_account_balance(&recipient_address).unwrap();
     
     // Package request details in Request enum 
     let new_account_request = Block::Transaction {  // TODO this could probably be replaced by the block struct itself
@@ -477,7 +668,9 @@ async fn add_transaction_to_ledger(request: Value, validator_node: ValidatorNode
         recipient: recipient_address, 
         recipient_balance,
         amount, 
+        fee,
         time, 
+        validator_rewards,
         hash: Vec::new(),
     };
 
@@ -485,10 +678,13 @@ async fn add_transaction_to_ledger(request: Value, validator_node: ValidatorNode
     let blockchain: Arc<Mutex<BlockChain>> = validator_node.blockchain.clone();
     let mut blockchain_guard: MutexGuard<BlockChain> = blockchain.lock().await;
 
-    // Write a new block to the blockchain
+    // Write a new block to the blockchain and publish it
     blockchain_guard.store_incoming_requests(&new_account_request);
-    blockchain_guard.push_block_to_chain(new_account_request);   
+    blockchain_guard.push_block_with_undo(new_account_request, undo);   
+    validator_node.emit_new_block(&blockchain_guard).await;
 
+    // return the height of the block just written
+    blockchain_guard.chain.len() as u64 - 1
 }
 
 /**
@@ -512,10 +708,27 @@ pub async fn save_failed_transaction_json(){
 pub async fn handle_utxo_transaction_request(request: Value, validator_node: ValidatorNode) -> Result<bool, String> {
     println!("Handling UTXO transaction request...");
 
+    // mark the transaction as pending so that clients can query its status
+    let request_hash: Vec<u8> = network::hash_network_request(request.clone()).await;
+    validator_node.set_tx_status(request_hash.clone(), TxStatus::Pending).await;
+
+    // hold transactions that are not valid yet because of their time locks, they are handled again once they mature. Until
+    // then this node votes to reject them when peers ask for its decision
+    if let Ok(utxo_transaction) = parse_utxo_transaction(&request) {
+        if let Err(reason) = check_utxo_time_locks(&utxo_transaction, &validator_node).await {
+            validator_node.client_decisions.lock().await.insert(request_hash.clone(), false);
+            match validator_node.hold_immature_transaction(request).await {
+                true => println!("Holding immature UTXO transaction: {}", reason),
+                false => validator_node.set_tx_status(request_hash, TxStatus::Rejected { reason: "too many immature transactions".to_string() }).await,
+            }
+            return Ok(false);
+        }
+    }
+
     // verify the UTXO transaction independently 
-    let validation_result = verify_utxo_transaction_independently(request.clone(), validator_node.clone()).await;
-    if !validation_result {
-        println!("UTXO transaction failed independent validation");
+    if let Err(reason) = verify_utxo_transaction_independently(request.clone(), validator_node.clone()).await {
+        println!("UTXO transaction failed independent validation: {}", reason);
+        validator_node.set_tx_status(request_hash, TxStatus::Rejected { reason }).await;
         return Ok(false);
     }
 
@@ -526,9 +739,7 @@ pub async fn handle_utxo_transaction_request(request: Value, validator_node: Val
     requests::send_consensus_request(request.clone(), validator_node.clone()).await;
 
     // await responses from all peers (checks that num peers matches num responses)
-    validator_node.await_all_block_decisions(
-        &network::hash_network_request(request.clone()).await
-    ).await;
+    validator_node.await_all_block_decisions(&request_hash).await;
 
     // Determine if the client's decision is the majority decision
     let peer_majority_decision: bool = consensus::determine_majority(request.clone(), validator_node.clone()).await;
@@ -536,13 +747,20 @@ pub async fn handle_utxo_transaction_request(request: Value, validator_node: Val
     // print peer majority decision
     println!("UTXO Transaction Majority Decision: {}", peer_majority_decision);
 
-    // return false if network consensus not reached
+    // record the rejection and return false if network consensus not reached
     if !peer_majority_decision {
+        validator_node.set_tx_status(request_hash, TxStatus::Rejected { reason: "rejected by network majority".to_string() }).await;
         return Ok(false);
     }
 
     // add the UTXO transaction to the ledger
-    add_utxo_transaction_to_ledger(request.clone(), validator_node.clone()).await;
+    match add_utxo_transaction_to_ledger(request.clone(), validator_node.clone()).await {
+        Ok(block_height) => validator_node.set_tx_status(request_hash, TxStatus::Committed { block_height }).await,
+        Err(reason) => {
+            validator_node.set_tx_status(request_hash, TxStatus::Rejected { reason: reason.clone() }).await;
+            return Err(reason);
+        }
+    }
 
     Ok(true)
 }
@@ -550,11 +768,83 @@ pub async fn handle_utxo_transaction_request(request: Value, validator_node: Val
 /**
  * @notice verify_utxo_transaction_independently() verifies a UTXO transaction independently.
  * This includes checking that all input UTXOs exist, signatures are valid, and transaction is properly balanced.
+ * A rejected transaction returns the reason.
+ * @dev The UTXO set is only locked for the lookups; the signatures are verified afterwards, in parallel (see verifier.rs).
  */
-pub async fn verify_utxo_transaction_independently(request: Value, validator_node: ValidatorNode) -> bool {
+pub async fn verify_utxo_transaction_independently(request: Value, validator_node: ValidatorNode) -> Result<(), String> {
     println!("Performing Independent Validation of UTXO Transaction Request...");
 
+    // Reject transactions made for a different network, their input signatures would not verify either
+    if request["chain_id"].as_str() != Some(blockchain::chain_id()) {
+        return Err(format!("chain id must be {}", blockchain::chain_id()));
+    }
+
     // Parse the UTXO transaction from the request
+    let utxo_transaction = parse_utxo_transaction(&request)?;
+
+    // Reject malformed transactions before taking any lock
+    utxo_transaction.check_structure().map_err(|rejection| rejection.to_string())?;
+
+    // Get the height of the block the transaction would be included in, for its time locks
+    let block_height: u64 = validator_node.blockchain.lock().await.chain.len() as u64;
+    let time: u64 = std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+    // Reject outputs crediting accounts that do not exist (see bridge.rs), the merkle tree is locked before the UTXO set
+    let merkle_tree_guard = validator_node.merkle_tree.lock().await;
+    for (account, _) in bridge::account_credits(&utxo_transaction)? {
+        if !merkle_tree_guard.account_exists(&account) {
+            return Err(format!("Credited account does not exist: {}", String::from_utf8_lossy(&account)));
+        }
+    }
+    drop(merkle_tree_guard);
+
+    // Lock the UTXO set for validation
+    let utxo_set_arc = validator_node.utxo_set.clone();
+    let utxo_set_guard = utxo_set_arc.lock().await;
+
+    // Check the consensus rules: all input UTXOs exist, no input is spent twice, outputs are well formed and above
+    // the dust threshold, and the amounts add up without overflowing (inputs >= outputs)
+    utxo_transaction.check_consensus_rules(&utxo_set_guard).map_err(|rejection| rejection.to_string())?;
+    let utxo_commitment: Vec<u8> = utxo_set_guard.commitment();
+
+    // Verify that the public key revealed by each input owns the UTXO it spends (it is the recipient or hashes to it)
+    for input in &utxo_transaction.inputs {
+        let utxo = match utxo_set_guard.get_utxo(&input.outpoint) {
+            Some(utxo) => utxo,
+            None => {
+                return Err("UTXO not found for signature validation".to_string());
+            }
+        };
+        if !address::recipient_matches(&utxo.recipient, &input.public_key) {
+            return Err("Public key does not match UTXO recipient".to_string());
+        }
+    }
+
+    // Reject spends before the lock time of the transaction or the relative lock of an input has passed
+    utxo_transaction.check_time_locks(&utxo_set_guard, block_height, time)?;
+
+    // Release the UTXO set before the signature work, which only needs the transaction
+    drop(utxo_set_guard);
+
+    // Verify the signatures of all inputs in parallel, over the sighash selected by each input's sighash flags, and the range
+    // proofs of confidential outputs
+    verifier::verify_signatures_off_runtime(utxo_transaction).await?;
+
+    // Store the decision along with the commitment to the UTXO set it was made against
+    let request_hash = network::hash_network_request(request).await;
+    validator_node.decision_commitments.lock().await.insert(request_hash.clone(), utxo_commitment);
+    let client_decisions_arc = validator_node.client_decisions.clone();
+    let mut client_decisions_guard = client_decisions_arc.lock().await;
+    client_decisions_guard.insert(request_hash, true);
+
+    println!("UTXO Transaction Independent Validation: PASSED");
+    Ok(())
+}
+
+/**
+ * @notice parse_utxo_transaction() parses the UTXO transaction of a UTXOTransaction request.
+ */
+fn parse_utxo_transaction(request: &Value) -> Result<UTXOTransaction, String> {
     let inputs = match request["inputs"].as_array() {
         Some(inputs_array) => {
             let mut parsed_inputs = Vec::new();
@@ -562,16 +852,14 @@ pub async fn verify_utxo_transaction_independently(request: Value, validator_nod
                 match serde_json::from_value::<TxInput>(input_val.clone()) {
                     Ok(input) => parsed_inputs.push(input),
                     Err(_) => {
-                        println!("Failed to parse transaction input");
-                        return false;
+                        return Err("Failed to parse transaction input".to_string());
                     }
                 }
             }
             parsed_inputs
         }
         None => {
-            println!("No inputs found in UTXO transaction");
-            return false;
+            return Err("No inputs found in UTXO transaction".to_string());
         }
     };
 
@@ -582,119 +870,54 @@ pub async fn verify_utxo_transaction_independently(request: Value, validator_nod
                 match serde_json::from_value::<TxOutput>(output_val.clone()) {
                     Ok(output) => parsed_outputs.push(output),
                     Err(_) => {
-                        println!("Failed to parse transaction output");
-                        return false;
+                        return Err("Failed to parse transaction output".to_string());
                     }
                 }
             }
             parsed_outputs
         }
         None => {
-            println!("No outputs found in UTXO transaction");
-            return false;
+            return Err("No outputs found in UTXO transaction".to_string());
         }
     };
 
     let timestamp = request["timestamp"].as_u64().unwrap_or(0);
+    let lock_time = request["lock_time"].as_u64().unwrap_or(0);
+    let explicit_fee = request["explicit_fee"].as_u64().unwrap_or(0);
 
-    // Create the UTXO transaction
-    let utxo_transaction = UTXOTransaction::new(inputs, outputs, timestamp);
-
-    // Lock the UTXO set for validation
-    let utxo_set_arc = validator_node.utxo_set.clone();
-    let utxo_set_guard = utxo_set_arc.lock().await;
-
-    // Validate that all input UTXOs exist
-    for input in &utxo_transaction.inputs {
-        if !utxo_set_guard.contains(&input.outpoint) {
-            println!("Referenced UTXO does not exist: {:?}", input.outpoint);
-            return false;
-        }
-    }
-
-    // Validate transaction amounts (inputs >= outputs)
-    let input_amount = match utxo_transaction.total_input_amount(&utxo_set_guard) {
-        Some(amount) => amount,
-        None => {
-            println!("Failed to calculate total input amount");
-            return false;
-        }
-    };
-
-    let output_amount = utxo_transaction.total_output_amount();
-    if input_amount < output_amount {
-        println!("Transaction outputs exceed inputs: {} < {}", input_amount, output_amount);
-        return false;
-    }
-
-    // Validate signatures for each input
-    for input in &utxo_transaction.inputs {
-        // Get the UTXO being spent
-        let utxo = match utxo_set_guard.get_utxo(&input.outpoint) {
-            Some(utxo) => utxo,
-            None => {
-                println!("UTXO not found for signature validation");
-                return false;
-            }
-        };
-
-        // Verify that the public key matches the UTXO recipient
-        if input.public_key != utxo.recipient {
-            println!("Public key does not match UTXO recipient");
-            return false;
-        }
-
-        // Create transaction message for signature verification
-        let message = format!("{:?}{:?}{}", 
-            input.outpoint.txid, 
-            input.outpoint.vout, 
-            utxo_transaction.timestamp
-        );
-
-        // Verify the signature (simplified - in production would use proper message format)
-        let sender_public_key_hex = hex::encode(&input.public_key);
-        let signature_valid = zk_proof::verify_transaction_signature(
-            &input.signature,
-            &sender_public_key_hex,
-            "", // recipient not used in UTXO model
-            "", // amount not used in UTXO model
-            0,  // nonce not used in UTXO model
-            validator_node.clone()
-        ).await;
-
-        if !signature_valid {
-            println!("Invalid signature for input: {:?}", input.outpoint);
-            return false;
-        }
-    }
-
-    // Store the decision 
-    let request_hash = network::hash_network_request(request).await;
-    let client_decisions_arc = validator_node.client_decisions.clone();
-    let mut client_decisions_guard = client_decisions_arc.lock().await;
-    client_decisions_guard.insert(request_hash, true);
+    Ok(UTXOTransaction::new(inputs, outputs, timestamp).with_lock_time(lock_time).with_explicit_fee(explicit_fee))
+}
 
-    println!("UTXO Transaction Independent Validation: PASSED");
-    true
+/**
+ * @notice check_utxo_time_locks() checks the lock time and relative locks of a UTXO transaction against the height of
+ * the next block and the clock of this validator.
+ */
+async fn check_utxo_time_locks(utxo_transaction: &UTXOTransaction, validator_node: &ValidatorNode) -> Result<(), String> {
+    let block_height: u64 = validator_node.blockchain.lock().await.chain.len() as u64;
+    let time: u64 = std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let utxo_set_guard = validator_node.utxo_set.lock().await;
+    utxo_transaction.check_time_locks(&utxo_set_guard, block_height, time)
 }
 
 /**
  * @notice add_utxo_transaction_to_ledger() adds a validated UTXO transaction to the blockchain and UTXO set.
+ * @dev Every UTXO block carries a coinbase that pays the transaction's fee plus the block subsidy to the reward addresses
+ * of the validator set. The coinbase is verified to never exceed fees plus subsidy before anything is applied.
+ * Outputs paid to account recipients are credited to the accounts (see bridge.rs) while both the merkle tree and the 
+ * UTXO set are locked, after every credit has been checked, so the inputs are spent exactly when the accounts are 
+ * credited. Returns the height of the new block.
  */
-async fn add_utxo_transaction_to_ledger(request: Value, validator_node: ValidatorNode) {
+async fn add_utxo_transaction_to_ledger(request: Value, validator_node: ValidatorNode) -> Result<u64, String> {
     println!("Adding UTXO transaction to ledger...");
 
     // Parse the UTXO transaction from the request
-    let inputs = request["inputs"].as_array().unwrap().iter()
-        .map(|v| serde_json::from_value::<TxInput>(v.clone()).unwrap())
-        .collect();
-    
-    let outputs = request["outputs"].as_array().unwrap().iter()
-        .map(|v| serde_json::from_value::<TxOutput>(v.clone()).unwrap())
+    let utxo_transaction = parse_utxo_transaction(&request)?;
+
+    // the reward addresses of the validator set are hex encoded public keys
+    let validators: Vec<Vec<u8>> = validator_node.validator_reward_addresses()
+        .into_iter()
+        .filter_map(|address| hex::decode(address).ok())
         .collect();
-    
-    let timestamp = request["timestamp"].as_u64().unwrap_or(0);
-    let utxo_transaction = UTXOTransaction::new(inputs, outputs, timestamp);
 
     // Get current block height
     let blockchain_arc = validator_node.blockchain.clone();
@@ -702,27 +925,286 @@ async fn add_utxo_transaction_to_ledger(request: Value, validator_node: Validato
     let block_height = blockchain_guard.chain.len() as u64;
     drop(blockchain_guard);
 
+    // Lock the merkle tree and check the new balances of credited accounts before anything is applied
+    let mut merkle_tree_guard = validator_node.merkle_tree.lock().await;
+    let mut credited_balances: Vec<(Vec<u8>, u64)> = Vec::new();
+    for (account, amount) in bridge::account_credits(&utxo_transaction)? {
+        let balance: u64 = merkle_tree_guard.get_account_balance(&account)
+            .ok_or(format!("Credited account does not exist: {}", String::from_utf8_lossy(&account)))?;
+        let new_balance: u64 = balance.checked_add(amount).ok_or("Credited account balance overflows".to_string())?;
+        credited_balances.push((account, new_balance));
+    }
+
+    // Lock the UTXO set, recheck the consensus rules and determine the fee before the inputs are spent
+    let utxo_set_arc = validator_node.utxo_set.clone();
+    let mut utxo_set_guard = utxo_set_arc.lock().await;
+    let fee: u64 = utxo_transaction.check_consensus_rules(&utxo_set_guard).map_err(|rejection| rejection.to_string())?;
+
+    // Build the coinbase paying fee + subsidy to the validator set (timestamp taken from the transaction so all nodes agree)
+    let reward: u64 = fee.saturating_add(block_subsidy(block_height));
+    let coinbase_outputs: Vec<TxOutput> = consensus::split_validator_reward(reward, &validators)
+        .into_iter()
+        .map(|(recipient, amount)| TxOutput::new(amount, recipient))
+        .collect();
+    let coinbase = CoinbaseTransaction::new(coinbase_outputs, block_height, utxo_transaction.timestamp);
+
+    // Never mint more than the fees plus the allowed subsidy
+    coinbase.verify_amount(fee).map_err(|e| format!("Rejected coinbase: {}", e))?;
+
+    // Apply transaction and coinbase to UTXO set, recording the spent UTXOs
+    let mut utxo_undo = utxo_set_guard.apply_transaction(&utxo_transaction, block_height)
+        .map_err(|e| format!("Failed to apply UTXO transaction: {}", e))?;
+    utxo_undo.extend(utxo_set_guard.apply_coinbase(&coinbase));
+    let mut undo = BlockUndo { utxos: utxo_undo, ..BlockUndo::default() };
+    let utxo_commitment: Vec<u8> = utxo_set_guard.commitment();
+    drop(utxo_set_guard);
+
+    // Credit the accounts paid by the transaction
+    for (account, balance) in credited_balances.iter() {
+        undo.accounts.record(&merkle_tree_guard, account);
+        merkle_tree_guard.change_balance(account, *balance);
+    }
+    drop(merkle_tree_guard);
+
+    // collect the owners (hex encoded public keys or addresses) of every spent or created output and credited account
+    let mut changed_owners: Vec<String> = utxo_transaction.inputs.iter().map(|input| hex::encode(&input.public_key))
+        .chain(utxo_transaction.outputs.iter().chain(coinbase.outputs.iter()).map(|output| match bridge::account_of_recipient(&output.recipient) {
+            Some(account) => String::from_utf8_lossy(account).to_string(),
+            None => address::display_recipient(&output.recipient),
+        }))
+        .collect();
+    changed_owners.sort();
+    changed_owners.dedup();
+
     // Create the block
     let new_block = Block::UTXOTransaction {
         transaction: utxo_transaction.clone(),
         block_height,
+        coinbase,
+        utxo_commitment,
         hash: Vec::new(), // Will be set by blockchain
     };
 
-    // Apply transaction to UTXO set
-    let utxo_set_arc = validator_node.utxo_set.clone();
-    let mut utxo_set_guard = utxo_set_arc.lock().await;
-    if let Err(e) = utxo_set_guard.apply_transaction(&utxo_transaction, block_height) {
-        eprintln!("Failed to apply UTXO transaction: {}", e);
-        return;
+    // Add block to blockchain and publish it along with the changed owners
+    let mut blockchain_guard = blockchain_arc.lock().await;
+    blockchain_guard.push_block_with_undo(new_block, undo);
+    validator_node.emit_new_block(&blockchain_guard).await;
+    for public_key in changed_owners {
+        validator_node.emit(NodeEvent::AccountChanged { public_key });
     }
+
+    println!("UTXO Transaction successfully added to ledger");
+    Ok(block_height)
+}
+
+// ------------------------------------------------------------------------------------------------------------------------------------------------------------------------ // Account To UTXO Verification Logic
+
+/**
+ * @notice handle_account_to_utxo_request() handles a request to burn an account balance into a UTXO (see bridge.rs). 
+ * Like a transfer, it is validated independently, put to network consensus and applied to the ledger if accepted.
+ */
+pub async fn handle_account_to_utxo_request(request: Value, validator_node: ValidatorNode) -> Result<bool, String> {
+    println!("Handling account to UTXO request...");
+
+    // mark the transaction as pending so that clients can query its status
+    let request_hash: Vec<u8> = network::hash_network_request(request.clone()).await;
+    validator_node.set_tx_status(request_hash.clone(), TxStatus::Pending).await;
+
+    // verify the request independently
+    let client_verdict: Result<(), String> = verify_account_to_utxo_independently(request.clone(), validator_node.clone()).await;
+
+    // Prepare for responses by updating the count of active peers
+    validator_node.update_active_peer_count().await;
+
+    // send for network consensus on the request
+    requests::send_consensus_request(request.clone(), validator_node.clone()).await;
+
+    // await responses from all peers (checks that num peers matches num responses)
+    validator_node.await_all_block_decisions(&request_hash).await;
+
+    // Determine if the client's decision is the majority decision
+    let peer_majority_decision: bool = consensus::determine_majority(request.clone(), validator_node.clone()).await;
+    println!("Account To UTXO Majority Decision: {}", peer_majority_decision);
+
+    // record the rejection and return false if network consensus not reached
+    if !peer_majority_decision {
+        let reason: String = client_verdict.err().unwrap_or("rejected by network majority".to_string());
+        validator_node.set_tx_status(request_hash, TxStatus::Rejected { reason }).await;
+        return Ok(false);
+    }
+
+    // burn the balance and mint the UTXO
+    match add_account_to_utxo_to_ledger(request.clone(), validator_node.clone()).await {
+        Ok(block_height) => validator_node.set_tx_status(request_hash, TxStatus::Committed { block_height }).await,
+        Err(reason) => {
+            validator_node.set_tx_status(request_hash, TxStatus::Rejected { reason: reason.clone() }).await;
+            return Err(reason);
+        }
+    }
+
+    Ok(true)
+}
+
+/**
+ * @notice verify_account_to_utxo_independently() performs the independent verification of an AccountToUtxo request.
+ * @dev the checks are those of a transfer (see verify_transaction_independently()), with the Schnorr proof made in the 
+ * AccountToUtxo domain, a positive amount, a recipient that is a UTXO recipient (see resolve_utxo_recipient()) and a 
+ * signed timestamp close to the validator's clock (see check_creation_timestamp() in utxo.rs).
+ * A rejected request returns the reason.
+ */
+async fn verify_account_to_utxo_independently(request: Value, validator_node: ValidatorNode) -> Result<(), String> {
+    println!("Performing Independent Validation of Account To UTXO Request...");
+
+    // retrieve request information
+    let sender_key_str: &str = request["sender_public_key"].as_str().unwrap_or_default();
+    let sender_address: Vec<u8> = sender_key_str.as_bytes().to_vec();
+    let recipient_str: &str = request["recipient"].as_str().unwrap_or_default();
+    let amount: Option<u64> = request["amount"].as_str().unwrap_or_default().parse().ok();
+    let fee: Option<u64> = request["fee"].as_str().unwrap_or_default().parse().ok();
+    let nonce: Option<u64> = request["nonce"].as_u64();
+    let timestamp: u64 = request["timestamp"].as_u64().unwrap_or_default();
+    let time: u64 = std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+    // lock client decisions map and the merkle tree
+    let mut client_decisions_guard: MutexGuard<HashMap<Vec<u8>, bool>> = validator_node.client_decisions.lock().await;
+    let merkle_tree_guard: MutexGuard<MerkleTree> = validator_node.merkle_tree.lock().await;
+
+    let decision: Result<(), String> = if !merkle_tree_guard.account_exists(&sender_address) {
+        Err("sender account does not exist".to_string())
+    } else if let Err(reason) = resolve_utxo_recipient(recipient_str) {
+        Err(reason)
+    } else if let Err(reason) = check_creation_timestamp(timestamp, time) {
+        Err(reason)
+    } else if request["chain_id"].as_str() != Some(blockchain::chain_id()) {
+        Err(format!("chain id must be {}", blockchain::chain_id()))
+    } else if nonce != merkle_tree_guard.get_nonce(&sender_address) {
+        Err(format!("nonce must be {}", merkle_tree_guard.get_nonce(&sender_address).unwrap_or_default()))
+    } else if !zk_proof::verify_schnorr_proof(
+        request["proof"].as_str().unwrap_or_default(),
+        &merkle_tree_guard.get_schnorr_commitment(&sender_address).unwrap_or_default(),
+        &zk_proof::account_to_utxo_message(
+            sender_key_str,
+            recipient_str,
+            request["amount"].as_str().unwrap_or_default(),
+            request["fee"].as_str().unwrap_or_default(),
+            nonce.unwrap_or_default(),
+            timestamp,
+        ),
+    ) {
+        Err("invalid proof".to_string())
+    } else if amount.is_none_or(|amount| amount == 0) {
+        Err("amount must be positive".to_string())
+    } else if fee.is_none_or(|fee| fee < MIN_TRANSACTION_FEE) {
+        Err(format!("fee must be at least {}", MIN_TRANSACTION_FEE))
+    } else if amount.unwrap_or_default().checked_add(fee.unwrap_or_default())
+        .is_none_or(|total| total > merkle_tree_guard.get_account_balance(&sender_address).unwrap()) {
+        Err("insufficient balance".to_string())
+    } else {
+        Ok(())
+    };
+
+    // insert the decision in the client decision map, along with the commitment to the UTXO set it was made against
+    let request_hash: Vec<u8> = network::hash_network_request(request.clone()).await;
+    let utxo_commitment: Vec<u8> = validator_node.utxo_set.lock().await.commitment();
+    validator_node.decision_commitments.lock().await.insert(request_hash.clone(), utxo_commitment);
+    client_decisions_guard.insert(request_hash, decision.is_ok());
+    decision
+}
+
+/**
+ * @notice resolve_utxo_recipient() returns the recipient bytes of the output minted by an AccountToUtxo request. Any 
+ * UTXO recipient is accepted but an account recipient, which would just be a transfer.
+ */
+fn resolve_utxo_recipient(recipient: &str) -> Result<Vec<u8>, String> {
+    match address::parse_recipient(recipient).map_err(|e| format!("malformed recipient: {}", e))? {
+        Recipient::Account(_) => Err("the recipient of a minted UTXO cannot be an account, use a transfer".to_string()),
+        recipient => Ok(recipient.into_bytes()),
+    }
+}
+
+/**
+ * @notice add_account_to_utxo_to_ledger() burns the amount and fee of an accepted AccountToUtxo request from the sender
+ * and mints a UTXO of the amount at bridge::mint_outpoint() of the sender and nonce. 
+ * @dev Both the merkle tree and the UTXO set are locked while the balance is burned and the UTXO is minted, after the 
+ * sender's balance has been checked again. The fee is split among the validator set as for transfers. Returns 
+ * the height of the new block.
+ */
+async fn add_account_to_utxo_to_ledger(request: Value, validator_node: ValidatorNode) -> Result<u64, String> {
+    println!("Adding account to UTXO transaction to ledger...");
+
+    // get the details of the request, which were checked during validation
+    let sender_address: Vec<u8> = request["sender_public_key"].as_str().unwrap_or_default().as_bytes().to_vec();
+    let recipient: Vec<u8> = resolve_utxo_recipient(request["recipient"].as_str().unwrap_or_default())?;
+    let amount: u64 = request["amount"].as_str().unwrap_or_default().parse().unwrap_or_default();
+    let fee: u64 = request["fee"].as_str().unwrap_or_default().parse().unwrap_or_default();
+
+    // the UTXO and the block take the signed timestamp of the request, so that every node records the same time
+    let time: u64 = request["timestamp"].as_u64().unwrap_or_default();
+
+    // the height of the new block
+    let block_height: u64 = validator_node.blockchain.lock().await.chain.len() as u64;
+
+    // lock the merkle tree, then the UTXO set, and check the burn once more
+    let mut merkle_tree_guard: MutexGuard<MerkleTree> = validator_node.merkle_tree.lock().await;
+    let mut utxo_set_guard: MutexGuard<UTXOSet> = validator_node.utxo_set.lock().await;
+    let sender_balance: u64 = merkle_tree_guard.get_account_balance(&sender_address).ok_or("sender account does not exist".to_string())?;
+    let sender_balance: u64 = amount.checked_add(fee).and_then(|total| sender_balance.checked_sub(total))
+        .ok_or("insufficient balance".to_string())?;
+    let sender_nonce: u64 = merkle_tree_guard.get_nonce(&sender_address).unwrap();
+    let outpoint = bridge::mint_outpoint(&sender_address, sender_nonce);
+    if utxo_set_guard.contains(&outpoint) { return Err("the UTXO minted by this request already exists".to_string()); }
+
+    // burn the amount and fee from the sender and mint the UTXO, recording both before they change
+    let mut undo = BlockUndo::default();
+    undo.accounts.record(&merkle_tree_guard, &sender_address);
+    undo.utxos.record(&utxo_set_guard, &outpoint);
+    merkle_tree_guard.change_balance(&sender_address, sender_balance);
+    merkle_tree_guard.increment_nonce(&sender_address);
+    let output = TxOutput::new(amount, recipient);
+    utxo_set_guard.add_utxo(outpoint.clone(), UTXO::new(output.amount, output.recipient.clone(), block_height, time));
+    let utxo_commitment: Vec<u8> = utxo_set_guard.commitment();
     drop(utxo_set_guard);
 
-    // Add block to blockchain
-    let mut blockchain_guard = blockchain_arc.lock().await;
-    blockchain_guard.push_block_to_chain(new_block);
+    // split the fee among the validators of the set that hold an account and credit their balances
+    let eligible_validators: Vec<Vec<u8>> = validator_node.validator_reward_addresses().into_iter()
+        .filter(|validator| merkle_tree_guard.account_exists(validator))
+        .collect();
+    let validator_rewards: Vec<(Vec<u8>, u64)> = consensus::split_validator_reward(fee, &eligible_validators);
+    for (validator, reward) in validator_rewards.iter() {
+        undo.accounts.record(&merkle_tree_guard, validator);
+        let validator_balance: u64 = merkle_tree_guard.get_account_balance(validator).unwrap();
+        merkle_tree_guard.change_balance(validator, validator_balance + reward);
+    }
 
-    println!("UTXO Transaction successfully added to ledger");
+    // publish the accounts whose balance or nonce changed and the owner of the minted UTXO
+    let mut changed_accounts: Vec<&Vec<u8>> = vec![&sender_address];
+    changed_accounts.extend(validator_rewards.iter().map(|(validator, _)| validator));
+    validator_node.emit_accounts_changed(&changed_accounts);
+    validator_node.emit(NodeEvent::AccountChanged { public_key: address::display_recipient(&output.recipient) });
+
+    // re-read the sender's balance in case it also received a validator reward
+    let sender_balance: u64 = merkle_tree_guard.get_account_balance(&sender_address).unwrap();
+    drop(merkle_tree_guard);
+
+    // Write the block to the blockchain and publish it
+    let new_block = Block::AccountToUtxo {
+        sender: sender_address,
+        sender_balance,
+        sender_nonce,
+        outpoint,
+        output,
+        fee,
+        time,
+        validator_rewards,
+        utxo_commitment,
+        hash: Vec::new(),
+    };
+    let mut blockchain_guard: MutexGuard<BlockChain> = validator_node.blockchain.lock().await;
+    blockchain_guard.store_incoming_requests(&new_block);
+    blockchain_guard.push_block_with_undo(new_block, undo);
+    validator_node.emit_new_block(&blockchain_guard).await;
+
+    Ok(blockchain_guard.chain.len() as u64 - 1)
 }
 
 // ------------------------------------------------------------------------------------------------------------------------------------------------------------------------ // Faucet Verification Logic
@@ -818,7 +1300,9 @@ async fn add_faucet_request_to_ledger(request: Value, validator_node: ValidatorN
     let account_balance: u64 = merkle_tree_guard.get_account_balance(&public_key).unwrap();
     let new_balance: u64 = account_balance + FAUCET_AMOUNT;
 
-    // update the account balance
+    // update the account balance, recording the previous one
+    let mut undo = BlockUndo::default();
+    undo.accounts.record(&merkle_tree_guard, &public_key);
     merkle_tree_guard.change_balance(&public_key, new_balance);
 
     // Update the blockchain with the faucet request
@@ -826,7 +1310,7 @@ async fn add_faucet_request_to_ledger(request: Value, validator_node: ValidatorN
 
     // Package processed request details in a Block
     let new_account_request = Block::Faucet { 
-        address: public_key, 
+        address: public_key.clone(), 
         account_balance: new_balance,
         time: time, 
         hash: Vec::new(),
@@ -838,7 +1322,11 @@ async fn add_faucet_request_to_ledger(request: Value, validator_node: ValidatorN
 
     // store and validate the request
     blockchain_guard.store_incoming_requests(&new_account_request);
-    blockchain_guard.push_block_to_chain(new_account_request);
+    blockchain_guard.push_block_with_undo(new_account_request, undo);
+
+    // publish the new block and the credited account
+    validator_node.emit_new_block(&blockchain_guard).await;
+    validator_node.emit_accounts_changed(&[&public_key]);
 }
 
 
@@ -847,38 +1335,68 @@ async fn add_faucet_request_to_ledger(request: Value, validator_node: ValidatorN
 
 /**
  * @notice handle_heartbeat_request() is an asynchronous function that handles incoming heartbeat requests from other nodes on the network.
+ * @dev Heartbeats from another chain, from a node key outside the validator set or without a valid Handshake signature are 
+ * rejected (see requests::heartbeat_request()). So are heartbeats signed more than HEARTBEAT_TIMEOUT away from the local time, 
+ * or not after the last heartbeat accepted from the same port, so that a recorded heartbeat cannot be replayed.
  */
 pub async fn handle_heartbeat(request: Value, validator_node: ValidatorNode) -> Result<(), String> {
     println!("Handling heartbeat recieved...");
 
-    // Extract the port address from the request
+    // Extract the port address and the time it was signed at from the request
     let port_address: String = request["port_address"].as_str()
         .ok_or_else(|| "Failed to extract port address from heartbeat request".to_string())?
         .to_string();
+    let timestamp: u64 = request["timestamp"].as_u64()
+        .ok_or_else(|| "Failed to extract timestamp from heartbeat request".to_string())?;
+    let node_public_key: &str = request["node_public_key"].as_str().unwrap_or_default();
 
-    // Retrieve and lock the active_peers vector
-    let active_peers: Arc<Mutex<Vec<(String, u64)>>> = validator_node.active_peers.clone();
-    let mut active_peers_guard = active_peers.lock().await;
+    // Only accept heartbeats of this network signed by the node key of a member of the validator set
+    if request["chain_id"].as_str() != Some(blockchain::chain_id()) {
+        return Err(format!("Heartbeat from chain {:?}, this node is on chain {}", request["chain_id"], blockchain::chain_id()));
+    }
+    if validator_node.validator_reward_address(node_public_key).is_none() {
+        return Err(format!("Heartbeat from {}, which is not in the validator set", node_public_key));
+    }
+    if !zk_proof::verify_message_signature(
+        node_public_key,
+        &zk_proof::handshake_message(&port_address, timestamp),
+        request["signature"].as_str().unwrap_or_default(),
+    ) {
+        return Err("Invalid heartbeat signature".to_string());
+    }
 
     // Get the current time
     let current_time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
         .map_err(|_| "Failed to get current time".to_string())?
         .as_secs();
 
-    // add the peer to the active peers list if it is not already present
-    if !active_peers_guard.iter().any(|peer| peer.0 == port_address) {
-        active_peers_guard.push((port_address.clone(), current_time));        
-    }else{
-        // update the timestamp of the peer
-        for peer in active_peers_guard.iter_mut() {
-            if peer.0 == port_address {
-                peer.1 = current_time;
-            }
-        }
+    // Only accept heartbeats signed recently
+    if timestamp.abs_diff(current_time) > HEARTBEAT_TIMEOUT.as_secs() {
+        return Err(format!("Stale heartbeat from {} signed at {}, the local time is {}", port_address, timestamp, current_time));
+    }
+
+    // Retrieve and lock the active_peers vector
+    let active_peers: Arc<Mutex<Vec<(String, u64)>>> = validator_node.active_peers.clone();
+    let mut active_peers_guard = active_peers.lock().await;
+
+    // add the peer to the active peers list if it is not already present, otherwise update the time of its last heartbeat
+    match active_peers_guard.iter_mut().find(|peer| peer.0 == port_address) {
+        Some(peer) if timestamp <= peer.1 => {
+            return Err(format!("Replayed heartbeat from {} signed at {}, last heartbeat was signed at {}", port_address, timestamp, peer.1));
+        },
+        Some(peer) => peer.1 = timestamp,
+        None => {
+            active_peers_guard.push((port_address.clone(), timestamp));
+            validator_node.emit(NodeEvent::PeerJoined { address: port_address.clone() });
+        },
     }
 
-    // Remove peers that have not sent a heartbeat within the HEARTBEAT_TIMEOUT
-    active_peers_guard.retain(|peer| current_time - peer.1 < HEARTBEAT_TIMEOUT.as_secs());
+    // Remove peers that have not sent a heartbeat within the HEARTBEAT_TIMEOUT, publishing their departure
+    active_peers_guard.retain(|peer| {
+        let active: bool = current_time.saturating_sub(peer.1) < HEARTBEAT_TIMEOUT.as_secs();
+        if !active { validator_node.emit(NodeEvent::PeerLeft { address: peer.0.clone() }); }
+        active
+    });
 
 
     // Print all active peers
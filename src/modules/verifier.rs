@@ -0,0 +1,142 @@
+use secp256k1::{All, Secp256k1};
+use rayon::prelude::*;
+use std::sync::OnceLock;
+
+use crate::modules::confidential::ConfidentialAmount;
+use crate::modules::utxo::UTXOTransaction;
+
+
+/**
+ * @notice verifier.rs contains the signature verification service of the node. Building a secp256k1 context is far more
+ * expensive than verifying a signature with it, so every signature is signed and checked with one context shared by the
+ * whole process (see secp_context()).
+ *
+ * The input signatures of a transaction, or of every transaction in a block, are independent of each other and are
+ * verified in parallel across all cores. Verification only reads the transaction, so callers check everything that
+ * needs the UTXO set first and release its lock before verifying signatures.
+ *
+ * The range proofs of confidential outputs (see confidential.rs) cost far more to verify than signatures, and are
+ * verified in parallel alongside them.
+ */
+// the secp256k1 context shared by every signature operation of the process
+static SECP_CONTEXT: OnceLock<Secp256k1<All>> = OnceLock::new();
+
+/**
+ * @notice secp_context() returns the secp256k1 context shared by the process, creating it on first use.
+ */
+pub fn secp_context() -> &'static Secp256k1<All> {
+    SECP_CONTEXT.get_or_init(Secp256k1::new)
+}
+
+/**
+ * @notice verify_transaction_signatures() verifies the signatures of all inputs of a transaction in parallel.
+ * @dev Returns the reason the first failing input (by index) was rejected.
+ */
+pub fn verify_transaction_signatures(transaction: &UTXOTransaction) -> Result<(), String> {
+    verify_block_signatures(std::slice::from_ref(transaction))
+}
+
+/**
+ * @notice verify_block_signatures() verifies the signatures of every input of every transaction in a block in parallel,
+ * so the work is spread across cores even when a block holds many small transactions.
+ * @dev Returns the reason the first failing input (by transaction, then input index) was rejected.
+ */
+pub fn verify_block_signatures(transactions: &[UTXOTransaction]) -> Result<(), String> {
+    let inputs: Vec<(&UTXOTransaction, usize)> = transactions.iter()
+        .flat_map(|transaction| (0..transaction.inputs.len()).map(move |input_index| (transaction, input_index)))
+        .collect();
+
+    let failure = inputs.par_iter().find_first(|(transaction, input_index)| transaction.verify_input(*input_index).is_err());
+    match failure {
+        Some((transaction, input_index)) => {
+            let reason = transaction.verify_input(*input_index).unwrap_err();
+            Err(format!("Invalid signature for input {:?}: {}", transaction.inputs[*input_index].outpoint, reason))
+        }
+        None => Ok(()),
+    }
+}
+
+/**
+ * @notice verify_range_proofs() verifies the range proofs of all confidential outputs of a transaction in parallel.
+ * @dev Returns the reason the first failing output (by index) was rejected.
+ */
+pub fn verify_range_proofs(transaction: &UTXOTransaction) -> Result<(), String> {
+    let outputs: Vec<(usize, &ConfidentialAmount)> = transaction.outputs.iter().enumerate()
+        .filter_map(|(index, output)| output.confidential.as_ref().map(|confidential| (index, confidential)))
+        .collect();
+
+    match outputs.par_iter().find_first(|(_, confidential)| confidential.verify().is_err()) {
+        Some((index, confidential)) => Err(format!("Confidential output {}: {}", index, confidential.verify().unwrap_err())),
+        None => Ok(()),
+    }
+}
+
+/**
+ * @notice verify_signatures_off_runtime() runs verify_transaction_signatures() and verify_range_proofs() on the blocking
+ * thread pool, so that verifying a large transaction does not stall the async tasks of the node.
+ */
+pub async fn verify_signatures_off_runtime(transaction: UTXOTransaction) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || verify_transaction_signatures(&transaction).and_then(|_| verify_range_proofs(&transaction)))
+        .await
+        .map_err(|e| format!("Signature verification failed to run: {}", e))?
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::confidential::{commit, random_blinding, Opening};
+    use crate::modules::utxo::{OutPoint, TxInput, TxOutput, SIGHASH_ALL};
+    use secp256k1::{PublicKey, SecretKey};
+
+    // Builds a transaction with one input per key, each signed by its key
+    fn signed_transaction(keys: &[SecretKey], timestamp: u64) -> UTXOTransaction {
+        let inputs = keys.iter().enumerate().map(|(vout, key)| {
+            let public_key = PublicKey::from_secret_key(secp_context(), key).serialize().to_vec();
+            TxInput::new(OutPoint::new(vec![9; 32], vout as u32), String::new(), public_key)
+        }).collect();
+        let mut transaction = UTXOTransaction::new(inputs, vec![TxOutput::new(10, vec![1; 20])], timestamp);
+        for (input_index, key) in keys.iter().enumerate() {
+            transaction.sign_input(input_index, key, SIGHASH_ALL).unwrap();
+        }
+        transaction
+    }
+
+    #[test]
+    fn test_parallel_verification_reports_the_first_bad_input() {
+        let keys: Vec<SecretKey> = (1..=16u8).map(|i| SecretKey::from_slice(&[i; 32]).unwrap()).collect();
+        let transaction = signed_transaction(&keys, 12345);
+        assert!(verify_transaction_signatures(&transaction).is_ok());
+
+        // swap the signatures of two inputs, both fail and the first one is reported
+        let mut tampered = transaction.clone();
+        tampered.inputs[3].signature = transaction.inputs[7].signature.clone();
+        tampered.inputs[7].signature = transaction.inputs[3].signature.clone();
+        let reason = verify_transaction_signatures(&tampered).unwrap_err();
+        assert!(reason.contains(&format!("{:?}", transaction.inputs[3].outpoint)), "{}", reason);
+    }
+
+    #[test]
+    fn test_block_verification_covers_every_transaction() {
+        let keys: Vec<SecretKey> = (1..=4u8).map(|i| SecretKey::from_slice(&[i; 32]).unwrap()).collect();
+        let mut block: Vec<UTXOTransaction> = (0..4).map(|i| signed_transaction(&keys, 12345 + i)).collect();
+        assert!(verify_block_signatures(&block).is_ok());
+
+        block[2].outputs[0].amount = 11;
+        assert!(verify_block_signatures(&block).is_err());
+        assert!(verify_block_signatures(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_range_proof_verification() {
+        let opening = Opening { amount: 40, blinding: random_blinding() };
+        let mut transaction = signed_transaction(&[SecretKey::from_slice(&[1; 32]).unwrap()], 12345);
+        transaction.outputs.push(TxOutput::confidential(&opening, vec![2; 20]));
+        transaction.outputs.push(TxOutput::confidential(&opening, vec![3; 20]));
+        assert!(verify_range_proofs(&transaction).is_ok());
+
+        // a proof moved to another commitment fails, and the first failing output is reported
+        transaction.outputs[2].confidential.as_mut().unwrap().commitment = commit(41, &opening.blinding).compress().to_bytes().to_vec();
+        assert_eq!(verify_range_proofs(&transaction).unwrap_err(), "Confidential output 2: Invalid range proof");
+    }
+}
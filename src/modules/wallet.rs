@@ -0,0 +1,852 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use secp256k1::{Secp256k1, SecretKey, PublicKey};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use rand::RngCore;
+use rand::seq::SliceRandom;
+use curve25519_dalek::scalar::Scalar;
+
+use crate::modules::constants::{KEYSTORE_PATH, WALLET_PASSWORD_ENV, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P, MNEMONIC_WORD_COUNT, DUST_THRESHOLD};
+use crate::modules::hd_keys::{self, ExtendedPrivateKey};
+use crate::modules::address::{self, Recipient};
+use crate::modules::multisig::{MultisigLock, PartiallySignedTransaction};
+use crate::modules::htlc::{self, Htlc};
+use crate::modules::coin_selection::{self, SelectionStrategy, Selection};
+use crate::modules::confidential::{self, Opening};
+use crate::modules::stealth::StealthKeys;
+use crate::modules::utxo::{OutPoint, UTXO, TxInput, TxOutput, UTXOTransaction, RelativeLock, SIGHASH_ALL};
+
+
+/**
+ * @notice wallet.rs contains the password-encrypted keystore used by the CLI to hold private keys, so that they never
+ * have to be printed or typed on the command line. The keystore is a JSON file (KEYSTORE_PATH) holding named accounts.
+ * The public key of each account is stored in the clear, the private key is encrypted:
+ *
+ *    1. A 32 byte encryption key is derived from the password and a random salt with scrypt.
+ *    2. The private key is encrypted with ChaCha20-Poly1305 under a random nonce. The account name and public key are
+ *       authenticated alongside it, so an encrypted key cannot be moved to another entry of the keystore.
+ *
+ * A wrong password or any modification of an entry makes decryption fail. The password is read from the WALLET_PASSWORD
+ * environment variable if it is set, otherwise it is prompted for without echoing it.
+ *
+ * A keystore can also hold an HD seed (see hd_keys.rs), encrypted the same way. New accounts and UTXO receive keys are
+ * then derived from the seed rather than generated at random, and the whole keystore can be restored from the mnemonic.
+ *
+ * Spends of multisig outputs (see multisig.rs) are co-signed offline through partially signed transaction files.
+ */
+/**
+ * @notice KdfParams are the scrypt cost parameters an account key was encrypted with. They are stored with every
+ * account so the defaults (see constants.rs) can be raised without breaking existing keystores.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct KdfParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+    pub salt: String,
+}
+
+/**
+ * @notice EncryptedKey is an encrypted private key along with everything but the password needed to decrypt it.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EncryptedKey {
+    pub kdf: String,
+    pub kdf_params: KdfParams,
+    pub cipher: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/**
+ * @notice WalletAccount is a named account of the keystore.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WalletAccount {
+    pub name: String,
+    pub public_key: String,
+    pub crypto: EncryptedKey,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derivation_path: Option<String>,
+}
+
+/**
+ * @notice HdSeed is the encrypted HD seed of a keystore, along with the next unused index of each derivation chain.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HdSeed {
+    pub crypto: EncryptedKey,
+    pub next_account_index: u32,
+    pub next_receive_index: u32,
+}
+
+/**
+ * @notice Keystore is the content of the keystore file.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct Keystore {
+    pub version: u32,
+    pub accounts: Vec<WalletAccount>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<HdSeed>,
+}
+
+impl Keystore {
+
+    // Loads the keystore at the given path, a missing file is an empty keystore
+    pub fn load(path: &Path) -> Result<Keystore, String> {
+        if !path.exists() { return Ok(Keystore { version: 1, ..Keystore::default() }); }
+        let keystore_json: String = fs::read_to_string(path).map_err(|e| format!("Failed to read keystore: {}", e))?;
+        serde_json::from_str(&keystore_json).map_err(|e| format!("Invalid keystore: {}", e))
+    }
+
+    // Writes the keystore to the given path
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let keystore_json: String = serde_json::to_string_pretty(self).unwrap();
+        fs::write(path, keystore_json).map_err(|e| format!("Failed to write keystore: {}", e))
+    }
+
+    // Returns the account with the given name
+    pub fn get(&self, name: &str) -> Option<&WalletAccount> {
+        self.accounts.iter().find(|account| account.name == name)
+    }
+
+    // Encrypts a private key under a new account name
+    pub fn add(&mut self, name: &str, secret_key: &SecretKey, password: &str, log_n: u8) -> Result<&WalletAccount, String> {
+        self.insert(name, secret_key, None, password, log_n)
+    }
+
+    // Encrypts a private key under a new account name, recording the path it was derived at
+    fn insert(&mut self, name: &str, secret_key: &SecretKey, derivation_path: Option<String>, password: &str, log_n: u8) -> Result<&WalletAccount, String> {
+        if name.is_empty() { return Err("Account name must not be empty".to_string()); }
+        if self.get(name).is_some() { return Err(format!("Account {} already exists", name)); }
+
+        let public_key: String = PublicKey::from_secret_key(&Secp256k1::new(), secret_key).to_string();
+        let crypto: EncryptedKey = encrypt_key(secret_key, password, &associated_data(name, &public_key), log_n)?;
+        self.accounts.push(WalletAccount { name: name.to_string(), public_key, crypto, derivation_path });
+        Ok(self.accounts.last().unwrap())
+    }
+
+    // Decrypts the private key of an account
+    pub fn unlock(&self, name: &str, password: &str) -> Result<SecretKey, String> {
+        let account: &WalletAccount = self.get(name).ok_or(format!("No account named {}", name))?;
+        decrypt_key(&account.crypto, password, &associated_data(&account.name, &account.public_key))
+    }
+
+    // Encrypts the HD seed of the keystore, a keystore holds at most one seed
+    pub fn set_seed(&mut self, seed: &[u8; 64], password: &str, log_n: u8) -> Result<(), String> {
+        if self.seed.is_some() { return Err("The wallet already has a seed".to_string()); }
+        let crypto: EncryptedKey = encrypt_bytes(seed, password, SEED_ASSOCIATED_DATA, log_n)?;
+        self.seed = Some(HdSeed { crypto, next_account_index: 0, next_receive_index: 0 });
+        Ok(())
+    }
+
+    // Decrypts the HD seed and returns the master key derived from it
+    pub fn unlock_seed(&self, password: &str) -> Result<ExtendedPrivateKey, String> {
+        let seed: &HdSeed = self.seed.as_ref().ok_or("The wallet has no seed, run wallet init or wallet restore".to_string())?;
+        ExtendedPrivateKey::from_seed(&decrypt_bytes(&seed.crypto, password, SEED_ASSOCIATED_DATA)?)
+    }
+
+    /**
+     * @notice derive() derives the next key of the account chain (or of the receive chain) from the HD seed and stores
+     * it under a new account name, encrypted with the same password as the seed.
+     */
+    pub fn derive(&mut self, name: &str, receive: bool, password: &str, log_n: u8) -> Result<&WalletAccount, String> {
+        let master: ExtendedPrivateKey = self.unlock_seed(password)?;
+        let seed: &HdSeed = self.seed.as_ref().unwrap();
+        let path: String = match receive {
+            true => hd_keys::receive_key_path(seed.next_receive_index),
+            false => hd_keys::account_key_path(seed.next_account_index),
+        };
+        let secret_key: SecretKey = master.derive_path(&path)?.secret_key;
+
+        self.insert(name, &secret_key, Some(path), password, log_n)?;
+        let seed: &mut HdSeed = self.seed.as_mut().unwrap();
+        match receive {
+            true => seed.next_receive_index += 1,
+            false => seed.next_account_index += 1,
+        }
+        Ok(self.accounts.last().unwrap())
+    }
+}
+
+// data authenticated alongside the encrypted HD seed
+const SEED_ASSOCIATED_DATA: &[u8] = b"miniBlockChain/keystore/seed";
+
+/**
+ * @notice associated_data() returns the data authenticated alongside the encrypted key of an account.
+ */
+fn associated_data(name: &str, public_key: &str) -> Vec<u8> {
+    format!("miniBlockChain/keystore/{}/{}", name, public_key).into_bytes()
+}
+
+/**
+ * @notice derive_encryption_key() stretches a password into a 32 byte encryption key with scrypt.
+ */
+fn derive_encryption_key(password: &str, params: &KdfParams) -> Result<[u8; 32], String> {
+    let salt: Vec<u8> = hex::decode(&params.salt).map_err(|e| format!("Invalid salt: {}", e))?;
+    let scrypt_params = scrypt::Params::new(params.log_n, params.r, params.p, 32)
+        .map_err(|e| format!("Invalid scrypt parameters: {}", e))?;
+
+    let mut key = [0u8; 32];
+    scrypt::scrypt(password.as_bytes(), &salt, &scrypt_params, &mut key)
+        .map_err(|e| format!("Failed to derive encryption key: {}", e))?;
+    Ok(key)
+}
+
+/**
+ * @notice encrypt_key() encrypts a private key with a password, authenticating the associated data alongside it.
+ * @dev log_n is the scrypt cost (SCRYPT_LOG_N for keystores, lower in tests).
+ */
+pub fn encrypt_key(secret_key: &SecretKey, password: &str, associated_data: &[u8], log_n: u8) -> Result<EncryptedKey, String> {
+    encrypt_bytes(&secret_key[..], password, associated_data, log_n)
+}
+
+/**
+ * @notice encrypt_bytes() encrypts arbitrary secret bytes (a private key or an HD seed) with a password.
+ */
+fn encrypt_bytes(secret: &[u8], password: &str, associated_data: &[u8], log_n: u8) -> Result<EncryptedKey, String> {
+
+    // fresh salt and nonce for every encryption
+    let mut salt = [0u8; 32];
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let kdf_params = KdfParams { log_n, r: SCRYPT_R, p: SCRYPT_P, salt: hex::encode(salt) };
+    let key: [u8; 32] = derive_encryption_key(password, &kdf_params)?;
+
+    let ciphertext: Vec<u8> = ChaCha20Poly1305::new(Key::from_slice(&key))
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: secret, aad: associated_data })
+        .map_err(|_| "Failed to encrypt secret".to_string())?;
+
+    Ok(EncryptedKey {
+        kdf: "scrypt".to_string(),
+        kdf_params,
+        cipher: "chacha20poly1305".to_string(),
+        nonce: hex::encode(nonce),
+        ciphertext: hex::encode(ciphertext),
+    })
+}
+
+/**
+ * @notice decrypt_key() decrypts a private key. It fails if the password is wrong or anything authenticated was changed.
+ */
+pub fn decrypt_key(crypto: &EncryptedKey, password: &str, associated_data: &[u8]) -> Result<SecretKey, String> {
+    let secret_key_bytes: Vec<u8> = decrypt_bytes(crypto, password, associated_data)?;
+    SecretKey::from_slice(&secret_key_bytes).map_err(|e| format!("Invalid private key: {}", e))
+}
+
+/**
+ * @notice decrypt_bytes() decrypts the secret bytes encrypted by encrypt_bytes().
+ */
+fn decrypt_bytes(crypto: &EncryptedKey, password: &str, associated_data: &[u8]) -> Result<Vec<u8>, String> {
+    if crypto.kdf != "scrypt" || crypto.cipher != "chacha20poly1305" {
+        return Err(format!("Unsupported keystore encryption: {}/{}", crypto.kdf, crypto.cipher));
+    }
+
+    let key: [u8; 32] = derive_encryption_key(password, &crypto.kdf_params)?;
+    let nonce: Vec<u8> = hex::decode(&crypto.nonce).map_err(|e| format!("Invalid nonce: {}", e))?;
+    let ciphertext: Vec<u8> = hex::decode(&crypto.ciphertext).map_err(|e| format!("Invalid ciphertext: {}", e))?;
+    if nonce.len() != 12 { return Err("Invalid nonce length".to_string()); }
+
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+        .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: associated_data })
+        .map_err(|_| "Wrong password or corrupted keystore".to_string())
+}
+
+/**
+ * @notice read_password() reads the keystore password from the WALLET_PASSWORD environment variable, or prompts for it.
+ */
+pub fn read_password(prompt: &str) -> Result<String, String> {
+    match std::env::var(WALLET_PASSWORD_ENV) {
+        Ok(password) => Ok(password),
+        Err(_) => rpassword::prompt_password(prompt).map_err(|e| format!("Failed to read password: {}", e)),
+    }
+}
+
+/**
+ * @notice resolve_private_key() returns the hex private key for the sender argument of the CLI, which is either the
+ * name of a keystore account (unlocked with the password) or a hex encoded private key.
+ */
+pub fn resolve_private_key(sender: &str) -> Result<String, String> {
+    let keystore: Keystore = Keystore::load(Path::new(KEYSTORE_PATH))?;
+    if keystore.get(sender).is_some() {
+        let password: String = read_password(&format!("Password for {}: ", sender))?;
+        return Ok(keystore.unlock(sender, &password)?.to_string());
+    }
+
+    // not an account name, accept a raw private key
+    match hex::decode(sender).ok().and_then(|bytes| SecretKey::from_slice(&bytes).ok()) {
+        Some(secret_key) => Ok(secret_key.to_string()),
+        None => Err(format!("{} is neither a wallet account nor a private key", sender)),
+    }
+}
+
+//------------------------------------ CLI Commands ------------------------------------//
+
+/**
+ * @notice create_account() stores a new keypair in the keystore under the given name. The key is derived from the HD
+ * seed if the keystore has one, otherwise it is generated at random. Returns the keypair so the caller can register the
+ * account with the network.
+ */
+pub fn create_account(name: &str) -> Result<(SecretKey, PublicKey), String> {
+    let mut keystore: Keystore = Keystore::load(Path::new(KEYSTORE_PATH))?;
+    if keystore.get(name).is_some() { return Err(format!("Account {} already exists", name)); }
+
+    // derive the next account key from the seed
+    if keystore.seed.is_some() {
+        let password: String = read_password("Wallet password: ")?;
+        keystore.derive(name, false, &password, SCRYPT_LOG_N)?;
+        let secret_key: SecretKey = keystore.unlock(name, &password)?;
+        keystore.save(Path::new(KEYSTORE_PATH))?;
+        return Ok((secret_key, PublicKey::from_secret_key(&Secp256k1::new(), &secret_key)));
+    }
+
+    let (secret_key, public_key) = crate::modules::zk_proof::generate_keypair().map_err(|e| e.to_string())?;
+    let password: String = read_new_password()?;
+    keystore.add(name, &secret_key, &password, SCRYPT_LOG_N)?;
+    keystore.save(Path::new(KEYSTORE_PATH))?;
+    Ok((secret_key, public_key))
+}
+
+/**
+ * @notice import_account() encrypts an existing hex private key into the keystore under the given name.
+ */
+pub fn import_account(name: &str, private_key: &str) -> Result<String, String> {
+    let secret_key: SecretKey = hex::decode(private_key).ok()
+        .and_then(|bytes| SecretKey::from_slice(&bytes).ok())
+        .ok_or("Invalid private key".to_string())?;
+
+    let mut keystore: Keystore = Keystore::load(Path::new(KEYSTORE_PATH))?;
+    if keystore.get(name).is_some() { return Err(format!("Account {} already exists", name)); }
+
+    let password: String = read_new_password()?;
+    let public_key: String = keystore.add(name, &secret_key, &password, SCRYPT_LOG_N)?.public_key.clone();
+    keystore.save(Path::new(KEYSTORE_PATH))?;
+    Ok(public_key)
+}
+
+/**
+ * @notice export_account() decrypts and returns the hex private key of an account.
+ */
+pub fn export_account(name: &str) -> Result<String, String> {
+    let keystore: Keystore = Keystore::load(Path::new(KEYSTORE_PATH))?;
+    if keystore.get(name).is_none() { return Err(format!("No account named {}", name)); }
+
+    let password: String = read_password(&format!("Password for {}: ", name))?;
+    Ok(keystore.unlock(name, &password)?.to_string())
+}
+
+/**
+ * @notice list_accounts() returns every account in the keystore.
+ */
+pub fn list_accounts() -> Result<Vec<WalletAccount>, String> {
+    Ok(Keystore::load(Path::new(KEYSTORE_PATH))?.accounts)
+}
+
+/**
+ * @notice init_seed() generates a new mnemonic and stores the seed it encodes in the keystore. The mnemonic is returned
+ * so it can be written down, it is not stored anywhere.
+ */
+pub fn init_seed() -> Result<String, String> {
+    let mut keystore: Keystore = Keystore::load(Path::new(KEYSTORE_PATH))?;
+    if keystore.seed.is_some() { return Err("The wallet already has a seed".to_string()); }
+
+    let mnemonic: String = hd_keys::generate_mnemonic(MNEMONIC_WORD_COUNT)?;
+    let password: String = read_new_password()?;
+    keystore.set_seed(&hd_keys::mnemonic_to_seed(&mnemonic, "")?, &password, SCRYPT_LOG_N)?;
+    keystore.save(Path::new(KEYSTORE_PATH))?;
+    Ok(mnemonic)
+}
+
+/**
+ * @notice restore_seed() restores the seed of a keystore from its mnemonic (prompted for) and re-derives the first 
+ * count keys of the account and receive chains, named account/i and receive/i.
+ */
+pub fn restore_seed(count: u32) -> Result<Vec<WalletAccount>, String> {
+    let mut keystore: Keystore = Keystore::load(Path::new(KEYSTORE_PATH))?;
+    if keystore.seed.is_some() { return Err("The wallet already has a seed".to_string()); }
+
+    let mnemonic: String = rpassword::prompt_password("Mnemonic: ").map_err(|e| format!("Failed to read mnemonic: {}", e))?;
+    let seed: [u8; 64] = hd_keys::mnemonic_to_seed(&mnemonic, "")?;
+    let password: String = read_new_password()?;
+    keystore.set_seed(&seed, &password, SCRYPT_LOG_N)?;
+
+    let mut restored: Vec<WalletAccount> = Vec::new();
+    for index in 0..count {
+        restored.push(keystore.derive(&format!("account/{}", index), false, &password, SCRYPT_LOG_N)?.clone());
+        restored.push(keystore.derive(&format!("receive/{}", index), true, &password, SCRYPT_LOG_N)?.clone());
+    }
+    keystore.save(Path::new(KEYSTORE_PATH))?;
+    Ok(restored)
+}
+
+/**
+ * @notice receive_chain_xpub() returns the xpub of the receive chain. It derives the public key of every receive key
+ * (see hd_keys::ExtendedPublicKey::derive_child()), so it can be handed to a watch-only service.
+ */
+pub fn receive_chain_xpub() -> Result<String, String> {
+    let keystore: Keystore = Keystore::load(Path::new(KEYSTORE_PATH))?;
+    let password: String = read_password("Wallet password: ")?;
+    let master: ExtendedPrivateKey = keystore.unlock_seed(&password)?;
+    Ok(master.derive_path(&hd_keys::receive_chain_path())?.extended_public_key().to_xpub())
+}
+
+/**
+ * @notice new_receive_key() derives the next key of the receive chain, stores it as receive/i and returns it. Paying
+ * each UTXO output to a fresh key keeps payments to the wallet from being linked to each other.
+ */
+pub fn new_receive_key() -> Result<WalletAccount, String> {
+    let mut keystore: Keystore = Keystore::load(Path::new(KEYSTORE_PATH))?;
+    let index: u32 = keystore.seed.as_ref().map(|seed| seed.next_receive_index).unwrap_or_default();
+
+    let password: String = read_password("Wallet password: ")?;
+    let account: WalletAccount = keystore.derive(&format!("receive/{}", index), true, &password, SCRYPT_LOG_N)?.clone();
+    keystore.save(Path::new(KEYSTORE_PATH))?;
+    Ok(account)
+}
+
+/**
+ * @notice multisig_lock() builds the M-of-N lock of hex public keys, to be used (hex encoded) as the recipient of UTXO
+ * outputs.
+ */
+pub fn multisig_lock(threshold: &str, public_keys: &[String]) -> Result<MultisigLock, String> {
+    let threshold: u8 = threshold.parse().map_err(|e| format!("Invalid threshold: {}", e))?;
+    let public_keys: Vec<PublicKey> = public_keys.iter()
+        .map(|public_key| PublicKey::from_str(public_key).map_err(|e| format!("Invalid public key {}: {}", public_key, e)))
+        .collect::<Result<Vec<PublicKey>, String>>()?;
+    MultisigLock::new(threshold, public_keys)
+}
+
+/**
+ * @notice create_psbt() writes a new partially signed transaction to a file, to be co-signed offline. It spends the 
+ * comma separated txid:vout outpoints, all locked to the spender (a hex public key or multisig lock), to the 
+ * (recipient, amount) pairs of outputs. Returns the txid.
+ */
+pub fn create_psbt(path: &Path, spender: &str, outpoints: &str, outputs: &[String]) -> Result<String, String> {
+    let spender: Vec<u8> = match address::parse_recipient(spender)? {
+        Recipient::PublicKey(bytes) | Recipient::Multisig(bytes) => bytes,
+        Recipient::PublicKeyHash(_) | Recipient::Stealth(_) => return Err("Inputs must reveal the public key or multisig lock they spend".to_string()),
+        Recipient::Script(_) => return Err("Script outputs are spent with an unlocking script, not co-signed".to_string()),
+        Recipient::Account(_) => return Err("Account balances are spent with account transactions".to_string()),
+    };
+
+    let inputs: Vec<TxInput> = outpoints.split(',')
+        .map(|outpoint| Ok(TxInput::new(parse_outpoint(outpoint)?, String::new(), spender.clone())))
+        .collect::<Result<Vec<TxInput>, String>>()?;
+
+    let psbt = PartiallySignedTransaction::new(UTXOTransaction::new(inputs, parse_outputs(outputs)?, unix_time()));
+    save_psbt(path, &psbt)?;
+    Ok(hex::encode(&psbt.transaction.hash))
+}
+
+/**
+ * @notice timelock_psbt() sets the lock time of the transaction of a partially signed transaction file (a block height 
+ * or unix time, 0 for none) and the relative locks of its inputs, given as input:blocks:N or input:seconds:N. The 
+ * locks are signed, so they can only be set before anyone signs. Returns the new txid.
+ */
+pub fn timelock_psbt(path: &Path, lock_time: &str, relative_locks: &[String]) -> Result<String, String> {
+    let psbt: PartiallySignedTransaction = load_psbt(path)?;
+    if psbt.partial_signatures.iter().any(|signatures| !signatures.is_empty()) {
+        return Err("Time locks must be set before the transaction is signed".to_string());
+    }
+
+    let mut transaction: UTXOTransaction = psbt.transaction;
+    for lock in relative_locks {
+        let (input_index, relative_lock) = match lock.split(':').collect::<Vec<&str>>().as_slice() {
+            [input_index, "blocks", blocks] => (input_index.parse::<usize>(), blocks.parse().map(RelativeLock::Blocks)),
+            [input_index, "seconds", seconds] => (input_index.parse::<usize>(), seconds.parse().map(RelativeLock::Seconds)),
+            _ => return Err(format!("Relative locks must be given as input:blocks:N or input:seconds:N, not {}", lock)),
+        };
+        let input_index: usize = input_index.ok().filter(|input_index| *input_index < transaction.inputs.len())
+            .ok_or_else(|| format!("Invalid input in {}", lock))?;
+        let relative_lock: RelativeLock = relative_lock.map_err(|e| format!("Invalid lock {}: {}", lock, e))?;
+        transaction.inputs[input_index] = transaction.inputs[input_index].clone().with_relative_lock(relative_lock);
+    }
+    let transaction: UTXOTransaction = transaction.with_lock_time(lock_time.parse().map_err(|e| format!("Invalid lock time: {}", e))?);
+
+    let psbt = PartiallySignedTransaction::new(transaction);
+    save_psbt(path, &psbt)?;
+    Ok(hex::encode(&psbt.transaction.hash))
+}
+
+/**
+ * @notice cosign_psbt() signs every input of a partially signed transaction file that the signer (a wallet account 
+ * name or a private key) can sign. Returns the number of inputs signed and those still lacking signatures.
+ */
+pub fn cosign_psbt(signer: &str, path: &Path) -> Result<(usize, Vec<usize>), String> {
+    let secret_key: SecretKey = SecretKey::from_str(&resolve_private_key(signer)?).map_err(|e| e.to_string())?;
+    let mut psbt: PartiallySignedTransaction = load_psbt(path)?;
+    let signed: usize = psbt.sign(&secret_key)?;
+    save_psbt(path, &psbt)?;
+    Ok((signed, psbt.unsigned_inputs()))
+}
+
+/**
+ * @notice combine_psbt() adds the signatures of another copy of a partially signed transaction to a file, so that
+ * signers can sign copies in parallel. Returns the inputs still lacking signatures.
+ */
+pub fn combine_psbt(path: &Path, other_path: &Path) -> Result<Vec<usize>, String> {
+    let mut psbt: PartiallySignedTransaction = load_psbt(path)?;
+    psbt.combine(&load_psbt(other_path)?)?;
+    save_psbt(path, &psbt)?;
+    Ok(psbt.unsigned_inputs())
+}
+
+/**
+ * @notice finalize_psbt() returns the signed transaction of a partially signed transaction file once every input has
+ * been signed by enough keys.
+ */
+pub fn finalize_psbt(path: &Path) -> Result<UTXOTransaction, String> {
+    load_psbt(path)?.finalize()
+}
+
+/**
+ * @notice create_htlc() builds an HTLC paying the receiver (a hex public key) for the preimage of the hex hash, and 
+ * refundable to the sender (a wallet account name or a private key) from the timeout. Without a hash a new secret is 
+ * generated and returned with the HTLC, to be kept until the swap is claimed.
+ */
+pub fn create_htlc(sender: &str, receiver: &str, timeout: &str, hash: Option<&String>) -> Result<(Htlc, Option<[u8; 32]>), String> {
+    let sender: PublicKey = PublicKey::from_secret_key(&Secp256k1::new(), &SecretKey::from_str(&resolve_private_key(sender)?).map_err(|e| e.to_string())?);
+    let receiver: PublicKey = PublicKey::from_str(receiver).map_err(|e| format!("Invalid receiver {}: {}", receiver, e))?;
+    let timeout: u64 = timeout.parse().map_err(|e| format!("Invalid timeout: {}", e))?;
+
+    let (hash, preimage) = match hash {
+        Some(hash) => (hex::decode(hash).ok().and_then(|hash| hash.try_into().ok()).ok_or(format!("Invalid hash {}", hash))?, None),
+        None => {
+            let preimage: [u8; 32] = htlc::generate_preimage();
+            (htlc::hash_preimage(&preimage), Some(preimage))
+        }
+    };
+    Ok((Htlc::new(hash, receiver, sender, timeout)?, preimage))
+}
+
+/**
+ * @notice claim_htlc() signs the claim of the HTLC output (given by its hex recipient and txid:vout) with the receiver 
+ * account or private key, revealing the hex preimage, and pays it to the (recipient, amount) pairs of outputs.
+ */
+pub fn claim_htlc(receiver: &str, recipient: &str, outpoint: &str, preimage: &str, outputs: &[String]) -> Result<UTXOTransaction, String> {
+    let htlc: Htlc = Htlc::from_recipient(&hex::decode(recipient).map_err(|e| format!("Invalid HTLC: {}", e))?)?;
+    let secret_key: SecretKey = SecretKey::from_str(&resolve_private_key(receiver)?).map_err(|e| e.to_string())?;
+    let preimage: Vec<u8> = hex::decode(preimage).map_err(|e| format!("Invalid preimage: {}", e))?;
+    htlc.claim(parse_outpoint(outpoint)?, parse_outputs(outputs)?, &preimage, &secret_key, unix_time())
+}
+
+/**
+ * @notice refund_htlc() signs the refund of the HTLC output (given by its hex recipient and txid:vout) with the sender 
+ * account or private key, paying it to the (recipient, amount) pairs of outputs. It is valid from the timeout.
+ */
+pub fn refund_htlc(sender: &str, recipient: &str, outpoint: &str, outputs: &[String]) -> Result<UTXOTransaction, String> {
+    let htlc: Htlc = Htlc::from_recipient(&hex::decode(recipient).map_err(|e| format!("Invalid HTLC: {}", e))?)?;
+    let secret_key: SecretKey = SecretKey::from_str(&resolve_private_key(sender)?).map_err(|e| e.to_string())?;
+    htlc.refund(parse_outpoint(outpoint)?, parse_outputs(outputs)?, &secret_key, unix_time())
+}
+
+/**
+ * @notice claimed_preimage() returns the hex preimage revealed by the JSON of an input that claimed an HTLC, e.g. as 
+ * returned by the getBlock JSON-RPC method, so the other side of a swap can claim theirs.
+ */
+pub fn claimed_preimage(input: &str) -> Result<String, String> {
+    let input: TxInput = serde_json::from_str(input).map_err(|e| format!("Invalid input: {}", e))?;
+    htlc::revealed_preimage(&input).map(hex::encode).ok_or("The input does not claim an HTLC".to_string())
+}
+
+/**
+ * @notice build_payment() builds and signs a UTXO transaction paying the amount to the recipient (an address or hex 
+ * public key) from the UTXOs of a key. The inputs are picked by the coin selection strategy (see coin_selection.rs), 
+ * the fee is estimated from the size of the signed transaction and change is paid back to the address of the key. The 
+ * privacy strategy also shuffles the outputs, so the change cannot be told from the payment by its position. Returns 
+ * the transaction and its fee.
+ */
+pub fn build_payment(secret_key: &SecretKey, utxos: &[(OutPoint, UTXO)], recipient: &str, amount: u64, strategy: SelectionStrategy) -> Result<(UTXOTransaction, u64), String> {
+    if amount < DUST_THRESHOLD { return Err(format!("Payments must be at least the dust threshold of {}", DUST_THRESHOLD)); }
+    let payment: TxOutput = TxOutput::pay_to(amount, recipient)?;
+    let public_key: Vec<u8> = PublicKey::from_secret_key(&Secp256k1::new(), secret_key).serialize().to_vec();
+    let change_recipient: Vec<u8> = address::hash160(&public_key).to_vec();
+    let timestamp: u64 = unix_time();
+
+    // only UTXOs paid to the key or its address can be signed for by the key alone, and only plain ones can be selected
+    // by amount
+    let spendable: Vec<(OutPoint, UTXO)> = utxos.iter()
+        .filter(|(_, utxo)| address::recipient_matches(&utxo.recipient, &public_key) && utxo.commitment.is_none())
+        .cloned()
+        .collect();
+
+    // the fee of a number of inputs and outputs is that of a transaction of the same shape
+    let fee = |inputs: usize, outputs: usize| {
+        let input = TxInput::new(OutPoint::new(vec![0; 32], 0), String::new(), public_key.clone());
+        let outputs: Vec<TxOutput> = [payment.clone(), TxOutput::new(0, change_recipient.clone())][..outputs].to_vec();
+        coin_selection::estimate_fee(&UTXOTransaction::new(vec![input; inputs], outputs, timestamp))
+    };
+    let selection: Selection = coin_selection::select_coins(&spendable, amount, strategy, fee)?;
+
+    let inputs: Vec<TxInput> = selection.utxos.iter()
+        .map(|(outpoint, _)| TxInput::new(outpoint.clone(), String::new(), public_key.clone()))
+        .collect();
+    let mut outputs: Vec<TxOutput> = vec![payment];
+    if selection.change > 0 { outputs.push(TxOutput::new(selection.change, change_recipient)); }
+    if strategy == SelectionStrategy::Privacy { outputs.shuffle(&mut rand::thread_rng()); }
+
+    let mut transaction = UTXOTransaction::new(inputs, outputs, timestamp);
+    for input_index in 0..transaction.inputs.len() {
+        transaction.sign_input(input_index, secret_key, SIGHASH_ALL)?;
+    }
+    Ok((transaction, selection.fee))
+}
+
+/**
+ * @notice confidential_payment() builds a confidential payment (see build_confidential_payment()) from the sender, a 
+ * wallet account name or a private key. The inputs are given as a comma separated list of txid:vout:amount for plain 
+ * UTXOs and txid:vout:amount:blinding (the opening printed when the output was created) for confidential ones.
+ */
+pub fn confidential_payment(sender: &str, inputs: &str, recipient: &str, amount: &str, fee: Option<&String>) -> Result<(UTXOTransaction, Vec<Opening>), String> {
+    let secret_key: SecretKey = SecretKey::from_str(&resolve_private_key(sender)?).map_err(|e| e.to_string())?;
+    let inputs: Vec<(OutPoint, Opening)> = inputs.split(',').map(parse_opened_outpoint).collect::<Result<_, _>>()?;
+    let amount: u64 = amount.parse().map_err(|e| format!("Invalid amount: {}", e))?;
+    let fee: Option<u64> = fee.map(|fee| fee.parse().map_err(|e| format!("Invalid fee: {}", e))).transpose()?;
+    build_confidential_payment(&secret_key, &inputs, recipient, amount, fee)
+}
+
+/**
+ * @notice build_confidential_payment() builds and signs a UTXO transaction paying the amount to the recipient (an address 
+ * or hex public key) in a confidential output (see confidential.rs), spending the opened UTXOs of a key. The rest, less 
+ * the fee, is paid back to the address of the key in a confidential change output, whose blinding factor is chosen so 
+ * the commitments balance. The fee is paid explicitly and defaults to the estimate for the size of the transaction. 
+ * Returns the transaction and the openings of its outputs, which are needed to spend them: the recipient must be given 
+ * the opening of the payment.
+ */
+pub fn build_confidential_payment(secret_key: &SecretKey, inputs: &[(OutPoint, Opening)], recipient: &str, amount: u64, fee: Option<u64>) -> Result<(UTXOTransaction, Vec<Opening>), String> {
+    let recipient: Vec<u8> = address::parse_recipient(recipient)?.into_bytes();
+    let public_key: Vec<u8> = PublicKey::from_secret_key(&Secp256k1::new(), secret_key).serialize().to_vec();
+    let change_recipient: Vec<u8> = address::hash160(&public_key).to_vec();
+    let timestamp: u64 = unix_time();
+
+    let tx_inputs: Vec<TxInput> = inputs.iter()
+        .map(|(outpoint, _)| TxInput::new(outpoint.clone(), String::new(), public_key.clone()))
+        .collect();
+    let input_amount: u64 = inputs.iter().try_fold(0u64, |total, (_, opening)| total.checked_add(opening.amount))
+        .ok_or("Input amount overflows".to_string())?;
+    let input_blinding: Scalar = inputs.iter().map(|(_, opening)| opening.blinding).sum();
+
+    // the size of a confidential output does not depend on its amount, so the fee is that of any payment with change
+    let fee: u64 = match fee {
+        Some(fee) => fee,
+        None => {
+            let placeholder = TxOutput::confidential(&Opening { amount: 0, blinding: Scalar::zero() }, change_recipient.clone());
+            let outputs: Vec<TxOutput> = vec![TxOutput { recipient: recipient.clone(), ..placeholder.clone() }, placeholder];
+            coin_selection::estimate_fee(&UTXOTransaction::new(tx_inputs.clone(), outputs, timestamp))
+        }
+    };
+    let change: u64 = amount.checked_add(fee).and_then(|spent| input_amount.checked_sub(spent))
+        .ok_or(format!("Insufficient funds: {} available, {} plus a fee of {} needed", input_amount, amount, fee))?;
+
+    // the blinding factors of the outputs add up to those of the inputs, so the commitments balance
+    let openings: Vec<Opening> = match change {
+        0 => vec![Opening { amount, blinding: input_blinding }],
+        _ => {
+            let payment = Opening { amount, blinding: confidential::random_blinding() };
+            vec![payment, Opening { amount: change, blinding: input_blinding - payment.blinding }]
+        }
+    };
+    let outputs: Vec<TxOutput> = openings.iter().zip([recipient, change_recipient])
+        .map(|(opening, recipient)| TxOutput::confidential(opening, recipient))
+        .collect();
+
+    let mut transaction = UTXOTransaction::new(tx_inputs, outputs, timestamp).with_explicit_fee(fee);
+    transaction.check_structure().map_err(|rejection| rejection.to_string())?;
+    for input_index in 0..transaction.inputs.len() {
+        transaction.sign_input(input_index, secret_key, SIGHASH_ALL)?;
+    }
+    Ok((transaction, openings))
+}
+
+/**
+ * @notice stealth_keys() returns the stealth keys of a wallet account or private key, whose key is the spend key.
+ */
+pub fn stealth_keys(name: &str) -> Result<StealthKeys, String> {
+    StealthKeys::from_spend_key(SecretKey::from_str(&resolve_private_key(name)?).map_err(|e| e.to_string())?)
+}
+
+// Parses a UTXO given as txid:vout:amount, or txid:vout:amount:blinding for a confidential one
+fn parse_opened_outpoint(input: &str) -> Result<(OutPoint, Opening), String> {
+    let parts: Vec<&str> = input.splitn(3, ':').collect();
+    let [txid, vout, opening] = parts[..] else { return Err(format!("UTXOs must be given as txid:vout:amount[:blinding], not {}", input)) };
+    let opening: Opening = match opening.contains(':') {
+        true => opening.parse()?,
+        false => Opening { amount: opening.parse().map_err(|e| format!("Invalid amount {}: {}", opening, e))?, blinding: Scalar::zero() },
+    };
+    Ok((parse_outpoint(&format!("{}:{}", txid, vout))?, opening))
+}
+
+// Parses an outpoint given as txid:vout
+fn parse_outpoint(outpoint: &str) -> Result<OutPoint, String> {
+    match outpoint.split_once(':') {
+        Some((txid, vout)) => Ok(OutPoint::new(
+            hex::decode(txid).map_err(|e| format!("Invalid txid {}: {}", txid, e))?,
+            vout.parse().map_err(|e| format!("Invalid vout {}: {}", vout, e))?,
+        )),
+        None => Err(format!("Outpoints must be given as txid:vout, not {}", outpoint)),
+    }
+}
+
+// Parses outputs given as recipient amount pairs
+fn parse_outputs(outputs: &[String]) -> Result<Vec<TxOutput>, String> {
+    if outputs.is_empty() || !outputs.len().is_multiple_of(2) { return Err("Outputs must be given as recipient amount pairs".to_string()); }
+    outputs.chunks(2)
+        .map(|output| TxOutput::pay_to(output[1].parse().map_err(|e| format!("Invalid amount {}: {}", output[1], e))?, &output[0]))
+        .collect()
+}
+
+fn unix_time() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()
+}
+
+fn load_psbt(path: &Path) -> Result<PartiallySignedTransaction, String> {
+    let json: String = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid partially signed transaction {}: {}", path.display(), e))
+}
+
+fn save_psbt(path: &Path, psbt: &PartiallySignedTransaction) -> Result<(), String> {
+    fs::write(path, serde_json::to_string_pretty(psbt).unwrap()).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/**
+ * @notice read_new_password() reads the password for a new keystore entry, asking for it twice when prompting.
+ */
+fn read_new_password() -> Result<String, String> {
+    let password: String = read_password("New password: ")?;
+    if std::env::var(WALLET_PASSWORD_ENV).is_err() && read_password("Repeat password: ")? != password {
+        return Err("Passwords do not match".to_string());
+    }
+    Ok(password)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // cheap scrypt cost so tests run quickly
+    const TEST_LOG_N: u8 = 4;
+
+    #[test]
+    fn test_encrypt_and_decrypt_key() {
+        let (secret_key, _) = crate::modules::zk_proof::generate_keypair().unwrap();
+        let crypto = encrypt_key(&secret_key, "hunter2", b"account", TEST_LOG_N).unwrap();
+
+        assert_eq!(decrypt_key(&crypto, "hunter2", b"account").unwrap(), secret_key);
+        assert!(decrypt_key(&crypto, "hunter3", b"account").is_err());
+        assert!(decrypt_key(&crypto, "hunter2", b"other account").is_err());
+
+        // the private key never appears in the clear
+        assert!(!crypto.ciphertext.contains(&secret_key.to_string()));
+    }
+
+    #[test]
+    fn test_keystore_accounts() {
+        let path = std::env::temp_dir().join(format!("keystore_test_{}.json", std::process::id()));
+        let (secret_key, public_key) = crate::modules::zk_proof::generate_keypair().unwrap();
+
+        let mut keystore = Keystore::load(&path).unwrap();
+        keystore.add("alice", &secret_key, "hunter2", TEST_LOG_N).unwrap();
+        assert!(keystore.add("alice", &secret_key, "hunter2", TEST_LOG_N).is_err());
+        keystore.save(&path).unwrap();
+
+        let keystore = Keystore::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(keystore.get("alice").unwrap().public_key, public_key.to_string());
+        assert_eq!(keystore.unlock("alice", "hunter2").unwrap(), secret_key);
+        assert!(keystore.unlock("bob", "hunter2").is_err());
+
+        // an encrypted key moved to another entry does not decrypt
+        let mut swapped = keystore.clone();
+        swapped.accounts[0].name = "mallory".to_string();
+        assert!(swapped.unlock("mallory", "hunter2").is_err());
+    }
+
+    #[test]
+    fn test_keys_derived_from_seed_are_restorable() {
+        let seed = hd_keys::mnemonic_to_seed(&hd_keys::generate_mnemonic(12).unwrap(), "").unwrap();
+
+        let mut keystore = Keystore::default();
+        assert!(keystore.derive("alice", false, "hunter2", TEST_LOG_N).is_err());
+        keystore.set_seed(&seed, "hunter2", TEST_LOG_N).unwrap();
+        assert!(keystore.unlock_seed("hunter3").is_err());
+
+        let alice = keystore.derive("alice", false, "hunter2", TEST_LOG_N).unwrap().clone();
+        let receive = keystore.derive("receive/0", true, "hunter2", TEST_LOG_N).unwrap().clone();
+        let bob = keystore.derive("bob", false, "hunter2", TEST_LOG_N).unwrap().clone();
+        assert_eq!(bob.derivation_path, Some(hd_keys::account_key_path(1)));
+        assert_ne!(alice.public_key, receive.public_key);
+
+        // the same seed derives the same keys in a fresh keystore
+        let mut restored = Keystore::default();
+        restored.set_seed(&seed, "other password", TEST_LOG_N).unwrap();
+        assert_eq!(restored.derive("account/0", false, "other password", TEST_LOG_N).unwrap().public_key, alice.public_key);
+        assert_eq!(restored.derive("receive/0", true, "other password", TEST_LOG_N).unwrap().public_key, receive.public_key);
+    }
+
+    #[test]
+    fn test_build_payment() {
+        let (secret_key, public_key) = crate::modules::zk_proof::generate_keypair().unwrap();
+        let (_, recipient) = crate::modules::zk_proof::generate_keypair().unwrap();
+        let public_key: Vec<u8> = public_key.serialize().to_vec();
+
+        // UTXOs paid to the key and to its address are spent, those of other keys never are
+        let utxos: Vec<(OutPoint, UTXO)> = vec![
+            (OutPoint::new(vec![1; 32], 0), UTXO::new(60, public_key.clone(), 1, 12345)),
+            (OutPoint::new(vec![2; 32], 0), UTXO::new(50, address::hash160(&public_key).to_vec(), 1, 12345)),
+            (OutPoint::new(vec![3; 32], 0), UTXO::new(1000, recipient.serialize().to_vec(), 1, 12345)),
+        ];
+        let mut utxo_set = crate::modules::utxo::UTXOSet::new();
+        for (outpoint, utxo) in &utxos { utxo_set.add_utxo(outpoint.clone(), utxo.clone()); }
+
+        let (transaction, fee) = build_payment(&secret_key, &utxos, &recipient.to_string(), 80, SelectionStrategy::LargestFirst).unwrap();
+        assert_eq!(transaction.check_consensus_rules(&utxo_set), Ok(fee));
+        assert_eq!(fee, coin_selection::estimate_fee(&transaction));
+        assert!((0..transaction.inputs.len()).all(|input_index| transaction.verify_input(input_index).is_ok()));
+        assert_eq!(transaction.outputs[0], TxOutput::new(80, recipient.serialize().to_vec()));
+        assert_eq!(transaction.outputs[1], TxOutput::new(110 - 80 - fee, address::hash160(&public_key).to_vec()));
+
+        assert!(build_payment(&secret_key, &utxos, &recipient.to_string(), 200, SelectionStrategy::LargestFirst).is_err());
+        assert!(build_payment(&secret_key, &utxos, &recipient.to_string(), DUST_THRESHOLD - 1, SelectionStrategy::LargestFirst).is_err());
+    }
+
+    #[test]
+    fn test_confidential_payment() {
+        let (secret_key, public_key) = crate::modules::zk_proof::generate_keypair().unwrap();
+        let (_, recipient) = crate::modules::zk_proof::generate_keypair().unwrap();
+        let owner: Vec<u8> = address::hash160(&public_key.serialize()).to_vec();
+        let mut utxo_set = crate::modules::utxo::UTXOSet::new();
+        utxo_set.add_utxo(OutPoint::new(vec![1; 32], 0), UTXO::new(500, owner.clone(), 1, 12345));
+
+        // hide a plain UTXO behind a payment and a change commitment
+        let inputs: Vec<(OutPoint, Opening)> = vec![parse_opened_outpoint(&format!("{}:0:500", hex::encode([1; 32]))).unwrap()];
+        let (transaction, openings) = build_confidential_payment(&secret_key, &inputs, &recipient.to_string(), 120, None).unwrap();
+        let fee: u64 = transaction.explicit_fee;
+        assert_eq!(transaction.check_consensus_rules(&utxo_set), Ok(fee));
+        assert!(crate::modules::verifier::verify_range_proofs(&transaction).is_ok());
+        assert!((0..transaction.inputs.len()).all(|input_index| transaction.verify_input(input_index).is_ok()));
+        assert_eq!(openings.iter().map(|opening| opening.amount).collect::<Vec<u64>>(), vec![120, 500 - 120 - fee]);
+        assert!(transaction.outputs.iter().all(|output| output.amount == 0));
+
+        // the printed opening of the change spends it, and spending it all leaves no change
+        utxo_set.apply_transaction(&transaction, 2).unwrap();
+        let change = format!("{}:1:{}", hex::encode(&transaction.hash), openings[1]);
+        let inputs: Vec<(OutPoint, Opening)> = vec![parse_opened_outpoint(&change).unwrap()];
+        let (spend, spend_openings) = build_confidential_payment(&secret_key, &inputs, &recipient.to_string(), openings[1].amount - 30, Some(30)).unwrap();
+        assert_eq!(spend.check_consensus_rules(&utxo_set), Ok(30));
+        assert_eq!(spend_openings.len(), 1);
+
+        // a fee the inputs cannot cover, or an opening of the wrong amount, fails
+        assert!(build_confidential_payment(&secret_key, &inputs, &recipient.to_string(), openings[1].amount, Some(1)).is_err());
+        let (outpoint, opening) = inputs[0].clone();
+        let wrong = vec![(outpoint, Opening { amount: opening.amount + 1, ..opening })];
+        let (unbalanced, _) = build_confidential_payment(&secret_key, &wrong, &recipient.to_string(), 10, Some(30)).unwrap();
+        assert_eq!(unbalanced.check_consensus_rules(&utxo_set), Err(crate::modules::utxo::TxRejection::UnbalancedCommitments));
+    }
+}
@@ -1,158 +1,358 @@
 use secp256k1::{SecretKey, PublicKey, Secp256k1, Message, Signature};
-use sha2::{Digest, Sha256};
+use sha2::{Digest, Sha256, Sha512};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
 use std::io;
+use std::fs;
 use rand::thread_rng;
 use rand::RngCore;
 use tokio::sync::Mutex;
 use std::sync::Arc;
 
-use crate::modules::constants::INTEGRATION_TEST;
-use crate::modules::validation::ValidatorNode;
+use crate::modules::blockchain::chain_id;
+use crate::modules::encoding::{Encoder, SIGNED_MESSAGE_TAG};
+use crate::modules::verifier::secp_context;
 
 /**
- * @notice zk_proof.rs contains the logic for generating and verifying digital signatures
- * for transaction authentication. Instead of zero-knowledge proofs, we use standard
- * ECDSA signatures with the secp256k1 curve.
+ * @notice zk_proof.rs contains the logic for proving ownership of an account without revealing anything that could be
+ * reused to spend from it.
  * 
- * Protocol: 
- *    When an account is created, the public key is derived from the private key and stored.
- *    When a transaction is requested, the sender signs the transaction details with their
- *    private key. Validators verify the signature using the sender's public key.
+ * Protocol (account model): 
+ *    When an account is created, a Schnorr secret x is derived from the secp256k1 private key and the commitment point 
+ *    P = x·G on the Ristretto group (curve25519) is registered alongside the public key. The owner signs the commitment 
+ *    with their secp256k1 key so that no one else can register a commitment for their public key.
+ * 
+ *    When a transfer is requested, the sender produces a non-interactive Schnorr proof of knowledge of x: a random 
+ *    nonce point R = k·G, the Fiat-Shamir challenge c = H(P, R, transaction message) and the response s = k + c·x. 
+ *    Validators check s·G == R + c·P against the stored commitment. The transaction message covers the sender, 
+ *    recipient, amount, fee and nonce, so a proof cannot be moved to a different transfer.
+ * 
+ * Protocol (UTXO model):
+ *    Inputs are authorised with standard ECDSA signatures over secp256k1 on the transaction sighash (see 
+ *    UTXOTransaction::sign_input() in utxo.rs).
  *    
- *    All previous signatures are tracked to prevent replay attacks.
+ * Domain separation and replay protection:
+ *    Every signed message starts with the tag of its SignatureDomain followed by the chain id (see signed_message()), so
+ *    a signature of one type of message is never valid as another type, nor on another network. Within 
+ *    a network, validators only accept a transfer whose nonce equals the sender's current nonce, which is incremented 
+ *    when the transfer is written to the ledger. A proof can therefore be accepted at most once without storing it.
  */
 
+// domain separation tags for the Schnorr proof of knowledge
+const SCHNORR_SECRET_TAG: &[u8] = b"miniBlockChain/schnorr/secret";
+const SCHNORR_NONCE_TAG: &[u8] = b"miniBlockChain/schnorr/nonce";
+const SCHNORR_CHALLENGE_TAG: &[u8] = b"miniBlockChain/schnorr/challenge";
+
+/**
+ * @notice SignatureDomain is the type of a signed message. Its tag is the first thing hashed into the message.
+ * @param Transfer - an account-model transfer, proven with a Schnorr proof
+ * @param UtxoInput - the sighash of a UTXO transaction input, signed with ECDSA (see utxo.rs)
+ * @param AccountCommitment - the Schnorr commitment registered for a new account, signed with ECDSA
+ * @param AccountToUtxo - the burn of an account balance into a UTXO (see bridge.rs), proven with a Schnorr proof
+ * @param Vote - the response of a validator to a consensus request (see consensus.rs), signed with its node key
+ * @param Handshake - the heartbeat a validator announces its port address with, signed with its node key
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureDomain {
+    Transfer,
+    UtxoInput,
+    AccountCommitment,
+    AccountToUtxo,
+    Vote,
+    Handshake,
+}
+
+impl SignatureDomain {
+
+    // Returns the domain separation tag of the message type
+    pub fn tag(&self) -> &'static [u8] {
+        match self {
+            SignatureDomain::Transfer => b"miniBlockChain/transfer",
+            SignatureDomain::UtxoInput => b"miniBlockChain/utxo-input",
+            SignatureDomain::AccountCommitment => b"miniBlockChain/account-commitment",
+            SignatureDomain::AccountToUtxo => b"miniBlockChain/account-to-utxo",
+            SignatureDomain::Vote => b"miniBlockChain/vote",
+            SignatureDomain::Handshake => b"miniBlockChain/handshake",
+        }
+    }
+}
+
+/**
+ * @notice signed_message() starts the canonical encoding of a signed message (see encoding.rs) with its domain tag and
+ * the chain id of the network. The caller writes the fields of the message and signs Encoder::hash() of the result.
+ */
+pub fn signed_message(domain: SignatureDomain) -> Encoder {
+    let mut message = Encoder::top_level(SIGNED_MESSAGE_TAG);
+    message.bytes(domain.tag()).str(chain_id());
+    message
+}
+
 /**
  * @notice create_transaction_message() creates a message hash from transaction details
- * that will be signed by the sender's private key.
+ * that will be signed by the sender's private key. The fee is included so that it cannot
+ * be altered by a relaying node without invalidating the signature. The message is tagged 
+ * with the Transfer domain and the chain id, so it is not valid on any other network.
  */
 fn create_transaction_message(
     sender_public_key: &str,
     recipient_public_key: &str, 
     amount: &str,
+    fee: &str,
     nonce: u64
 ) -> Vec<u8> {
-    let mut hasher = Sha256::new();
-    hasher.update(sender_public_key.as_bytes());
-    hasher.update(recipient_public_key.as_bytes());
-    hasher.update(amount.as_bytes());
-    hasher.update(nonce.to_le_bytes());
-    hasher.finalize().to_vec()
+    let mut message = signed_message(SignatureDomain::Transfer);
+    message.str(sender_public_key).str(recipient_public_key).str(amount).str(fee).u64(nonce);
+    message.hash().to_vec()
+}
+
+//------------------------------------ Schnorr Proof of Knowledge ------------------------------------//
+
+/**
+ * @notice hash_to_scalar() hashes a domain tag and a list of byte strings into a scalar of the Ristretto group.
+ * @dev The 64 byte SHA512 output is reduced modulo the group order, which keeps the bias negligible.
+ */
+fn hash_to_scalar(tag: &[u8], parts: &[&[u8]]) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(tag);
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    let digest: [u8; 64] = hasher.finalize().into();
+    Scalar::from_bytes_mod_order_wide(&digest)
+}
+
+/**
+ * @notice derive_schnorr_secret() deterministically derives the Schnorr secret x of an account from its hex encoded 
+ * secp256k1 private key, so that no extra key material needs to be stored by the owner.
+ */
+fn derive_schnorr_secret(private_key: &str) -> Result<Scalar, String> {
+    let private_key_bytes: Vec<u8> = hex::decode(private_key).map_err(|e| format!("Failed to decode private key: {}", e))?;
+    SecretKey::from_slice(&private_key_bytes).map_err(|e| format!("Invalid private key: {}", e))?;
+    Ok(hash_to_scalar(SCHNORR_SECRET_TAG, &[&private_key_bytes]))
+}
+
+/**
+ * @notice derive_schnorr_commitment() returns the compressed commitment point P = x·G of the account owning the given 
+ * private key. This is the value registered with the account at creation.
+ */
+pub fn derive_schnorr_commitment(private_key: &str) -> Result<Vec<u8>, String> {
+    let secret: Scalar = derive_schnorr_secret(private_key)?;
+    Ok((RISTRETTO_BASEPOINT_POINT * secret).compress().to_bytes().to_vec())
+}
+
+/**
+ * @notice decode_commitment() parses a compressed commitment point, rejecting invalid encodings and the identity.
+ */
+fn decode_commitment(commitment: &[u8]) -> Option<RistrettoPoint> {
+    if commitment.len() != 32 { return None; }
+    let point: RistrettoPoint = CompressedRistretto::from_slice(commitment).decompress()?;
+    if point.is_identity() { None } else { Some(point) }
+}
+
+/**
+ * @notice create_schnorr_proof() creates a non-interactive Schnorr proof of knowledge of the secret behind the account 
+ * commitment, bound to the given message through the Fiat-Shamir challenge.
+ * @dev The nonce k mixes fresh randomness with the secret and the message, so a weak RNG alone cannot leak the secret.
+ * @return the proof as hex encoded R || s (64 bytes)
+ */
+pub fn create_schnorr_proof(private_key: &str, message: &[u8]) -> Result<String, String> {
+    let secret: Scalar = derive_schnorr_secret(private_key)?;
+    let commitment: CompressedRistretto = (RISTRETTO_BASEPOINT_POINT * secret).compress();
+
+    // commit to a random nonce point
+    let mut randomness = [0u8; 64];
+    thread_rng().fill_bytes(&mut randomness);
+    let nonce: Scalar = hash_to_scalar(SCHNORR_NONCE_TAG, &[secret.as_bytes(), message, &randomness]);
+    let nonce_point: CompressedRistretto = (RISTRETTO_BASEPOINT_POINT * nonce).compress();
+
+    // derive the challenge and the response
+    let challenge: Scalar = hash_to_scalar(SCHNORR_CHALLENGE_TAG, &[commitment.as_bytes(), nonce_point.as_bytes(), message]);
+    let response: Scalar = nonce + challenge * secret;
+
+    let mut proof: Vec<u8> = nonce_point.to_bytes().to_vec();
+    proof.extend_from_slice(response.as_bytes());
+    Ok(hex::encode(proof))
 }
 
 /**
- * @notice sign_transaction() accepts transaction details and a private key, 
- * creates a signature for the transaction.
+ * @notice verify_schnorr_proof() checks a Schnorr proof against a compressed account commitment and the message it 
+ * must be bound to, by checking s·G == R + c·P.
  */
-pub fn sign_transaction(
-    private_key: &String,
-    sender_public_key: &String,
-    recipient_public_key: &String,
-    amount: &String,
+pub fn verify_schnorr_proof(proof_hex: &str, commitment: &[u8], message: &[u8]) -> bool {
+
+    // parse the commitment and the proof
+    let commitment_point: RistrettoPoint = match decode_commitment(commitment) {
+        Some(point) => point,
+        None => return false,
+    };
+    let proof: Vec<u8> = match hex::decode(proof_hex) {
+        Ok(proof) if proof.len() == 64 => proof,
+        _ => return false,
+    };
+    let nonce_point: RistrettoPoint = match CompressedRistretto::from_slice(&proof[..32]).decompress() {
+        Some(point) => point,
+        None => return false,
+    };
+    let mut response_bytes = [0u8; 32];
+    response_bytes.copy_from_slice(&proof[32..]);
+    let response: Scalar = match Scalar::from_canonical_bytes(response_bytes) {
+        Some(response) => response,
+        None => return false,
+    };
+
+    // recompute the challenge and check the verification equation
+    let challenge: Scalar = hash_to_scalar(SCHNORR_CHALLENGE_TAG, &[commitment, &proof[..32], message]);
+    RISTRETTO_BASEPOINT_POINT * response == nonce_point + commitment_point * challenge
+}
+
+/**
+ * @notice create_transaction_proof() creates the Schnorr proof authorising an account-model transfer. The proof is bound
+ * to the sender, recipient, amount, fee and nonce of the transfer.
+ */
+pub fn create_transaction_proof(
+    private_key: &str,
+    sender_public_key: &str,
+    recipient_public_key: &str,
+    amount: &str,
+    fee: &str,
     nonce: u64
 ) -> Result<String, String> {
-    println!("zk_proof::sign_transaction() : Signing transaction with private key..."); 
+    println!("zk_proof::create_transaction_proof() : Proving knowledge of the account secret...");
 
-    // Create secp256k1 context
-    let secp = Secp256k1::new();
-    
-    // Convert private key from hex string to SecretKey
-    let secret_key = SecretKey::from_slice(&hex::decode(private_key)
-        .map_err(|e| format!("Failed to decode private key: {}", e))?)
-        .map_err(|e| format!("Invalid private key: {}", e))?;
-    
-    // Create message hash from transaction details
-    let message_bytes = create_transaction_message(sender_public_key, recipient_public_key, amount, nonce);
-    let message = Message::from_slice(&message_bytes)
-        .map_err(|e| format!("Failed to create message: {}", e))?;
-    
-    // Sign the message
-    let signature = secp.sign(&message, &secret_key);
-    
-    // Return signature as hex string
-    Ok(hex::encode(signature.serialize_compact()))
+    let message: Vec<u8> = create_transaction_message(sender_public_key, recipient_public_key, amount, fee, nonce);
+    create_schnorr_proof(private_key, &message)
 }
 
 /**
- * @notice verify_transaction_signature() verifies that a signature is valid for the given
- * transaction details and sender's public key.
+ * @notice verify_transaction_proof() verifies the Schnorr proof of an account-model transfer against the commitment 
+ * registered for the sender.
+ * @dev Replays are rejected by the caller, which must pass the sender's current nonce from the merkle tree.
  */
-pub async fn verify_transaction_signature(
-    signature_hex: &str,
+pub fn verify_transaction_proof(
+    proof_hex: &str,
+    commitment: &[u8],
     sender_public_key_hex: &str,
     recipient_public_key: &str,
     amount: &str,
-    nonce: u64,
-    validator_node: ValidatorNode
+    fee: &str,
+    nonce: u64
 ) -> bool {
-    println!("zk_proof::verify_transaction_signature() : Verifying transaction signature..."); 
+    println!("zk_proof::verify_transaction_proof() : Verifying proof of knowledge...");
 
-    // Create secp256k1 context
-    let secp = Secp256k1::new();
-    
-    // Parse signature from hex
-    let signature_bytes = match hex::decode(signature_hex) {
-        Ok(bytes) => bytes,
-        Err(_) => return false,
-    };
-    
-    let signature = match Signature::from_compact(&signature_bytes) {
-        Ok(sig) => sig,
-        Err(_) => return false,
-    };
-    
-    // Parse public key from hex
-    let public_key_bytes = match hex::decode(sender_public_key_hex) {
-        Ok(bytes) => bytes,
-        Err(_) => return false,
+    let message: Vec<u8> = create_transaction_message(sender_public_key_hex, recipient_public_key, amount, fee, nonce);
+    verify_schnorr_proof(proof_hex, commitment, &message)
+}
+
+/**
+ * @notice account_to_utxo_message() creates the message an AccountToUtxo transaction is proven over (see bridge.rs) with
+ * create_schnorr_proof() and verify_schnorr_proof(). It holds the same fields as a transfer, the recipient being that of 
+ * the minted output, and the timestamp the UTXO is minted with, but is tagged with the AccountToUtxo domain so that a 
+ * transfer proof can never be replayed as a burn or the other way around.
+ * @dev As for transfers, the verifier passes the sender's current nonce.
+ */
+pub fn account_to_utxo_message(sender_public_key: &str, recipient: &str, amount: &str, fee: &str, nonce: u64, timestamp: u64) -> Vec<u8> {
+    let mut message = signed_message(SignatureDomain::AccountToUtxo);
+    message.str(sender_public_key).str(recipient).str(amount).str(fee).u64(nonce).u64(timestamp);
+    message.hash().to_vec()
+}
+
+/**
+ * @notice commitment_signature_message() returns the digest signed by an account owner to register a commitment.
+ */
+fn commitment_signature_message(public_key_hex: &str, commitment: &[u8]) -> Vec<u8> {
+    let mut message = signed_message(SignatureDomain::AccountCommitment);
+    message.str(public_key_hex).bytes(commitment);
+    message.hash().to_vec()
+}
+
+/**
+ * @notice sign_account_commitment() signs the Schnorr commitment of a new account with its secp256k1 private key, 
+ * proving that the owner of the public key chose this commitment.
+ */
+pub fn sign_account_commitment(private_key: &str, public_key_hex: &str, commitment: &[u8]) -> Result<String, String> {
+    sign_message(private_key, &commitment_signature_message(public_key_hex, commitment))
+}
+
+/**
+ * @notice verify_account_commitment() checks that a commitment is a valid point and was signed by the owner of the 
+ * public key it is registered for.
+ */
+pub fn verify_account_commitment(public_key_hex: &str, commitment: &[u8], signature_hex: &str) -> bool {
+    decode_commitment(commitment).is_some()
+        && verify_message_signature(public_key_hex, &commitment_signature_message(public_key_hex, commitment), signature_hex)
+}
+
+//------------------------------------ Validator Messages ------------------------------------//
+
+/**
+ * @notice vote_message() returns the digest a validator signs its response to a consensus request over: the hash of the
+ * request, its decision and the commitment to its UTXO set.
+ */
+pub fn vote_message(request_hash: &[u8], decision: bool, utxo_commitment: &[u8]) -> Vec<u8> {
+    let mut message = signed_message(SignatureDomain::Vote);
+    message.bytes(request_hash).u8(decision as u8).bytes(utxo_commitment);
+    message.hash().to_vec()
+}
+
+/**
+ * @notice handshake_message() returns the digest a validator signs the heartbeat announcing its port address over, along 
+ * with the time the heartbeat was sent so that it cannot be replayed later.
+ */
+pub fn handshake_message(port_address: &str, timestamp: u64) -> Vec<u8> {
+    let mut message = signed_message(SignatureDomain::Handshake);
+    message.str(port_address).u64(timestamp);
+    message.hash().to_vec()
+}
+
+/**
+ * @notice read_node_key() reads the hex encoded private key a validator signs its votes and heartbeats with from a key
+ * file, and returns it along with its hex encoded public key.
+ */
+pub fn read_node_key(path: &str) -> Result<(String, String), String> {
+    let private_key: String = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read node key file {}: {}", path, e))?
+        .trim().to_string();
+    let secret_key = SecretKey::from_slice(&hex::decode(&private_key)
+        .map_err(|e| format!("Failed to decode node key: {}", e))?)
+        .map_err(|e| format!("Invalid node key: {}", e))?;
+    let public_key = PublicKey::from_secret_key(secp_context(), &secret_key);
+    Ok((private_key, hex::encode(public_key.serialize())))
+}
+
+/**
+ * @notice sign_message() signs the digest of a signed message (see signed_message()) with a hex encoded secp256k1 
+ * private key and returns the hex encoded compact ECDSA signature.
+ */
+pub fn sign_message(private_key: &str, message: &[u8]) -> Result<String, String> {
+    let secret_key = SecretKey::from_slice(&hex::decode(private_key)
+        .map_err(|e| format!("Failed to decode private key: {}", e))?)
+        .map_err(|e| format!("Invalid private key: {}", e))?;
+    let message = Message::from_slice(message)
+        .map_err(|e| format!("Failed to create message: {}", e))?;
+    Ok(hex::encode(secp_context().sign(&message, &secret_key).serialize_compact()))
+}
+
+/**
+ * @notice verify_message_signature() checks a signature made by sign_message() against the hex encoded public key of the
+ * signer. Malformed keys and signatures do not verify.
+ */
+pub fn verify_message_signature(public_key_hex: &str, message: &[u8], signature_hex: &str) -> bool {
+    let public_key = match hex::decode(public_key_hex).ok().and_then(|bytes| PublicKey::from_slice(&bytes).ok()) {
+        Some(public_key) => public_key,
+        None => return false,
     };
-    
-    let public_key = match PublicKey::from_slice(&public_key_bytes) {
-        Ok(pk) => pk,
-        Err(_) => return false,
+    let signature = match hex::decode(signature_hex).ok().and_then(|bytes| Signature::from_compact(&bytes).ok()) {
+        Some(signature) => signature,
+        None => return false,
     };
-    
-    // Create message hash from transaction details
-    let message_bytes = create_transaction_message(sender_public_key_hex, recipient_public_key, amount, nonce);
-    let message = match Message::from_slice(&message_bytes) {
-        Ok(msg) => msg,
+    let message = match Message::from_slice(message) {
+        Ok(message) => message,
         Err(_) => return false,
     };
-    
-    // Verify signature
-    if secp.verify(&message, &signature, &public_key).is_err() {
-        return false;
-    }
-    
-    // Check if signature has been used before (replay attack prevention)
-    let used_signatures = validator_node.used_zk_proofs.clone();
-    let mut used_signatures_guard = used_signatures.lock().await;
-    
-    let sender_address = sender_public_key_hex.as_bytes().to_vec();
-    
-    // Check if this signature has been used before
-    if let Some(used_sigs) = used_signatures_guard.get(&sender_address) {
-        if used_sigs.contains(&signature_hex.to_string()) {
-            println!("Signature has already been used, rejecting...");
-            
-            // Save rejection indicator for integration testing
-            if INTEGRATION_TEST { 
-                let proof_rejected = serde_json::to_string(&1).unwrap();
-                std::fs::write("proof_rejected.json", proof_rejected).expect("Unable to write file");
-            }
-            
-            return false;
-        }
-    }
-    
-    // Add signature to used signatures
-    if let Some(sigs) = used_signatures_guard.get_mut(&sender_address) {
-        sigs.push(signature_hex.to_string());
-    } else {
-        used_signatures_guard.insert(sender_address, vec![signature_hex.to_string()]);
-    }
-    
-    true
+    secp_context().verify(&message, &signature, &public_key).is_ok()
 }
 
 /**
@@ -211,84 +411,95 @@ mod tests {
     use super::*;
 
     /**
-     * @test test_sign_and_verify_transaction() verifies that signing and verifying transactions works correctly
+     * @test test_schnorr_transaction_proof() verifies that a transfer proof only verifies against the sender's commitment
+     * and the exact transfer it was created for
      */
-    #[tokio::test]
-    async fn test_sign_and_verify_transaction() {
-        // Generate keypair
+    #[test]
+    fn test_schnorr_transaction_proof() {
         let (secret_key, public_key) = generate_keypair().unwrap();
+        let (other_secret_key, _) = generate_keypair().unwrap();
         let private_key_hex = secret_key.to_string();
         let public_key_hex = hex::encode(public_key.serialize());
-        
-        // Transaction details
-        let recipient_public_key = "04abcd1234...";
-        let amount = "100";
-        let nonce = 1u64;
-        
-        // Sign transaction
-        let signature = sign_transaction(
-            &private_key_hex,
-            &public_key_hex,
-            &recipient_public_key.to_string(),
-            &amount.to_string(),
-            nonce
-        ).unwrap();
-        
-        // Create a mock validator node
-        let validator_node = ValidatorNode::new();
-        
-        // Verify signature
-        let is_valid = verify_transaction_signature(
-            &signature,
-            &public_key_hex,
-            recipient_public_key,
-            amount,
-            nonce,
-            validator_node
-        ).await;
-        
-        assert!(is_valid);
+        let commitment = derive_schnorr_commitment(&private_key_hex).unwrap();
+
+        let proof = create_transaction_proof(&private_key_hex, &public_key_hex, "02abcd", "100", "1", 3).unwrap();
+        let message = create_transaction_message(&public_key_hex, "02abcd", "100", "1", 3);
+        assert!(verify_schnorr_proof(&proof, &commitment, &message));
+
+        // the proof is bound to the amount and nonce of the transfer
+        assert!(!verify_schnorr_proof(&proof, &commitment, &create_transaction_message(&public_key_hex, "02abcd", "101", "1", 3)));
+        assert!(!verify_schnorr_proof(&proof, &commitment, &create_transaction_message(&public_key_hex, "02abcd", "100", "1", 4)));
+
+        // a different account's commitment does not verify
+        let other_commitment = derive_schnorr_commitment(&other_secret_key.to_string()).unwrap();
+        assert!(!verify_schnorr_proof(&proof, &other_commitment, &message));
+
+        // a tampered response does not verify
+        let mut tampered = hex::decode(&proof).unwrap();
+        tampered[40] ^= 1;
+        assert!(!verify_schnorr_proof(&hex::encode(tampered), &commitment, &message));
+
+        // the proof only verifies for the nonce it was made for
+        assert!(verify_transaction_proof(&proof, &commitment, &public_key_hex, "02abcd", "100", "1", 3));
+        assert!(!verify_transaction_proof(&proof, &commitment, &public_key_hex, "02abcd", "100", "1", 4));
+
+        // a transfer proof is not valid as the proof of a burn into a UTXO, and the other way around
+        let burn_message = account_to_utxo_message(&public_key_hex, "02abcd", "100", "1", 3, 1_700_000_000);
+        assert!(!verify_schnorr_proof(&proof, &commitment, &burn_message));
+        let burn_proof = create_schnorr_proof(&private_key_hex, &burn_message).unwrap();
+        assert!(verify_schnorr_proof(&burn_proof, &commitment, &burn_message));
+        assert!(!verify_transaction_proof(&burn_proof, &commitment, &public_key_hex, "02abcd", "100", "1", 3));
+
+        // the burn proof only verifies for the timestamp it was made for
+        let later_message = account_to_utxo_message(&public_key_hex, "02abcd", "100", "1", 3, 1_700_000_001);
+        assert!(!verify_schnorr_proof(&burn_proof, &commitment, &later_message));
     }
-    
+
+    /**
+     * @test test_account_commitment_signature() verifies that only the owner of a public key can register its commitment
+     */
+    #[test]
+    fn test_account_commitment_signature() {
+        let (secret_key, public_key) = generate_keypair().unwrap();
+        let (other_secret_key, _) = generate_keypair().unwrap();
+        let public_key_hex = hex::encode(public_key.serialize());
+        let commitment = derive_schnorr_commitment(&secret_key.to_string()).unwrap();
+
+        let signature = sign_account_commitment(&secret_key.to_string(), &public_key_hex, &commitment).unwrap();
+        assert!(verify_account_commitment(&public_key_hex, &commitment, &signature));
+
+        let forged = sign_account_commitment(&other_secret_key.to_string(), &public_key_hex, &commitment).unwrap();
+        assert!(!verify_account_commitment(&public_key_hex, &commitment, &forged));
+
+        // the identity point is not a valid commitment
+        assert!(!verify_account_commitment(&public_key_hex, &[0u8; 32], &signature));
+    }
+
     /**
-     * @test test_invalid_signature_rejection() verifies that invalid signatures are rejected
+     * @test test_validator_message_signatures() verifies that votes and handshakes only verify against the node key that
+     * signed them, for the exact content signed, and never as a message of another domain
      */
-    #[tokio::test]
-    async fn test_invalid_signature_rejection() {
-        // Generate two different keypairs
-        let (_, public_key1) = generate_keypair().unwrap();
-        let (secret_key2, _) = generate_keypair().unwrap();
-        
-        let public_key_hex = hex::encode(public_key1.serialize());
-        let private_key_hex = secret_key2.to_string(); // Wrong private key!
-        
-        // Transaction details
-        let recipient_public_key = "04abcd1234...";
-        let amount = "100";
-        let nonce = 1u64;
-        
-        // Sign with wrong private key
-        let signature = sign_transaction(
-            &private_key_hex,
-            &public_key_hex,
-            &recipient_public_key.to_string(),
-            &amount.to_string(),
-            nonce
-        ).unwrap();
-        
-        // Create a mock validator node
-        let validator_node = ValidatorNode::new();
-        
-        // Verify signature (should fail)
-        let is_valid = verify_transaction_signature(
-            &signature,
-            &public_key_hex,
-            recipient_public_key,
-            amount,
-            nonce,
-            validator_node
-        ).await;
-        
-        assert!(!is_valid);
+    #[test]
+    fn test_validator_message_signatures() {
+        let (secret_key, public_key) = generate_keypair().unwrap();
+        let (other_secret_key, _) = generate_keypair().unwrap();
+        let public_key_hex = hex::encode(public_key.serialize());
+
+        let vote = vote_message(&[1, 2, 3], true, &[4; 32]);
+        let signature = sign_message(&secret_key.to_string(), &vote).unwrap();
+        assert!(verify_message_signature(&public_key_hex, &vote, &signature));
+        assert!(!verify_message_signature(&public_key_hex, &vote_message(&[1, 2, 3], false, &[4; 32]), &signature));
+        assert!(!verify_message_signature(&public_key_hex, &vote_message(&[1, 2, 3], true, &[5; 32]), &signature));
+        assert!(!verify_message_signature(&public_key_hex, &vote, &sign_message(&other_secret_key.to_string(), &vote).unwrap()));
+        assert!(!verify_message_signature(&public_key_hex, &vote, "not a signature"));
+
+        let handshake = handshake_message("127.0.0.1:8081", 1_000);
+        let signature = sign_message(&secret_key.to_string(), &handshake).unwrap();
+        assert!(verify_message_signature(&public_key_hex, &handshake, &signature));
+        assert!(!verify_message_signature(&public_key_hex, &handshake_message("127.0.0.1:8082", 1_000), &signature));
+        assert!(!verify_message_signature(&public_key_hex, &handshake_message("127.0.0.1:8081", 1_005), &signature));
+
+        // the domain tag keeps messages of one type from being taken for another
+        assert_ne!(vote_message(&[], false, &[]), handshake_message("", 0));
     }
-}
\ No newline at end of file
+}
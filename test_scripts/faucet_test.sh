@@ -60,7 +60,7 @@ xterm -hold -e "bash -c 'cargo run validate'" &
 sleep 7
 
 # Make a new account (this will save the account details to new_account_details.json)
-cargo run make 
+SAVE_TEST_ACCOUNT_DETAILS=1 cargo run make
 
 # Check if the account details file was created successfully
 if [ -f "./new_account_details.json" ]; then
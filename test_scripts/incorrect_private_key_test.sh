@@ -5,7 +5,7 @@
 # the two accounts using the private key of the recipient instead of the sender. This should cause a failure
 # in the transaction and the block should not be added to the blockchain.
 #
-# Ensure that the INTEGRATION_TEST flag is set to true in constants.rs before running this script.
+# The script sets SAVE_TEST_ACCOUNT_DETAILS for `cargo run make` to save the new account details to new_account_details.json.
 
 # Ensure jq and xterm are installed
 if ! [ -x "$(command -v jq)" ]; then
@@ -67,7 +67,7 @@ for i in 1 2; do
     echo "Creating account $i..."
 
     # Make a new account (this will save the account details to new_account_details.json)
-    cargo run make 
+    SAVE_TEST_ACCOUNT_DETAILS=1 cargo run make
 
     # Check if the account details file was created successfully
     if [ -f "./new_account_details.json" ]; then
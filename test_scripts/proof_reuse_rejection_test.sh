@@ -92,13 +92,13 @@ sleep 5
 for json_entry in "${blockchain_data[@]}"; do
 
 
-    # Ensure that there are only 3 blocks in the chain
-    if [[ "$(echo "$json_entry" | jq -r 'length')" != 4 ]]; then
-        echo "Error: The blockchain does not contain 4 blocks."
+    # Ensure that there are only 5 blocks in the chain
+    if [[ "$(echo "$json_entry" | jq -r 'length')" != 5 ]]; then
+        echo "Error: The blockchain does not contain 5 blocks."
         killall xterm
         exit 1
     else
-        echo "Blockchain contains 4 blocks."
+        echo "Blockchain contains 5 blocks."
     fi
 
 
@@ -132,9 +132,18 @@ for json_entry in "${blockchain_data[@]}"; do
         echo "NewAccount block verified."
     fi
 
-    # Check if 'Transaction' is the fourth key
-    if [[ "$(echo "$json_entry" | jq -r '.[3] | keys[]')" != "Transaction" ]]; then
-        echo "Error: The fourth block is not 'Transaction'. It is '$(echo "$json_entry" | jq -r '.[3] | keys[]')'."
+    # Check if 'Faucet' is the fourth key (the sender is funded to pay the transaction fee)
+    if [[ "$(echo "$json_entry" | jq -r '.[3] | keys[]')" != "Faucet" ]]; then
+        echo "Error: The fourth block is not 'Faucet'. It is '$(echo "$json_entry" | jq -r '.[3] | keys[]')'."
+        killall xterm
+        exit 1
+    else
+        echo "Faucet block verified."
+    fi
+
+    # Check if 'Transaction' is the fifth key
+    if [[ "$(echo "$json_entry" | jq -r '.[4] | keys[]')" != "Transaction" ]]; then
+        echo "Error: The fifth block is not 'Transaction'. It is '$(echo "$json_entry" | jq -r '.[4] | keys[]')'."
         killall xterm
         exit 1
     else
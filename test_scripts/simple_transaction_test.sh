@@ -64,7 +64,7 @@ for i in 1 2; do
     echo "Creating account $i..."
 
     # Make a new account (this will save the account details to new_account_details.json)
-    cargo run make 
+    SAVE_TEST_ACCOUNT_DETAILS=1 cargo run make
 
     # Check if the account details file was created successfully
     if [ -f "./new_account_details.json" ]; then
@@ -227,13 +227,13 @@ for json_entry in "${blockchain_data[@]}"; do
         echo "Sender address matches the public key."
     fi
 
-    # check that the sender_balance in the Transaction block is 50
-    if [[ "$(echo "$json_entry" | jq -r '.[4].Transaction.sender_balance')" != 50 ]]; then
-        echo "Error: The sender balance in the Transaction block is not 50."
+    # check that the sender_balance in the Transaction block is 49 (50 sent + the default fee of 1)
+    if [[ "$(echo "$json_entry" | jq -r '.[4].Transaction.sender_balance')" != 49 ]]; then
+        echo "Error: The sender balance in the Transaction block is not 49."
         killall xterm
         exit 1
     else
-        echo "Sender balance is 50."
+        echo "Sender balance is 49."
     fi
 
     # Extract the recipient address and check against the expected public key
@@ -0,0 +1,108 @@
+use mini_block_chain::modules::{
+    blockchain::{chain_id, Block},
+    encoding::{self, Encoder, BLOCK_TAG, NETWORK_REQUEST_TAG, TRANSACTION_ID_TAG, COINBASE_ID_TAG, SIGNED_MESSAGE_TAG},
+    network,
+    requests::NetworkRequest,
+    utxo::{CoinbaseTransaction, OutPoint, RelativeLock, TxInput, TxOutput, UTXOTransaction},
+    zk_proof::{signed_message, SignatureDomain},
+};
+use serde_json::Value;
+
+// Test vectors for the canonical encoding (see encoding.rs). Each vector in tests/data/encoding_vectors.json holds the
+// hex of the top level encoding of a fixed value and the hex of its SHA256 hash. Any change to these bytes changes
+// block hashes, transaction ids and signatures, so it must come with a new ENCODING_VERSION and new vectors.
+
+// Checks an encoding and its hash against the vector with the given name
+fn assert_vector(name: &str, encoding: &[u8], hash: &[u8]) {
+    let vectors: Value = serde_json::from_str(include_str!("data/encoding_vectors.json")).unwrap();
+    let vector = vectors.as_array().unwrap().iter()
+        .find(|vector| vector["name"] == name)
+        .unwrap_or_else(|| panic!("missing vector {}: encoding {} hash {}", name, hex::encode(encoding), hex::encode(hash)));
+
+    assert_eq!(vector["encoding"], hex::encode(encoding), "encoding of {}", name);
+    assert_eq!(vector["hash"], hex::encode(hash), "hash of {}", name);
+}
+
+fn utxo_transaction() -> UTXOTransaction {
+    UTXOTransaction::new(
+        vec![TxInput::new(OutPoint::new(vec![0xaa; 32], 1), "3045".to_string(), vec![0x02; 33]).with_relative_lock(RelativeLock::Blocks(6))],
+        vec![TxOutput::new(50, vec![0x11; 20]), TxOutput::new(7, vec![0x03; 33])],
+        1_700_000_000,
+    ).with_lock_time(800_000)
+}
+
+fn coinbase_transaction() -> CoinbaseTransaction {
+    CoinbaseTransaction::new(vec![TxOutput::new(100, vec![0x22; 20])], 12, 1_700_000_000)
+}
+
+#[test]
+fn test_block_vectors() {
+    let genesis = Block::Genesis { time: 1_700_000_000 };
+    assert_vector("genesis_block", &encoding::canonical_bytes(BLOCK_TAG, &genesis), &genesis.compute_hash());
+
+    let mut transfer = Block::Transaction {
+        sender: vec![0x01; 4],
+        sender_balance: 90,
+        recipient: vec![0x02; 4],
+        recipient_balance: 10,
+        amount: 8,
+        fee: 2,
+        time: 1_700_000_000,
+        sender_nonce: 3,
+        validator_rewards: vec![(vec![0x03; 4], 1), (vec![0x04; 4], 1)],
+        hash: vec![],
+    };
+    let hash = transfer.compute_hash();
+    if let Block::Transaction { hash: block_hash, .. } = &mut transfer { *block_hash = hash.clone(); }
+    assert_vector("transaction_block", &encoding::canonical_bytes(BLOCK_TAG, &transfer), &hash);
+
+    // the stored hash is not part of the contents it commits to
+    assert_eq!(transfer.compute_hash(), hash);
+}
+
+#[test]
+fn test_transaction_id_vectors() {
+    let transaction = utxo_transaction();
+    let mut encoder = Encoder::top_level(TRANSACTION_ID_TAG);
+    encoder.u64(1).value(&transaction.inputs[0].outpoint).bytes(&transaction.inputs[0].public_key)
+        .value(&transaction.inputs[0].relative_lock).list(&transaction.outputs).u64(transaction.timestamp).u64(transaction.lock_time).u64(transaction.explicit_fee);
+    assert_eq!(encoder.hash().to_vec(), transaction.compute_hash());
+    assert_vector("utxo_transaction_id", &encoder.into_bytes(), &transaction.compute_hash());
+
+    let coinbase = coinbase_transaction();
+    let mut encoder = Encoder::top_level(COINBASE_ID_TAG);
+    encoder.list(&coinbase.outputs).u64(coinbase.block_height).u64(coinbase.timestamp);
+    assert_eq!(encoder.hash().to_vec(), coinbase.compute_hash());
+    assert_vector("coinbase_transaction_id", &encoder.into_bytes(), &coinbase.compute_hash());
+
+    // signatures are not part of the transaction id
+    let mut signed = transaction.clone();
+    signed.inputs[0].signature = "3046".to_string();
+    assert_eq!(signed.compute_hash(), transaction.compute_hash());
+}
+
+#[tokio::test]
+async fn test_network_request_vector() {
+    let transaction = utxo_transaction();
+    let request = NetworkRequest::UTXOTransaction {
+        inputs: transaction.inputs,
+        outputs: transaction.outputs,
+        timestamp: transaction.timestamp,
+        lock_time: transaction.lock_time,
+        explicit_fee: transaction.explicit_fee,
+        chain_id: "test-0123456789abcdef".to_string(),
+    };
+    let hash = encoding::canonical_hash(NETWORK_REQUEST_TAG, &request);
+    assert_vector("utxo_network_request", &encoding::canonical_bytes(NETWORK_REQUEST_TAG, &request), &hash);
+
+    // the hash of a request does not depend on how its JSON is formatted
+    let json: Value = serde_json::from_str(&serde_json::to_string_pretty(&request).unwrap()).unwrap();
+    assert_eq!(network::hash_network_request(json).await, hash);
+}
+
+#[test]
+fn test_signed_message_prefix() {
+    let mut expected = Encoder::top_level(SIGNED_MESSAGE_TAG);
+    expected.bytes(b"miniBlockChain/transfer").str(chain_id());
+    assert_eq!(signed_message(SignatureDomain::Transfer), expected);
+}
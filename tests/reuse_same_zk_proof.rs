@@ -1,4 +1,5 @@
 use mini_block_chain::modules::{
+    blockchain::chain_id,
     zk_proof,
     network,
     requests
@@ -19,10 +20,11 @@ extern crate hex;
 
 
 /**
- * @test this rust code tests whether the nodes are correctly protecting against the same signature being used twice
- * This is important because if the same signature is used twice, a listener to the network could steal a valid signature
+ * @test this rust code tests whether the nodes are correctly protecting against the same proof being used twice
+ * This is important because if the same proof is used twice, a listener to the network could steal a valid proof
  * and use it to send a transaction on behalf of the original sender. There is a mechanism in place to prevent reuse
- * of the same signature that is tested here.
+ * of the same proof that is tested here: validators only accept the sender's current nonce, which the first transfer
+ * increments.
  * 
  * This rust code is called within the shell script test_scripts/proof_reuse_rejection_test.sh 
  * 
@@ -45,40 +47,48 @@ async fn test_signature_reuse() {
     let recipient_private_key_str: String = recipient_keypair.0.to_string();
     let recipient_public_key_str: String = hex::encode(recipient_keypair.1.serialize());
 
+    // wait to ensure the account creation requests have been processed, then fund the sender so it can pay the fee
+    tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+    requests::send_faucet_request(sender_public_key_str.clone()).await;
+
     // For simplicity, use nonce 0
     let nonce: u64 = 0;
     let amount = "0";
+    let fee = "1";
 
-    // Sign the transaction
-    let signature = zk_proof::sign_transaction(
+    // Prove knowledge of the sender's account secret for the transaction
+    let proof = zk_proof::create_transaction_proof(
         &sender_private_key_str,
         &sender_public_key_str,
         &recipient_public_key_str,
-        &amount.to_string(),
+        amount,
+        fee,
         nonce
-    ).expect("Failed to sign transaction");
+    ).expect("Failed to create transaction proof");
 
     // Package the message
     let request = requests::NetworkRequest::Transaction {
         sender_public_key: sender_public_key_str,
-        signature: signature,
+        proof,
         recipient_public_key: recipient_public_key_str,
         amount: amount.to_string(),
+        fee: fee.to_string(),
         nonce: nonce,
+        chain_id: chain_id().to_string(),
     };
     let request_json: String = serde_json::to_string(&request).unwrap();    
 
-    // wait 2 seconds to ensure the account creation requests have been processed
+    // wait 2 seconds to ensure the faucet request has been processed
     tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
     
     // Send the transaction request to the network
-    requests::send_json_request_to_all_ports(request_json.clone()).await; // ! NOTE: This one should pass, the signature is valid and has not been used before
+    requests::send_json_request_to_all_ports(request_json.clone()).await; // ! NOTE: This one should pass, the proof is valid and has not been used before
 
     // wait 2 seconds to ensure the transaction request has been processed
     tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
 
     // Transaction request 2
-    requests::send_json_request_to_all_ports(request_json.clone()).await; // ! NOTE: This one should fail, the signature has been used before
+    requests::send_json_request_to_all_ports(request_json.clone()).await; // ! NOTE: This one should fail, the nonce has already been used
 }
 
 
@@ -89,14 +99,8 @@ async fn make_account()-> (SecretKey, PublicKey) {
     // Generate a new keypair
     let (secret_key, public_key) = zk_proof::generate_keypair().unwrap();
 
-    // Hash the public key for storage
-    let public_key_hash: Vec<u8> = zk_proof::get_public_key_hash(&public_key);
-    
-    // Package account creation request
-    let request = requests::NetworkRequest::AccountCreation {
-        public_key: public_key.to_string(),
-        public_key_hash: hex::encode(public_key_hash),
-    };
+    // Package account creation request (public key hash and signed schnorr commitment)
+    let request = requests::account_creation_request(&secret_key, &public_key).unwrap();
     
     // Serialize request to JSON
     let request_json: String = serde_json::to_string(&request).map_err(|e| io::Error::new(io::ErrorKind::Other, e)).unwrap();
@@ -0,0 +1,240 @@
+use mini_block_chain::modules::{
+    validation::{self, ValidatorNode},
+    blockchain::{self, Block, ValidatorConfig},
+    merkle_tree::{MerkleTree, Account},
+    utxo::{UTXOSet, UTXOTransaction, TxInput, TxOutput, OutPoint, SIGHASH_ALL},
+    requests::{self, NetworkRequest},
+    zk_proof,
+    bridge,
+};
+use proptest::prelude::*;
+use proptest::sample::Index;
+use secp256k1::{PublicKey, SecretKey};
+
+/**
+ * Property tests for undo records
+ * These tests send random requests through the request handlers of a validator node and check that:
+ * - Reverting the block of an accepted request restores the merkle tree, its root, the UTXO set and the blockchain exactly
+ * - Reverting every block from the tip down walks back through each earlier state
+ * - A rejected request leaves the ledger unchanged
+ */
+
+// A request sent to the validator node, between the keys of the test
+#[derive(Debug, Clone)]
+enum Change {
+    // registers an account for a key, rejected if it already has one
+    Create { key: Index },
+    // tops up an account with the faucet
+    Faucet { account: Index },
+    // transfers between two accounts, paying the fee to the validator set
+    Transfer { sender: Index, recipient: Index, amount: u64, fee: u64 },
+    // burns from an account into a UTXO paid to its key
+    Burn { sender: Index, amount: u64, fee: u64 },
+    // spends a UTXO of a key, crediting part of it to an account and keeping the change
+    Spend { utxo: Index, credit: Index, amount: u64 },
+}
+
+fn change_strategy() -> impl Strategy<Value = Change> {
+    prop_oneof![
+        any::<Index>().prop_map(|key| Change::Create { key }),
+        any::<Index>().prop_map(|account| Change::Faucet { account }),
+        (any::<Index>(), any::<Index>(), 0..150u64, 0..4u64)
+            .prop_map(|(sender, recipient, amount, fee)| Change::Transfer { sender, recipient, amount, fee }),
+        (any::<Index>(), 1..150u64, 0..4u64).prop_map(|(sender, amount, fee)| Change::Burn { sender, amount, fee }),
+        (any::<Index>(), any::<Index>(), any::<u64>()).prop_map(|(utxo, credit, amount)| Change::Spend { utxo, credit, amount }),
+    ]
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()
+}
+
+// Creates a validator node with accounts for the first keys, whose validator set is paid at the first two keys
+async fn initial_node(keys: &[(SecretKey, PublicKey)], balances: &[u64]) -> ValidatorNode {
+    let mut validator_node = ValidatorNode::new();
+    for ((secret_key, public_key), balance) in keys.iter().zip(balances) {
+        validator_node.merkle_tree.lock().await.insert_account(Account {
+            public_key: public_key.to_string().into_bytes(),
+            public_key_hash: zk_proof::get_public_key_hash(public_key),
+            balance: *balance,
+            nonce: 0,
+            schnorr_commitment: zk_proof::derive_schnorr_commitment(&secret_key.to_string()).unwrap(),
+        });
+    }
+    validator_node.validators = keys[..2].iter()
+        .map(|(_, public_key)| ValidatorConfig { node_public_key: public_key.to_string(), reward_address: public_key.to_string() })
+        .collect();
+    validator_node
+}
+
+// Sends the request of a change to the validator node, which applies it to its ledger if it is valid
+async fn apply_change(change: &Change, keys: &[(SecretKey, PublicKey)], validator_node: &ValidatorNode) {
+    let accounts: Vec<&(SecretKey, PublicKey)> = {
+        let merkle_tree_guard = validator_node.merkle_tree.lock().await;
+        keys.iter().filter(|(_, public_key)| merkle_tree_guard.account_exists(public_key.to_string().as_bytes())).collect()
+    };
+    let nonce_of = |public_key: &PublicKey| {
+        let merkle_tree = validator_node.merkle_tree.clone();
+        let public_key: Vec<u8> = public_key.to_string().into_bytes();
+        async move { merkle_tree.lock().await.get_nonce(&public_key).unwrap_or_default() }
+    };
+
+    match change {
+        Change::Create { key } => {
+            let (secret_key, public_key) = key.get(keys);
+            let request = requests::account_creation_request(secret_key, public_key).unwrap();
+            let _ = validation::handle_account_creation_request(serde_json::to_value(request).unwrap(), validator_node.clone()).await;
+        }
+        Change::Faucet { account } if !accounts.is_empty() => {
+            let public_key: String = account.get(&accounts).1.to_string();
+            let request = serde_json::to_value(NetworkRequest::Faucet { public_key }).unwrap();
+            validation::handle_faucet_request(request, validator_node.clone()).await.unwrap();
+        }
+        Change::Transfer { sender, recipient, amount, fee } if !accounts.is_empty() => {
+            let (secret_key, public_key) = sender.get(&accounts);
+            let recipient: String = recipient.get(&accounts).1.to_string();
+            let nonce: u64 = nonce_of(public_key).await;
+            let request = requests::transaction_request(&secret_key.to_string(), recipient, amount.to_string(), fee.to_string(), nonce).unwrap();
+            validation::handle_transaction_request(serde_json::to_value(request).unwrap(), validator_node.clone()).await.unwrap();
+        }
+        Change::Burn { sender, amount, fee } if !accounts.is_empty() => {
+            let (secret_key, public_key) = sender.get(&accounts);
+            let (nonce, timestamp) = (nonce_of(public_key).await, now());
+            let (amount, fee) = (amount.to_string(), fee.to_string());
+            let message = zk_proof::account_to_utxo_message(&public_key.to_string(), &public_key.to_string(), &amount, &fee, nonce, timestamp);
+            let request = serde_json::to_value(NetworkRequest::AccountToUtxo {
+                sender_public_key: public_key.to_string(),
+                proof: zk_proof::create_schnorr_proof(&secret_key.to_string(), &message).unwrap(),
+                recipient: public_key.to_string(),
+                amount,
+                fee,
+                nonce,
+                timestamp,
+                chain_id: blockchain::chain_id().to_string(),
+            }).unwrap();
+            validation::handle_account_to_utxo_request(request, validator_node.clone()).await.unwrap();
+        }
+        Change::Spend { utxo, credit, amount } if !accounts.is_empty() => {
+            let mut utxos: Vec<(OutPoint, u64, &(SecretKey, PublicKey))> = Vec::new();
+            for key in keys {
+                let utxo_set_guard = validator_node.utxo_set.lock().await;
+                utxos.extend(utxo_set_guard.get_utxos_for_recipient(&key.1.serialize()).into_iter().map(|(outpoint, utxo)| (outpoint, utxo.amount, key)));
+            }
+            utxos.sort_by(|a, b| a.0.cmp(&b.0));
+            if utxos.is_empty() { return; }
+            let (outpoint, total, (secret_key, public_key)) = utxo.get(&utxos).clone();
+
+            // credit part of the UTXO to the account, keeping all but a fee of 1 as change
+            if total < 3 { return; }
+            let credited: u64 = 1 + amount % (total - 2);
+            let mut tx = UTXOTransaction::new(
+                vec![TxInput::new(outpoint, String::new(), public_key.serialize().to_vec())],
+                vec![
+                    TxOutput::new(credited, bridge::account_recipient(&credit.get(&accounts).1.to_string()).unwrap()),
+                    TxOutput::new(total - credited - 1, public_key.serialize().to_vec()),
+                ],
+                12345,
+            );
+            tx.sign_input(0, secret_key, SIGHASH_ALL).unwrap();
+            let request = serde_json::to_value(NetworkRequest::UTXOTransaction {
+                inputs: tx.inputs,
+                outputs: tx.outputs,
+                timestamp: tx.timestamp,
+                lock_time: tx.lock_time,
+                explicit_fee: tx.explicit_fee,
+                chain_id: blockchain::chain_id().to_string(),
+            }).unwrap();
+            validation::handle_utxo_transaction_request(request, validator_node.clone()).await.unwrap();
+        }
+        // no account to send from
+        _ => (),
+    }
+}
+
+// The ledger state of a validator node: its merkle tree and root, UTXO set and commitment, and blockchain
+async fn ledger_state(validator_node: &ValidatorNode) -> (MerkleTree, Option<Vec<u8>>, UTXOSet, Vec<u8>, Vec<Block>) {
+    let merkle_tree: MerkleTree = validator_node.merkle_tree.lock().await.clone();
+    let utxo_set: UTXOSet = validator_node.utxo_set.lock().await.clone();
+    let root: Option<Vec<u8>> = merkle_tree.root_hash();
+    let commitment: Vec<u8> = utxo_set.commitment();
+    (merkle_tree, root, utxo_set, commitment, validator_node.blockchain.lock().await.chain.clone())
+}
+
+// Reverts the block at the tip of the validator node's chain, locking the merkle tree, then the UTXO set, then the blockchain
+async fn revert_block(validator_node: &ValidatorNode) -> Result<Block, String> {
+    let mut merkle_tree_guard = validator_node.merkle_tree.lock().await;
+    let mut utxo_set_guard = validator_node.utxo_set.lock().await;
+    validator_node.blockchain.lock().await.revert_block(&mut merkle_tree_guard, &mut utxo_set_guard)
+}
+
+fn keys() -> Vec<(SecretKey, PublicKey)> {
+    (0..4).map(|_| zk_proof::generate_keypair().unwrap()).collect()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn apply_then_revert_is_the_identity(
+        balances in prop::collection::vec(0..300u64, 0..4),
+        changes in prop::collection::vec(change_strategy(), 1..4),
+    ) {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let keys = keys();
+            let validator_node = initial_node(&keys, &balances).await;
+
+            // bring the ledger to a random state, then apply one more change
+            let (last, earlier) = changes.split_last().unwrap();
+            for change in earlier { apply_change(change, &keys, &validator_node).await; }
+            let before = ledger_state(&validator_node).await;
+            apply_change(last, &keys, &validator_node).await;
+
+            // a rejected request writes no block and changes nothing
+            if validator_node.blockchain.lock().await.chain.len() == before.4.len() {
+                prop_assert_eq!(ledger_state(&validator_node).await, before);
+                return Ok(());
+            }
+
+            let block: Block = revert_block(&validator_node).await.unwrap();
+            let reverted_genesis: bool = matches!(block, Block::Genesis { .. });
+            prop_assert!(!reverted_genesis);
+            let after = ledger_state(&validator_node).await;
+            prop_assert_eq!(&after.0, &before.0);
+            prop_assert_eq!(&after.1, &before.1);
+            prop_assert_eq!(&after.2, &before.2);
+            prop_assert_eq!(&after.3, &before.3);
+            prop_assert_eq!(&after.4, &before.4);
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn reverting_from_the_tip_restores_every_earlier_state(
+        balances in prop::collection::vec(0..300u64, 1..4),
+        changes in prop::collection::vec(change_strategy(), 1..8),
+    ) {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let keys = keys();
+            let validator_node = initial_node(&keys, &balances).await;
+
+            // apply every change, keeping the state before each one that wrote a block
+            let mut states = Vec::new();
+            for change in changes.iter() {
+                let before = ledger_state(&validator_node).await;
+                apply_change(change, &keys, &validator_node).await;
+                if validator_node.blockchain.lock().await.chain.len() > before.4.len() { states.push(before); }
+            }
+
+            // revert them all, passing back through each state
+            while let Some(before) = states.pop() {
+                revert_block(&validator_node).await.unwrap();
+                prop_assert_eq!(ledger_state(&validator_node).await, before);
+            }
+
+            // the genesis block cannot be reverted
+            prop_assert_eq!(validator_node.blockchain.lock().await.chain.len(), 1);
+            prop_assert!(revert_block(&validator_node).await.is_err());
+            Ok(())
+        })?;
+    }
+}
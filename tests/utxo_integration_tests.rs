@@ -1,9 +1,22 @@
 use mini_block_chain::modules::{
-    validation::ValidatorNode,
-    utxo::{UTXOSet, UTXOTransaction, TxInput, TxOutput, OutPoint, UTXO},
+    validation::{self, ValidatorNode},
+    utxo::{UTXOSet, UTXOTransaction, CoinbaseTransaction, TxInput, TxOutput, OutPoint, UTXO, SIGHASH_ALL},
     zk_proof,
-    blockchain::Block,
+    blockchain,
+    multisig::{MultisigLock, PartiallySignedTransaction},
+    requests::NetworkRequest,
+    script::Script,
+    bridge,
+    merkle_tree::Account,
+    confidential::{self, Opening},
+    utxo_commitment,
+    constants::MAX_TIMESTAMP_DRIFT,
+    consensus,
+    network,
 };
+use tokio::io::AsyncReadExt;
+use secp256k1::{PublicKey, SecretKey};
+use sha2::{Sha256, Digest};
 
 /**
  * Integration tests for UTXO functionality
@@ -44,16 +57,19 @@ async fn test_utxo_transaction_lifecycle() {
         outpoint: outpoint1.clone(),
         signature: "test_signature".to_string(),
         public_key: sender_key.clone(),
+        relative_lock: None,
     };
     
     let output1 = TxOutput {
         amount: 700,
         recipient: recipient_key.clone(),
+        confidential: None,
     };
     
     let output2 = TxOutput {
         amount: 250, // Change back to sender (50 token fee)
         recipient: sender_key.clone(),
+        confidential: None,
     };
     
     let tx = UTXOTransaction::new(
@@ -67,7 +83,7 @@ async fn test_utxo_transaction_lifecycle() {
     
     // Verify transaction amounts
     assert_eq!(tx.total_input_amount(&utxo_set_guard), Some(1000));
-    assert_eq!(tx.total_output_amount(), 950);
+    assert_eq!(tx.total_output_amount(), Some(950));
     assert_eq!(tx.fee(&utxo_set_guard), Some(50));
     
     // Apply transaction
@@ -132,11 +148,13 @@ fn test_utxo_serialization_optimization() {
         outpoint: OutPoint::new(vec![1, 2, 3], 0),
         signature: "signature".to_string(),
         public_key: vec![4, 5, 6],
+        relative_lock: None,
     };
     
     let output = TxOutput {
         amount: 100,
         recipient: vec![7, 8, 9],
+        confidential: None,
     };
     
     let tx = UTXOTransaction::new(vec![input], vec![output], 12345);
@@ -175,6 +193,7 @@ fn test_utxo_serialization_optimization() {
     deserialized.rebuild_index();
     
     assert_eq!(utxo_set.len(), deserialized.len());
+    assert_eq!(utxo_set.commitment(), deserialized.commitment());
     println!("UTXO Set (100 UTXOs) serialization took: {:?}", serialize_duration);
     println!("UTXO Set (100 UTXOs) deserialization took: {:?}", deserialize_duration);
 }
@@ -195,11 +214,13 @@ async fn test_utxo_double_spend_prevention() {
         outpoint: outpoint.clone(),
         signature: "signature1".to_string(),
         public_key: sender_key.clone(),
+        relative_lock: None,
     };
     
     let output = TxOutput {
         amount: 900,
         recipient: vec![6, 7, 8, 9, 10],
+        confidential: None,
     };
     
     let tx1 = UTXOTransaction::new(vec![input], vec![output], 12345);
@@ -213,11 +234,13 @@ async fn test_utxo_double_spend_prevention() {
         outpoint: outpoint.clone(),
         signature: "signature2".to_string(),
         public_key: sender_key.clone(),
+        relative_lock: None,
     };
     
     let output2 = TxOutput {
         amount: 800,
         recipient: vec![11, 12, 13, 14, 15],
+        confidential: None,
     };
     
     let tx2 = UTXOTransaction::new(vec![input2], vec![output2], 12346);
@@ -253,16 +276,19 @@ async fn test_multiple_input_utxo_transaction() {
             outpoint: outpoint1.clone(),
             signature: "sig1".to_string(),
             public_key: sender_key.clone(),
+            relative_lock: None,
         },
         TxInput {
             outpoint: outpoint2.clone(),
             signature: "sig2".to_string(),
             public_key: sender_key.clone(),
+            relative_lock: None,
         },
         TxInput {
             outpoint: outpoint3.clone(),
             signature: "sig3".to_string(),
             public_key: sender_key.clone(),
+            relative_lock: None,
         },
     ];
     
@@ -270,10 +296,12 @@ async fn test_multiple_input_utxo_transaction() {
         TxOutput {
             amount: 800,
             recipient: recipient_key.clone(),
+            confidential: None,
         },
         TxOutput {
             amount: 100, // Change back to sender (50 token fee)
             recipient: sender_key.clone(),
+            confidential: None,
         },
     ];
     
@@ -281,7 +309,7 @@ async fn test_multiple_input_utxo_transaction() {
     
     // Verify transaction amounts
     assert_eq!(tx.total_input_amount(&utxo_set_guard), Some(950)); // 300 + 400 + 250
-    assert_eq!(tx.total_output_amount(), 900); // 800 + 100
+    assert_eq!(tx.total_output_amount(), Some(900)); // 800 + 100
     assert_eq!(tx.fee(&utxo_set_guard), Some(50));
     
     // Apply transaction
@@ -310,11 +338,13 @@ fn test_utxo_hash_consistency() {
         outpoint: OutPoint::new(vec![1, 2, 3], 0),
         signature: "signature".to_string(),
         public_key: vec![4, 5, 6],
+        relative_lock: None,
     };
     
     let output = TxOutput {
         amount: 100,
         recipient: vec![7, 8, 9],
+        confidential: None,
     };
     
     let tx1 = UTXOTransaction::new(vec![input.clone()], vec![output.clone()], 12345);
@@ -326,4 +356,485 @@ fn test_utxo_hash_consistency() {
     
     // Hash should be 32 bytes (SHA256)
     assert_eq!(tx1.hash.len(), 32);
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_multisig_output_spent_by_co_signers() {
+    let validator_node = ValidatorNode::new();
+
+    // A 2-of-3 treasury output
+    let keys: Vec<_> = (0..3).map(|_| zk_proof::generate_keypair().unwrap()).collect();
+    let lock = MultisigLock::new(2, keys.iter().map(|(_, public_key)| *public_key).collect()).unwrap();
+    let treasury_outpoint = OutPoint::new(vec![0xdd; 32], 0);
+    validator_node.utxo_set.lock().await.add_utxo(treasury_outpoint.clone(), UTXO::new(1000, lock.to_bytes(), 1, 12345));
+
+    // Two of the key holders co-sign the spend offline
+    let (_, recipient) = zk_proof::generate_keypair().unwrap();
+    let tx = UTXOTransaction::new(
+        vec![TxInput::new(treasury_outpoint, String::new(), lock.to_bytes())],
+        vec![TxOutput::new(990, recipient.serialize().to_vec())],
+        12345,
+    );
+    let mut psbt = PartiallySignedTransaction::new(tx);
+    psbt.sign(&keys[1].0).unwrap();
+    assert!(psbt.finalize().is_err());
+    psbt.sign(&keys[2].0).unwrap();
+    let signed = psbt.finalize().unwrap();
+
+    let request = |transaction: &UTXOTransaction| serde_json::to_value(NetworkRequest::UTXOTransaction {
+        inputs: transaction.inputs.clone(),
+        outputs: transaction.outputs.clone(),
+        timestamp: transaction.timestamp,
+        lock_time: transaction.lock_time,
+        explicit_fee: transaction.explicit_fee,
+        chain_id: blockchain::chain_id().to_string(),
+    }).unwrap();
+    assert!(validation::verify_utxo_transaction_independently(request(&signed), validator_node.clone()).await.is_ok());
+
+    // A single signature does not meet the threshold
+    let mut under_signed = signed.clone();
+    under_signed.inputs[0].signature = signed.inputs[0].signature[..signed.inputs[0].signature.len() / 2].to_string();
+    assert!(validation::verify_utxo_transaction_independently(request(&under_signed), validator_node.clone()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_script_output_with_hash_and_time_lock() {
+    let validator_node = ValidatorNode::new();
+
+    // Coins the receiver can claim with the preimage of a hash, or the sender can take back from time 1600000000
+    let ((sender_key, sender), (receiver_key, receiver)) = (zk_proof::generate_keypair().unwrap(), zk_proof::generate_keypair().unwrap());
+    let hash = Sha256::digest(b"preimage");
+    let locking = Script::from_asm(&format!(
+        "OP_IF OP_SHA256 <{}> OP_EQUALVERIFY <{}> OP_ELSE 1600000000 OP_CHECKLOCKTIMEVERIFY OP_DROP <{}> OP_ENDIF OP_CHECKSIG",
+        hex::encode(hash), receiver, sender,
+    )).unwrap();
+    let outpoint = OutPoint::new(vec![0xee; 32], 0);
+    validator_node.utxo_set.lock().await.add_utxo(outpoint.clone(), UTXO::new(1000, locking.to_recipient(), 1, 12345));
+
+    // Spends the output with the key at the lock time, choosing the branch with the rest of the unlocking script
+    let spend = |secret_key, lock_time: u64, branch: Script| {
+        let mut tx = UTXOTransaction::new(
+            vec![TxInput::new(outpoint.clone(), String::new(), locking.to_recipient())],
+            vec![TxOutput::new(990, vec![1; 20])],
+            12345,
+        ).with_lock_time(lock_time);
+        let signature = tx.input_signature(0, secret_key, SIGHASH_ALL).unwrap();
+        let unlocking = Script::new().push_data(&signature);
+        tx.inputs[0].signature = hex::encode([unlocking.as_bytes(), branch.as_bytes()].concat());
+        serde_json::to_value(NetworkRequest::UTXOTransaction {
+            inputs: tx.inputs,
+            outputs: tx.outputs,
+            timestamp: tx.timestamp,
+            lock_time: tx.lock_time,
+            explicit_fee: tx.explicit_fee,
+            chain_id: blockchain::chain_id().to_string(),
+        }).unwrap()
+    };
+    let claim = Script::new().push_data(b"preimage").push_number(1);
+    let refund = Script::new().push_number(0);
+
+    // the receiver needs the right preimage, the sender must lock the refund at least until the script's lock time
+    assert!(validation::verify_utxo_transaction_independently(spend(&receiver_key, 0, claim), validator_node.clone()).await.is_ok());
+    let wrong_preimage = Script::new().push_data(b"guess").push_number(1);
+    assert!(validation::verify_utxo_transaction_independently(spend(&receiver_key, 0, wrong_preimage), validator_node.clone()).await.is_err());
+    assert!(validation::verify_utxo_transaction_independently(spend(&sender_key, 1_599_999_999, refund.clone()), validator_node.clone()).await.is_err());
+    assert!(validation::verify_utxo_transaction_independently(spend(&sender_key, 1_600_000_000, refund.clone()), validator_node.clone()).await.is_ok());
+
+    // a block height lock time does not satisfy a time lock, and a lock time in the future is not valid yet
+    assert!(validation::verify_utxo_transaction_independently(spend(&sender_key, 5, refund.clone()), validator_node.clone()).await.is_err());
+    assert!(validation::verify_utxo_transaction_independently(spend(&sender_key, 4_000_000_000, refund), validator_node.clone()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_immature_transaction_held_until_its_lock_time() {
+    let validator_node = ValidatorNode::new();
+    let (secret_key, public_key) = zk_proof::generate_keypair().unwrap();
+    let outpoint = OutPoint::new(vec![0xf0; 32], 0);
+    validator_node.utxo_set.lock().await.add_utxo(outpoint.clone(), UTXO::new(1000, public_key.serialize().to_vec(), 1, 12345));
+
+    // A vesting payout signed in advance that is not valid before block 3
+    let mut tx = UTXOTransaction::new(
+        vec![TxInput::new(outpoint, String::new(), public_key.serialize().to_vec())],
+        vec![TxOutput::new(990, vec![1; 20])],
+        12345,
+    ).with_lock_time(3);
+    tx.sign_input(0, &secret_key, SIGHASH_ALL).unwrap();
+    let request = serde_json::to_value(NetworkRequest::UTXOTransaction {
+        inputs: tx.inputs,
+        outputs: tx.outputs,
+        timestamp: tx.timestamp,
+        lock_time: tx.lock_time,
+        explicit_fee: tx.explicit_fee,
+        chain_id: blockchain::chain_id().to_string(),
+    }).unwrap();
+
+    // Validators reject it as premature and hold it instead of voting on it
+    assert!(validation::verify_utxo_transaction_independently(request.clone(), validator_node.clone()).await.is_err());
+    assert!(!validation::handle_utxo_transaction_request(request.clone(), validator_node.clone()).await.unwrap());
+    assert_eq!(*validator_node.immature_transactions.lock().await, vec![request.clone()]);
+    assert!(validator_node.take_matured_transactions().await.is_empty());
+
+    // Once the chain reaches block 3 it is released and valid
+    let genesis = validator_node.blockchain.lock().await.chain[0].clone();
+    validator_node.blockchain.lock().await.chain.extend([genesis.clone(), genesis]);
+    assert_eq!(validator_node.take_matured_transactions().await, vec![request.clone()]);
+    assert!(validator_node.immature_transactions.lock().await.is_empty());
+    assert!(validation::verify_utxo_transaction_independently(request, validator_node.clone()).await.is_ok());
+}
+
+// Answers a consensus request on a peer and returns the vote it sends back to the requesting node
+async fn vote_of(peer: &ValidatorNode, request_hash: &[u8], utxo_commitment: Option<Vec<u8>>) -> serde_json::Value {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let consensus_request = serde_json::to_value(NetworkRequest::ConsensusRequest {
+        request_hash: request_hash.to_vec(),
+        response_port: listener.local_addr().unwrap().to_string(),
+        utxo_commitment,
+        chain_id: blockchain::chain_id().to_string(),
+    }).unwrap();
+    consensus::handle_consensus_request(consensus_request, peer.clone()).await.unwrap();
+    let (mut socket, _) = listener.accept().await.unwrap();
+    let mut response: Vec<u8> = Vec::new();
+    socket.read_to_end(&mut response).await.unwrap();
+    serde_json::from_slice(&response).unwrap()
+}
+
+#[tokio::test]
+async fn test_peers_vote_to_reject_transactions_immature_to_them() {
+    let (secret_key, public_key) = zk_proof::generate_keypair().unwrap();
+    let outpoint = OutPoint::new(vec![0xf2; 32], 0);
+    let behind = ValidatorNode::new();
+    let stranger = ValidatorNode::new();
+    let mut ahead = ValidatorNode::new();
+    ahead.validators = [&behind, &stranger].iter()
+        .map(|peer| blockchain::ValidatorConfig { node_public_key: peer.node_public_key.clone(), reward_address: String::new() })
+        .collect();
+    for node in [&ahead, &behind] {
+        node.utxo_set.lock().await.add_utxo(outpoint.clone(), UTXO::new(1000, public_key.serialize().to_vec(), 1, 12345));
+    }
+
+    // A payout that is not valid before block 3, which one node has reached and the other has not
+    let genesis = ahead.blockchain.lock().await.chain[0].clone();
+    ahead.blockchain.lock().await.chain.extend([genesis.clone(), genesis]);
+    let mut tx = UTXOTransaction::new(
+        vec![TxInput::new(outpoint, String::new(), public_key.serialize().to_vec())],
+        vec![TxOutput::new(990, vec![1; 20])],
+        12345,
+    ).with_lock_time(3);
+    tx.sign_input(0, &secret_key, SIGHASH_ALL).unwrap();
+    let request = serde_json::to_value(NetworkRequest::UTXOTransaction {
+        inputs: tx.inputs,
+        outputs: tx.outputs,
+        timestamp: tx.timestamp,
+        lock_time: tx.lock_time,
+        explicit_fee: tx.explicit_fee,
+        chain_id: blockchain::chain_id().to_string(),
+    }).unwrap();
+    let request_hash: Vec<u8> = network::hash_network_request(request.clone()).await;
+
+    // the node ahead accepts it, the node behind holds it
+    assert!(validation::verify_utxo_transaction_independently(request.clone(), ahead.clone()).await.is_ok());
+    assert!(!validation::handle_utxo_transaction_request(request, behind.clone()).await.unwrap());
+    assert_eq!(behind.immature_transactions.lock().await.len(), 1);
+
+    // asked for its decision, the node behind votes to reject, as does a node that never received the transaction
+    let utxo_commitment: Option<Vec<u8>> = ahead.decision_commitments.lock().await.get(&request_hash).cloned();
+    for peer in [&behind, &stranger] {
+        let vote = vote_of(peer, &request_hash, utxo_commitment.clone()).await;
+        assert_eq!(vote["decision"], serde_json::json!(false));
+        consensus::handle_consensus_response(vote, ahead.clone()).await.unwrap();
+    }
+    assert_eq!(ahead.peer_decisions.lock().await.get(&request_hash), Some(&(0, 2)));
+}
+
+#[tokio::test]
+async fn test_confidential_transaction_validation() {
+    let validator_node = ValidatorNode::new();
+    let (secret_key, public_key) = zk_proof::generate_keypair().unwrap();
+    let outpoint = OutPoint::new(vec![0xf1; 32], 0);
+    validator_node.utxo_set.lock().await.add_utxo(outpoint.clone(), UTXO::new(1000, public_key.serialize().to_vec(), 1, 12345));
+
+    // Hide 1000 tokens in a payment of 700 and change of 280, paying an explicit fee of 20
+    let blinding = confidential::random_blinding();
+    let outputs = vec![
+        TxOutput::confidential(&Opening { amount: 700, blinding }, vec![1; 20]),
+        TxOutput::confidential(&Opening { amount: 280, blinding: -blinding }, vec![2; 20]),
+    ];
+    let input = TxInput::new(outpoint, String::new(), public_key.serialize().to_vec());
+    let signed_request = |outputs: Vec<TxOutput>| {
+        let mut tx = UTXOTransaction::new(vec![input.clone()], outputs, 12345).with_explicit_fee(20);
+        tx.sign_input(0, &secret_key, SIGHASH_ALL).unwrap();
+        serde_json::to_value(NetworkRequest::UTXOTransaction {
+            inputs: tx.inputs,
+            outputs: tx.outputs,
+            timestamp: tx.timestamp,
+            lock_time: tx.lock_time,
+            explicit_fee: tx.explicit_fee,
+            chain_id: blockchain::chain_id().to_string(),
+        }).unwrap()
+    };
+    assert!(validation::verify_utxo_transaction_independently(signed_request(outputs.clone()), validator_node.clone()).await.is_ok());
+
+    // The commitments balance with the range proofs swapped, but the proofs no longer match their commitments
+    let mut swapped = outputs.clone();
+    swapped[0].confidential.as_mut().unwrap().range_proof = outputs[1].confidential.clone().unwrap().range_proof;
+    swapped[1].confidential.as_mut().unwrap().range_proof = outputs[0].confidential.clone().unwrap().range_proof;
+    let reason = validation::verify_utxo_transaction_independently(signed_request(swapped), validator_node.clone()).await.unwrap_err();
+    assert_eq!(reason, "Confidential output 0: Invalid range proof");
+}
+
+// Current unix time, the timestamp of requests minting a UTXO
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()
+}
+
+// Builds an AccountToUtxo request at nonce 0 burning 60 (plus a fee of 1) from the account of a key into a UTXO paid to the key
+fn burn_request(secret_key: &SecretKey, public_key: &PublicKey, timestamp: u64) -> serde_json::Value {
+    let message = zk_proof::account_to_utxo_message(&public_key.to_string(), &public_key.to_string(), "60", "1", 0, timestamp);
+    serde_json::to_value(NetworkRequest::AccountToUtxo {
+        sender_public_key: public_key.to_string(),
+        proof: zk_proof::create_schnorr_proof(&secret_key.to_string(), &message).unwrap(),
+        recipient: public_key.to_string(),
+        amount: "60".to_string(),
+        fee: "1".to_string(),
+        nonce: 0,
+        timestamp,
+        chain_id: blockchain::chain_id().to_string(),
+    }).unwrap()
+}
+
+#[tokio::test]
+async fn test_account_utxo_bridge_conserves_supply() {
+    let validator_node = ValidatorNode::new();
+    let (secret_key, public_key) = zk_proof::generate_keypair().unwrap();
+    let account: Vec<u8> = public_key.to_string().into_bytes();
+    validator_node.merkle_tree.lock().await.insert_account(Account {
+        public_key: account.clone(),
+        public_key_hash: zk_proof::get_public_key_hash(&public_key),
+        balance: 100,
+        nonce: 0,
+        schnorr_commitment: zk_proof::derive_schnorr_commitment(&secret_key.to_string()).unwrap(),
+    });
+
+    // the signed timestamp must be a unix time close to the validator's clock
+    for timestamp in [0, now() - MAX_TIMESTAMP_DRIFT - 60, now() + MAX_TIMESTAMP_DRIFT + 60] {
+        assert!(!validation::handle_account_to_utxo_request(burn_request(&secret_key, &public_key, timestamp), validator_node.clone()).await.unwrap());
+    }
+    let mut tampered = burn_request(&secret_key, &public_key, now());
+    tampered["timestamp"] = serde_json::json!(tampered["timestamp"].as_u64().unwrap() - 1);
+    assert!(!validation::handle_account_to_utxo_request(tampered, validator_node.clone()).await.unwrap());
+
+    // Burn 60 (plus a fee of 1, burned too as no validator is rewarded) from the account into a UTXO paid to its key
+    let timestamp: u64 = now() - 30;
+    let request = burn_request(&secret_key, &public_key, timestamp);
+    assert!(validation::handle_account_to_utxo_request(request.clone(), validator_node.clone()).await.unwrap());
+    let minted = bridge::mint_outpoint(&account, 0);
+    assert_eq!(validator_node.merkle_tree.lock().await.get_account_balance(&account), Some(39));
+    assert_eq!(validator_node.utxo_set.lock().await.get_utxo(&minted).unwrap().amount, 60);
+
+    // the UTXO and the block take the signed timestamp, not the clock of the node
+    assert_eq!(validator_node.utxo_set.lock().await.get_utxo(&minted).unwrap().timestamp, timestamp);
+    assert!(matches!(validator_node.blockchain.lock().await.chain.last(), Some(blockchain::Block::AccountToUtxo { time, .. }) if *time == timestamp));
+
+    // the nonce was used, so the request cannot be replayed
+    assert!(!validation::handle_account_to_utxo_request(request, validator_node.clone()).await.unwrap());
+    assert_eq!(validator_node.merkle_tree.lock().await.get_account_balance(&account), Some(39));
+
+    // Spend the minted UTXO back: 50 credited to the account, 9 of change, 1 of fee
+    let spend = |credited: Vec<u8>| {
+        let mut tx = UTXOTransaction::new(
+            vec![TxInput::new(minted.clone(), String::new(), public_key.serialize().to_vec())],
+            vec![TxOutput::new(50, credited), TxOutput::new(9, public_key.serialize().to_vec())],
+            12345,
+        );
+        tx.sign_input(0, &secret_key, SIGHASH_ALL).unwrap();
+        serde_json::to_value(NetworkRequest::UTXOTransaction {
+            inputs: tx.inputs,
+            outputs: tx.outputs,
+            timestamp: tx.timestamp,
+            lock_time: tx.lock_time,
+            explicit_fee: tx.explicit_fee,
+            chain_id: blockchain::chain_id().to_string(),
+        }).unwrap()
+    };
+
+    // outputs can only credit existing accounts
+    let (_, stranger) = zk_proof::generate_keypair().unwrap();
+    let to_stranger = spend(bridge::account_recipient(&stranger.to_string()).unwrap());
+    assert!(validation::verify_utxo_transaction_independently(to_stranger, validator_node.clone()).await.is_err());
+
+    assert!(validation::handle_utxo_transaction_request(spend(bridge::account_recipient(&public_key.to_string()).unwrap()), validator_node.clone()).await.unwrap());
+    let utxo_set_guard = validator_node.utxo_set.lock().await;
+    assert!(!utxo_set_guard.contains(&minted));
+    assert_eq!(utxo_set_guard.len(), 2); // the change and the coinbase paying the fee to the validator set
+    assert_eq!(utxo_set_guard.get_balance(&public_key.serialize()), 9);
+    assert_eq!(validator_node.merkle_tree.lock().await.get_account_balance(&account), Some(89));
+}
+
+#[tokio::test]
+async fn test_fees_split_among_validator_set() {
+    let mut validator_node = ValidatorNode::new();
+    let (secret_key, public_key) = zk_proof::generate_keypair().unwrap();
+    let account: Vec<u8> = public_key.to_string().into_bytes();
+    let new_account = |public_key: &PublicKey, balance: u64, schnorr_commitment: Vec<u8>| Account {
+        public_key: public_key.to_string().into_bytes(),
+        public_key_hash: zk_proof::get_public_key_hash(public_key),
+        balance,
+        nonce: 0,
+        schnorr_commitment,
+    };
+    validator_node.merkle_tree.lock().await.insert_account(
+        new_account(&public_key, 100, zk_proof::derive_schnorr_commitment(&secret_key.to_string()).unwrap()));
+
+    // two validators collect fees at existing accounts, a third one at an address that is not an account
+    let mut reward_addresses: Vec<String> = Vec::new();
+    for _ in 0..2 {
+        let (_, reward_key) = zk_proof::generate_keypair().unwrap();
+        validator_node.merkle_tree.lock().await.insert_account(new_account(&reward_key, 0, Vec::new()));
+        reward_addresses.push(reward_key.to_string());
+    }
+    reward_addresses.sort();
+    validator_node.validators = reward_addresses.iter().chain([&"02ffff".to_string()])
+        .map(|reward_address| blockchain::ValidatorConfig { node_public_key: reward_address.clone(), reward_address: reward_address.clone() })
+        .collect();
+
+    // Burn 60 with a fee of 3 from the account, no peer voted on it
+    let timestamp: u64 = now();
+    let message = zk_proof::account_to_utxo_message(&public_key.to_string(), &public_key.to_string(), "60", "3", 0, timestamp);
+    let request = serde_json::to_value(NetworkRequest::AccountToUtxo {
+        sender_public_key: public_key.to_string(),
+        proof: zk_proof::create_schnorr_proof(&secret_key.to_string(), &message).unwrap(),
+        recipient: public_key.to_string(),
+        amount: "60".to_string(),
+        fee: "3".to_string(),
+        nonce: 0,
+        timestamp,
+        chain_id: blockchain::chain_id().to_string(),
+    }).unwrap();
+    assert!(validation::handle_account_to_utxo_request(request, validator_node.clone()).await.unwrap());
+
+    // the fee is split among the reward addresses of the validator set that are accounts, whichever votes were received
+    let merkle_tree_guard = validator_node.merkle_tree.lock().await;
+    assert_eq!(merkle_tree_guard.get_account_balance(&account), Some(37));
+    assert_eq!(merkle_tree_guard.get_account_balance(reward_addresses[0].as_bytes()), Some(2));
+    assert_eq!(merkle_tree_guard.get_account_balance(reward_addresses[1].as_bytes()), Some(1));
+    assert!(validator_node.consensus_voters.lock().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_blocks_record_utxo_set_commitment() {
+    let validator_node = ValidatorNode::new();
+    let (secret_key, public_key) = zk_proof::generate_keypair().unwrap();
+    let account: Vec<u8> = public_key.to_string().into_bytes();
+    validator_node.merkle_tree.lock().await.insert_account(Account {
+        public_key: account.clone(),
+        public_key_hash: zk_proof::get_public_key_hash(&public_key),
+        balance: 100,
+        nonce: 0,
+        schnorr_commitment: zk_proof::derive_schnorr_commitment(&secret_key.to_string()).unwrap(),
+    });
+
+    // Mint a UTXO from the account, then spend it into two new ones
+    let request = burn_request(&secret_key, &public_key, now());
+    assert!(validation::handle_account_to_utxo_request(request, validator_node.clone()).await.unwrap());
+    let minted_commitment: Vec<u8> = validator_node.utxo_set.lock().await.commitment();
+
+    let mut tx = UTXOTransaction::new(
+        vec![TxInput::new(bridge::mint_outpoint(&account, 0), String::new(), public_key.serialize().to_vec())],
+        vec![TxOutput::new(30, vec![7; 20]), TxOutput::new(29, public_key.serialize().to_vec())],
+        12345,
+    );
+    tx.sign_input(0, &secret_key, SIGHASH_ALL).unwrap();
+    let request = serde_json::to_value(NetworkRequest::UTXOTransaction {
+        inputs: tx.inputs,
+        outputs: tx.outputs,
+        timestamp: tx.timestamp,
+        lock_time: tx.lock_time,
+        explicit_fee: tx.explicit_fee,
+        chain_id: blockchain::chain_id().to_string(),
+    }).unwrap();
+    assert!(validation::handle_utxo_transaction_request(request, validator_node.clone()).await.unwrap());
+    let spent_commitment: Vec<u8> = validator_node.utxo_set.lock().await.commitment();
+    assert_ne!(minted_commitment, spent_commitment);
+
+    // each block records the commitment of the UTXO set after it was applied
+    let mut chain: Vec<blockchain::Block> = validator_node.blockchain.lock().await.chain.clone();
+    assert_eq!(utxo_commitment::recorded_commitment(&chain[1]), Some(minted_commitment.as_slice()));
+    assert_eq!(utxo_commitment::recorded_commitment(&chain[2]), Some(spent_commitment.as_slice()));
+
+    // replaying the chain rebuilds the same UTXO set, and a block recording another commitment is caught
+    let replayed: UTXOSet = utxo_commitment::replay_chain(&chain).unwrap();
+    assert_eq!(replayed.commitment(), spent_commitment);
+    assert_eq!(replayed.len(), validator_node.utxo_set.lock().await.len());
+    let mut inflated: Vec<blockchain::Block> = chain.clone();
+    if let blockchain::Block::UTXOTransaction { utxo_commitment, .. } = &mut chain[2] {
+        *utxo_commitment = minted_commitment;
+    }
+    assert!(utxo_commitment::replay_chain(&chain).is_err());
+
+    // a block whose coinbase pays more than its fee is caught, even if it records the commitment of the inflated set
+    let mut inflated_set: UTXOSet = utxo_commitment::replay_chain(&inflated[..2]).unwrap();
+    if let blockchain::Block::UTXOTransaction { transaction, block_height, coinbase, utxo_commitment, .. } = &mut inflated[2] {
+        *coinbase = CoinbaseTransaction::new(vec![TxOutput::new(1_000, vec![9; 20])], *block_height, coinbase.timestamp);
+        inflated_set.apply_transaction(transaction, *block_height).unwrap();
+        inflated_set.apply_coinbase(coinbase);
+        *utxo_commitment = inflated_set.commitment();
+    }
+    assert!(utxo_commitment::replay_chain(&inflated).unwrap_err().contains("invalid coinbase"));
+}
+
+#[tokio::test]
+async fn test_revert_blocks_restores_ledger_state() {
+    let validator_node = ValidatorNode::new();
+    let (secret_key, public_key) = zk_proof::generate_keypair().unwrap();
+    let account: Vec<u8> = public_key.to_string().into_bytes();
+    validator_node.merkle_tree.lock().await.insert_account(Account {
+        public_key: account.clone(),
+        public_key_hash: zk_proof::get_public_key_hash(&public_key),
+        balance: 100,
+        nonce: 0,
+        schnorr_commitment: zk_proof::derive_schnorr_commitment(&secret_key.to_string()).unwrap(),
+    });
+    let initial_tree = validator_node.merkle_tree.lock().await.clone();
+
+    // Burn 60 from the account into a UTXO, then spend it back to the account with change
+    let request = burn_request(&secret_key, &public_key, now());
+    assert!(validation::handle_account_to_utxo_request(request, validator_node.clone()).await.unwrap());
+    let minted_tree = validator_node.merkle_tree.lock().await.clone();
+    let minted_utxo_set = validator_node.utxo_set.lock().await.clone();
+
+    let mut tx = UTXOTransaction::new(
+        vec![TxInput::new(bridge::mint_outpoint(&account, 0), String::new(), public_key.serialize().to_vec())],
+        vec![TxOutput::new(50, bridge::account_recipient(&public_key.to_string()).unwrap()), TxOutput::new(9, public_key.serialize().to_vec())],
+        12345,
+    );
+    tx.sign_input(0, &secret_key, SIGHASH_ALL).unwrap();
+    let request = serde_json::to_value(NetworkRequest::UTXOTransaction {
+        inputs: tx.inputs,
+        outputs: tx.outputs,
+        timestamp: tx.timestamp,
+        lock_time: tx.lock_time,
+        explicit_fee: tx.explicit_fee,
+        chain_id: blockchain::chain_id().to_string(),
+    }).unwrap();
+    assert!(validation::handle_utxo_transaction_request(request, validator_node.clone()).await.unwrap());
+    assert_eq!(validator_node.merkle_tree.lock().await.get_account_balance(&account), Some(89));
+
+    // Revert both blocks from the tip down, locking the merkle tree, then the UTXO set, then the blockchain
+    let mut merkle_tree_guard = validator_node.merkle_tree.lock().await;
+    let mut utxo_set_guard = validator_node.utxo_set.lock().await;
+    let mut blockchain_guard = validator_node.blockchain.lock().await;
+
+    let reverted = blockchain_guard.revert_block(&mut merkle_tree_guard, &mut utxo_set_guard).unwrap();
+    assert!(matches!(reverted, blockchain::Block::UTXOTransaction { .. }));
+    assert_eq!(*merkle_tree_guard, minted_tree);
+    assert_eq!(*utxo_set_guard, minted_utxo_set);
+    assert_eq!(utxo_set_guard.commitment(), minted_utxo_set.commitment());
+
+    let reverted = blockchain_guard.revert_block(&mut merkle_tree_guard, &mut utxo_set_guard).unwrap();
+    assert!(matches!(reverted, blockchain::Block::AccountToUtxo { .. }));
+    assert_eq!(*merkle_tree_guard, initial_tree);
+    assert!(utxo_set_guard.is_empty());
+    assert_eq!(blockchain_guard.chain.len(), 1);
+
+    // the genesis block cannot be reverted
+    assert!(blockchain_guard.revert_block(&mut merkle_tree_guard, &mut utxo_set_guard).is_err());
+}
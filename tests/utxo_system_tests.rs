@@ -27,10 +27,12 @@ async fn test_end_to_end_utxo_workflow() {
         TxOutput {
             amount: 5000,
             recipient: vec![1, 2, 3, 4, 5], // Alice
+            confidential: None,
         },
         TxOutput {
             amount: 3000,
             recipient: vec![6, 7, 8, 9, 10], // Bob
+            confidential: None,
         },
     ];
     
@@ -59,16 +61,19 @@ async fn test_end_to_end_utxo_workflow() {
         outpoint: alice_utxo_outpoint,
         signature: "alice_signature".to_string(),
         public_key: alice_key.clone(),
+        relative_lock: None,
     }];
     
     let transaction_outputs = vec![
         TxOutput {
             amount: 2000,
             recipient: charlie_key.clone(),
+            confidential: None,
         },
         TxOutput {
             amount: 2900, // Change back to Alice (100 token fee)
             recipient: alice_key.clone(),
+            confidential: None,
         },
     ];
     
@@ -102,16 +107,19 @@ async fn test_end_to_end_utxo_workflow() {
         outpoint: charlie_utxo_outpoint,
         signature: "charlie_signature".to_string(),
         public_key: charlie_key.clone(),
+        relative_lock: None,
     }];
     
     let tx2_outputs = vec![
         TxOutput {
             amount: 1500,
             recipient: bob_key.clone(),
+            confidential: None,
         },
         TxOutput {
             amount: 450, // Change back to Charlie (50 token fee)
             recipient: charlie_key.clone(),
+            confidential: None,
         },
     ];
     
@@ -238,16 +246,18 @@ async fn test_utxo_transaction_complex_scenarios() {
                 outpoint: OutPoint::new(vec![0x01; 32], 0),
                 signature: "alice_sig1".to_string(),
                 public_key: alice.clone(),
+                relative_lock: None,
             },
             TxInput {
                 outpoint: OutPoint::new(vec![0x02; 32], 0),
                 signature: "alice_sig2".to_string(),
                 public_key: alice.clone(),
+                relative_lock: None,
             },
         ],
         vec![
-            TxOutput { amount: 1200, recipient: charlie.clone() },
-            TxOutput { amount: 250, recipient: alice.clone() }, // Change (50 fee)
+            TxOutput { amount: 1200, recipient: charlie.clone(), confidential: None },
+            TxOutput { amount: 250, recipient: alice.clone(), confidential: None }, // Change (50 fee)
         ],
         12349,
     );
@@ -267,12 +277,13 @@ async fn test_utxo_transaction_complex_scenarios() {
             outpoint: OutPoint::new(vec![0x04; 32], 0),
             signature: "bob_sig".to_string(),
             public_key: bob.clone(),
+            relative_lock: None,
         }],
         vec![
-            TxOutput { amount: 500, recipient: alice.clone() },
-            TxOutput { amount: 500, recipient: charlie.clone() },
-            TxOutput { amount: 500, recipient: dave.clone() },
-            TxOutput { amount: 450, recipient: bob.clone() }, // Change (50 fee)
+            TxOutput { amount: 500, recipient: alice.clone(), confidential: None },
+            TxOutput { amount: 500, recipient: charlie.clone(), confidential: None },
+            TxOutput { amount: 500, recipient: dave.clone(), confidential: None },
+            TxOutput { amount: 450, recipient: bob.clone(), confidential: None }, // Change (50 fee)
         ],
         12350,
     );
@@ -315,10 +326,10 @@ fn test_utxo_edge_cases() {
     assert_ne!(outpoint1, outpoint3);
     
     // Test UTXO transaction with no inputs (should not be possible in real usage)
-    let tx = UTXOTransaction::new(vec![], vec![TxOutput { amount: 100, recipient: vec![1, 2, 3] }], 12345);
+    let tx = UTXOTransaction::new(vec![], vec![TxOutput { amount: 100, recipient: vec![1, 2, 3], confidential: None }], 12345);
     assert_eq!(tx.inputs.len(), 0);
     assert_eq!(tx.outputs.len(), 1);
-    assert_eq!(tx.total_output_amount(), 100);
+    assert_eq!(tx.total_output_amount(), Some(100));
     
     // Test UTXO transaction with no outputs (burning tokens)
     let tx = UTXOTransaction::new(
@@ -326,13 +337,14 @@ fn test_utxo_edge_cases() {
             outpoint: OutPoint::new(vec![1, 2, 3], 0),
             signature: "sig".to_string(),
             public_key: vec![4, 5, 6],
+            relative_lock: None,
         }],
         vec![],
         12345,
     );
     assert_eq!(tx.inputs.len(), 1);
     assert_eq!(tx.outputs.len(), 0);
-    assert_eq!(tx.total_output_amount(), 0);
+    assert_eq!(tx.total_output_amount(), Some(0));
 }
 
 #[tokio::test]
@@ -345,10 +357,12 @@ async fn test_utxo_blockchain_integration() {
             outpoint: OutPoint::new(vec![1, 2, 3], 0),
             signature: "signature".to_string(),
             public_key: vec![4, 5, 6],
+            relative_lock: None,
         }],
         vec![TxOutput {
             amount: 100,
             recipient: vec![7, 8, 9],
+            confidential: None,
         }],
         12345,
     );
@@ -357,6 +371,8 @@ async fn test_utxo_blockchain_integration() {
     let utxo_block = Block::UTXOTransaction {
         transaction: utxo_tx.clone(),
         block_height: 1,
+        coinbase: CoinbaseTransaction::new(vec![], 1, 12345),
+        utxo_commitment: Vec::new(),
         hash: Vec::new(), // Will be set by blockchain
     };
     
@@ -369,7 +385,7 @@ async fn test_utxo_blockchain_integration() {
         assert_eq!(blockchain_guard.chain.len(), 2); // Genesis + UTXO block
         
         // Verify block type
-        if let Block::UTXOTransaction { transaction, block_height, hash } = &blockchain_guard.chain[1] {
+        if let Block::UTXOTransaction { transaction, block_height, hash, .. } = &blockchain_guard.chain[1] {
             assert_eq!(transaction.hash, utxo_tx.hash);
             assert_eq!(*block_height, 1);
             assert!(!hash.is_empty());
@@ -397,10 +413,12 @@ async fn test_utxo_network_request_serialization() {
             outpoint: OutPoint::new(vec![1, 2, 3], 0),
             signature: "signature".to_string(),
             public_key: vec![4, 5, 6],
+            relative_lock: None,
         }],
         vec![TxOutput {
             amount: 100,
             recipient: vec![7, 8, 9],
+            confidential: None,
         }],
         12345,
     );